@@ -0,0 +1,76 @@
+// benches/parsing.rs
+// Criterion benchmarks for the DURATION, SIZE and SIGNAL parsers
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+#[cfg(unix)]
+use timeout::signal;
+use timeout::units;
+
+/// Run `parse` on each input as its own benchmark in `group`
+fn inputs<T>(c: &mut Criterion, group: &str, parse: fn(&str) -> T, inputs: &[(&str, &str)]) {
+    let mut group = c.benchmark_group(group);
+    for (name, input) in inputs {
+        group.bench_with_input(BenchmarkId::from_parameter(name), input, |b, input| {
+            b.iter(|| parse(black_box(input)))
+        });
+    }
+    group.finish();
+}
+
+fn parse_duration(c: &mut Criterion) {
+    inputs(
+        c,
+        "parse_duration",
+        units::parse_duration,
+        &[
+            ("short", "5s"),
+            ("bare", "30"),
+            ("long", "1h30m"),
+            ("fractional", "0.5s"),
+            ("large", "365d"),
+            ("invalid", "5x"),
+        ],
+    );
+}
+
+fn parse_memory_limit(c: &mut Criterion) {
+    inputs(
+        c,
+        "parse_memory_limit",
+        units::parse_memory_limit,
+        &[
+            ("short", "512M"),
+            ("bare", "1048576"),
+            ("long", "512MiB"),
+            ("decimal", "2GB"),
+            ("percent", "50%"),
+            ("invalid", "5XB"),
+        ],
+    );
+}
+
+#[cfg(unix)]
+fn parse_signal(c: &mut Criterion) {
+    use signal::TimeoutSignal;
+    inputs(
+        c,
+        "from_str_or_num",
+        TimeoutSignal::from_str_or_num,
+        &[
+            ("name", "TERM"),
+            ("sig_name", "SIGKILL"),
+            ("lower", "hup"),
+            ("number", "9"),
+            ("realtime", "SIGRTMIN+3"),
+            ("invalid", "TREM"),
+            ("invalid_number", "999"),
+        ],
+    );
+}
+
+#[cfg(not(unix))]
+fn parse_signal(_c: &mut Criterion) {}
+
+criterion_group!(benches, parse_duration, parse_memory_limit, parse_signal);
+criterion_main!(benches);
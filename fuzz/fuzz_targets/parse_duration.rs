@@ -0,0 +1,29 @@
+// fuzz/fuzz_targets/parse_duration.rs
+// parse_duration must return Ok or Err for any input, never panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use timeout::{units, TimeoutError};
+use std::time::Duration;
+
+/// The largest DURATION timeout accepts, about 585 years
+const MAX: Duration = Duration::from_secs(u64::MAX / 1_000_000_000 + 1);
+
+fuzz_target!(|data: &[u8]| {
+    // Arguments reach timeout as strings, so invalid UTF-8 never gets here
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    match units::parse_duration(input) {
+        Ok(duration) => assert!(duration <= MAX, "{:?} parsed as {:?}", input, duration),
+        Err(TimeoutError::InvalidDuration {
+            input: reported,
+            reason,
+        }) => {
+            assert_eq!(reported, input.trim());
+            assert!(!reason.is_empty());
+        }
+        Err(e) => panic!("{:?} gave the wrong kind of error: {:?}", input, e),
+    }
+});
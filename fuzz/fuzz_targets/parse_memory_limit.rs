@@ -0,0 +1,33 @@
+// fuzz/fuzz_targets/parse_memory_limit.rs
+// parse_memory_limit must return Ok or Err for any input, never panic
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use timeout::{units, Platform, TimeoutError};
+
+fuzz_target!(|data: &[u8]| {
+    // Arguments reach timeout as strings, so invalid UTF-8 never gets here
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    match units::parse_memory_limit(input) {
+        // A percentage is at most all of memory; anything else that fits
+        // in a u64 is a valid size
+        Ok(bytes) if input.trim().ends_with('%') => assert!(
+            bytes <= Platform::total_memory().unwrap_or(u64::MAX),
+            "{:?} parsed as {} bytes",
+            input,
+            bytes
+        ),
+        Ok(_) => {}
+        Err(TimeoutError::InvalidMemoryLimit {
+            input: reported,
+            reason,
+        }) => {
+            assert_eq!(reported, input.trim());
+            assert!(!reason.is_empty());
+        }
+        Err(e) => panic!("{:?} gave the wrong kind of error: {:?}", input, e),
+    }
+});
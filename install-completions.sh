@@ -20,7 +20,7 @@ if [ -n "$ZSH_VERSION" ]; then
 elif [ -n "$BASH_VERSION" ]; then
     SHELL_NAME="bash"
 else
-    echo "Please specify your shell (bash, zsh, fish, powershell, elvish):"
+    echo "Please specify your shell (bash, zsh, fish, powershell, elvish, nushell):"
     read -r SHELL_NAME
 fi
 
@@ -74,9 +74,17 @@ case "$SHELL_NAME" in
         echo "  Add to your rc.elv: use timeout-completions"
         ;;
     
+    nushell|nu)
+        COMPLETION_DIR="${XDG_CONFIG_HOME:-$HOME/.config}/nushell/completions"
+        mkdir -p "$COMPLETION_DIR"
+        "$BINARY" --generate-completions nushell > "$COMPLETION_DIR/timeout.nu"
+        echo "✓ Nushell completions installed to: $COMPLETION_DIR/timeout.nu"
+        echo "  Add to your config.nu: use $COMPLETION_DIR/timeout.nu *"
+        ;;
+    
     *)
         echo "Unknown shell: $SHELL_NAME"
-        echo "Supported shells: bash, zsh, fish, powershell, elvish"
+        echo "Supported shells: bash, zsh, fish, powershell, elvish, nushell"
         exit 1
         ;;
 esac
@@ -86,6 +86,45 @@ run_test "Custom signal SIGINT" 124 \
 run_test "SIGKILL signal" 124 \
     "$TIMEOUT_BIN" -s SIGKILL 1s sleep 10
 
+run_test "Verbose summary line" 0 \
+    sh -c "'$TIMEOUT_BIN' -v 0.2 sleep 5 2>&1 | grep -q \"Command 'sleep' timed out after .*s (limit: 200ms), sent SIGTERM, exit code 124\""
+
+run_test "Uncatchable signal with kill-after warns" 0 \
+    sh -c "'$TIMEOUT_BIN' -v -s KILL -k 1s 0.1 sleep 5 2>&1 | grep -q 'cannot be caught'"
+
+run_test "Signal by number" 124 \
+    "$TIMEOUT_BIN" -s 9 1s sleep 10
+
+run_test "Unknown signal exits 125" 125 \
+    "$TIMEOUT_BIN" -s NOSUCHSIG 1s true
+
+run_test "Misspelt signal suggests the closest name" 0 \
+    sh -c "'$TIMEOUT_BIN' -s SIGNUP 1s true 2>&1 | grep -q 'did you mean SIGHUP?'"
+
+run_test "No suggestion for a distant name" 1 \
+    sh -c "'$TIMEOUT_BIN' -s FOO 1s true 2>&1 | grep -q 'did you mean'"
+
+# Real-time signals are Linux-only
+if [ "$(uname)" = Linux ]; then
+    run_test "Real-time signal reaches the command" 7 \
+        "$TIMEOUT_BIN" --preserve-status -s SIGRTMIN+1 1s bash -c 'trap "exit 7" RTMIN+1; sleep 5 & wait'
+
+    run_test "Real-time signal by number" 0 \
+        bash -c "'$TIMEOUT_BIN' --dry-run -s \$(kill -l RTMIN) 1s true | grep -q 'SIGRTMIN+0'"
+
+    run_test "Real-time signal out of range" 125 \
+        "$TIMEOUT_BIN" -s SIGRTMIN+32 1s true
+
+    run_test "Command ended by SIGRTMAX exits 128+N" 0 \
+        bash -c "'$TIMEOUT_BIN' 1s sh -c 'kill -s RTMAX \$\$'; [ \$? = \$((128 + \$(kill -l RTMAX))) ]"
+
+    run_test "Command ended by SIGRTMIN exits 128+N" 0 \
+        bash -c "'$TIMEOUT_BIN' 1s sh -c 'kill -s RTMIN \$\$'; [ \$? = \$((128 + \$(kill -l RTMIN))) ]"
+
+    run_test "Preserved status of SIGRTMAX at the deadline" 0 \
+        bash -c "'$TIMEOUT_BIN' --preserve-status -s SIGRTMAX 0.2 sleep 5; [ \$? = \$((128 + \$(kill -l RTMAX))) ]"
+fi
+
 echo ""
 echo "=== Kill After Tests ==="
 echo ""
@@ -127,6 +166,18 @@ else
     TESTS_FAILED=$((TESTS_FAILED + 1))
 fi
 
+run_test "Suppressed exit code becomes 0" 0 \
+    "$TIMEOUT_BIN" --suppress-exit-code 1 5s sh -c 'exit 1'
+
+run_test "Other exit codes are kept" 2 \
+    "$TIMEOUT_BIN" --suppress-exit-code 1 5s sh -c 'exit 2'
+
+run_test "A timeout is not suppressed" 124 \
+    "$TIMEOUT_BIN" --suppress-exit-code 124 0.2 sleep 1
+
+run_test "Exit code 0 cannot be suppressed" 2 \
+    "$TIMEOUT_BIN" --suppress-exit-code 0 5s true
+
 echo ""
 echo "=== Floating Point Duration Test ==="
 echo ""
@@ -143,20 +194,1897 @@ echo ""
 run_test "Zero duration" 124 \
     "$TIMEOUT_BIN" 0s sleep 1
 
+run_test "Largest accepted duration" 0 \
+    "$TIMEOUT_BIN" --dry-run 18446744073 true
+
+run_test "Duration just past the maximum" 125 \
+    "$TIMEOUT_BIN" --dry-run 18446744074 true
+
+run_test "Huge duration rejected instead of panicking" 125 \
+    "$TIMEOUT_BIN" 9999999999d true
+
+run_test "NaN duration rejected" 125 \
+    "$TIMEOUT_BIN" nans true
+
+run_test "Multi-byte duration suffix rejected instead of panicking" 125 \
+    "$TIMEOUT_BIN" "5é" true
+
+run_test "TIMEOUT_DURATION used when DURATION is omitted" 124 \
+    env TIMEOUT_DURATION=1s "$TIMEOUT_BIN" sleep 5
+
+run_test "Explicit DURATION overrides TIMEOUT_DURATION" 0 \
+    env TIMEOUT_DURATION=1s "$TIMEOUT_BIN" 5s sleep 2
+
+run_test "Invalid TIMEOUT_DURATION" 125 \
+    env TIMEOUT_DURATION=abc "$TIMEOUT_BIN" sleep 1
+
 echo ""
-echo "=== Help and Version Tests ==="
+echo "=== Idle Timeout Tests ==="
 echo ""
 
-# Test 12: Help flag
-echo -ne "${YELLOW}Testing: Help flag${NC} ... "
-if "$TIMEOUT_BIN" --help > /dev/null 2>&1; then
-    echo -e "${GREEN}PASS${NC}"
-    TESTS_PASSED=$((TESTS_PASSED + 1))
-else
-    echo -e "${RED}FAIL${NC}"
-    TESTS_FAILED=$((TESTS_FAILED + 1))
+run_test "Idle timeout fires on silent command" 124 \
+    "$TIMEOUT_BIN" --idle-timeout 1s 10s sleep 5
+
+run_test "Output keeps idle timeout from firing" 0 \
+    "$TIMEOUT_BIN" --idle-timeout 1s 10s sh -c "for i in 1 2 3; do echo \$i; sleep 0.5; done"
+
+run_test "Overall duration still applies with idle timeout" 124 \
+    "$TIMEOUT_BIN" --idle-timeout 1s 2s sh -c "while :; do echo x; sleep 0.2; done"
+
+run_test "Zero duration with idle timeout means no overall limit" 0 \
+    "$TIMEOUT_BIN" --idle-timeout 1s 0 sleep 0.5
+
+run_test "Idle timeout with custom status" 42 \
+    "$TIMEOUT_BIN" --idle-timeout 1s --status 42 10s sleep 5
+
+echo ""
+echo "=== Output Limit Tests ==="
+echo ""
+
+run_test "Flooding output hits max-output" 123 \
+    "$TIMEOUT_BIN" --max-output 1M 10s yes
+
+run_test "Output under the limit is fine" 0 \
+    "$TIMEOUT_BIN" --max-output 1K 5s echo hello
+
+run_test "Stderr limit counts stderr only" 123 \
+    "$TIMEOUT_BIN" --max-output-stderr 10 10s sh -c "echo 12345678901234567890; yes >&2"
+
+run_test "Stdout limit ignores stderr" 0 \
+    "$TIMEOUT_BIN" --max-output-stdout 10 5s sh -c "echo 12345678901234567890 >&2"
+
+run_test "Invalid output limit" 125 \
+    "$TIMEOUT_BIN" --max-output 5X 5s true
+
+run_test "Decimal size suffix" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --max-output 2KB 5s true | grep -q 'max_output_bytes.:2000'"
+
+run_test "Binary size suffix, any case" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --max-output 2kib 5s true | grep -q 'max_output_bytes.:2048'"
+
+run_test "Terabyte size suffix" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --max-output 1T 5s true | grep -q 'max_output_bytes.:1099511627776'"
+
+run_test "Percentage of memory" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --max-output 50% 5s true | grep -q 'max_output_bytes.:[1-9]'"
+
+run_test "Percentage out of range" 125 \
+    "$TIMEOUT_BIN" --max-output 150% 5s true
+
+# --mem-limit only exists where RLIMIT_AS is supported
+if [ "$(uname)" = Linux ]; then
+    run_test "Zero memory limit rejected" 125 \
+        "$TIMEOUT_BIN" --mem-limit 0 5s true
+
+    run_test "No-mem-limit overrides mem-limit" 0 \
+        sh -c "'$TIMEOUT_BIN' --dry-run=json --mem-limit 1G --no-mem-limit 5s true | grep -q 'memory_limit_bytes.:null'"
+
+    run_test "No-cpu-limit overrides cpu-limit" 0 \
+        sh -c "'$TIMEOUT_BIN' --dry-run=json --cpu-limit 5 --no-cpu-limit 5s true | grep -q 'cpu_limit_secs.:null'"
+fi
+
+run_test "Size too large" 125 \
+    "$TIMEOUT_BIN" --max-output 16384P 5s true
+
+run_test "Unknown multi-letter suffix" 125 \
+    "$TIMEOUT_BIN" --max-output 5XB 5s true
+
+run_test "Size suffix after a multi-byte character" 125 \
+    "$TIMEOUT_BIN" --max-output "5€x" 5s true
+
+echo ""
+echo "=== Output Pattern Tests ==="
+echo ""
+
+run_test "Kill pattern stops command" 124 \
+    "$TIMEOUT_BIN" --kill-on-pattern "panic:|OutOfMemory" 10s sh -c "echo start; echo 'panic: boom'; sleep 5"
+
+run_test "Kill pattern on stderr" 124 \
+    "$TIMEOUT_BIN" --kill-on-pattern "OutOfMemory" 10s sh -c "echo OutOfMemory >&2; sleep 5"
+
+run_test "Success pattern exits 0" 0 \
+    "$TIMEOUT_BIN" --success-on-pattern "listening on" 10s sh -c "echo 'Server listening on :8080'; sleep 5"
+
+run_test "Success pattern with --no-kill" 0 \
+    "$TIMEOUT_BIN" --success-on-pattern "ready" --no-kill 10s sh -c "echo ready; sleep 1"
+
+run_test "Unmatched success pattern still times out" 124 \
+    "$TIMEOUT_BIN" --success-on-pattern "never" 1s sleep 5
+
+NOKILL_DIR=$(mktemp -d)
+
+run_test "No-kill exits 124 at the deadline" 124 \
+    "$TIMEOUT_BIN" --no-kill 0.2 sleep 1
+
+run_test "No-kill leaves the command running" 0 \
+    sh -c "'$TIMEOUT_BIN' --no-kill 0.2 sh -c 'sleep 0.5; touch \"$NOKILL_DIR/survived\"' 2>/dev/null; sleep 1; [ -e '$NOKILL_DIR/survived' ]"
+
+run_test "No-kill honours --status" 7 \
+    "$TIMEOUT_BIN" --no-kill --status 7 0.2 sleep 1
+
+run_test "No-kill prints the PID" 0 \
+    sh -c "'$TIMEOUT_BIN' --no-kill 0.2 sleep 1 2>&1 | grep -q 'leaving it running (--no-kill): PID [0-9]*, process group [0-9]*'"
+
+run_test "No-kill metrics record no signal" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --no-kill 0.2 sleep 1 2>&1 | grep -q '\"timed_out\":true,.*\"signal\":\"none\"'"
+
+run_test "No-kill conflicts with --kill-after" 2 \
+    "$TIMEOUT_BIN" --no-kill -k 1s 1s true
+
+run_test "No-kill conflicts with --no-notify" 2 \
+    "$TIMEOUT_BIN" --no-kill --no-notify 1s true
+
+# A suspended command would hold a pipe open, so these tests collect
+# output in files. $1: timeout binary, $2: scratch directory
+cat > "$NOKILL_DIR/stopped.sh" <<'SCRIPT'
+"$1" --stop-instead 0.3 sh -c 'echo $$ > "$0/pid"; sleep 1; touch "$0/resumed"' "$2" 2>/dev/null
+[ $? -eq 124 ] || exit 1
+pid=$(cat "$2/pid")
+ps -o stat= -p "$pid" | grep -q '^T' || exit 2
+[ ! -e "$2/resumed" ] || exit 3
+kill -CONT "-$pid"
+sleep 1.5
+[ -e "$2/resumed" ]
+SCRIPT
+
+run_test "Stop-instead suspends the command until SIGCONT" 0 \
+    sh "$NOKILL_DIR/stopped.sh" "$TIMEOUT_BIN" "$NOKILL_DIR"
+
+run_test "Stop-instead prints the resume hint" 0 \
+    sh -c "'$TIMEOUT_BIN' --stop-instead 0.2 sleep 1 2>'$NOKILL_DIR/out'; kill -KILL -\$(grep -o 'gdb -p [0-9]*' '$NOKILL_DIR/out' | cut -d' ' -f3); grep -q 'process group suspended; resume with kill -CONT -[0-9]* or inspect with gdb -p [0-9]*' '$NOKILL_DIR/out'"
+
+run_test "Stop-instead metrics record SIGSTOP" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --stop-instead 0.2 sleep 1 2>'$NOKILL_DIR/out'; kill -KILL -\$(grep -o 'gdb -p [0-9]*' '$NOKILL_DIR/out' | cut -d' ' -f3); grep -q '\"timed_out\":true,.*\"signal\":\"SIGSTOP\"' '$NOKILL_DIR/out'"
+
+run_test "Stop-instead conflicts with --kill-after" 2 \
+    "$TIMEOUT_BIN" --stop-instead -k 1s 1s true
+
+run_test "Stop-instead conflicts with --signal" 2 \
+    "$TIMEOUT_BIN" --stop-instead -s INT 1s true
+
+run_test "On-timeout hook runs before the command is signalled" 7 \
+    "$TIMEOUT_BIN" --preserve-status --on-timeout "touch '$NOKILL_DIR/hooked'" 0.3 \
+    sh -c "trap '[ -e \"$NOKILL_DIR/hooked\" ] && exit 7; exit 1' TERM; sleep 5 & wait"
+
+run_test "On-timeout hook substitutes placeholders" 0 \
+    sh -c "'$TIMEOUT_BIN' --on-timeout 'echo pid={pid} pgid={pgid} ms={elapsed_ms} cmd={command} {other}' 0.2 sleep 1 | grep -q '^pid=[0-9]* pgid=[0-9]* ms=[0-9]* cmd=sleep {other}\$'"
+
+run_test "On-timeout hook is killed after its own timeout" 124 \
+    "$TIMEOUT_BIN" --status 9 3 "$TIMEOUT_BIN" --on-timeout 'sleep 10' --on-timeout-timeout 0.3 0.2 sleep 10
+
+run_test "On-timeout hook exit code in metrics" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --on-timeout 'exit 3' 0.2 sleep 1 2>&1 | grep -q '\"hook_exit_code\":3'"
+
+run_test "On-timeout hook not run when the command finishes" 0 \
+    sh -c "'$TIMEOUT_BIN' --on-timeout 'touch \"$NOKILL_DIR/early\"' 1 true; [ ! -e '$NOKILL_DIR/early' ]"
+
+run_test "On-timeout-timeout requires --on-timeout" 2 \
+    "$TIMEOUT_BIN" --on-timeout-timeout 1s 1s true
+
+run_test "On-success hook gets the exit code" 0 \
+    sh -c "'$TIMEOUT_BIN' --on-success 'echo ok {exit_code} {timed_out} {command}' 1s true | grep -qx 'ok 0 false true'"
+
+run_test "On-failure hook runs on a nonzero exit" 3 \
+    sh -c "'$TIMEOUT_BIN' --on-failure 'touch \"$NOKILL_DIR/failed\"' --on-success 'touch \"$NOKILL_DIR/succeeded\"' 1s sh -c 'exit 3'; code=\$?; [ -e '$NOKILL_DIR/failed' ] && [ ! -e '$NOKILL_DIR/succeeded' ] && exit \$code"
+
+run_test "On-timeout hook runs before the on-failure hook" 0 \
+    sh -c "'$TIMEOUT_BIN' --on-timeout 'echo timeout >> \"$NOKILL_DIR/order\"' --on-failure 'echo failure {exit_code} {timed_out} >> \"$NOKILL_DIR/order\"' 0.2 sleep 1; [ \"\$(cat '$NOKILL_DIR/order' | tr '\n' ' ')\" = 'timeout failure 124 true ' ]"
+
+run_test "On-failure hook runs when COMMAND cannot be found" 127 \
+    sh -c "'$TIMEOUT_BIN' --on-failure 'echo failure {exit_code}' 1s nonexistent_command_xyz | grep -qx 'failure 127' && exit 127"
+
+run_test "Failing hook does not change the exit code" 1 \
+    "$TIMEOUT_BIN" --on-failure 'exit 4' 1s false
+
+run_test "Hook failures fatal" 125 \
+    "$TIMEOUT_BIN" --hook-failures-fatal --on-success 'exit 4' 1s true
+
+run_test "Failing on-timeout hook is fatal too" 125 \
+    "$TIMEOUT_BIN" --hook-failures-fatal --on-timeout 'exit 4' 0.2 sleep 1
+
+run_test "Hook timeout bounds a completion hook" 0 \
+    "$TIMEOUT_BIN" --status 9 3 "$TIMEOUT_BIN" --hook-timeout 0.3 --on-success 'sleep 10' 1s true
+
+run_test "Hook timeout requires a hook" 2 \
+    "$TIMEOUT_BIN" --hook-timeout 1s 1s true
+
+run_test "Dry run shows the on-timeout hook" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --on-timeout 'echo hi' 1s true | grep -q 'on timeout: *echo hi (at most 30000 ms)'"
+
+run_test "Pre-kill hook runs before the command is signalled" 7 \
+    "$TIMEOUT_BIN" --preserve-status --pre-kill "touch '$NOKILL_DIR/pre-killed'" 0.3 \
+    sh -c "trap '[ -e \"$NOKILL_DIR/pre-killed\" ] && exit 7; exit 1' TERM; sleep 5 & wait"
+
+run_test "Pre-kill hook substitutes placeholders" 0 \
+    sh -c "'$TIMEOUT_BIN' -s INT --pre-kill 'echo pid={PID} signal={SIGNAL} ms={ELAPSED_MS}' 0.2 sleep 1 | grep -q '^pid=[0-9]* signal=SIGINT ms=[0-9]*\$'"
+
+run_test "Pre-kill hook runs after the on-timeout hook" 0 \
+    sh -c "'$TIMEOUT_BIN' --on-timeout 'echo timeout {signal} >> \"$NOKILL_DIR/pre-order\"' --pre-kill 'echo pre-kill >> \"$NOKILL_DIR/pre-order\"' 0.2 sleep 1; [ \"\$(cat '$NOKILL_DIR/pre-order' | tr '\n' ' ')\" = 'timeout SIGTERM pre-kill ' ]"
+
+run_test "Pre-kill hook not run when the command finishes" 0 \
+    sh -c "'$TIMEOUT_BIN' --pre-kill 'touch \"$NOKILL_DIR/pre-early\"' 1 true; [ ! -e '$NOKILL_DIR/pre-early' ]"
+
+run_test "Failing pre-kill hook does not change the exit code" 124 \
+    "$TIMEOUT_BIN" --pre-kill 'exit 4' 0.2 sleep 1
+
+run_test "Pre-kill conflicts with --no-kill" 2 \
+    "$TIMEOUT_BIN" --pre-kill 'true' --no-kill 1s true
+
+run_test "Dry run shows the pre-kill hook" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --pre-kill 'echo hi' 1s true | grep -q 'pre kill: *echo hi (at most 10000 ms)'"
+
+rm -rf "$NOKILL_DIR"
+
+run_test "Invalid pattern rejected" 125 \
+    "$TIMEOUT_BIN" --kill-on-pattern "(" 5s true
+
+echo ""
+echo "=== Timestamp Tests ==="
+echo ""
+
+run_test "Timestamps prefix each line" 0 \
+    sh -c "'$TIMEOUT_BIN' --timestamps 5s sh -c 'echo one; echo two' | grep -c '^\[00:00:0[0-9]\.[0-9]\] ' | grep -qx 2"
+
+run_test "Timestamps on stderr" 0 \
+    sh -c "'$TIMEOUT_BIN' --timestamps 5s sh -c 'echo oops >&2' 2>&1 >/dev/null | grep -q '^\[00:00:00\.[0-9]\] oops'"
+
+run_test "Timestamps with remaining budget" 0 \
+    sh -c "'$TIMEOUT_BIN' --timestamps=remaining 5s echo hi | grep -q '^\[00:00:00\.[0-9] | 00:00:0[0-9]\.[0-9] left\] hi'"
+
+run_test "Timestamps keep exit code" 3 \
+    "$TIMEOUT_BIN" --timestamps 5s sh -c "echo x; exit 3"
+
+run_test "Invalid timestamps mode" 2 \
+    "$TIMEOUT_BIN" --timestamps=wallclock 5s true
+
+echo ""
+echo "=== Log File Tests ==="
+echo ""
+
+LOG_DIR=$(mktemp -d)
+
+run_test "Log file records both streams" 0 \
+    sh -c "'$TIMEOUT_BIN' --log-file '$LOG_DIR/both.log' 5s sh -c 'echo out; echo err >&2' && grep -q ' \[O\] out\$' '$LOG_DIR/both.log' && grep -q ' \[E\] err\$' '$LOG_DIR/both.log'"
+
+run_test "Log file records timeout" 0 \
+    sh -c "'$TIMEOUT_BIN' --log-file '$LOG_DIR/timeout.log' 1s sleep 5; grep -q '\[timeout\] duration: sent SIGTERM, exit code 124' '$LOG_DIR/timeout.log'"
+
+run_test "Log append keeps earlier lines" 0 \
+    sh -c "echo earlier > '$LOG_DIR/append.log' && '$TIMEOUT_BIN' --log-file '$LOG_DIR/append.log' --log-append 5s echo later && grep -q earlier '$LOG_DIR/append.log' && grep -q later '$LOG_DIR/append.log'"
+
+run_test "Unwritable log file" 125 \
+    "$TIMEOUT_BIN" --log-file /nonexistent/dir/log 5s true
+
+run_test "Log append requires log file" 2 \
+    "$TIMEOUT_BIN" --log-append 5s true
+
+rm -rf "$LOG_DIR"
+
+echo ""
+echo "=== Deadline Tests ==="
+echo ""
+
+run_test "Until stops the command" 124 \
+    "$TIMEOUT_BIN" --until +0.3 sleep 5
+
+run_test "Until relative duration" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --until +2h true | grep -q '\"duration_ms\":7200000,'"
+
+run_test "Until RFC 3339 UTC" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --until 2099-01-01T00:00:00Z true | grep -q '\"deadline\":\"2099-01-01T00:00:00.000Z\"'"
+
+run_test "Until RFC 3339 positive offset" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --until 2099-01-01T02:30:00+02:30 true | grep -q '\"deadline\":\"2099-01-01T00:00:00.000Z\"'"
+
+run_test "Until RFC 3339 negative offset" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --until 2098-12-31T19:00:00-05:00 true | grep -q '\"deadline\":\"2099-01-01T00:00:00.000Z\"'"
+
+run_test "Until RFC 3339 fraction and lowercase" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --until 2099-01-01t00:00:00.25z true | grep -q '\"deadline\":\"2099-01-01T00:00:00.250Z\"'"
+
+run_test "Until without zone is local time" 0 \
+    sh -c "TZ=JST-9 '$TIMEOUT_BIN' --dry-run=json --until '2099-01-01 09:00:00' true | grep -q '\"deadline\":\"2099-01-01T00:00:00.000Z\"'"
+
+run_test "Until local time in summer (DST)" 0 \
+    sh -c "TZ=EST5EDT,M3.2.0,M11.1.0 '$TIMEOUT_BIN' --dry-run=json --until 2099-07-01T12:00:00 true | grep -q '\"deadline\":\"2099-07-01T16:00:00.000Z\"'"
+
+run_test "Until local time in winter (DST)" 0 \
+    sh -c "TZ=EST5EDT,M3.2.0,M11.1.0 '$TIMEOUT_BIN' --dry-run=json --until 2099-01-01T12:00 true | grep -q '\"deadline\":\"2099-01-01T17:00:00.000Z\"'"
+
+# HH:MM is relative to the current local time; GNU date computes the inputs
+if date -d '+1 hour' +%H:%M >/dev/null 2>&1; then
+    run_test "Until clock time later today" 0 \
+        sh -c "ms=\$(TZ=UTC0 '$TIMEOUT_BIN' --dry-run=json --until \$(TZ=UTC0 date -d '+1 hour' +%H:%M:%S) true | grep -o '\"duration_ms\":[0-9]*' | cut -d: -f2); [ \$ms -gt 3590000 ] && [ \$ms -le 3600000 ]"
+
+    run_test "Until clock time already past means tomorrow" 0 \
+        sh -c "ms=\$(TZ=UTC0 '$TIMEOUT_BIN' --dry-run=json --until \$(TZ=UTC0 date -d '-1 hour' +%H:%M:%S) true | grep -o '\"duration_ms\":[0-9]*' | cut -d: -f2); [ \$ms -gt 82790000 ] && [ \$ms -le 82800000 ]"
+fi
+
+run_test "Until verbose shows the deadline" 0 \
+    sh -c "'$TIMEOUT_BIN' -v --until 2099-01-01T00:00:00Z true 2>&1 | grep -q 'Running until 2099-01-01T00:00:00.000Z'"
+
+run_test "Until in the past rejected" 125 \
+    "$TIMEOUT_BIN" --until 2020-01-01T00:00:00Z true
+
+run_test "Until nonexistent date rejected" 125 \
+    "$TIMEOUT_BIN" --until 2099-02-30T00:00:00Z true
+
+run_test "Until invalid time of day rejected" 125 \
+    "$TIMEOUT_BIN" --until 24:00 true
+
+run_test "Until invalid offset rejected" 125 \
+    "$TIMEOUT_BIN" --until 2099-01-01T00:00:00+2:00 true
+
+run_test "Until garbage rejected" 125 \
+    "$TIMEOUT_BIN" --until tomorrow true
+
+run_test "Until conflicts with DURATION" 2 \
+    "$TIMEOUT_BIN" --until +1h 5s true
+
+echo ""
+echo "=== Retry Tests ==="
+echo ""
+
+RETRY_DIR=$(mktemp -d)
+
+run_test "Retries exhausted exit 124" 0 \
+    sh -c "'$TIMEOUT_BIN' --retries 2 --retry-delay 0.1 0.2 sh -c 'echo x >> \"$RETRY_DIR/all\"; sleep 5'; [ \$? -eq 124 ] && [ \$(wc -l < '$RETRY_DIR/all') -eq 3 ]"
+
+run_test "Retry succeeds after a timeout" 0 \
+    "$TIMEOUT_BIN" --retries 3 --retry-delay 0.1 0.5 sh -c "if [ -e '$RETRY_DIR/seen' ]; then exit 0; fi; touch '$RETRY_DIR/seen'; sleep 5"
+
+run_test "No retry when command exits" 0 \
+    sh -c "'$TIMEOUT_BIN' --retries 2 --retry-delay 0.1 5s sh -c 'echo x >> \"$RETRY_DIR/once\"; exit 3'; [ \$? -eq 3 ] && [ \$(wc -l < '$RETRY_DIR/once') -eq 1 ]"
+
+run_test "Metrics printed per attempt" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --retries 1 --retry-delay 0.1 0.2 sleep 5 2>&1 | grep -q '\"attempt\":2,'"
+
+run_test "Retry on listed exit code" 0 \
+    "$TIMEOUT_BIN" --retries 2 --retry-delay 0.1 --retry-on-exit 3,100-120 5s sh -c "if [ -e '$RETRY_DIR/code' ]; then exit 0; fi; touch '$RETRY_DIR/code'; exit 111"
+
+run_test "Unlisted exit code is not retried" 4 \
+    "$TIMEOUT_BIN" --retries 2 --retry-delay 0.1 --retry-on-exit 3 5s sh -c "exit 4"
+
+run_test "Retry on any exit code keeps the last status" 0 \
+    sh -c "'$TIMEOUT_BIN' --retries 2 --retry-delay 0.1 --retry-on-exit any 5s sh -c 'echo x >> \"$RETRY_DIR/any\"; exit 9'; [ \$? -eq 9 ] && [ \$(wc -l < '$RETRY_DIR/any') -eq 3 ]"
+
+run_test "Exit and timeout retries share the budget" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --retries 2 --retry-delay 0.1 --retry-on-exit 5 0.3 sh -c 'echo x >> \"$RETRY_DIR/mixed\"; [ \$(wc -l < \"$RETRY_DIR/mixed\") -eq 1 ] && exit 5; sleep 5' 2>&1 | grep -q '\"attempt\":3,\"iteration\":1,\"retries_timeout\":1,\"retries_exit\":1,'"
+
+run_test "Exit code list parsing" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --retries 1 --retry-on-exit '1, 100-120,255' 5s true | grep -q 'retry on exit: 1,100-120,255'"
+
+run_test "Retry on any exit code in dry run" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --retries 1 --retry-on-exit ANY 5s true | grep -q '\"retry_on_exit\":\"any\"'"
+
+run_test "Exit code 0 rejected" 125 \
+    "$TIMEOUT_BIN" --retries 1 --retry-on-exit 0 5s true
+
+run_test "Exit code above 255 rejected" 125 \
+    "$TIMEOUT_BIN" --retries 1 --retry-on-exit 1,256 5s true
+
+run_test "Backwards exit code range rejected" 125 \
+    "$TIMEOUT_BIN" --retries 1 --retry-on-exit 120-100 5s true
+
+run_test "Malformed exit code rejected" 125 \
+    "$TIMEOUT_BIN" --retries 1 --retry-on-exit 1,,2 5s true
+
+run_test "Total timeout stops retries" 0 \
+    sh -c "'$TIMEOUT_BIN' --retries 10 --retry-delay 0.1 --total-timeout 1 0.3 sh -c 'echo x >> \"$RETRY_DIR/total\"; sleep 5'; [ \$? -eq 124 ] && [ \$(wc -l < '$RETRY_DIR/total') -le 3 ]"
+
+run_test "Total timeout cuts the last attempt short" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --retries 5 --retry-delay 0.1 --total-timeout 1 0.6 sleep 5 2>&1 | grep -q '\"attempt\":2,.*\"budget\":\"total\",'"
+
+run_test "Total timeout exits 124 despite --preserve-status" 124 \
+    "$TIMEOUT_BIN" --preserve-status --total-timeout 0.3 5s sleep 5
+
+run_test "Clamped last attempt honours --preserve-status" 143 \
+    "$TIMEOUT_BIN" --preserve-status --total-timeout 0.3 --clamp-last-attempt 5s sleep 5
+
+run_test "Retry delay past the total timeout is skipped" 124 \
+    "$TIMEOUT_BIN" --retries 3 --retry-delay 5 --total-timeout 2 0.2 sleep 5
+
+run_test "Clamp requires total timeout" 2 \
+    "$TIMEOUT_BIN" --clamp-last-attempt 5s true
+
+run_test "Retry on exit requires retries" 2 \
+    "$TIMEOUT_BIN" --retry-on-exit 1 5s true
+
+run_test "Retry delay requires retries" 2 \
+    "$TIMEOUT_BIN" --retry-delay 1s 5s true
+
+rm -rf "$RETRY_DIR"
+
+echo ""
+echo "=== Watch Mode Tests ==="
+echo ""
+
+WATCH_DIR=$(mktemp -d)
+
+run_test "Watch runs --count times" 0 \
+    sh -c "'$TIMEOUT_BIN' --every 0.1 --count 3 1s sh -c 'echo x >> \"$WATCH_DIR/count\"' && [ \$(wc -l < '$WATCH_DIR/count') -eq 3 ]"
+
+run_test "Watch interval is start to start" 0 \
+    sh -c "start=\$(date +%s%N); '$TIMEOUT_BIN' --every 0.5 --count 3 1s sleep 0.3; end=\$(date +%s%N); [ \$(( (end - start) / 1000000 )) -lt 1500 ]"
+
+run_test "Watch exits with the last status" 4 \
+    "$TIMEOUT_BIN" --every 0.1 --count 2 1s sh -c "exit 4"
+
+run_test "Watch until failure" 124 \
+    "$TIMEOUT_BIN" --every 0.1 --until-failure 0.2 sleep 5
+
+run_test "Watch until success" 0 \
+    sh -c "'$TIMEOUT_BIN' --every 0.1 --until-success 1s sh -c 'echo x >> \"$WATCH_DIR/succ\"; [ \$(wc -l < \"$WATCH_DIR/succ\") -ge 3 ]' && [ \$(wc -l < '$WATCH_DIR/succ') -eq 3 ]"
+
+run_test "Watch metrics per run" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --every 0.1 --count 2 1s true 2>&1 | grep -q '\"iteration\":2,'"
+
+run_test "Ctrl-C stops watching promptly" 130 \
+    sh -c "'$TIMEOUT_BIN' --every 30 1s true & pid=\$!; sleep 0.5; kill -INT \$pid; wait \$pid"
+
+run_test "Count requires every" 2 \
+    "$TIMEOUT_BIN" --count 3 1s true
+
+run_test "Until-failure conflicts with until-success" 2 \
+    "$TIMEOUT_BIN" --every 1 --until-failure --until-success 1s true
+
+rm -rf "$WATCH_DIR"
+
+echo ""
+echo "=== Control Signal Tests ==="
+echo ""
+
+run_test "SIGUSR1 extends the deadline" 0 \
+    sh -c "'$TIMEOUT_BIN' --extend-step 1 0.5 sleep 1 & pid=\$!; sleep 0.2; kill -USR1 \$pid; wait \$pid"
+
+run_test "SIGUSR2 reports the time left" 0 \
+    sh -c "('$TIMEOUT_BIN' 5 sleep 0.5 & pid=\$!; sleep 0.2; kill -USR2 \$pid; wait \$pid) 2>&1 | grep -q 'left before command'"
+
+run_test "Second SIGUSR2 within a second expires" 124 \
+    sh -c "'$TIMEOUT_BIN' 5 sleep 3 & pid=\$!; sleep 0.2; kill -USR2 \$pid; sleep 0.1; kill -USR2 \$pid; wait \$pid"
+
+run_test "Metrics count extensions" 0 \
+    sh -c "(TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --extend-step 1 0.5 sleep 0.8 & pid=\$!; sleep 0.2; kill -USR1 \$pid; sleep 0.1; kill -USR1 \$pid; wait \$pid) 2>&1 | grep -q '\"extensions\":2,'"
+
+run_test "Forward signals passes SIGUSR1 on" 3 \
+    sh -c "'$TIMEOUT_BIN' --forward-signals 5 sh -c 'trap \"exit 3\" USR1; sleep 3 & wait' & pid=\$!; sleep 0.3; kill -USR1 \$pid; wait \$pid"
+
+run_test "Forward signals conflicts with extend step" 2 \
+    "$TIMEOUT_BIN" --forward-signals --extend-step 1m 1s true
+
+CONTROL_DIR=$(mktemp -d)
+SOCK="$CONTROL_DIR/control.sock"
+
+run_test "Control socket status reports the child" 0 \
+    sh -c "'$TIMEOUT_BIN' --control-socket '$SOCK' 5 sleep 1 & sleep 0.3; '$TIMEOUT_BIN' --control '$SOCK' status | grep -q '\"pid\":[0-9]*,\"elapsed_ms\"'; r=\$?; wait; exit \$r"
+
+run_test "Control socket is owner-only" 0 \
+    sh -c "'$TIMEOUT_BIN' --control-socket '$SOCK' 5 sleep 1 & sleep 0.3; ls -l '$SOCK' | grep -q '^srw-------'; r=\$?; wait; exit \$r"
+
+run_test "Control socket extend" 0 \
+    sh -c "'$TIMEOUT_BIN' --control-socket '$SOCK' 0.5 sleep 1 & pid=\$!; sleep 0.2; '$TIMEOUT_BIN' --control '$SOCK' extend 1 >/dev/null; wait \$pid"
+
+run_test "Control socket expire" 124 \
+    sh -c "'$TIMEOUT_BIN' --control-socket '$SOCK' 5 sleep 3 & pid=\$!; sleep 0.2; '$TIMEOUT_BIN' --control '$SOCK' expire >/dev/null; wait \$pid"
+
+run_test "Control socket kill sends SIGKILL" 137 \
+    sh -c "'$TIMEOUT_BIN' --preserve-status --control-socket '$SOCK' 5 sh -c 'trap \"\" TERM; sleep 3' & pid=\$!; sleep 0.2; '$TIMEOUT_BIN' --control '$SOCK' kill >/dev/null; wait \$pid"
+
+run_test "Control socket is removed on exit" 0 \
+    sh -c "'$TIMEOUT_BIN' --control-socket '$SOCK' 1 true && [ ! -e '$SOCK' ]"
+
+run_test "Control socket leaves nothing else behind" 0 \
+    sh -c "[ \"\$('$TIMEOUT_BIN' --control-socket '$SOCK' 5 ls -A '$CONTROL_DIR')\" = control.sock ] && [ -z \"\$(ls -A '$CONTROL_DIR')\" ]"
+
+run_test "Control socket keeps COMMAND's umask" 0 \
+    sh -c "umask 027; '$TIMEOUT_BIN' --control-socket '$SOCK' 5 sh -c umask | grep -q '027\$'"
+
+run_test "Unknown control request" 1 \
+    sh -c "'$TIMEOUT_BIN' --control-socket '$SOCK' 5 sleep 1 & sleep 0.3; '$TIMEOUT_BIN' --control '$SOCK' bogus; r=\$?; wait; exit \$r"
+
+touch "$SOCK"
+run_test "Existing control socket path is refused" 125 \
+    "$TIMEOUT_BIN" --control-socket "$SOCK" 1s true
+
+run_test "Control socket force replaces it" 0 \
+    "$TIMEOUT_BIN" --control-socket "$SOCK" --control-socket-force 1s true
+
+run_test "Control client without a server" 125 \
+    "$TIMEOUT_BIN" --control "$SOCK" status
+
+rm -rf "$CONTROL_DIR"
+
+echo ""
+echo "=== Output Redirection Tests ==="
+echo ""
+
+REDIR_DIR=$(mktemp -d)
+
+run_test "Stdout and stderr to separate files" 0 \
+    sh -c "'$TIMEOUT_BIN' --stdout '$REDIR_DIR/out' --stderr '$REDIR_DIR/err' 5s sh -c 'echo out; echo err >&2' && [ \"\$(cat '$REDIR_DIR/out')\" = out ] && [ \"\$(cat '$REDIR_DIR/err')\" = err ]"
+
+run_test "Merge output into stdout file" 0 \
+    sh -c "'$TIMEOUT_BIN' --stdout '$REDIR_DIR/merged' --merge-output 5s sh -c 'echo out; echo err >&2' && [ \"\$(cat '$REDIR_DIR/merged' | wc -l)\" -eq 2 ]"
+
+run_test "Stdout append keeps earlier content" 0 \
+    sh -c "'$TIMEOUT_BIN' --stdout '$REDIR_DIR/out' --stdout-append 5s echo again && grep -q '^out\$' '$REDIR_DIR/out' && grep -q '^again\$' '$REDIR_DIR/out'"
+
+run_test "Quiet child prints nothing" 0 \
+    sh -c "[ -z \"\$('$TIMEOUT_BIN' --quiet-child 5s sh -c 'echo out; echo err >&2' 2>&1)\" ]"
+
+run_test "Stdin null does not block a reading command" 1 \
+    sh -c "sleep 10 | '$TIMEOUT_BIN' --stdin null 5s sh -c 'read line'"
+
+run_test "Stdin close makes reads fail" 1 \
+    "$TIMEOUT_BIN" --stdin close 5s cat
+
+run_test "Stdin from file" 0 \
+    sh -c "echo hello > '$REDIR_DIR/in' && [ \"\$('$TIMEOUT_BIN' --stdin '$REDIR_DIR/in' 5s cat)\" = hello ]"
+
+run_test "Missing stdin file" 125 \
+    "$TIMEOUT_BIN" --stdin /nonexistent/input 5s true
+
+run_test "Unwritable redirect file" 125 \
+    "$TIMEOUT_BIN" --stdout /nonexistent/dir/out 5s true
+
+run_test "Redirect conflicts with piping options" 125 \
+    "$TIMEOUT_BIN" --stdout "$REDIR_DIR/out" --timestamps 5s true
+
+rm -rf "$REDIR_DIR"
+
+echo ""
+echo "=== Pseudo-terminal Tests ==="
+echo ""
+
+run_test "Command sees a TTY under --pty" 0 \
+    "$TIMEOUT_BIN" --pty 5s sh -c "test -t 0 && test -t 1 && test -t 2"
+
+run_test "Timeout with --pty" 124 \
+    "$TIMEOUT_BIN" --pty 1s sleep 5
+
+run_test "Idle timeout through --pty" 124 \
+    "$TIMEOUT_BIN" --pty --idle-timeout 1s 10s sh -c "echo once; sleep 5"
+
+run_test "Timestamps through --pty" 0 \
+    sh -c "'$TIMEOUT_BIN' --pty --timestamps 5s echo hi | grep -q '^\[00:00:00\.[0-9]\] hi'"
+
+run_test "Pty conflicts with redirection" 2 \
+    "$TIMEOUT_BIN" --pty --stdout /dev/null 5s true
+
+# These run timeout on a terminal of its own, using util-linux script(1)
+if [ "$(uname)" = Linux ] && command -v script > /dev/null; then
+    TTY_DIR=$(mktemp -d)
+
+    run_test "Terminal resize reaches the pty" 0 \
+        sh -c "sleep 3 | script -qec \"stty rows 30 cols 100; (sleep 0.5; stty rows 40 cols 120 < /dev/tty) & '$TIMEOUT_BIN' --pty 5s sh -c 'sleep 1.5; stty size' | grep -q '40 120'\" /dev/null"
+
+    run_test "Terminal restored after --pty timeout" 0 \
+        sh -c "sleep 3 | script -qec \"stty -a > '$TTY_DIR/before'; '$TIMEOUT_BIN' --pty 1s sleep 5; stty -a | cmp -s - '$TTY_DIR/before'\" /dev/null"
+
+    run_test "Terminal restored after --foreground timeout" 0 \
+        sh -c "sleep 3 | script -qec \"stty -a > '$TTY_DIR/before'; '$TIMEOUT_BIN' -f 1s sh -c 'stty -echo -icanon; sleep 5'; stty -a | cmp -s - '$TTY_DIR/before'\" /dev/null"
+
+    rm -rf "$TTY_DIR"
 fi
 
+echo ""
+echo "=== Command Resolution Tests ==="
+echo ""
+
+RESOLVE_DIR=$(mktemp -d)
+touch "$RESOLVE_DIR/not-executable"
+chmod -x "$RESOLVE_DIR/not-executable"
+
+run_test "Check finds command in PATH" 0 \
+    "$TIMEOUT_BIN" --check 5s sh
+
+run_test "Check reports missing command" 127 \
+    "$TIMEOUT_BIN" --check 5s nonexistent_command_xyz
+
+run_test "Check rejects directory" 126 \
+    "$TIMEOUT_BIN" --check 5s "$RESOLVE_DIR"
+
+run_test "Check rejects non-executable file" 126 \
+    "$TIMEOUT_BIN" --check 5s "$RESOLVE_DIR/not-executable"
+
+run_test "Check validates the duration" 125 \
+    "$TIMEOUT_BIN" --check 5x sh
+
+run_test "Check validates the signal" 125 \
+    "$TIMEOUT_BIN" --check -s NOSUCHSIG 5s sh
+
+run_test "Check validates kill-after" 125 \
+    "$TIMEOUT_BIN" --check -k soon 5s sh
+
+run_test "Check validates the memory limit" 125 \
+    "$TIMEOUT_BIN" --check --mem-limit 12Q 5s sh
+
+run_test "Check does not run the command" 0 \
+    sh -c "'$TIMEOUT_BIN' --check 5s touch '$RESOLVE_DIR/ran' && [ ! -e '$RESOLVE_DIR/ran' ]"
+
+run_test "Check verbose confirms the options" 0 \
+    sh -c "'$TIMEOUT_BIN' --check -v 5s sh 2>&1 | grep -q 'options are valid'"
+
+run_test "Relative path with slash bypasses PATH" 127 \
+    env PATH=/usr/bin:/bin "$TIMEOUT_BIN" --check 5s ./sh
+
+run_test "Non-executable in PATH is permission denied" 126 \
+    env PATH="$RESOLVE_DIR" "$TIMEOUT_BIN" 5s not-executable
+
+run_test "Empty command rejected" 2 \
+    "$TIMEOUT_BIN" 5s " "
+
+rm -rf "$RESOLVE_DIR"
+
+echo ""
+echo "=== Argument File Tests ==="
+echo ""
+
+ARGS_DIR=$(mktemp -d)
+printf '# exit status\n\n-c\nexit 3\n' > "$ARGS_DIR/args"
+printf '@%s\n' "$ARGS_DIR/loop" > "$ARGS_DIR/loop"
+
+run_test "Arguments read from @file" 3 \
+    "$TIMEOUT_BIN" 5s sh "@$ARGS_DIR/args"
+
+run_test "Missing @file is passed on as written" 0 \
+    sh -c "'$TIMEOUT_BIN' 5s echo '@$ARGS_DIR/missing' | grep -qx '@$ARGS_DIR/missing'"
+
+run_test "@scope/pkg argument is passed on as written" 0 \
+    sh -c "'$TIMEOUT_BIN' 5s echo @angular/core | grep -qx @angular/core"
+
+run_test "Missing @file nested in an @file is kept too" 0 \
+    sh -c "printf '@types/node\\n' > '$ARGS_DIR/nested' && '$TIMEOUT_BIN' 5s echo '@$ARGS_DIR/nested' | grep -qx @types/node"
+
+run_test "Unreadable @file is rejected" 125 \
+    "$TIMEOUT_BIN" 5s echo "@$ARGS_DIR"
+
+run_test "Recursive @file is rejected" 125 \
+    "$TIMEOUT_BIN" 5s echo "@$ARGS_DIR/loop"
+
+rm -rf "$ARGS_DIR"
+
+echo ""
+echo "=== Dry Run Tests ==="
+echo ""
+
+run_test "Dry run prints plan" 0 \
+    "$TIMEOUT_BIN" --dry-run -k 10 -s INT 5m sleep 1000
+
+run_test "Dry run as JSON" 0 \
+    "$TIMEOUT_BIN" --dry-run=json 5s sh -c "exit 3"
+
+run_test "--porcelain prints the JSON plan" 0 \
+    sh -c "test \"\$('$TIMEOUT_BIN' --porcelain -k 1 5s true)\" = \"\$('$TIMEOUT_BIN' --dry-run=json -k 1 5s true)\""
+
+run_test "--porcelain conflicts with --dry-run" 2 \
+    "$TIMEOUT_BIN" --porcelain --dry-run 5s true
+
+run_test "Dry run leaves out settings not in effect" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run 5s true | cut -d: -f1 | tr '\n' ' ' | grep -qx 'command argv duration signal extend step backend '"
+
+run_test "Dry run shows sizes in units" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --mem-limit 512M --max-output 1500000 5s true | tr '\n' ' ' | grep -q 'max output:    1500KB .*memory limit:  512MiB'"
+
+run_test "Dry run rejects invalid duration" 125 \
+    "$TIMEOUT_BIN" --dry-run 5x sleep 1
+
+run_test "Dry run rejects missing command" 125 \
+    "$TIMEOUT_BIN" --dry-run 5s nonexistent_command_xyz
+
+echo ""
+echo "=== Batch Mode Tests ==="
+echo ""
+
+run_test "Batch mode returns highest exit code" 1 \
+    sh -c "printf 'true\\0false\\0true\\0' | $TIMEOUT_BIN --batch 5s"
+
+run_test "Batch mode with timeout" 124 \
+    sh -c "printf 'true\\nsleep 10\\n' | $TIMEOUT_BIN --batch-newline 1s"
+
+run_test "Batch mode with empty input" 0 \
+    sh -c ": | $TIMEOUT_BIN --batch 5s"
+
+run_test "Batch mode splits records like sh" 0 \
+    sh -c "printf 'printf \"%%s|\" \"a b\" c\\0' | $TIMEOUT_BIN --batch 5s | grep -qx 'a b|c|'"
+
+run_test "Batch mode rejects an unbalanced quote" 125 \
+    sh -c "printf 'true\\necho \"a b\\n' | $TIMEOUT_BIN --batch-newline 5s"
+
+run_test "Batch mode runs nothing when a record is unbalanced" 1 \
+    sh -c "printf 'echo ran\\necho \"a b\\n' | $TIMEOUT_BIN --batch-newline 5s 2>/dev/null | grep -q ran"
+
+run_test "Parallel copies of a command" 0 \
+    "$TIMEOUT_BIN" --parallel 3 5s sleep 1
+
+run_test "Parallel batch returns highest exit code" 124 \
+    sh -c "printf 'true\\nsleep 10\\nfalse\\n' | $TIMEOUT_BIN --parallel 2 --batch-newline 1s"
+
+echo ""
+echo "=== PID Watchdog Tests ==="
+echo ""
+
+run_test "Watched process is signalled at the deadline" 124 \
+    sh -c "sleep 5 & '$TIMEOUT_BIN' --pid \$! 0.3"
+
+run_test "Watched process really was terminated" 143 \
+    sh -c "sleep 5 & p=\$!; '$TIMEOUT_BIN' --pid \$p 0.3; wait \$p"
+
+run_test "Watched process ending in time exits 0" 0 \
+    sh -c "sleep 0.3 & '$TIMEOUT_BIN' --pid \$! 5"
+
+run_test "Watched process ignoring SIGTERM is killed" 137 \
+    sh -c "sh -c 'trap \"\" TERM; sleep 5' & '$TIMEOUT_BIN' -k 0.3 --pid \$! 0.3"
+
+run_test "Watched process gets the --signal" 7 \
+    sh -c "sh -c 'trap \"exit 7\" HUP; sleep 5 & wait' & p=\$!; '$TIMEOUT_BIN' -s HUP --pid \$p 0.3; wait \$p"
+
+run_test "Missing PID exits 125" 125 \
+    sh -c "true & p=\$!; wait; '$TIMEOUT_BIN' --pid \$p 1"
+
+run_test "PID metrics record the mode" 0 \
+    sh -c "sleep 5 & TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --pid \$! 0.2 2>&1 | grep -q '\"watched_pid\":[0-9][0-9]*,.*\"mode\":\"pid\"'"
+
+run_test "PID conflicts with COMMAND" 2 \
+    "$TIMEOUT_BIN" --pid 12345 1s sleep 1
+
+run_test "PID conflicts with --preserve-status" 2 \
+    "$TIMEOUT_BIN" --pid 12345 --preserve-status 1s
+
+# Only meaningful without root, which may signal anything
+if [ "$(id -u)" != 0 ]; then
+    run_test "PID of another user is refused up front" 125 \
+        "$TIMEOUT_BIN" --pid 1 1s
+fi
+
+echo ""
+echo "=== Webhook Tests ==="
+echo ""
+
+if command -v python3 > /dev/null; then
+    WEBHOOK_DIR=$(mktemp -d)
+    # Accepts one POST, saves its Authorization header and body, and prints
+    # the port it listens on
+    cat > "$WEBHOOK_DIR/server.py" << 'EOF'
+import http.server, sys
+class Handler(http.server.BaseHTTPRequestHandler):
+    def do_POST(self):
+        body = self.rfile.read(int(self.headers['Content-Length'])).decode()
+        with open(sys.argv[1], 'w') as f:
+            f.write(self.headers.get('Authorization', '') + '\n' + body + '\n')
+        self.send_response(204)
+        self.end_headers()
+    def log_message(self, *args):
+        pass
+server = http.server.HTTPServer(('127.0.0.1', 0), Handler)
+server.timeout = 10
+print(server.server_address[1], flush=True)
+server.handle_request()
+EOF
+    webhook_server() {
+        rm -f "$WEBHOOK_DIR/request" "$WEBHOOK_DIR/port"
+        python3 "$WEBHOOK_DIR/server.py" "$WEBHOOK_DIR/request" > "$WEBHOOK_DIR/port" &
+        WEBHOOK_PID=$!
+        while [ ! -s "$WEBHOOK_DIR/port" ]; do sleep 0.05; done
+        WEBHOOK_URL="http://127.0.0.1:$(cat "$WEBHOOK_DIR/port")/hook"
+    }
+
+    webhook_server
+    run_test "Webhook posts the run report" 124 \
+        "$TIMEOUT_BIN" --webhook "$WEBHOOK_URL" --webhook-header 'Authorization: Bearer t0k' 0.2 sleep 1
+    wait "$WEBHOOK_PID"
+    run_test "Webhook report has the metrics, argv and header" 0 \
+        sh -c "grep -qx 'Bearer t0k' '$WEBHOOK_DIR/request' && grep -q '\"argv\":\[\"sleep\",\"1\"\],.*\"timed_out\":true,.*\"exit_code\":124,.*\"started_at\":\"[0-9T:.-]*Z\",.*\"hostname\":\".*\",\"event\":\"timeout\",\"error\":null,\"ended_at\":\"[0-9T:.-]*Z\"}' '$WEBHOOK_DIR/request'"
+
+    webhook_server
+    run_test "Webhook reports a command that cannot be found" 127 \
+        "$TIMEOUT_BIN" --webhook "$WEBHOOK_URL" 1s nonexistent_command_xyz
+    wait "$WEBHOOK_PID"
+    run_test "Webhook report for a missing command has the error" 0 \
+        grep -q '"exit_code":127,.*"event":"failure","error":"command not found' "$WEBHOOK_DIR/request"
+
+    rm -rf "$WEBHOOK_DIR"
+fi
+
+run_test "Webhook-on skips other outcomes" 0 \
+    sh -c "! '$TIMEOUT_BIN' --webhook http://127.0.0.1:1/ --webhook-on timeout,failure 1s true 2>&1 | grep -q 'delivery'"
+
+run_test "Failed webhook delivery keeps the exit code" 3 \
+    "$TIMEOUT_BIN" --webhook http://127.0.0.1:1/ 1s sh -c 'exit 3'
+
+run_test "Failed webhook delivery is reported" 0 \
+    sh -c "'$TIMEOUT_BIN' --webhook http://127.0.0.1:1/ 1s true 2>&1 | grep -q 'webhook delivery to http://127.0.0.1:1/ failed: .*(after 2 attempts)'"
+
+run_test "Webhook header must be 'Name: value'" 125 \
+    "$TIMEOUT_BIN" --webhook http://127.0.0.1:1/ --webhook-header 'no colon' 1s true
+
+run_test "Webhook URL must be http" 125 \
+    "$TIMEOUT_BIN" --webhook ftp://example.com/ 1s true
+
+run_test "Webhook-on requires --webhook" 2 \
+    "$TIMEOUT_BIN" --webhook-on timeout 1s true
+
+run_test "Dry run shows the webhook without header values" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --webhook http://127.0.0.1:1/ --webhook-on timeout --webhook-header 'Authorization: secret' 1s true | grep -qx 'webhook: *http://127.0.0.1:1/ on timeout, with headers Authorization'"
+
+echo ""
+echo "=== sd_notify Tests ==="
+echo ""
+
+if [ "$(uname)" = Linux ] && command -v python3 > /dev/null; then
+    SD_DIR=$(mktemp -d)
+    # Stands in for systemd: collects datagrams on the socket until none
+    # arrive for a second. A leading '@' binds in the abstract namespace.
+    cat > "$SD_DIR/notify.py" << 'EOF'
+import socket, sys
+path = sys.argv[1]
+sock = socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM)
+sock.bind('\0' + path[1:] if path.startswith('@') else path)
+sock.settimeout(1)
+print('ready', flush=True)
+with open(sys.argv[2], 'w') as f:
+    try:
+        while True:
+            f.write(sock.recv(4096).decode() + '\n')
+    except socket.timeout:
+        pass
+EOF
+    notify_socket() {
+        rm -f "$SD_DIR/sock" "$SD_DIR/messages" "$SD_DIR/ready"
+        python3 "$SD_DIR/notify.py" "$1" "$SD_DIR/messages" > "$SD_DIR/ready" &
+        NOTIFY_PID=$!
+        while [ ! -s "$SD_DIR/ready" ]; do sleep 0.05; done
+    }
+
+    notify_socket "$SD_DIR/sock"
+    run_test "sd-notify proxy times out as usual" 124 \
+        env NOTIFY_SOCKET="$SD_DIR/sock" WATCHDOG_USEC=200000 \
+        "$TIMEOUT_BIN" --sd-notify proxy 0.5 sleep 2
+    wait "$NOTIFY_PID"
+    run_test "sd-notify proxy sends READY, WATCHDOG and STOPPING" 0 \
+        sh -c "grep -qx 'READY=1' '$SD_DIR/messages' && grep -qx 'MAINPID=[0-9]*' '$SD_DIR/messages' && [ \$(grep -cx 'WATCHDOG=1' '$SD_DIR/messages') -ge 2 ] && tail -n 1 '$SD_DIR/messages' | grep -qx 'STOPPING=1'"
+
+    notify_socket "$SD_DIR/sock"
+    run_test "sd-notify proxy hides the socket from COMMAND" 0 \
+        env NOTIFY_SOCKET="$SD_DIR/sock" WATCHDOG_USEC=200000 \
+        "$TIMEOUT_BIN" --sd-notify proxy 1s sh -c 'test -z "$NOTIFY_SOCKET$WATCHDOG_USEC"'
+    wait "$NOTIFY_PID"
+    run_test "sd-notify proxy sends no STOPPING when COMMAND finishes" 0 \
+        sh -c "grep -qx 'READY=1' '$SD_DIR/messages' && ! grep -q 'STOPPING' '$SD_DIR/messages'"
+
+    notify_socket "@timeout-test-$$"
+    run_test "sd-notify proxy to an abstract socket" 0 \
+        env NOTIFY_SOCKET="@timeout-test-$$" "$TIMEOUT_BIN" --sd-notify proxy 1s true
+    wait "$NOTIFY_PID"
+    run_test "sd-notify abstract socket got READY" 0 \
+        grep -qx 'READY=1' "$SD_DIR/messages"
+
+    notify_socket "$SD_DIR/sock"
+    run_test "sd-notify passthrough leaves the socket to COMMAND" 0 \
+        env NOTIFY_SOCKET="$SD_DIR/sock" "$TIMEOUT_BIN" --sd-notify passthrough 1s \
+        sh -c 'test "$NOTIFY_SOCKET" = "'"$SD_DIR/sock"'" && python3 -c "import socket, os; socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM).sendto(b\"READY=1\", os.environ[\"NOTIFY_SOCKET\"])"'
+    wait "$NOTIFY_PID"
+    run_test "sd-notify passthrough sends nothing itself" 0 \
+        sh -c "[ \"\$(cat '$SD_DIR/messages')\" = 'READY=1' ]"
+
+    rm -rf "$SD_DIR"
+fi
+
+run_test "sd-notify without NOTIFY_SOCKET is a no-op" 0 \
+    sh -c "env -u NOTIFY_SOCKET '$TIMEOUT_BIN' -v --sd-notify proxy 1s true 2>&1 | grep -q 'NOTIFY_SOCKET is not set'"
+
+run_test "sd-notify rejects unknown modes" 2 \
+    "$TIMEOUT_BIN" --sd-notify always 1s true
+
+run_test "Dry run shows the sd-notify mode" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --sd-notify proxy 1s true | grep -q '\"sd_notify\":\"proxy\"'"
+
+echo ""
+echo "=== Journald Tests ==="
+echo ""
+
+if [ "$(uname)" = Linux ] && command -v python3 > /dev/null; then
+    JOURNAL_DIR=$(mktemp -d)
+    # Stands in for journald: saves each entry, reading it from the memfd
+    # when one is passed, until none arrive for a second
+    cat > "$JOURNAL_DIR/journal.py" << 'EOF'
+import os, socket, sys
+sock = socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM)
+sock.bind(sys.argv[1])
+sock.settimeout(1)
+print('ready', flush=True)
+with open(sys.argv[2], 'wb') as f:
+    try:
+        while True:
+            entry, fds, _, _ = socket.recv_fds(sock, 1 << 16, 1)
+            for fd in fds:
+                entry = b'MEMFD=1\n' + os.pread(fd, os.fstat(fd).st_size, 0)
+            f.write(entry + b'\n')
+    except socket.timeout:
+        pass
+EOF
+    journal() {
+        rm -f "$JOURNAL_DIR/sock" "$JOURNAL_DIR/entries" "$JOURNAL_DIR/ready"
+        python3 "$JOURNAL_DIR/journal.py" "$JOURNAL_DIR/sock" "$JOURNAL_DIR/entries" > "$JOURNAL_DIR/ready" &
+        JOURNAL_PID=$!
+        while [ ! -s "$JOURNAL_DIR/ready" ]; do sleep 0.05; done
+    }
+
+    journal
+    run_test "Log to journald keeps the exit code" 124 \
+        env TIMEOUT_JOURNAL_SOCKET="$JOURNAL_DIR/sock" "$TIMEOUT_BIN" --log journald 0.2 sleep 1
+    wait "$JOURNAL_PID"
+    run_test "Journal entry has the outcome fields" 0 \
+        sh -c "grep -qx 'MESSAGE=Command .sleep. timed out after .*exit code 124' '$JOURNAL_DIR/entries' && grep -qx 'PRIORITY=4' '$JOURNAL_DIR/entries' && grep -qx 'SYSLOG_IDENTIFIER=timeout' '$JOURNAL_DIR/entries' && grep -qx 'TIMEOUT_COMMAND=sleep' '$JOURNAL_DIR/entries' && grep -qx 'TIMEOUT_TIMED_OUT=true' '$JOURNAL_DIR/entries' && grep -qx 'TIMEOUT_EXIT_CODE=124' '$JOURNAL_DIR/entries' && grep -qx 'TIMEOUT_SIGNAL=SIGTERM' '$JOURNAL_DIR/entries' && grep -qx 'TIMEOUT_ELAPSED_MS=[0-9]*' '$JOURNAL_DIR/entries'"
+
+    journal
+    run_test "Quiet leaves journaled errors off stderr" 0 \
+        sh -c "out=\$(env TIMEOUT_JOURNAL_SOCKET='$JOURNAL_DIR/sock' '$TIMEOUT_BIN' --log journald --quiet 1s nonexistent_command_xyz 2>&1); test \$? = 127 && test -z \"\$out\""
+    wait "$JOURNAL_PID"
+    run_test "Journal entry for a missing command is an error" 0 \
+        sh -c "grep -qx 'PRIORITY=3' '$JOURNAL_DIR/entries' && grep -q '^TIMEOUT_ERROR=command not found' '$JOURNAL_DIR/entries'"
+
+    journal
+    BIG=$(head -c 100000 /dev/zero | tr '\0' x)
+    run_test "Journal entries too big for a datagram go by memfd" 3 \
+        env TIMEOUT_JOURNAL_SOCKET="$JOURNAL_DIR/sock" "$TIMEOUT_BIN" --log journald 1s sh -c 'exit 3' "$BIG" "$BIG" "$BIG"
+    wait "$JOURNAL_PID"
+    run_test "Memfd journal entry arrives whole" 0 \
+        sh -c "grep -qx 'MEMFD=1' '$JOURNAL_DIR/entries' && grep -qx 'TIMEOUT_EXIT_CODE=3' '$JOURNAL_DIR/entries' && [ \$(grep '^TIMEOUT_ARGV=' '$JOURNAL_DIR/entries' | wc -c) -gt 300000 ]"
+
+    rm -rf "$JOURNAL_DIR"
+fi
+
+run_test "Errors stay on stderr with --log stderr" 0 \
+    sh -c "'$TIMEOUT_BIN' --log stderr 1s nonexistent_command_xyz 2>&1 | grep -q 'command not found'"
+
+run_test "No journal socket warns and falls back to stderr" 0 \
+    sh -c "TIMEOUT_JOURNAL_SOCKET=/nonexistent/socket '$TIMEOUT_BIN' --log journald 1s nonexistent_command_xyz 2>&1 | grep -c 'logging to stderr instead\|command not found' | grep -qx 2"
+
+run_test "Dry run shows the log target" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --log journald 1s true | grep -qx 'log: *journald'"
+
+echo ""
+echo "=== Syslog Tests ==="
+echo ""
+
+if command -v python3 > /dev/null; then
+    SYSLOG_DIR=$(mktemp -d)
+    # Stands in for a syslog server on a unix, UDP or TCP socket: prints its
+    # port once listening, then saves the first message it gets
+    cat > "$SYSLOG_DIR/syslog.py" << 'EOF'
+import socket, sys
+kind, out = sys.argv[1], sys.argv[2]
+if kind == 'unix':
+    sock = socket.socket(socket.AF_UNIX, socket.SOCK_DGRAM)
+    sock.bind(sys.argv[3])
+elif kind == 'udp':
+    sock = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+    sock.bind(('127.0.0.1', 0))
+else:
+    sock = socket.socket(socket.AF_INET, socket.SOCK_STREAM)
+    sock.bind(('127.0.0.1', 0))
+    sock.listen(1)
+sock.settimeout(10)
+print(sock.getsockname()[1] if kind != 'unix' else 'ready', flush=True)
+if kind == 'tcp':
+    conn, _ = sock.accept()
+    message = b''
+    while chunk := conn.recv(1 << 16):
+        message += chunk
+else:
+    message = sock.recv(1 << 16)
+with open(out, 'wb') as f:
+    f.write(message + b'\n')
+EOF
+    syslog_server() {
+        rm -f "$SYSLOG_DIR/sock" "$SYSLOG_DIR/message" "$SYSLOG_DIR/ready"
+        python3 "$SYSLOG_DIR/syslog.py" "$1" "$SYSLOG_DIR/message" "$SYSLOG_DIR/sock" > "$SYSLOG_DIR/ready" &
+        SYSLOG_PID=$!
+        while [ ! -s "$SYSLOG_DIR/ready" ]; do sleep 0.05; done
+        SYSLOG_PORT=$(cat "$SYSLOG_DIR/ready")
+    }
+
+    syslog_server unix
+    run_test "Log to syslog keeps the exit code" 124 \
+        env TIMEOUT_SYSLOG_SOCKET="$SYSLOG_DIR/sock" "$TIMEOUT_BIN" --log syslog 0.2 sleep 1
+    wait "$SYSLOG_PID"
+    run_test "Syslog message is RFC 5424 with the outcome fields" 0 \
+        grep -qE '^<12>1 [0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9:.]+Z [^ ]+ timeout [0-9]+ outcome \[timeout@32473 command="sleep" argv="sleep 1" exit_code="124" timed_out="true" elapsed_ms="[0-9]+" signal="SIGTERM" attempt="1" reason="duration"\] Command .sleep. timed out after' "$SYSLOG_DIR/message"
+
+    syslog_server unix
+    run_test "Quiet leaves syslogged errors off stderr" 0 \
+        sh -c "out=\$(env TIMEOUT_SYSLOG_SOCKET='$SYSLOG_DIR/sock' '$TIMEOUT_BIN' --log syslog:local3 --quiet 1s nonexistent_command_xyz 2>&1); test \$? = 127 && test -z \"\$out\""
+    wait "$SYSLOG_PID"
+    run_test "Syslog message for a missing command is err in the facility" 0 \
+        sh -c "grep -q '^<155>1 .* error=\"command not found' '$SYSLOG_DIR/message'"
+
+    syslog_server udp
+    run_test "Log to syslog over UDP" 3 \
+        "$TIMEOUT_BIN" --log "syslog://127.0.0.1:$SYSLOG_PORT/daemon" --syslog-format rfc3164 1s sh -c 'exit 3'
+    wait "$SYSLOG_PID"
+    run_test "RFC 3164 message carries the outcome as JSON" 0 \
+        grep -qE '^<29>[A-Z][a-z]{2} [ 0-9][0-9] [0-9]{2}:[0-9]{2}:[0-9]{2} [^ ]+ timeout\[[0-9]+\]: \{"schema_version":2,"command":"sh",.*"exit_code":3,.*"event":"failure"' "$SYSLOG_DIR/message"
+
+    syslog_server tcp
+    run_test "Log to syslog over TCP" 0 \
+        "$TIMEOUT_BIN" --log "syslog+tcp://127.0.0.1:$SYSLOG_PORT" 1s true
+    wait "$SYSLOG_PID"
+    run_test "TCP syslog messages are octet-counted" 0 \
+        sh -c "line=\$(head -n 1 '$SYSLOG_DIR/message'); len=\${line%% *}; msg=\${line#* }; test \"\$len\" = \"\${#msg}\" && case \"\$msg\" in '<14>1 '*'exit_code=\"0\"'*) true;; *) false;; esac"
+
+    rm -rf "$SYSLOG_DIR"
+fi
+
+run_test "Unknown syslog facility is rejected" 125 \
+    "$TIMEOUT_BIN" --log syslog:bogus 1s true
+
+run_test "Syslog target without a host is rejected" 125 \
+    "$TIMEOUT_BIN" --log syslog://:514 1s true
+
+run_test "Unreachable syslog server warns and keeps the exit code" 0 \
+    sh -c "'$TIMEOUT_BIN' --log syslog+tcp://127.0.0.1:1 1s sh -c 'exit 3' 2>&1 | grep -q 'cannot send'; '$TIMEOUT_BIN' --log syslog+tcp://127.0.0.1:1 1s sh -c 'exit 3' 2>/dev/null; test \$? = 3"
+
+run_test "Dry run shows syslog targets and format" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --log 'syslog://[::1]/local7' --syslog-format rfc3164 1s true | tr '\n' ' ' | grep -q 'log: *syslog://\[::1\]:514/local7 syslog format: rfc3164'"
+
+echo ""
+echo "=== Metrics Output Tests ==="
+echo ""
+
+METRICS_DIR=$(mktemp -d)
+run_test "Metrics file gets one record per run" 0 \
+    sh -c "'$TIMEOUT_BIN' --metrics-file '$METRICS_DIR/a.jsonl' 1s true && '$TIMEOUT_BIN' --metrics-file '$METRICS_DIR/a.jsonl' 1s sh -c 'exit 3'; [ \$(grep -c '^{\"schema_version\":2,' '$METRICS_DIR/a.jsonl') = 2 ]"
+
+run_test "Metrics file keeps stderr clean even with TIMEOUT_METRICS" 0 \
+    sh -c "out=\$(TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --metrics-file '$METRICS_DIR/b.jsonl' 1s true 2>&1); test -z \"\$out\" && grep -q '\"command\":\"true\"' '$METRICS_DIR/b.jsonl'"
+
+run_test "TIMEOUT_METRICS_FILE names a metrics file" 0 \
+    sh -c "TIMEOUT_METRICS_FILE='$METRICS_DIR/c.jsonl' '$TIMEOUT_BIN' 1s true && grep -q '\"command\":\"true\"' '$METRICS_DIR/c.jsonl'"
+
+run_test "Metrics flag takes precedence over TIMEOUT_METRICS_FILE" 0 \
+    sh -c "TIMEOUT_METRICS_FILE='$METRICS_DIR/env.jsonl' '$TIMEOUT_BIN' --metrics-file '$METRICS_DIR/flag.jsonl' 1s true && test -s '$METRICS_DIR/flag.jsonl' && ! test -e '$METRICS_DIR/env.jsonl'"
+
+run_test "Metrics go to an inherited fd" 0 \
+    sh -c "out=\$(TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --metrics-fd 3 1s true 2>&1 3>'$METRICS_DIR/fd.jsonl'); test -z \"\$out\" && grep -q '\"command\":\"true\"' '$METRICS_DIR/fd.jsonl'"
+
+run_test "Metrics fd that was not inherited fails fast" 125 \
+    "$TIMEOUT_BIN" --metrics-fd 7 1s true
+
+run_test "Unwritable metrics file fails before COMMAND runs" 0 \
+    sh -c "'$TIMEOUT_BIN' --metrics-file /nonexistent/dir/m.jsonl 1s touch '$METRICS_DIR/ran'; test \$? = 125 && ! test -e '$METRICS_DIR/ran'"
+
+run_test "Concurrent runs append whole records" 0 \
+    sh -c "for i in 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20; do '$TIMEOUT_BIN' --metrics-file '$METRICS_DIR/many.jsonl' 1s echo \$i > /dev/null & done; wait; [ \$(grep -c '^{\"schema_version\":2,.*\"mode\":\"command\"}$' '$METRICS_DIR/many.jsonl') = 20 ]"
+
+run_test "Metrics file and fd conflict" 2 \
+    "$TIMEOUT_BIN" --metrics-file "$METRICS_DIR/x" --metrics-fd 3 1s true
+
+run_test "Dry run shows the metrics target" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --metrics-file m.jsonl 1s true | grep -qx 'metrics: *file m.jsonl'"
+run_test "Metrics as logfmt on stderr" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --metrics-format logfmt 1s echo 'a b' 2>&1 >/dev/null | grep -q '^schema_version=2 command=echo argv=\"\\[.*a b.*\\]\" .* mode=command$'"
+
+run_test "Metrics as csv on an fd get one header" 0 \
+    sh -c "{ '$TIMEOUT_BIN' --metrics-format csv --metrics-fd 3 1s true; '$TIMEOUT_BIN' --metrics-format csv --metrics-fd 3 1s true; } 3>'$METRICS_DIR/fd.csv'; [ \$(grep -c '^schema_version,' '$METRICS_DIR/fd.csv') = 1 ] && [ \$(grep -c '^2,true,' '$METRICS_DIR/fd.csv') = 2 ]"
+
+run_test "Verbose notes where and how metrics were written" 0 \
+    sh -c "'$TIMEOUT_BIN' -v --metrics-format logfmt --metrics-file '$METRICS_DIR/v.log' 1s true 2>&1 | grep -q 'metrics written to file $METRICS_DIR/v.log as logfmt'"
+
+run_test "Unknown metrics format rejected" 2 \
+    "$TIMEOUT_BIN" --metrics-format xml 1s true
+
+run_test "Dry run shows the metrics format" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --metrics-file m.csv --metrics-format csv 1s true | grep -qx 'metrics format: csv'"
+
+run_test "Metrics report peak RSS in bytes" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' 5s sh -c 'x=\$(head -c 20000000 /dev/zero | tr \"\\\\0\" a); : \"\$x\"' 2>&1 | grep -Eq '\"max_rss_bytes\":[0-9]{8,9},'"
+
+run_test "Metrics report CPU time of a busy command" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' 0.5 sh -c 'while :; do :; done' 2>&1 | grep -Eq '\"user_cpu_ms\":[1-9][0-9]+,\"sys_cpu_ms\":[0-9]+,\"minor_faults\":[0-9]+'"
+
+run_test "Verbose prints resource usage" 0 \
+    sh -c "'$TIMEOUT_BIN' -v 1s true 2>&1 | grep -Eq 'Resources.*: peak rss [0-9.]+ (KiB|MiB), cpu [0-9]+\\.[0-9]s user / [0-9]+\\.[0-9]s sys\\.'"
+
+run_test "Resource usage is null for a process left running" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --no-kill 0.2 sleep 2 2>&1 | grep -q '\"max_rss_bytes\":null,\"user_cpu_ms\":null'"
+
+run_test "Metrics record the signals sent" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' 0.2 sleep 5 2>&1 | grep -Eq '\"signals_sent\":\\[\\{\"signal\":\"SIGTERM\",\"offset_ms\":[0-9]+\\},\\{\"signal\":\"SIGCONT\",\"offset_ms\":[0-9]+\\}\\],\"kill_signal_sent\":false'"
+
+run_test "Metrics record escalation to SIGKILL" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' -k 0.2 0.2 sh -c 'trap \"\" TERM; sleep 5' 2>&1 | grep -q '\"signal\":\"SIGKILL\",\"offset_ms\":[0-9]*}\\],\"kill_signal_sent\":true'"
+
+run_test "Metrics record shutdown latency" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' 0.2 sh -c 'trap \"sleep 0.5; kill \$!; exit 0\" TERM; sleep 5 & wait' 2>&1 | grep -Eq '\"shutdown_latency_ms\":(4[5-9][0-9]|[5-9][0-9][0-9]|[0-9]{4}),'"
+
+run_test "No shutdown latency without a signal" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' 5s true 2>&1 | grep -Eq '\"signals_sent\":\\[\\],\"kill_signal_sent\":false,\"term_signal_sent_at_ms\":null,\"child_exited_at_ms\":[0-9]+,\"shutdown_latency_ms\":null'"
+
+run_test "Verbose prints shutdown latency" 0 \
+    sh -c "'$TIMEOUT_BIN' -v 0.2 sh -c 'trap \"sleep 0.5; kill \$!; exit 0\" TERM; sleep 5 & wait' 2>&1 | grep -Eq 'Shutdown.*: child exited [0-9]\\.[0-9]s after SIGTERM\\.'"
+rm -rf "$METRICS_DIR"
+
+echo ""
+echo "=== Summary Line Tests ==="
+echo ""
+
+SUMMARY_DIR=$(mktemp -d)
+run_test "Summary line of a timed-out run" 0 \
+    sh -c "'$TIMEOUT_BIN' --summary-format '[{outcome}] {command} exit={exit_code} {signal}' 0.2 sleep 5 2>&1 | grep -qx '\\[timeout\\] sleep exit=124 SIGTERM'"
+
+run_test "Summary line keeps the exit status" 3 \
+    "$TIMEOUT_BIN" --summary-format "{outcome}" 1s sh -c "exit 3"
+
+run_test "Summary line of a successful run" 0 \
+    sh -c "'$TIMEOUT_BIN' --summary-format '{outcome} {argv} limit={duration}' 90s echo hi 2>&1 >/dev/null | grep -qx 'ok echo hi limit=1m 30s'"
+
+run_test "Summary line when COMMAND cannot run" 0 \
+    sh -c "'$TIMEOUT_BIN' --summary-format '{outcome} {exit_code} {pid}' 1s /nonexistent/command 2>&1 | grep -qx 'exec-error 127 -'"
+
+# Background jobs of sh ignore SIGINT, so COMMAND outlives it here; the
+# line still says timeout passed one on
+run_test "Summary line after Ctrl-C" 0 \
+    sh -c "'$TIMEOUT_BIN' --summary-format '{outcome} {signal}' 10s sleep 1 2>'$SUMMARY_DIR/int' & pid=\$!; sleep 0.3; kill -INT \$pid; wait \$pid; [ \"\$(cat '$SUMMARY_DIR/int')\" = 'signal SIGINT' ]"
+
+run_test "Summary line printed once per attempt's run" 0 \
+    sh -c "[ \$('$TIMEOUT_BIN' --summary-format '{attempt}' --retries 2 0.1 sleep 1 2>&1 | wc -l) = 1 ]"
+
+run_test "Summary braces can be escaped" 0 \
+    sh -c "'$TIMEOUT_BIN' --summary-format '{{{exit_code}}}' 1s true 2>&1 | grep -qx '{0}'"
+
+run_test "Unknown summary placeholder fails at startup" 125 \
+    "$TIMEOUT_BIN" --summary-format "{outcome} {elapsd}" 1s true
+
+run_test "Unmatched summary brace fails at startup" 125 \
+    "$TIMEOUT_BIN" --summary-format "exit }" 1s true
+
+run_test "Summary output appends to a file" 0 \
+    sh -c "'$TIMEOUT_BIN' --summary-output '$SUMMARY_DIR/s' --summary-format '{exit_code}' 1s true && '$TIMEOUT_BIN' --summary-output '$SUMMARY_DIR/s' --summary-format '{exit_code}' 1s false; [ \"\$(cat '$SUMMARY_DIR/s' | tr '\n' ' ')\" = '0 1 ' ]"
+
+run_test "Summary output needs a format" 2 \
+    "$TIMEOUT_BIN" --summary-output "$SUMMARY_DIR/x" 1s true
+rm -rf "$SUMMARY_DIR"
+
+echo ""
+echo "=== Status FD Tests ==="
+echo ""
+
+STATUS_DIR=$(mktemp -d)
+run_test "Status fd of a timed-out run" 0 \
+    sh -c "'$TIMEOUT_BIN' --status-fd 3 0.2 sleep 5 3>'$STATUS_DIR/r.json'; grep -Eqx '\\{\"timed_out\":true,\"exit_code\":124,\"signal\":\"SIGTERM\",\"elapsed_ms\":[0-9]+,\"child_pid\":[0-9]+\\}' '$STATUS_DIR/r.json'"
+
+run_test "Status fd keeps the exit status" 3 \
+    sh -c "'$TIMEOUT_BIN' --status-fd 3 1s sh -c 'exit 3' 3>'$STATUS_DIR/r.json'"
+
+run_test "Status fd of a successful run" 0 \
+    sh -c "'$TIMEOUT_BIN' --status-fd 3 1s true 3>'$STATUS_DIR/r.json'; grep -Eqx '\\{\"timed_out\":false,\"exit_code\":0,\"signal\":null,\"elapsed_ms\":[0-9]+,\"child_pid\":[0-9]+\\}' '$STATUS_DIR/r.json'"
+
+run_test "Status fd when COMMAND is not found" 0 \
+    sh -c "'$TIMEOUT_BIN' --status-fd 3 1s /nonexistent/command 2>/dev/null 3>'$STATUS_DIR/r.json'; grep -qx '{\"timed_out\":false,\"exit_code\":127,\"signal\":null,\"elapsed_ms\":null,\"child_pid\":null}' '$STATUS_DIR/r.json'"
+
+run_test "Status fd when COMMAND cannot be run" 0 \
+    sh -c "touch '$STATUS_DIR/noexec'; chmod -x '$STATUS_DIR/noexec'; '$TIMEOUT_BIN' --status-fd 3 1s '$STATUS_DIR/noexec' 2>/dev/null 3>'$STATUS_DIR/r.json'; grep -q '\"exit_code\":126' '$STATUS_DIR/r.json'"
+
+# Background jobs of sh ignore SIGINT, so COMMAND outlives it here
+run_test "Status fd after Ctrl-C" 0 \
+    sh -c "'$TIMEOUT_BIN' --status-fd 3 10s sleep 1 3>'$STATUS_DIR/int' & pid=\$!; sleep 0.3; kill -INT \$pid; wait \$pid; grep -q '\"signal\":\"SIGINT\"' '$STATUS_DIR/int'"
+
+run_test "Status fd stays off stdout and stderr" 0 \
+    sh -c "[ -z \"\$('$TIMEOUT_BIN' --status-fd 3 1s true 2>&1 3>/dev/null)\" ]"
+
+run_test "Status fd must be open" 125 \
+    "$TIMEOUT_BIN" --status-fd 7 1s true
+
+run_test "Status fd must be writable" 125 \
+    sh -c "'$TIMEOUT_BIN' --status-fd 3 1s true 3</dev/null"
+
+run_test "Dry run shows the status fd" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --status-fd 3 1s true | grep -qx 'status fd:     3'"
+rm -rf "$STATUS_DIR"
+
+echo ""
+echo "=== PID File Tests ==="
+echo ""
+
+PIDFILE_DIR=$(mktemp -d)
+run_test "PID file holds COMMAND's PID and process group" 0 \
+    sh -c "'$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/a.pid' 5s sh -c 'sleep 0.3; [ \"\$(head -n 1 \"$PIDFILE_DIR/a.pid\")\" = \$\$ ] && [ \$(wc -l < \"$PIDFILE_DIR/a.pid\") = 2 ]'"
+
+run_test "PID file removed after a normal exit" 0 \
+    sh -c "'$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/b.pid' 5s true && [ ! -e '$PIDFILE_DIR/b.pid' ]"
+
+run_test "PID file removed after a timeout" 0 \
+    sh -c "'$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/c.pid' 0.2 sleep 5; [ \$? = 124 ] && [ ! -e '$PIDFILE_DIR/c.pid' ]"
+
+run_test "PID file removed when timeout is sent SIGTERM" 0 \
+    sh -c "'$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/d.pid' 10s sleep 5 & pid=\$!; sleep 0.3; [ -s '$PIDFILE_DIR/d.pid' ] && kill -TERM \$pid; wait \$pid; [ ! -e '$PIDFILE_DIR/d.pid' ]"
+
+run_test "PID file removed when COMMAND is not found" 0 \
+    sh -c "'$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/e.pid' 5s /nonexistent/command 2>/dev/null; [ \$? = 127 ] && [ ! -e '$PIDFILE_DIR/e.pid' ]"
+
+run_test "Stale PID file is refused" 125 \
+    sh -c "echo 1 > '$PIDFILE_DIR/f.pid'; '$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/f.pid' 5s true"
+
+run_test "Stale PID file is left alone" 0 \
+    sh -c "echo 1 > '$PIDFILE_DIR/g.pid'; '$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/g.pid' 5s true 2>/dev/null; [ \"\$(cat '$PIDFILE_DIR/g.pid')\" = 1 ]"
+
+run_test "PID file force overwrites a stale file" 0 \
+    sh -c "echo 1 > '$PIDFILE_DIR/h.pid'; '$TIMEOUT_BIN' --pid-file '$PIDFILE_DIR/h.pid' --pid-file-force 5s sh -c 'sleep 0.3; [ \"\$(head -n 1 \"$PIDFILE_DIR/h.pid\")\" = \$\$ ]'"
+
+run_test "Unwritable PID file fails before COMMAND runs" 125 \
+    "$TIMEOUT_BIN" --pid-file "$PIDFILE_DIR/missing/dir/x.pid" 5s true
+
+run_test "PID file force needs a PID file" 2 \
+    "$TIMEOUT_BIN" --pid-file-force 5s true
+rm -rf "$PIDFILE_DIR"
+
+echo ""
+echo "=== Simulate Timeout Tests ==="
+echo ""
+
+run_test "Simulated timeout does not wait for the duration" 0 \
+    sh -c "start=\$(date +%s); '$TIMEOUT_BIN' --simulate-timeout 1h sleep 10; [ \$? = 124 ] && [ \$((\$(date +%s) - start)) -lt 5 ]"
+
+run_test "Simulated timeout honours --status" 7 \
+    "$TIMEOUT_BIN" --simulate-timeout --status 7 1h sleep 10
+
+run_test "Simulated timeout honours --preserve-status" 143 \
+    "$TIMEOUT_BIN" --simulate-timeout --preserve-status 1h sleep 10
+
+run_test "Simulated timeout runs the --on-timeout hook" 0 \
+    sh -c "'$TIMEOUT_BIN' --simulate-timeout --on-timeout 'echo hook ran' 1h sleep 10 | grep -qx 'hook ran'"
+
+run_test "Simulated timeout escalates with --kill-after" 137 \
+    "$TIMEOUT_BIN" --simulate-timeout -s CONT -k 0.3 1h sleep 5
+
+run_test "Simulated timeout reports the requested limit" 0 \
+    sh -c "TIMEOUT_METRICS=1 '$TIMEOUT_BIN' --simulate-timeout 1h sleep 10 2>&1 | grep -q '\"duration_ms\":3600000'"
+
+run_test "Simulated timeout in the dry run" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --simulate-timeout -k 5 1h sleep 10 | grep -qx 'simulated:     send SIGTERM, then send SIGKILL after 5000 ms if COMMAND is still running, then exit 124'"
+
+run_test "Simulated timeout in the JSON dry run" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run=json --simulate-timeout --no-kill 1h sleep 10 | grep -q '\"simulated_steps\":\[\"leave COMMAND running\",\"exit 124\"\]'"
+
+run_test "Simulated timeout cannot be used with --pid" 2 \
+    "$TIMEOUT_BIN" --simulate-timeout --pid $$ 1h
+
+echo ""
+echo "=== Boot Time Tests ==="
+echo ""
+
+if [ "$(uname)" = Linux ]; then
+    run_test "Boot time clock times out" 124 \
+        "$TIMEOUT_BIN" --boottime 0.3 sleep 5
+
+    run_test "Boot time clock lets a quick command finish" 0 \
+        "$TIMEOUT_BIN" --boottime 5s sleep 0.1
+
+    run_test "Boot time deadline is a CLOCK_BOOTTIME timerfd" 0 \
+        sh -c "'$TIMEOUT_BIN' --boottime 5s sleep 2 & pid=\$!; sleep 0.5; found=1; for fd in /proc/\$pid/fdinfo/*; do grep -q '^clockid:[[:space:]]*7\$' \$fd 2>/dev/null && found=0; done; kill \$pid; wait \$pid; exit \$found"
+
+    run_test "Boot time deadline can be extended" 0 \
+        sh -c "'$TIMEOUT_BIN' --boottime --extend-step 1 0.5 sleep 1.2 & pid=\$!; sleep 0.2; kill -USR1 \$pid; wait \$pid"
+
+    run_test "Boot time clock with --pid" 124 \
+        sh -c "sleep 5 & '$TIMEOUT_BIN' --boottime --pid \$! 0.3"
+
+    run_test "Boot time clock in the dry run" 0 \
+        sh -c "'$TIMEOUT_BIN' --dry-run --boottime 5s true | grep -qx 'clock:         boottime'"
+else
+    run_test "Boot time clock is Linux-only" 2 \
+        "$TIMEOUT_BIN" --boottime 1s true
+fi
+
+echo ""
+echo "=== Color Tests ==="
+echo ""
+
+run_test "Messages are plain on a pipe" 0 \
+    sh -c "'$TIMEOUT_BIN' -v 0.2 sleep 5 2>&1 | grep -q '^Timeout: sending signal'"
+
+run_test "Color always colors a pipe" 0 \
+    sh -c "'$TIMEOUT_BIN' --color=always -v 0.2 sleep 5 2>&1 | grep -q \"\$(printf '\\033')\""
+
+run_test "Color never beats CLICOLOR_FORCE" 1 \
+    sh -c "CLICOLOR_FORCE=1 '$TIMEOUT_BIN' --color=never -v 0.2 sleep 5 2>&1 | grep -q \"\$(printf '\\033')\""
+
+run_test "Unknown color rejected" 2 \
+    "$TIMEOUT_BIN" --color=sometimes 1s true
+
+echo ""
+echo "=== CPU Affinity Tests ==="
+echo ""
+
+if [ "$(uname)" = Linux ]; then
+    run_test "CPU affinity pins COMMAND" 0 \
+        sh -c "'$TIMEOUT_BIN' --cpu-affinity 0 5s grep -qx 'Cpus_allowed_list:.0' /proc/self/status"
+
+    run_test "CPU affinity leaves timeout itself alone" 0 \
+        sh -c "[ \"\$('$TIMEOUT_BIN' --cpu-affinity 0 5s sh -c 'grep Cpus_allowed_list /proc/\$PPID/status')\" = \"\$(grep Cpus_allowed_list /proc/self/status)\" ]"
+
+    run_test "CPU affinity shown in the dry run" 0 \
+        sh -c "'$TIMEOUT_BIN' --dry-run --cpu-affinity 4,0-2,3 5s true | grep -qx 'cpu affinity:  0-4'"
+
+    run_test "CPU affinity to a missing CPU fails" 125 \
+        "$TIMEOUT_BIN" --cpu-affinity 1023 5s true
+
+    run_test "Backwards CPU range rejected" 125 \
+        "$TIMEOUT_BIN" --cpu-affinity 3-1 5s true
+
+    run_test "CPU past the set size rejected" 125 \
+        "$TIMEOUT_BIN" --cpu-affinity 1024 5s true
+
+    run_test "CPU affinity cannot be used with --pid" 2 \
+        "$TIMEOUT_BIN" --cpu-affinity 0 --pid $$ 5s
+else
+    run_test "CPU affinity is Linux-only" 125 \
+        "$TIMEOUT_BIN" --cpu-affinity 0 5s true
+fi
+
+echo ""
+echo "=== I/O Priority Tests ==="
+echo ""
+
+if [ "$(uname)" = Linux ]; then
+    if command -v ionice > /dev/null 2>&1; then
+        run_test "ionice sets the best-effort level" 0 \
+            sh -c "'$TIMEOUT_BIN' --ionice best-effort:7 5s ionice | grep -qx 'best-effort: prio 7'"
+
+        run_test "ionice sets the idle class" 0 \
+            sh -c "'$TIMEOUT_BIN' --ionice idle 5s ionice | grep -qx 'idle'"
+
+        run_test "ionice leaves timeout itself alone" 0 \
+            sh -c "[ \"\$('$TIMEOUT_BIN' --ionice idle 5s sh -c 'ionice -p \$PPID')\" = \"\$(ionice -p \$\$)\" ]"
+    fi
+
+    run_test "ionice shown in the dry run" 0 \
+        sh -c "'$TIMEOUT_BIN' --dry-run --ionice be 5s true | grep -qx 'io priority:   best-effort:4'"
+
+    run_test "ionice level out of range rejected" 125 \
+        "$TIMEOUT_BIN" --ionice best-effort:8 5s true
+
+    run_test "ionice idle level rejected" 125 \
+        "$TIMEOUT_BIN" --ionice idle:3 5s true
+
+    run_test "ionice unknown class rejected" 125 \
+        "$TIMEOUT_BIN" --ionice fast 5s true
+
+    run_test "ionice cannot be used with --pid" 2 \
+        "$TIMEOUT_BIN" --ionice idle --pid $$ 5s
+else
+    run_test "ionice is Linux-only" 125 \
+        "$TIMEOUT_BIN" --ionice idle 5s true
+fi
+
+echo ""
+echo "=== Namespace Tests ==="
+echo ""
+
+if [ "$(uname)" = Linux ]; then
+    if "$TIMEOUT_BIN" --namespaced 5s true 2>/dev/null; then
+        run_test "Namespaced COMMAND has its own network namespace" 0 \
+            sh -c "[ \"\$('$TIMEOUT_BIN' --namespaced 5s readlink /proc/self/ns/net)\" != \"\$(readlink /proc/self/ns/net)\" ]"
+
+        run_test "Namespaced COMMAND has only a loopback interface" 0 \
+            sh -c "'$TIMEOUT_BIN' --namespaced 5s cat /proc/net/dev | tail -n +3 | grep -v '^ *lo:' | grep -c . | grep -qx 0"
+
+        run_test "Namespaced COMMAND keeps its exit status" 3 \
+            "$TIMEOUT_BIN" --namespaced 5s sh -c "exit 3"
+
+        run_test "Namespaced COMMAND still times out" 124 \
+            "$TIMEOUT_BIN" --namespaced 0.3 sleep 5
+    fi
+
+    run_test "Namespaced shown in the dry run" 0 \
+        sh -c "'$TIMEOUT_BIN' --dry-run --namespaced 5s true | grep -qx 'namespaces:    mount, network, uts, ipc'"
+
+    run_test "Namespaced cannot be used with --pid" 2 \
+        "$TIMEOUT_BIN" --namespaced --pid $$ 5s
+else
+    run_test "Namespaced is Linux-only" 2 \
+        "$TIMEOUT_BIN" --namespaced 5s true
+fi
+
+echo ""
+echo "=== Chroot Tests ==="
+echo ""
+
+if [ "$(id -u)" = 0 ] && [ "$(uname)" = Linux ]; then
+    CHROOT_DIR=$(mktemp -d)
+    mkdir -p "$CHROOT_DIR/bin"
+    cp /bin/sh "$CHROOT_DIR/bin/"
+    # sh and the libraries it links against, at the same paths inside
+    for lib in $(ldd /bin/sh | grep -o '/[^ ]*'); do
+        mkdir -p "$CHROOT_DIR$(dirname "$lib")"
+        cp "$lib" "$CHROOT_DIR$lib"
+    done
+    echo inside > "$CHROOT_DIR/marker"
+
+    run_test "Chroot confines COMMAND to DIR" 0 \
+        sh -c "[ \"\$('$TIMEOUT_BIN' --chroot '$CHROOT_DIR' 5s /bin/sh -c 'read x < /marker; echo \$x')\" = inside ]"
+
+    run_test "Chroot starts COMMAND in the new root" 0 \
+        sh -c "[ \"\$('$TIMEOUT_BIN' --chroot '$CHROOT_DIR' 5s /bin/sh -c pwd)\" = / ]"
+
+    run_test "Chroot looks COMMAND up inside DIR" 3 \
+        env PATH=/bin "$TIMEOUT_BIN" --chroot "$CHROOT_DIR" 5s sh -c "exit 3"
+
+    run_test "Chroot without COMMAND inside exits 127" 127 \
+        "$TIMEOUT_BIN" --chroot "$CHROOT_DIR" 5s /bin/true
+
+    run_test "Chroot still times out" 124 \
+        "$TIMEOUT_BIN" --chroot "$CHROOT_DIR" 0.3 /bin/sh -c "while :; do :; done"
+
+    rm -rf "$CHROOT_DIR"
+fi
+
+run_test "Chroot into a missing directory is refused" 126 \
+    "$TIMEOUT_BIN" --chroot /nonexistent/jail 5s true
+
+run_test "Chroot shown in the dry run" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --chroot /srv/jail 5s sh | grep -qx 'chroot:        /srv/jail'"
+
+run_test "Chroot cannot be used with --check" 2 \
+    "$TIMEOUT_BIN" --check --chroot /srv/jail 5s sh
+
+echo ""
+echo "=== Process Group Tests ==="
+echo ""
+
+run_test "No process group keeps COMMAND in the caller's group" 0 \
+    sh -c "[ \"\$('$TIMEOUT_BIN' --no-process-group 5s sh -c 'ps -o pgid= -p \$\$' | tr -d ' ')\" = \"\$(ps -o pgid= -p \$\$ | tr -d ' ')\" ]"
+
+run_test "No process group still times out" 124 \
+    "$TIMEOUT_BIN" --no-process-group 0.3 sleep 5
+
+run_test "No process group still escalates to SIGKILL" 137 \
+    "$TIMEOUT_BIN" --no-process-group -s TERM -k 0.2 --preserve-status 0.2 sh -c "trap '' TERM; sleep 5"
+
+run_test "No process group shown in the dry run" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --no-process-group 5s true | grep -q 'signals to process\$'"
+
+run_test "No process group cannot be used with --pty" 2 \
+    "$TIMEOUT_BIN" --no-process-group --pty 5s true
+
+run_test "No process group cannot be used with --pid" 2 \
+    "$TIMEOUT_BIN" --no-process-group --pid $$ 5s
+
+run_test "Set session makes COMMAND a session leader" 0 \
+    sh -c "[ \"\$('$TIMEOUT_BIN' --set-session 5s sh -c 'ps -o sid= -p \$\$' | tr -d ' ')\" != \"\$(ps -o sid= -p \$\$ | tr -d ' ')\" ]"
+
+run_test "Set session still times out" 124 \
+    "$TIMEOUT_BIN" --set-session 0.3 sleep 5
+
+run_test "Set session shown in the dry run" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --set-session 5s true | grep -q '^session: *new'"
+
+run_test "Set session cannot be used with --foreground" 2 \
+    "$TIMEOUT_BIN" --set-session --foreground 5s true
+
+run_test "Set session cannot be used with --no-process-group" 2 \
+    "$TIMEOUT_BIN" --set-session --no-process-group 5s true
+
+if command -v setsid > /dev/null 2>&1 && setsid -w true > /dev/null 2>&1; then
+    run_test "Session leader runs COMMAND" 0 \
+        setsid -w "$TIMEOUT_BIN" 5s true
+
+    run_test "Session leader still times out" 124 \
+        setsid -w "$TIMEOUT_BIN" 0.3 sleep 5
+fi
+
+echo ""
+echo "=== Config Tests ==="
+echo ""
+
+CONFIG_DIR=$(mktemp -d)
+printf 'kill_after = "10s"\nsignal = "INT"\n' > "$CONFIG_DIR/config.toml"
+printf 'kill_afer = "10s"\n' > "$CONFIG_DIR/unknown.toml"
+
+run_test "Config file sets the kill-after default" 0 \
+    sh -c "TIMEOUT_CONFIG='$CONFIG_DIR/config.toml' '$TIMEOUT_BIN' --dry-run 5s true | grep -q '^kill-after: *10000 ms'"
+
+run_test "Config file read with --config" 0 \
+    sh -c "'$TIMEOUT_BIN' --config '$CONFIG_DIR/config.toml' --dry-run=json 5s true | grep -q '\"signal\": *\"SIGINT\"'"
+
+run_test "Command line overrides the config file" 0 \
+    sh -c "'$TIMEOUT_BIN' --config '$CONFIG_DIR/config.toml' -s HUP --dry-run=json 5s true | grep -q '\"signal\": *\"SIGHUP\"'"
+
+run_test "No config ignores TIMEOUT_CONFIG" 0 \
+    sh -c "TIMEOUT_CONFIG='$CONFIG_DIR/unknown.toml' '$TIMEOUT_BIN' --no-config 5s true"
+
+run_test "Dump config shows where settings came from" 0 \
+    sh -c "'$TIMEOUT_BIN' --config '$CONFIG_DIR/config.toml' --dump-config | grep -q '^kill_after = \"10s\"  # config file'"
+
+run_test "Config file with an unknown key" 125 \
+    "$TIMEOUT_BIN" --config "$CONFIG_DIR/unknown.toml" 5s true
+
+run_test "Missing --config file" 125 \
+    "$TIMEOUT_BIN" --config "$CONFIG_DIR/missing.toml" 5s true
+
+run_test "Config cannot be used with --no-config" 2 \
+    "$TIMEOUT_BIN" --config "$CONFIG_DIR/config.toml" --no-config 5s true
+
+run_test "TIMEOUT_OPTS sets default options" 0 \
+    sh -c "TIMEOUT_OPTS=\"-k 3 --signal 'INT'\" '$TIMEOUT_BIN' --no-config --dry-run=json 5s true | grep -q '\"kill_after_ms\":3000,.*\"signal\":\"SIGINT\"'"
+
+run_test "Command line overrides TIMEOUT_OPTS" 0 \
+    sh -c "TIMEOUT_OPTS='-k 3' '$TIMEOUT_BIN' --no-config -k 1 --dry-run=json 5s true | grep -q '\"kill_after_ms\":1000,'"
+
+run_test "TIMEOUT_OPTS preserve status" 143 \
+    env TIMEOUT_OPTS=--preserve-status "$TIMEOUT_BIN" --no-config 0.2 sleep 5
+
+run_test "TIMEOUT_OPTS with DURATION" 125 \
+    env TIMEOUT_OPTS="-k 3 5s" "$TIMEOUT_BIN" --no-config 5s true
+
+run_test "TIMEOUT_OPTS with an unknown option" 0 \
+    sh -c "TIMEOUT_OPTS=--bogus '$TIMEOUT_BIN' 5s true 2>&1 | grep -q \"TIMEOUT_OPTS: unexpected argument '--bogus'\""
+
+run_test "TIMEOUT_KILL_AFTER and TIMEOUT_SIGNAL" 0 \
+    sh -c "TIMEOUT_KILL_AFTER=2 TIMEOUT_SIGNAL=HUP '$TIMEOUT_BIN' --no-config --dry-run=json 5s true | grep -q '\"kill_after_ms\":2000,.*\"signal\":\"SIGHUP\"'"
+
+run_test "Invalid TIMEOUT_SIGNAL" 125 \
+    env TIMEOUT_SIGNAL=BOGUS "$TIMEOUT_BIN" 5s true
+
+rm -rf "$CONFIG_DIR"
+
+echo ""
+echo "=== Quiet Tests ==="
+echo ""
+
+QUIET_DIR=$(mktemp -d)
+printf '#!/nonexistent/interpreter\n' > "$QUIET_DIR/script"
+chmod +x "$QUIET_DIR/script"
+
+run_test "Quiet leaves warnings off stderr" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' -q --on-success 'exit 3' 1s true 2>&1) && test -z \"\$out\""
+
+run_test "Quiet leaves the TIMEOUT_LOG warning off" 0 \
+    sh -c "out=\$(TIMEOUT_LOG='timeout=[' '$TIMEOUT_BIN' --quiet 1s true 2>&1) && test -z \"\$out\""
+
+run_test "Quiet with resource limits prints nothing" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' -q --cpu-limit 5 --mem-limit 512M 1s true 2>&1) && test -z \"\$out\""
+
+run_test "Quiet keeps errors" 0 \
+    sh -c "'$TIMEOUT_BIN' -q 1s nonexistent_command_xyz 2>&1 | grep -q 'command not found'"
+
+run_test "Quiet keeps the exit status" 127 \
+    "$TIMEOUT_BIN" -q 1s nonexistent_command_xyz
+
+run_test "Twice quiet leaves errors off too" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' -qq 1s nonexistent_command_xyz 2>&1); test \$? = 127 && test -z \"\$out\""
+
+run_test "Twice quiet leaves exec failures in the child off" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' -qq 1s '$QUIET_DIR/script' 2>&1); test \$? = 127 && test -z \"\$out\""
+
+run_test "Twice quiet still times out" 124 \
+    sh -c "out=\$('$TIMEOUT_BIN' -qq 0.2 sleep 5 2>&1); status=\$?; test -z \"\$out\" && exit \$status"
+
+run_test "Twice quiet leaves COMMAND's output alone" 0 \
+    sh -c "test \"\$('$TIMEOUT_BIN' -qq 1s sh -c 'echo out; echo err >&2' 2>&1)\" = \"\$(printf 'out\nerr')\""
+
+run_test "Quiet and verbose conflict" 2 \
+    "$TIMEOUT_BIN" -q -v 1s true
+
+run_test "Quiet leaves the journal fallback warning off" 0 \
+    sh -c "TIMEOUT_JOURNAL_SOCKET=/nonexistent/socket '$TIMEOUT_BIN' --log journald --quiet 1s nonexistent_command_xyz 2>&1 | grep -c 'logging to stderr instead\|command not found' | grep -qx 1"
+
+rm -rf "$QUIET_DIR"
+
+echo ""
+echo "=== Progress Tests ==="
+echo ""
+
+run_test "Progress stays off when stderr is not a terminal" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' --progress --progress-interval 0.1 0.5 true 2>&1) && test -z \"\$out\""
+
+run_test "Progress keeps the exit status" 124 \
+    "$TIMEOUT_BIN" --progress 0.3 sleep 5
+
+run_test "Progress interval requires --progress" 2 \
+    "$TIMEOUT_BIN" --progress-interval 1 1s true
+
+run_test "Progress cannot be used with --parallel" 2 \
+    "$TIMEOUT_BIN" --progress --parallel 2 1s true
+
+run_test "Invalid progress interval rejected" 125 \
+    "$TIMEOUT_BIN" --progress --progress-interval soon 1s true
+
+if command -v script > /dev/null 2>&1 && script -qec true /dev/null > /dev/null 2>&1; then
+    run_test "Progress counts down on a terminal" 0 \
+        sh -c "script -qec \"sh -c '\\\"$TIMEOUT_BIN\\\" --progress --progress-interval 0.1 1.5 sleep 5'\" /dev/null | grep -q 'SIGTERM in 00:01'"
+
+    run_test "Progress shows the escalation" 0 \
+        sh -c "script -qec \"sh -c '\\\"$TIMEOUT_BIN\\\" --progress --progress-interval 0.1 -k 2 -s USR1 0.2 sh -c \\\"trap : USR1; while :; do sleep 1; done\\\"'\" /dev/null | grep -q 'sent SIGUSR1, killing in 00:0[12]'"
+
+    run_test "Progress is erased before exit" 0 \
+        sh -c "script -qec \"sh -c '\\\"$TIMEOUT_BIN\\\" --progress --progress-interval 0.1 0.5 sleep 5; echo done'\" /dev/null | tr '\\r' '\\n' | grep -v '^ *\$' | tail -1 | grep -qx done"
+
+    run_test "Progress is erased before a message" 0 \
+        sh -c "script -qec \"sh -c '\\\"$TIMEOUT_BIN\\\" --progress --progress-interval 0.1 --color never -v 0.5 sleep 5'\" /dev/null | tr '\\r' '\\n' | grep -B1 '^Timeout: sending signal SIGTERM' | head -1 | grep -qx ' *'"
+
+    run_test "Quiet turns progress off" 0 \
+        sh -c "! script -qec \"sh -c '\\\"$TIMEOUT_BIN\\\" -q --progress --progress-interval 0.1 0.5 sleep 5'\" /dev/null | grep -q 'SIGTERM in'"
+fi
+
+echo ""
+echo "=== Heartbeat Tests ==="
+echo ""
+
+run_test "Heartbeat prints while COMMAND runs" 0 \
+    sh -c "'$TIMEOUT_BIN' --heartbeat 0.2 0.7 sleep 5 | grep -c '^\\[timeout\\] still running sleep' | grep -qx '[23]'"
+
+run_test "Heartbeat keeps the exit status" 124 \
+    "$TIMEOUT_BIN" --heartbeat 0.2 0.5 sleep 5
+
+run_test "Heartbeat text and stderr" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' --heartbeat 0.2:alive --heartbeat-stderr 0.5 sleep 5 2>&1 >/dev/null); echo \"\$out\" | grep -qx alive"
+
+run_test "Heartbeat stderr requires --heartbeat" 2 \
+    "$TIMEOUT_BIN" --heartbeat-stderr 1s true
+
+run_test "Zero heartbeat interval rejected" 125 \
+    "$TIMEOUT_BIN" --heartbeat 0 1s true
+
+run_test "Dry run shows the heartbeat" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --heartbeat 1m:hi 1s true | grep -qx 'heartbeat: *every 60000 ms to stdout: hi'"
+
+echo ""
+echo "=== Time Report Tests ==="
+echo ""
+
+TIME_DIR=$(mktemp -d)
+run_test "Time report after a normal exit" 0 \
+    sh -c "'$TIMEOUT_BIN' --time 1s true 2>&1 | tr '\t' ' ' | grep -qx 'timed out no'"
+
+run_test "Time report keeps COMMAND's exit status" 3 \
+    "$TIMEOUT_BIN" --time 1s sh -c "exit 3"
+
+run_test "Time report after a timeout" 0 \
+    sh -c "'$TIMEOUT_BIN' --time 0.2 sleep 1 2>&1 | grep -q '^timed out.yes'"
+
+run_test "Time report after SIGKILL escalation" 0 \
+    sh -c "'$TIMEOUT_BIN' --time -k 0.2 0.2 sh -c 'trap \"\" TERM; sleep 2' 2>&1 | grep -q '^real.0\\.[4-9]'"
+
+run_test "Time format directives" 0 \
+    sh -c "'$TIMEOUT_BIN' --time-format '%x|%C|%T|%%' 1s sh -c 'exit 4' 2>&1 | grep -qx '4|sh -c exit 4|no|%'"
+
+run_test "Time format reports peak RSS in KiB" 0 \
+    sh -c "'$TIMEOUT_BIN' --time-format '%M' 5s sh -c 'x=\$(head -c 20000000 /dev/zero | tr \"\\\\0\" a); : \"\$x\"' 2>&1 | grep -Eqx '[0-9]{5,6}'"
+
+run_test "Time output goes to a file" 0 \
+    sh -c "out=\$('$TIMEOUT_BIN' --time-output '$TIME_DIR/t' --time-format '%e' 1s true 2>&1); test -z \"\$out\" && grep -Eqx '[0-9]+\\.[0-9]{2}' '$TIME_DIR/t'"
+
+run_test "Time output gets one report per run" 0 \
+    sh -c "'$TIMEOUT_BIN' --time-output '$TIME_DIR/every' --time-format '%x' --every 0.1 --count 3 1s true; [ \$(grep -cx 0 '$TIME_DIR/every') = 3 ]"
+
+run_test "Unwritable time output fails before COMMAND runs" 0 \
+    sh -c "'$TIMEOUT_BIN' --time-output /nonexistent/dir/t 1s touch '$TIME_DIR/ran'; test \$? = 125 && ! test -e '$TIME_DIR/ran'"
+
+run_test "Dry run shows the time report target" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --time-output t.txt 1s true | grep -qx 'time: *file t.txt'"
+rm -rf "$TIME_DIR"
+
+echo ""
+echo "=== StatsD Tests ==="
+echo ""
+
+if command -v python3 > /dev/null; then
+    STATSD_DIR=$(mktemp -d)
+    # Stands in for a StatsD server: prints its UDP port once listening,
+    # then saves the first datagram it gets
+    cat > "$STATSD_DIR/statsd.py" << 'EOF'
+import socket, sys
+sock = socket.socket(socket.AF_INET, socket.SOCK_DGRAM)
+sock.bind(('127.0.0.1', 0))
+sock.settimeout(10)
+print(sock.getsockname()[1], flush=True)
+with open(sys.argv[1], 'wb') as f:
+    f.write(sock.recv(1 << 16) + b'\n')
+EOF
+    python3 "$STATSD_DIR/statsd.py" "$STATSD_DIR/packet" > "$STATSD_DIR/ready" &
+    STATSD_PID=$!
+    while [ ! -s "$STATSD_DIR/ready" ]; do sleep 0.05; done
+    run_test "StatsD alongside a metrics file" 124 \
+        "$TIMEOUT_BIN" --statsd "127.0.0.1:$(cat "$STATSD_DIR/ready")" --statsd-prefix ci.timeout --statsd-tags --metrics-file "$STATSD_DIR/m.jsonl" 0.2 sleep 1
+    wait "$STATSD_PID"
+    run_test "StatsD datagram has the counter, timer and gauges" 0 \
+        sh -c "grep -qx 'ci.timeout.runs.timeout:1|c|#command:sleep,timed_out:true,outcome:timeout' '$STATSD_DIR/packet' && grep -q '^ci.timeout.elapsed_ms:[0-9]*|ms|#' '$STATSD_DIR/packet' && grep -q '^ci.timeout.exit_code:124|g|#' '$STATSD_DIR/packet' && grep -q '^ci.timeout.kill_after_used:0|g|#' '$STATSD_DIR/packet' && grep -q '\"timed_out\":true' '$STATSD_DIR/m.jsonl'"
+    rm -rf "$STATSD_DIR"
+fi
+
+run_test "StatsD prefix with a separator is rejected" 125 \
+    "$TIMEOUT_BIN" --statsd 127.0.0.1 --statsd-prefix 'ci|timeout' 1s true
+
+run_test "StatsD tags need --statsd" 2 \
+    "$TIMEOUT_BIN" --statsd-tags 1s true
+
+run_test "Dry run shows the StatsD target" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --statsd metrics.example --statsd-tags 1s true | grep -qx 'statsd: *metrics.example:8125, prefix timeout, with tags'"
+
+echo ""
+echo "=== OpenTelemetry Tests ==="
+echo ""
+
+if "$TIMEOUT_BIN" --dry-run --otel 1s true > /dev/null 2>&1; then
+    run_test "Dry run shows the OTLP endpoint and parent" 0 \
+        sh -c "OTEL_EXPORTER_OTLP_ENDPOINT=http://collector.example:4318/ TRACEPARENT=00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01 '$TIMEOUT_BIN' --dry-run --otel 1s true | grep -qx 'otel: *http://collector.example:4318/v1/traces, child of 00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01'"
+
+    run_test "OTLP endpoint that is not HTTP is rejected" 125 \
+        env OTEL_EXPORTER_OTLP_TRACES_ENDPOINT=ftp://collector.example "$TIMEOUT_BIN" --otel 1s true
+
+    # Nothing listens on port 1, so the export fails quickly
+    run_test "Unreachable collector keeps the exit code" 3 \
+        env OTEL_EXPORTER_OTLP_ENDPOINT=http://127.0.0.1:1 "$TIMEOUT_BIN" --otel 1s sh -c 'exit 3'
+
+    run_test "COMMAND gets a TRACEPARENT of its own" 0 \
+        env OTEL_EXPORTER_OTLP_ENDPOINT=http://127.0.0.1:1 TRACEPARENT=00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01 "$TIMEOUT_BIN" --otel 1s sh -c 'case "$TRACEPARENT" in 00-0af7651916cd43dd8448eb211c80319c-*-01) [ "${TRACEPARENT#*319c-}" != b7ad6b7169203331-01 ] ;; *) exit 1 ;; esac'
+fi
+
+echo ""
+echo "=== Prometheus Textfile Tests ==="
+echo ""
+
+PROM_DIR=$(mktemp -d)
+run_test "Textfile written after a timeout" 124 \
+    "$TIMEOUT_BIN" --prom-textfile "$PROM_DIR/job.prom" 0.2 sleep 1
+run_test "Textfile counter carries over" 0 \
+    "$TIMEOUT_BIN" --prom-textfile "$PROM_DIR/job.prom" 1s sleep 0
+run_test "Textfile has the counts and last run" 0 \
+    sh -c "grep -qx 'timeout_runs_total{command=\"sleep\",outcome=\"timeout\"} 1' '$PROM_DIR/job.prom' && grep -qx 'timeout_runs_total{command=\"sleep\",outcome=\"success\"} 1' '$PROM_DIR/job.prom' && grep -qx 'timeout_last_run_timed_out{command=\"sleep\"} 0' '$PROM_DIR/job.prom' && grep -qx '# TYPE timeout_runs_total counter' '$PROM_DIR/job.prom'"
+run_test "Textfile no temporary files left" 0 \
+    sh -c "[ \"\$(ls -A '$PROM_DIR')\" = job.prom ]"
+rm -rf "$PROM_DIR"
+
+run_test "Textfile in a missing directory keeps the exit code" 3 \
+    "$TIMEOUT_BIN" --prom-textfile /nonexistent/dir/job.prom 1s sh -c 'exit 3'
+
+run_test "Dry run shows the textfile" 0 \
+    sh -c "'$TIMEOUT_BIN' --dry-run --prom-textfile /var/lib/node_exporter/job.prom 1s true | grep -qx 'prom textfile: /var/lib/node_exporter/job.prom'"
+
+echo ""
+echo "=== Debug Trace Tests ==="
+echo ""
+
+run_test "Debug trace shows the deadline firing" 0 \
+    sh -c "TIMEOUT_DEBUG=1 '$TIMEOUT_BIN' 0.2 sleep 1 2>&1 | grep -q 'select: deadline arm fired (duration)'"
+
+run_test "Debug trace shows signals sent" 0 \
+    sh -c "TIMEOUT_DEBUG=1 '$TIMEOUT_BIN' -s HUP 0.2 sleep 1 2>&1 | grep -q 'kill.*SIGHUP) = Ok'"
+
+run_test "Debug trace shows the wait4 status" 0 \
+    sh -c "TIMEOUT_DEBUG=1 '$TIMEOUT_BIN' 1 sh -c 'exit 3' 2>&1 | grep -q 'Z +[0-9.]*s wait4(.*) = Ok((Exited(Pid([0-9]*), 3), Some(ResourceUsage'"
+
+run_test "No debug trace without TIMEOUT_DEBUG" 0 \
+    sh -c "! '$TIMEOUT_BIN' 0.2 sleep 1 2>&1 | grep -q 'Debug'"
+
+COALESCE_TRACE=$(mktemp)
+run_test "SIGTERM from the parent during the grace period is passed on" 137 \
+    sh -c "TIMEOUT_DEBUG=1 '$TIMEOUT_BIN' -k 3 0.3 sh -c 'trap \"\" TERM; sleep 10' 2>'$COALESCE_TRACE' & pid=\$!; sleep 0.6; kill -TERM \$pid; sleep 1.2; kill -TERM \$pid; wait \$pid"
+run_test "A repeated signal within a second is coalesced" 0 \
+    sh -c "grep -c 'SIGTERM to [0-9]* coalesced' '$COALESCE_TRACE' | grep -qx 1 && grep -c 'kill[pg]*([0-9]*, SIGTERM) = Ok' '$COALESCE_TRACE' | grep -qx 2"
+rm -f "$COALESCE_TRACE"
+
+run_test "TIMEOUT_LOG=error leaves warnings out" 0 \
+    sh -c "TIMEOUT_LOG=error '$TIMEOUT_BIN' --prom-textfile /nonexistent/dir/job.prom 1s true 2>&1 | wc -c | grep -qx ' *0'"
+
+run_test "TIMEOUT_LOG=off leaves errors out but not the exit code" 127 \
+    sh -c "TIMEOUT_LOG=off '$TIMEOUT_BIN' 1s /nonexistent/command 2>&1 | wc -c | grep -qx ' *0' && TIMEOUT_LOG=off '$TIMEOUT_BIN' 1s /nonexistent/command 2>/dev/null"
+
+run_test "TIMEOUT_LOG=debug traces like TIMEOUT_DEBUG" 0 \
+    sh -c "TIMEOUT_LOG=debug '$TIMEOUT_BIN' 0.2 sleep 1 2>&1 | grep -q 'select: deadline arm fired (duration)'"
+
+run_test "TIMEOUT_LOG by module" 0 \
+    sh -c "TIMEOUT_LOG=info,timeout::platform=debug '$TIMEOUT_BIN' 1s true 2>&1 | grep -q 'wait4' && ! TIMEOUT_LOG=info,timeout::platform=debug '$TIMEOUT_BIN' 1s true 2>&1 | grep -q 'started as PID'"
+
+run_test "Invalid TIMEOUT_LOG is warned about" 0 \
+    sh -c "TIMEOUT_LOG='timeout=[' '$TIMEOUT_BIN' 1s true 2>&1 | grep -q 'ignoring TIMEOUT_LOG'"
+
+run_test "TIMEOUT_LOG keeps the metrics record" 0 \
+    sh -c "TIMEOUT_LOG=off TIMEOUT_METRICS=1 '$TIMEOUT_BIN' 1s true 2>&1 | grep -q '\"exit_code\":0'"
+
+echo ""
+echo "=== Info Tests ==="
+echo ""
+
+run_test "Info lists capabilities" 0 \
+    sh -c "'$TIMEOUT_BIN' --info | grep -q '^pidfd: *\(not \)\?available'"
+
+run_test "Info as JSON" 0 \
+    sh -c "'$TIMEOUT_BIN' --info --json | grep -q '\"pty\":\(true\|false\)}'"
+
+run_test "Capabilities as JSON" 0 \
+    sh -c "'$TIMEOUT_BIN' --capabilities | grep -q '\"features\":{\"cpu_limit\":{\"supported\":\(true\|false\)'"
+
+run_test "Capabilities give a reason when unsupported" 0 \
+    sh -c "'$TIMEOUT_BIN' --capabilities | grep -q '\"job_objects\":{\"supported\":false,\"reason\":\"only available on Windows'"
+
+run_test "Capabilities need no DURATION" 0 \
+    "$TIMEOUT_BIN" --capabilities
+
+run_test "Capabilities cannot be used with --info" 2 \
+    "$TIMEOUT_BIN" --capabilities --info
+
+run_test "JSON requires info" 2 \
+    "$TIMEOUT_BIN" --json 1s true
+
+run_test "List signals as a table" 0 \
+    sh -c "'$TIMEOUT_BIN' --list-signals | grep -q '^SIGHUP  *1  Hangup\$'"
+
+run_test "Every listed signal is accepted by --signal" 0 \
+    sh -c "'$TIMEOUT_BIN' --list-signals | while read -r name number rest; do '$TIMEOUT_BIN' --dry-run -s \"\$number\" 1s true | grep -q \"^signal: *\$name\\\$\" || exit 1; done"
+
+echo ""
+echo "=== Help and Version Tests ==="
+echo ""
+
+# Test 12: Help flag
+echo -ne "${YELLOW}Testing: Help flag${NC} ... "
+if "$TIMEOUT_BIN" --help > /dev/null 2>&1; then
+    echo -e "${GREEN}PASS${NC}"
+    TESTS_PASSED=$((TESTS_PASSED + 1))
+else
+    echo -e "${RED}FAIL${NC}"
+    TESTS_FAILED=$((TESTS_FAILED + 1))
+fi
+
+run_test "Generate man page" 0 \
+    sh -c "'$TIMEOUT_BIN' --generate-man | grep -q '^\.TH timeout 1'"
+
+run_test "Man page has exit status, signal and environment sections" 0 \
+    sh -c "'$TIMEOUT_BIN' --generate-man | grep -c -e '^\.SH \"EXIT STATUS\"' -e '^\.SH SIGNALS' -e '^\.SH ENVIRONMENT' | grep -qx 3"
+
+run_test "Generate man conflicts with completions" 2 \
+    "$TIMEOUT_BIN" --generate-man --generate-completions bash
+
+run_test "Completions dir writes every shell" 0 \
+    sh -c "d=\$(mktemp -d) && '$TIMEOUT_BIN' --completions-dir \"\$d/c\" && test -s \"\$d/c/timeout.bash\" && test -s \"\$d/c/_timeout\" && test -s \"\$d/c/timeout.fish\" && test -s \"\$d/c/_timeout.ps1\" && test -s \"\$d/c/timeout.elv\" && test -s \"\$d/c/timeout.nu\"; s=\$?; rm -rf \"\$d\"; exit \$s"
+
+run_test "Completions dir that cannot be created" 125 \
+    "$TIMEOUT_BIN" --completions-dir /dev/null/completions
+
+run_test "Completions dir conflicts with generate completions" 2 \
+    "$TIMEOUT_BIN" --completions-dir /tmp --generate-completions bash
+
+run_test "Generate completions for an unknown shell" 2 \
+    "$TIMEOUT_BIN" --generate-completions tcsh
+
+run_test "Generate completions ignores case" 0 \
+    sh -c "'$TIMEOUT_BIN' --generate-completions ZSH | grep -q '#compdef timeout'"
+
+run_test "Generate Nushell completions" 0 \
+    sh -c "'$TIMEOUT_BIN' --generate-completions nushell | grep -q 'export extern \"timeout\"'"
+
+run_test "Generate man page to a file" 0 \
+    sh -c "d=\$(mktemp -d) && '$TIMEOUT_BIN' --generate-man --output \"\$d/timeout.1\" && grep -q '^\.TH timeout 1' \"\$d/timeout.1\"; s=\$?; rm -rf \"\$d\"; exit \$s"
+
+run_test "Generate man to an unwritable path fails" 125 \
+    "$TIMEOUT_BIN" --generate-man --output /nonexistent/dir/timeout.1
+
+run_test "Output requires generate man" 2 \
+    "$TIMEOUT_BIN" --output /dev/null 1 true
+
 # Test 13: Version flag
 echo -ne "${YELLOW}Testing: Version flag${NC} ... "
 if "$TIMEOUT_BIN" --version > /dev/null 2>&1; then
@@ -0,0 +1,76 @@
+// src/affinity.rs
+// --cpu-affinity: the CPUs COMMAND may run on, given as a CPU list such as
+// "0,2-4" (Linux)
+
+/// One more than the highest CPU number sched_setaffinity takes
+/// (CPU_SETSIZE)
+pub const MAX_CPUS: usize = 1024;
+
+/// Parse a CPU list as `taskset -c` takes it: CPU numbers and inclusive
+/// ranges separated by commas, e.g. `0-3,7`. The CPUs come back sorted,
+/// each once. Fails with the reason.
+pub fn parse_cpu_list(input: &str) -> Result<Vec<usize>, String> {
+    let cpu = |s: &str| -> Result<usize, String> {
+        let s = s.trim();
+        let n: usize = s
+            .parse()
+            .map_err(|_| format!("'{}' is not a CPU number", s))?;
+        if n >= MAX_CPUS {
+            return Err(format!("CPU {} is out of range (0-{})", n, MAX_CPUS - 1));
+        }
+        Ok(n)
+    };
+
+    let mut cpus = Vec::new();
+    for part in input.split(',') {
+        if part.trim().is_empty() {
+            return Err("empty entry in the list".to_string());
+        }
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (cpu(first)?, cpu(last)?);
+                if first > last {
+                    return Err(format!("range {}-{} goes backwards", first, last));
+                }
+                cpus.extend(first..=last);
+            }
+            None => cpus.push(cpu(part)?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// `cpus` (sorted) in the same syntax, with runs folded into ranges, e.g.
+/// `0,2-4`
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < cpus.len() {
+        let mut j = i;
+        while j + 1 < cpus.len() && cpus[j + 1] == cpus[j] + 1 {
+            j += 1;
+        }
+        parts.push(if i == j {
+            cpus[i].to_string()
+        } else {
+            format!("{}-{}", cpus[i], cpus[j])
+        });
+        i = j + 1;
+    }
+    parts.join(",")
+}
+
+/// Pin the calling process to `cpus`; called in the child between fork
+/// and exec, so only COMMAND is affected
+#[cfg(target_os = "linux")]
+pub fn apply(cpus: &[usize]) -> nix::Result<()> {
+    use nix::sched::{sched_setaffinity, CpuSet};
+
+    let mut set = CpuSet::new();
+    for &cpu in cpus {
+        set.set(cpu)?;
+    }
+    sched_setaffinity(nix::unistd::Pid::from_raw(0), &set)
+}
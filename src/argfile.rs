@@ -0,0 +1,60 @@
+// src/argfile.rs
+// `@file` arguments, expanded into the arguments the file lists
+
+use crate::TimeoutError;
+use std::io;
+
+/// Maximum nesting of `@file` references inside argument files
+const MAX_ARG_FILE_DEPTH: usize = 10;
+
+/// Expand `@file` arguments into the arguments listed in that file.
+///
+/// Files hold one argument per line; blank lines and lines starting with
+/// `#` are skipped, and `@file` lines are expanded recursively. As with
+/// GCC, an `@` argument naming no file is kept as written, so arguments
+/// such as `@types/node` reach COMMAND unchanged.
+pub fn expand_arg_files(args: &[String], depth: usize) -> Result<Vec<String>, TimeoutError> {
+    let mut expanded = Vec::with_capacity(args.len());
+
+    for arg in args {
+        let path = match arg.strip_prefix('@') {
+            Some(path) if !path.is_empty() => path,
+            _ => {
+                expanded.push(arg.clone());
+                continue;
+            }
+        };
+
+        if depth >= MAX_ARG_FILE_DEPTH {
+            return Err(TimeoutError::InvalidArgFile {
+                path: path.to_string(),
+                reason: format!("nested more than {} levels deep", MAX_ARG_FILE_DEPTH),
+            });
+        }
+
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                expanded.push(arg.clone());
+                continue;
+            }
+            Err(e) => {
+                return Err(TimeoutError::InvalidArgFile {
+                    path: path.to_string(),
+                    reason: e.to_string(),
+                })
+            }
+        };
+
+        let lines: Vec<String> = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string)
+            .collect();
+
+        expanded.extend(expand_arg_files(&lines, depth + 1)?);
+    }
+
+    Ok(expanded)
+}
@@ -1,19 +1,94 @@
 // src/args.rs
 // Command-line argument parsing
 
-use clap::Parser;
+use crate::completions::CompletionShell;
+#[cfg(unix)]
+use crate::TimeoutSignal;
+use clap::{ArgGroup, Parser};
+use std::path::{Path, PathBuf};
 
 /// Run a command with a time limit
 #[derive(Parser, Debug)]
 #[command(name = "timeout")]
 #[command(version = "1.0")]
 #[command(about = "Start COMMAND, and kill it if still running after DURATION", long_about = None)]
+#[command(group(ArgGroup::new("hooks").multiple(true).args(["on_timeout", "on_success", "on_failure"])))]
 pub struct Args {
-    /// Generate shell completions (bash, zsh, fish, powershell, elvish)
-    #[arg(long = "generate-completions", value_name = "SHELL", hide = true)]
-    pub generate_completions: Option<String>,
+    /// Generate shell completions (bash, zsh, fish, powershell, elvish, nushell)
+    #[arg(
+        long = "generate-completions",
+        value_name = "SHELL",
+        value_enum,
+        ignore_case = true,
+        hide = true
+    )]
+    pub generate_completions: Option<CompletionShell>,
+
+    /// Write completions for every shell --generate-completions knows into
+    /// DIR, creating it if needed, and exit
+    #[arg(
+        long = "completions-dir",
+        value_name = "DIR",
+        hide = true,
+        conflicts_with = "generate_completions"
+    )]
+    pub completions_dir: Option<PathBuf>,
+
+    /// Print a troff man page to stdout and exit
+    #[arg(
+        long = "generate-man",
+        hide = true,
+        conflicts_with_all = ["generate_completions", "completions_dir"]
+    )]
+    pub generate_man: bool,
+
+    /// With --generate-man, write the man page to PATH instead of stdout
+    #[arg(
+        long = "output",
+        value_name = "PATH",
+        hide = true,
+        requires = "generate_man"
+    )]
+    pub output: Option<PathBuf>,
+
+    /// Read default options from PATH instead of the usual config file
+    #[arg(long = "config", value_name = "PATH", conflicts_with = "no_config")]
+    pub config: Option<PathBuf>,
+
+    /// Ignore config files
+    #[arg(long = "no-config")]
+    pub no_config: bool,
+
+    /// Print the settings a config file may hold as now in effect, each
+    /// with where it came from, and exit
+    #[arg(long = "dump-config")]
+    pub dump_config: bool,
+
+    /// Print which platform features are available here and exit
+    #[arg(long = "info")]
+    pub info: bool,
+
+    /// With --info, print JSON instead of text
+    #[arg(long = "json", requires = "info")]
+    pub json: bool,
+
+    /// Print a JSON report of which features this build supports on this
+    /// machine, and why not where it does not, and exit
+    #[arg(long = "capabilities", conflicts_with = "info")]
+    pub capabilities: bool,
+
+    /// Print every signal --signal accepts, with its number, and exit
+    #[cfg(unix)]
+    #[arg(long = "list-signals")]
+    pub list_signals: bool,
 
     /// Send this signal to COMMAND on timeout, rather than SIGTERM
+    #[cfg(unix)]
+    #[arg(short = 's', long = "signal", value_name = "SIGNAL")]
+    pub signal: Option<TimeoutSignal>,
+
+    /// Send this signal to COMMAND on timeout, rather than SIGTERM
+    #[cfg(not(unix))]
     #[arg(short = 's', long = "signal", value_name = "SIGNAL")]
     pub signal: Option<String>,
 
@@ -21,56 +96,704 @@ pub struct Args {
     #[arg(short = 'k', long = "kill-after", value_name = "DURATION")]
     pub kill_after: Option<String>,
 
+    /// Also time out if COMMAND writes nothing to stdout/stderr for this long.
+    /// Output is piped through timeout, so COMMAND no longer sees a TTY there
+    #[arg(long = "idle-timeout", value_name = "DURATION")]
+    pub idle_timeout: Option<String>,
+
+    /// Stop COMMAND once stdout and stderr together exceed SIZE bytes (e.g. 50M)
+    #[arg(long = "max-output", value_name = "SIZE")]
+    pub max_output: Option<String>,
+
+    /// Stop COMMAND once its stdout alone exceeds SIZE bytes
+    #[arg(long = "max-output-stdout", value_name = "SIZE")]
+    pub max_output_stdout: Option<String>,
+
+    /// Stop COMMAND once its stderr alone exceeds SIZE bytes
+    #[arg(long = "max-output-stderr", value_name = "SIZE")]
+    pub max_output_stderr: Option<String>,
+
+    /// Stop COMMAND as on timeout when a line of its output matches REGEX
+    #[arg(long = "kill-on-pattern", value_name = "REGEX")]
+    pub kill_on_pattern: Option<String>,
+
+    /// Exit 0 as soon as a line of COMMAND's output matches REGEX
+    #[arg(long = "success-on-pattern", value_name = "REGEX")]
+    pub success_on_pattern: Option<String>,
+
+    /// Leave COMMAND running instead of signalling it when it times out (or
+    /// matches --success-on-pattern); timeout prints its PID and exits
+    #[arg(
+        long = "no-kill",
+        conflicts_with_all = ["kill_after", "pre_kill", "retries", "every"]
+    )]
+    #[cfg_attr(unix, arg(conflicts_with = "no_notify"))]
+    pub no_kill: bool,
+
+    /// Run CMD through the shell when COMMAND times out, before it is
+    /// signalled; {pid}, {pgid}, {elapsed_ms} and {command} are substituted
+    #[arg(long = "on-timeout", value_name = "CMD")]
+    pub on_timeout: Option<String>,
+
+    /// Kill the --on-timeout hook if it runs longer than this (default:
+    /// --hook-timeout)
+    #[arg(
+        long = "on-timeout-timeout",
+        value_name = "DURATION",
+        requires = "on_timeout"
+    )]
+    pub on_timeout_timeout: Option<String>,
+
+    /// Run CMD through the shell when COMMAND times out, just before the
+    /// signal is sent, and kill it after 10s; {PID}, {SIGNAL} and
+    /// {ELAPSED_MS} are substituted
+    #[arg(long = "pre-kill", value_name = "CMD")]
+    pub pre_kill: Option<String>,
+
+    /// Run CMD through the shell once COMMAND has exited 0 in time; the
+    /// --on-timeout placeholders plus {exit_code} and {timed_out} are substituted
+    #[arg(long = "on-success", value_name = "CMD")]
+    pub on_success: Option<String>,
+
+    /// Run CMD through the shell once COMMAND has failed, timed out or been
+    /// killed, after any --on-timeout hook; placeholders as for --on-success
+    #[arg(long = "on-failure", value_name = "CMD")]
+    pub on_failure: Option<String>,
+
+    /// Kill any hook that runs longer than this (default: 30s)
+    #[arg(long = "hook-timeout", value_name = "DURATION", requires = "hooks")]
+    pub hook_timeout: Option<String>,
+
+    /// Exit 125 if a hook fails, is killed or cannot be run
+    #[arg(long = "hook-failures-fatal", requires = "hooks")]
+    pub hook_failures_fatal: bool,
+
+    /// POST a JSON report of the run to URL once COMMAND has been reaped;
+    /// a delivery failure is reported but does not change the exit code
+    #[arg(long = "webhook", value_name = "URL")]
+    pub webhook: Option<String>,
+
+    /// Only POST to --webhook for these outcomes (default: all of them)
+    #[arg(
+        long = "webhook-on",
+        value_name = "EVENTS",
+        value_delimiter = ',',
+        value_parser = ["success", "failure", "timeout"],
+        requires = "webhook"
+    )]
+    pub webhook_on: Vec<String>,
+
+    /// Add a header to the --webhook request, e.g. 'Authorization: Bearer
+    /// TOKEN'; may be repeated
+    #[arg(long = "webhook-header", value_name = "HEADER", requires = "webhook")]
+    pub webhook_header: Vec<String>,
+
+    /// Under a systemd Type=notify service: with passthrough COMMAND talks to
+    /// NOTIFY_SOCKET itself; with proxy timeout sends READY=1 once COMMAND
+    /// is started, feeds the watchdog and sends STOPPING=1 on timeout
+    #[arg(
+        long = "sd-notify",
+        value_name = "MODE",
+        value_parser = ["passthrough", "proxy"]
+    )]
+    pub sd_notify: Option<String>,
+
+    /// Prefix each line of COMMAND's output with the time since it started,
+    /// and with =remaining also the time left before DURATION expires
+    #[arg(
+        long = "timestamps",
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "elapsed",
+        value_parser = ["elapsed", "remaining"]
+    )]
+    pub timestamps: Option<String>,
+
+    /// Run COMMAND up to N more times, with a fresh DURATION, if it times out
+    #[arg(long = "retries", value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Also retry when COMMAND exits with one of CODES: a list such as
+    /// 1,100-120, or `any` for every nonzero status
+    #[arg(long = "retry-on-exit", value_name = "CODES", requires = "retries")]
+    pub retry_on_exit: Option<String>,
+
+    /// Wait this long before each retry (default: 1s)
+    #[arg(long = "retry-delay", value_name = "DURATION", requires = "retries")]
+    pub retry_delay: Option<String>,
+
+    /// Keep the retry delay fixed, or double it after each attempt
+    #[arg(
+        long = "retry-backoff",
+        value_name = "MODE",
+        value_parser = ["fixed", "exponential"],
+        requires = "retries"
+    )]
+    pub retry_backoff: Option<String>,
+
+    /// Longest delay exponential backoff may reach (default: 5m)
+    #[arg(
+        long = "retry-max-delay",
+        value_name = "DURATION",
+        requires = "retries"
+    )]
+    pub retry_max_delay: Option<String>,
+
+    /// Stop everything once DURATION has passed since the first attempt
+    /// started, counting all attempts and the delays between them
+    #[arg(long = "total-timeout", value_name = "DURATION")]
+    pub total_timeout: Option<String>,
+
+    /// Treat an attempt cut short by --total-timeout as an ordinary timeout
+    /// (honouring --status and --preserve-status) instead of exiting 124
+    #[arg(long = "clamp-last-attempt", requires = "total_timeout")]
+    pub clamp_last_attempt: bool,
+
+    /// Also write COMMAND's output to FILE, each line timestamped and tagged [O] or [E]
+    #[arg(long = "log-file", value_name = "FILE")]
+    pub log_file: Option<String>,
+
+    /// Append to the --log-file instead of truncating it
+    #[arg(long = "log-append", requires = "log_file")]
+    pub log_append: bool,
+
+    /// Also record how each run ended in TARGET: journald, syslog[:FACILITY]
+    /// for /dev/log, or syslog://HOST[:PORT][/FACILITY] (UDP) or
+    /// syslog+tcp://... for a remote server; may be repeated
+    #[arg(long = "log", value_name = "TARGET")]
+    pub log: Vec<String>,
+
+    /// Write --log syslog messages as RFC 5424 (default) or RFC 3164
+    #[arg(
+        long = "syslog-format",
+        value_name = "FORMAT",
+        value_parser = ["rfc5424", "rfc3164"],
+        requires = "log"
+    )]
+    pub syslog_format: Option<String>,
+
+    /// Export a span for each run over OTLP/HTTP to
+    /// OTEL_EXPORTER_OTLP_ENDPOINT, as a child of TRACEPARENT, and hand
+    /// COMMAND a TRACEPARENT of its own
+    #[arg(long = "otel")]
+    pub otel: bool,
+
+    /// Send StatsD datapoints for each run over UDP to HOST[:PORT] (port
+    /// 8125 by default): a runs counter by outcome, an elapsed_ms timer and
+    /// exit_code and kill_after_used gauges
+    #[arg(long = "statsd", value_name = "HOST:PORT")]
+    pub statsd: Option<String>,
+
+    /// Start --statsd metric names with PREFIX (default: timeout)
+    #[arg(long = "statsd-prefix", value_name = "PREFIX", requires = "statsd")]
+    pub statsd_prefix: Option<String>,
+
+    /// Tag --statsd datapoints with command, timed_out and outcome, in the
+    /// DogStatsD format
+    #[arg(long = "statsd-tags", requires = "statsd")]
+    pub statsd_tags: bool,
+
+    /// After each run, rewrite PATH in the Prometheus text format for
+    /// node_exporter's textfile collector: gauges for the last run and a
+    /// timeout_runs_total counter kept from the file already there
+    #[arg(long = "prom-textfile", value_name = "PATH")]
+    pub prom_textfile: Option<String>,
+
+    /// Append the TIMEOUT_METRICS record of each run to PATH instead of
+    /// printing it on stderr; overrides TIMEOUT_METRICS_FILE
+    #[arg(long = "metrics-file", value_name = "PATH")]
+    pub metrics_file: Option<String>,
+
+    /// Write the TIMEOUT_METRICS record of each run to the inherited file
+    /// descriptor N, e.g. 3 from `3>metrics.log`
+    #[cfg(unix)]
+    #[arg(
+        long = "metrics-fd",
+        value_name = "N",
+        value_parser = clap::value_parser!(i32).range(0..),
+        conflicts_with = "metrics_file"
+    )]
+    pub metrics_fd: Option<i32>,
+
+    /// Write one JSON object describing how the run ended (timed_out,
+    /// exit_code, signal, elapsed_ms, child_pid) to the inherited file
+    /// descriptor N, e.g. 3 from `3>result.json`
+    #[cfg(unix)]
+    #[arg(
+        long = "status-fd",
+        value_name = "N",
+        value_parser = clap::value_parser!(i32).range(0..)
+    )]
+    pub status_fd: Option<i32>,
+
+    /// Write metrics records as json (default), logfmt key=value pairs, or
+    /// csv rows under a header row
+    #[arg(
+        long = "metrics-format",
+        value_name = "FORMAT",
+        value_parser = ["json", "logfmt", "csv"]
+    )]
+    pub metrics_format: Option<String>,
+
+    /// Print one line on stderr at the end of each run, laid out by
+    /// FORMAT's {placeholders}, e.g. '[{outcome}] {command} took {elapsed}';
+    /// {{ and }} are literal braces
+    #[arg(long = "summary-format", value_name = "FORMAT")]
+    pub summary_format: Option<String>,
+
+    /// Append the --summary-format line to FILE instead of stderr
+    #[arg(
+        long = "summary-output",
+        value_name = "FILE",
+        requires = "summary_format"
+    )]
+    pub summary_output: Option<String>,
+
+    /// Once COMMAND has finished or been stopped, print a time(1)-style
+    /// report on stderr: real, user and system time, peak resident set
+    /// size and whether it timed out
+    #[arg(long = "time")]
+    pub time: bool,
+
+    /// Lay out the --time report with GNU time's directives, e.g.
+    /// '%e %U %S %M' (and %T for timed out); implies --time
+    #[arg(long = "time-format", value_name = "FORMAT")]
+    pub time_format: Option<String>,
+
+    /// Write the --time report to FILE instead of stderr; implies --time
+    #[arg(long = "time-output", value_name = "FILE")]
+    pub time_output: Option<String>,
+
+    /// Where COMMAND's stdin comes from: inherit (default), null, close, or a FILE to read
+    #[arg(long = "stdin", value_name = "SOURCE")]
+    pub stdin: Option<String>,
+
+    /// Send COMMAND's stdout to FILE instead of timeout's stdout
+    #[arg(long = "stdout", value_name = "FILE")]
+    pub stdout: Option<String>,
+
+    /// Append to the --stdout FILE instead of truncating it
+    #[arg(long = "stdout-append", requires = "stdout")]
+    pub stdout_append: bool,
+
+    /// Send COMMAND's stderr to FILE instead of timeout's stderr
+    #[arg(long = "stderr", value_name = "FILE", conflicts_with = "merge_output")]
+    pub stderr: Option<String>,
+
+    /// Append to the --stderr FILE instead of truncating it
+    #[arg(long = "stderr-append", requires = "stderr")]
+    pub stderr_append: bool,
+
+    /// Send COMMAND's stderr wherever its stdout goes, like 2>&1
+    #[arg(long = "merge-output")]
+    pub merge_output: bool,
+
+    /// Discard COMMAND's stdout and stderr
+    #[arg(
+        long = "quiet-child",
+        conflicts_with_all = ["stdout", "stderr", "merge_output"]
+    )]
+    pub quiet_child: bool,
+
     /// When not running timeout directly from a shell prompt,
     /// allow COMMAND to read from the TTY and get TTY signals
+    ///
+    /// COMMAND stays in the terminal's foreground process group instead of
+    /// getting one of its own, so it can read the terminal and Ctrl-C and
+    /// Ctrl-Z reach it. Only COMMAND itself is signalled at the deadline:
+    /// any children it started are not timed out.
+    ///
+    /// Example: timeout --foreground 10m vim notes.txt
     #[cfg(unix)]
     #[arg(short = 'f', long = "foreground")]
     pub foreground: bool,
 
+    /// Leave COMMAND in timeout's own process group and signal only
+    /// COMMAND itself, not what it started; unlike --foreground, TTY
+    /// signals are not affected
+    #[cfg(unix)]
+    #[arg(
+        long = "no-process-group",
+        conflicts_with_all = ["pty", "stop_instead"]
+    )]
+    pub no_process_group: bool,
+
+    /// Start COMMAND in a new session of its own, as setsid(1) does: it
+    /// leads a new process group and has no controlling terminal
+    #[cfg(unix)]
+    #[arg(
+        long = "set-session",
+        conflicts_with_all = ["foreground", "no_process_group", "pty"]
+    )]
+    pub set_session: bool,
+
+    /// Write COMMAND's PID to PATH once it has started, and on Unix its
+    /// process group ID on a second line; removed when COMMAND ends
+    #[arg(
+        long = "pid-file",
+        value_name = "PATH",
+        conflicts_with_all = ["batch", "batch_newline", "parallel"]
+    )]
+    pub pid_file: Option<String>,
+
+    /// Overwrite an existing --pid-file PATH instead of refusing to start
+    #[arg(long = "pid-file-force", requires = "pid_file")]
+    pub pid_file_force: bool,
+
+    /// Run COMMAND on a pseudo-terminal, so it still sees a TTY while
+    /// timeout relays (and can watch, log or timestamp) its output
+    #[cfg(unix)]
+    #[arg(
+        long = "pty",
+        conflicts_with_all = ["foreground", "stdin", "stdout", "stderr", "merge_output", "quiet_child"]
+    )]
+    pub pty: bool,
+
+    /// Start COMMAND without a console (CREATE_NO_WINDOW), for tools that
+    /// write straight to the console even when their output is redirected
+    #[cfg(windows)]
+    #[arg(long = "no-window", visible_alias = "detach")]
+    pub no_window: bool,
+
+    /// Extend the time limit by this much each time timeout receives SIGUSR1
+    /// (default: 5m); SIGUSR2 prints the time left
+    #[cfg(unix)]
+    #[arg(long = "extend-step", value_name = "DURATION")]
+    pub extend_step: Option<String>,
+
+    /// Pass SIGUSR1 and SIGUSR2 on to COMMAND instead of using them to
+    /// extend or report the time limit
+    #[cfg(unix)]
+    #[arg(long = "forward-signals", conflicts_with = "extend_step")]
+    pub forward_signals: bool,
+
+    /// Serve status, extend, expire and kill requests for the running COMMAND
+    /// on a Unix domain socket at PATH (see README for the protocol)
+    #[cfg(unix)]
+    #[arg(
+        long = "control-socket",
+        value_name = "PATH",
+        conflicts_with_all = ["batch", "batch_newline", "parallel"]
+    )]
+    pub control_socket: Option<String>,
+
+    /// Replace whatever already exists at the --control-socket PATH
+    #[cfg(unix)]
+    #[arg(long = "control-socket-force", requires = "control_socket")]
+    pub control_socket_force: bool,
+
+    /// Send REQUEST (status, extend DURATION, expire or kill) to the timeout
+    /// serving the control socket at PATH, print the reply and exit
+    #[cfg(unix)]
+    #[arg(
+        long = "control",
+        value_names = ["PATH", "REQUEST"],
+        num_args = 2..=3,
+        conflicts_with = "control_socket"
+    )]
+    pub control: Option<Vec<String>>,
+
+    /// Apply the time limit to the already running process PID instead of
+    /// starting COMMAND; exits 0 if it ends in time
+    #[cfg(unix)]
+    #[arg(
+        long = "pid",
+        value_name = "PID",
+        value_parser = clap::value_parser!(i32).range(1..),
+        conflicts_with_all = [
+            "command", "foreground", "no_process_group", "set_session", "preserve_status", "detect_stopped", "pty",
+            "stop_instead", "no_kill", "check", "dry_run", "porcelain", "batch", "batch_newline",
+            "parallel", "every", "retries", "idle_timeout", "max_output",
+            "max_output_stdout", "max_output_stderr", "kill_on_pattern",
+            "success_on_pattern", "timestamps", "log_file", "stdin", "stdout",
+            "stderr", "merge_output", "quiet_child", "sd_notify", "pid_file",
+            "simulate_timeout", "cpu_affinity", "ionice", "heartbeat", "chroot"
+        ]
+    )]
+    #[cfg_attr(
+        any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"),
+        arg(conflicts_with_all = ["cpu_limit", "mem_limit"])
+    )]
+    #[cfg_attr(target_os = "linux", arg(conflicts_with = "namespaced"))]
+    pub pid: Option<i32>,
+
+    /// How often to check whether the --pid process has exited where pidfds
+    /// are not available (default: 100ms)
+    #[cfg(unix)]
+    #[arg(long = "poll-interval", value_name = "DURATION", requires = "pid")]
+    pub poll_interval: Option<String>,
+
     /// Exit with the same status as COMMAND, even when the command times out
     #[arg(long = "preserve-status")]
     pub preserve_status: bool,
 
-    /// Diagnose to stderr any signal sent upon timeout
-    #[arg(short = 'v', long = "verbose")]
-    pub verbose: bool,
+    /// Diagnose to stderr any signal sent upon timeout; -vv adds each step
+    /// (limits, signals, wait results), -vvv timeout's internal events
+    #[arg(short = 'v', long = "verbose", action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Leave timeout's own warnings and notes off stderr, and its error
+    /// messages too once a --log target has recorded them; -qq leaves off
+    /// every error message, so only the exit status tells
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        action = clap::ArgAction::Count,
+        conflicts_with = "verbose"
+    )]
+    pub quiet: u8,
+
+    /// Color timeout's own messages: auto (only when stderr is a terminal
+    /// and NO_COLOR is not set), always or never
+    #[arg(
+        long = "color",
+        value_name = "WHEN",
+        default_value = "auto",
+        value_parser = ["auto", "always", "never"]
+    )]
+    pub color: String,
+
+    /// Keep a line on stderr with the time used and left, and once the
+    /// deadline passes how COMMAND is being stopped; only when stderr is a
+    /// terminal and --quiet is not given
+    #[arg(long = "progress", conflicts_with_all = ["batch", "batch_newline", "parallel"])]
+    #[cfg_attr(unix, arg(conflicts_with = "pid"))]
+    pub progress: bool,
+
+    /// How often --progress redraws its line [default: 0.5s]
+    #[arg(
+        long = "progress-interval",
+        value_name = "DURATION",
+        requires = "progress"
+    )]
+    pub progress_interval: Option<String>,
+
+    /// Print a line every INTERVAL while COMMAND runs, e.g. "[timeout]
+    /// still running make (2m 0s elapsed, 8m 0s remaining)", or TEXT
+    /// instead, so CI watchdogs that kill silent jobs leave it alone
+    #[arg(long = "heartbeat", value_name = "INTERVAL[:TEXT]")]
+    pub heartbeat: Option<String>,
+
+    /// Print --heartbeat lines on stderr instead of stdout
+    #[arg(long = "heartbeat-stderr", requires = "heartbeat")]
+    pub heartbeat_stderr: bool,
 
     /// Detect and report when process is stopped (SIGSTOP, SIGTSTP, etc.)
+    ///
+    /// When COMMAND is stopped, by Ctrl-Z or SIGSTOP for instance, timeout
+    /// records it in the run's metrics as stopped_detected, reports it with
+    /// -v, and resumes COMMAND with SIGCONT. Stops are only reported through
+    /// SIGCHLD, so timeout watches for COMMAND's exit that way rather than
+    /// through a pidfd.
+    ///
+    /// Example: timeout -v --detect-stopped 30s ./interactive-program
     #[cfg(unix)]
     #[arg(long = "detect-stopped")]
     pub detect_stopped: bool,
 
     /// Do not send the initial signal when timeout expires (send only kill signal)
+    ///
+    /// Give it with --kill-after: nothing is sent at the deadline, and
+    /// COMMAND gets SIGKILL when the --kill-after grace period runs out.
+    /// Without --kill-after nothing is ever sent and timeout waits for
+    /// COMMAND to exit by itself. Useful for a COMMAND that handles SIGTERM
+    /// badly.
+    ///
+    /// Example: timeout --no-notify -k 5s 1m ./daemon
     #[cfg(unix)]
     #[arg(long = "no-notify")]
     pub no_notify: bool,
 
+    /// Suspend COMMAND with SIGSTOP instead of signalling it when it times
+    /// out, and leave it stopped for a debugger; timeout prints its PID and exits
+    #[cfg(unix)]
+    #[arg(
+        long = "stop-instead",
+        conflicts_with_all = ["kill_after", "signal", "pre_kill", "no_kill", "no_notify", "foreground", "retries", "every"]
+    )]
+    pub stop_instead: bool,
+
     /// Exit with this status code on timeout instead of 124
     #[arg(long = "status", value_name = "STATUS")]
     pub status_on_timeout: Option<i32>,
 
+    /// Exit 0 when COMMAND exits with status N, e.g. 1 for grep finding no
+    /// match; may be repeated. A timeout still exits 124.
+    #[arg(
+        long = "suppress-exit-code",
+        value_name = "N",
+        value_parser = clap::value_parser!(i32).range(1..=255)
+    )]
+    pub suppress_exit_code: Vec<i32>,
+
     /// Limit CPU time in seconds (Linux/FreeBSD/DragonFly only)
     #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
-    #[arg(long = "cpu-limit", value_name = "SECONDS")]
+    #[arg(
+        long = "cpu-limit",
+        value_name = "SECONDS",
+        overrides_with = "no_cpu_limit"
+    )]
     pub cpu_limit: Option<u64>,
 
     /// Limit memory usage (Linux/FreeBSD/DragonFly only)
-    /// Accepts values like "100M", "1G", "512K", or raw bytes
+    /// Accepts values like "100M", "1G", "512K", "500MB" (decimal), "2GiB", "1T", "50%" of RAM, or raw bytes
     #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
-    #[arg(long = "mem-limit", value_name = "SIZE")]
+    #[arg(
+        long = "mem-limit",
+        value_name = "SIZE",
+        overrides_with = "no_mem_limit"
+    )]
     pub mem_limit: Option<String>,
 
-    /// Duration before timeout (e.g., 10, 10s, 5m, 2h, 1d). If no unit, seconds are assumed.
+    /// Run COMMAND only on these CPUs, e.g. 0,2-4, so a benchmark does not
+    /// migrate between cores (Linux only)
+    #[arg(long = "cpu-affinity", value_name = "CPUS")]
+    pub cpu_affinity: Option<String>,
+
+    /// Give COMMAND this I/O scheduling class and level, e.g.
+    /// best-effort:4 or idle, as ionice does (Linux only)
+    #[arg(long = "ionice", value_name = "CLASS[:LEVEL]")]
+    pub ionice: Option<String>,
+
+    /// Run COMMAND in new mount, network, UTS and IPC namespaces, with no
+    /// network and its own mounts and hostname; needs CAP_SYS_ADMIN or
+    /// unprivileged user namespaces
+    #[cfg(target_os = "linux")]
+    #[arg(long = "namespaced")]
+    pub namespaced: bool,
+
+    /// Confine COMMAND to DIR with chroot, looking it up on PATH inside;
+    /// DIR must hold COMMAND and everything it needs (root or
+    /// CAP_SYS_CHROOT)
+    #[cfg(unix)]
+    #[arg(long = "chroot", value_name = "DIR", conflicts_with = "check")]
+    pub chroot: Option<String>,
+
+    /// Run COMMAND without a CPU time limit, overriding an earlier --cpu-limit
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+    #[arg(long = "no-cpu-limit", overrides_with = "cpu_limit")]
+    pub no_cpu_limit: bool,
+
+    /// Run COMMAND without a memory limit, overriding an earlier --mem-limit
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+    #[arg(long = "no-mem-limit", overrides_with = "mem_limit")]
+    pub no_mem_limit: bool,
+
+    /// Resolve COMMAND against PATH and exit 0, 126 or 127 without running it
+    #[arg(long = "check", conflicts_with_all = ["batch", "batch_newline"])]
+    pub check: bool,
+
+    /// Time COMMAND out as soon as it starts instead of after DURATION, to
+    /// try out --on-timeout, --kill-after, --status and the metrics without
+    /// waiting; with --dry-run, show what the timeout would do
+    #[arg(long = "simulate-timeout")]
+    pub simulate_timeout: bool,
+
+    /// Print the execution plan (text or json) and exit without running COMMAND
     #[arg(
-        value_name = "DURATION",
-        required_unless_present = "generate_completions"
+        long = "dry-run",
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "text",
+        value_parser = ["text", "json"],
+        conflicts_with_all = ["batch", "batch_newline", "check"]
+    )]
+    pub dry_run: Option<String>,
+
+    /// Print the execution plan as JSON and exit, the same as --dry-run=json
+    #[arg(
+        long = "porcelain",
+        conflicts_with_all = ["dry_run", "batch", "batch_newline", "check"]
+    )]
+    pub porcelain: bool,
+
+    /// Run COMMAND again every INTERVAL, measured from one start to the next
+    #[arg(
+        long = "every",
+        value_name = "INTERVAL",
+        conflicts_with_all = ["batch", "batch_newline", "parallel"]
+    )]
+    pub every: Option<String>,
+
+    /// Stop watching after N runs (default: run until interrupted)
+    #[arg(
+        long = "count",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        requires = "every"
+    )]
+    pub count: Option<u32>,
+
+    /// Stop watching at the first run that fails or times out, exiting with its status
+    #[arg(long = "until-failure", requires = "every")]
+    pub until_failure: bool,
+
+    /// Stop watching at the first run that succeeds, exiting 0
+    #[arg(
+        long = "until-success",
+        requires = "every",
+        conflicts_with = "until_failure"
+    )]
+    pub until_success: bool,
+
+    /// Read NUL-terminated command lines from stdin and run each in turn.
+    /// Each is split into words as sh would: quotes group, a backslash
+    /// escapes, and nothing is expanded.
+    #[arg(long = "batch")]
+    pub batch: bool,
+
+    /// Like --batch, but command lines are newline-terminated
+    #[arg(long = "batch-newline", conflicts_with = "batch")]
+    pub batch_newline: bool,
+
+    /// Run N copies of COMMAND (or N batch command lines) at once
+    #[arg(
+        long = "parallel",
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["check", "dry_run", "porcelain"]
+    )]
+    pub parallel: Option<u32>,
+
+    /// Stop COMMAND at TIME instead of after DURATION: an RFC 3339 timestamp,
+    /// HH:MM[:SS] (the next time the local clock shows it) or +DURATION
+    #[arg(long = "until", value_name = "TIME")]
+    pub until: Option<String>,
+
+    /// Count DURATION on CLOCK_BOOTTIME, which keeps going while the
+    /// machine is suspended, so a suspend does not put the timeout off
+    #[cfg(target_os = "linux")]
+    #[arg(long = "boottime")]
+    pub boottime: bool,
+
+    /// Duration before timeout (e.g., 10, 10s, 5m, 2h, 1d). If no unit, seconds are assumed.
+    /// May be omitted when TIMEOUT_DURATION or --until is given
+    #[arg(value_name = "DURATION")]
+    #[cfg_attr(
+        unix,
+        arg(required_unless_present_any = ["generate_completions", "completions_dir", "dump_config", "generate_man", "info", "capabilities", "list_signals", "until", "control"])
+    )]
+    #[cfg_attr(
+        not(unix),
+        arg(required_unless_present_any = ["generate_completions", "completions_dir", "dump_config", "generate_man", "info", "capabilities", "until"])
     )]
     pub duration: Option<String>,
 
     /// Command to execute
     #[arg(
         value_name = "COMMAND",
-        required_unless_present = "generate_completions"
+        conflicts_with_all = ["batch", "batch_newline"],
+        value_parser = parse_command
+    )]
+    #[cfg_attr(
+        unix,
+        arg(required_unless_present_any = ["generate_completions", "completions_dir", "dump_config", "generate_man", "info", "capabilities", "list_signals", "batch", "batch_newline", "control", "pid"])
+    )]
+    #[cfg_attr(
+        not(unix),
+        arg(required_unless_present_any = ["generate_completions", "completions_dir", "dump_config", "generate_man", "info", "capabilities", "batch", "batch_newline"])
     )]
     pub command: Option<String>,
 
@@ -83,7 +806,32 @@ pub struct Args {
     pub args: Vec<String>,
 }
 
+/// Reject empty or whitespace-only COMMAND values up front
+fn parse_command(s: &str) -> Result<String, String> {
+    if s.trim().is_empty() {
+        Err("command must not be empty".to_string())
+    } else {
+        Ok(s.to_string())
+    }
+}
+
 impl Args {
+    /// Record terminator for batch mode, or None when not in batch mode
+    pub fn batch_delimiter(&self) -> Option<u8> {
+        if self.batch_newline {
+            Some(b'\n')
+        } else if self.batch {
+            Some(b'\0')
+        } else {
+            None
+        }
+    }
+
+    /// Whether -v was given at all
+    pub fn verbose(&self) -> bool {
+        self.verbose > 0
+    }
+
     /// Get foreground setting with default for non-Unix platforms
     #[cfg(not(unix))]
     pub fn foreground(&self) -> bool {
@@ -95,6 +843,121 @@ impl Args {
         self.foreground
     }
 
+    /// Whether --no-process-group was given; never on non-Unix platforms
+    #[cfg(not(unix))]
+    pub fn no_process_group(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    pub fn no_process_group(&self) -> bool {
+        self.no_process_group
+    }
+
+    /// Whether --set-session was given; never on non-Unix platforms
+    #[cfg(not(unix))]
+    pub fn set_session(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    pub fn set_session(&self) -> bool {
+        self.set_session
+    }
+
+    /// The --dry-run format, `json` for --porcelain
+    pub fn dry_run(&self) -> Option<&str> {
+        self.dry_run.as_deref().or(self.porcelain.then_some("json"))
+    }
+
+    /// Whether --list-signals was given; never on non-Unix platforms
+    #[cfg(not(unix))]
+    pub fn list_signals(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    pub fn list_signals(&self) -> bool {
+        self.list_signals
+    }
+
+    /// The --control PATH and REQUEST words; never given on non-Unix platforms
+    #[cfg(not(unix))]
+    pub fn control(&self) -> Option<&[String]> {
+        None
+    }
+
+    #[cfg(unix)]
+    pub fn control(&self) -> Option<&[String]> {
+        self.control.as_deref()
+    }
+
+    /// The --status-fd descriptor; never given on non-Unix platforms
+    #[cfg(not(unix))]
+    pub fn status_fd(&self) -> Option<i32> {
+        None
+    }
+
+    #[cfg(unix)]
+    pub fn status_fd(&self) -> Option<i32> {
+        self.status_fd
+    }
+
+    /// Get pty setting with default for non-Unix platforms
+    #[cfg(not(unix))]
+    pub fn pty(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    pub fn pty(&self) -> bool {
+        self.pty
+    }
+
+    /// Whether --no-window was given; never on non-Windows platforms
+    #[cfg(not(windows))]
+    pub fn no_window(&self) -> bool {
+        false
+    }
+
+    #[cfg(windows)]
+    pub fn no_window(&self) -> bool {
+        self.no_window
+    }
+
+    /// Whether --boottime was given; never outside Linux
+    #[cfg(not(target_os = "linux"))]
+    pub fn boottime(&self) -> bool {
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn boottime(&self) -> bool {
+        self.boottime
+    }
+
+    /// Whether --namespaced was given; never outside Linux
+    #[cfg(not(target_os = "linux"))]
+    pub fn namespaced(&self) -> bool {
+        false
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn namespaced(&self) -> bool {
+        self.namespaced
+    }
+
+    /// The --chroot directory; never outside Unix
+    #[cfg(not(unix))]
+    pub fn chroot(&self) -> Option<&Path> {
+        None
+    }
+
+    #[cfg(unix)]
+    pub fn chroot(&self) -> Option<&Path> {
+        self.chroot.as_deref().map(Path::new)
+    }
+
     /// Get detect_stopped setting with default for non-Unix platforms
     #[cfg(not(unix))]
     pub fn detect_stopped(&self) -> bool {
@@ -0,0 +1,145 @@
+// src/batch.rs
+// --batch and --parallel: command lines read from stdin, run one after
+// another or several at a time
+
+use crate::args::Args;
+use crate::driver::run_argv;
+use crate::logging::{error, info};
+use crate::plan::Plan;
+use crate::{TimeoutError, EXIT_CANCELED};
+use owo_colors::OwoColorize;
+use std::io::{self, Read};
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+/// Split batch input into command lines; empty records are skipped. Each
+/// record is split into words the way sh would, so quotes group and a
+/// backslash escapes, but nothing is expanded.
+fn split_batch(input: &[u8], delimiter: u8) -> Result<Vec<Vec<String>>, TimeoutError> {
+    let invalid = |index: usize, record: &[u8], reason| TimeoutError::InvalidBatch {
+        record: index + 1,
+        input: String::from_utf8_lossy(record).into_owned(),
+        reason,
+    };
+    let mut commands = Vec::new();
+    for (index, record) in input.split(|&b| b == delimiter).enumerate() {
+        let words = shlex::bytes::split(record)
+            .ok_or_else(|| invalid(index, record, "unterminated quote or trailing backslash"))?;
+        let argv = words
+            .into_iter()
+            .map(batch_word)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid(index, record, "invalid UTF-8"))?;
+        if !argv.is_empty() {
+            commands.push(argv);
+        }
+    }
+    Ok(commands)
+}
+
+/// One word of a batch command line, from its raw bytes. Arguments are
+/// passed on as strings, as on the command line, so a word that is not
+/// UTF-8 is refused rather than mangled.
+fn batch_word(word: Vec<u8>) -> Option<String> {
+    #[cfg(unix)]
+    let word = {
+        use std::os::unix::ffi::OsStringExt;
+        std::ffi::OsString::from_vec(word).into_string().ok()
+    };
+    #[cfg(not(unix))]
+    let word = String::from_utf8(word).ok();
+    word
+}
+
+/// Read all batch command lines from stdin. A failure is reported here and
+/// its exit status returned, before any command runs.
+pub fn read_batch(delimiter: u8) -> Result<Vec<Vec<String>>, i32> {
+    let mut input = Vec::new();
+    if let Err(e) = io::stdin().read_to_end(&mut input) {
+        error!("{}: failed to read batch input: {}", "timeout".red(), e);
+        return Err(EXIT_CANCELED);
+    }
+    split_batch(&input, delimiter).map_err(|e| {
+        error!("{}: {}", "timeout".red(), e);
+        e.exit_code()
+    })
+}
+
+/// Run every command line read from stdin sequentially, returning the
+/// highest exit code seen (0 if all succeeded)
+pub async fn run_batch(args: &Args, plan: &Plan, delimiter: u8) -> i32 {
+    let commands = match read_batch(delimiter) {
+        Ok(commands) => commands,
+        Err(code) => return code,
+    };
+
+    let mut worst = 0;
+    for argv in commands {
+        let code = run_argv(args, plan, &argv, 1).await;
+
+        if args.verbose() {
+            info!(
+                "{}: '{}' exited with status {}",
+                "Batch".cyan(),
+                argv.join(" "),
+                code
+            );
+        }
+
+        worst = worst.max(code);
+    }
+
+    worst
+}
+
+/// Run command lines with at most `jobs` of them in flight, each under its
+/// own timeout. Results are reported as they complete; returns the highest
+/// exit code seen.
+pub async fn run_parallel(
+    args: Arc<Args>,
+    plan: Arc<Plan>,
+    commands: Vec<Vec<String>>,
+    jobs: usize,
+) -> i32 {
+    let mut pending = commands.into_iter();
+    let mut set = JoinSet::new();
+    let mut worst = 0;
+
+    loop {
+        while set.len() < jobs {
+            let Some(argv) = pending.next() else {
+                break;
+            };
+            let args = Arc::clone(&args);
+            let plan = Arc::clone(&plan);
+            set.spawn(async move {
+                let code = run_argv(&args, &plan, &argv, 1).await;
+                (argv, code)
+            });
+        }
+
+        let Some(joined) = set.join_next().await else {
+            break;
+        };
+
+        let code = match joined {
+            Ok((argv, code)) => {
+                info!(
+                    "{}: '{}' exited with status {}",
+                    "Parallel".cyan(),
+                    argv.join(" "),
+                    code
+                );
+                code
+            }
+            Err(e) => {
+                error!("{}: parallel task failed: {}", "timeout".red(), e);
+                EXIT_CANCELED
+            }
+        };
+
+        worst = worst.max(code);
+    }
+
+    worst
+}
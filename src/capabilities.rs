@@ -0,0 +1,336 @@
+// src/capabilities.rs
+// What this build can do on this machine: compile-time facts from Platform
+// plus runtime probes of the kernel. Behind --capabilities, and behind the
+// warnings about options that cannot take effect, so the two never disagree.
+
+use crate::Platform;
+use serde::{Serialize, Serializer};
+
+/// Bumped whenever a key is renamed or removed; new features are additions
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// One feature, and why it cannot be used when it cannot. Serialized as
+/// `{"supported":true}`, with `reason` or `detail` when set; the name is
+/// the key it is listed under.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Feature {
+    #[serde(skip)]
+    pub name: &'static str,
+    pub supported: bool,
+    /// Why not, when unsupported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// More about a supported feature, e.g. the Landlock ABI level
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Feature {
+    fn supported(name: &'static str) -> Feature {
+        Feature {
+            name,
+            supported: true,
+            reason: None,
+            detail: None,
+        }
+    }
+
+    fn unsupported(name: &'static str, reason: impl Into<String>) -> Feature {
+        Feature {
+            name,
+            supported: false,
+            reason: Some(reason.into()),
+            detail: None,
+        }
+    }
+
+    /// `reason`, or nothing for a supported feature
+    pub fn why_not(&self) -> &str {
+        self.reason.as_deref().unwrap_or_default()
+    }
+
+    fn with_detail(mut self, detail: impl Into<String>) -> Feature {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Every feature, in the order --capabilities lists them
+pub fn all() -> Vec<Feature> {
+    vec![
+        cpu_limit(),
+        mem_limit(),
+        pdeathsig(),
+        pidfd(),
+        clone3(),
+        signalfd(),
+        kqueue(),
+        cgroup_v2(),
+        landlock(),
+        cpu_affinity(),
+        ionice(),
+        namespaces(),
+        boottime(),
+        pty(),
+        job_objects(),
+    ]
+}
+
+/// The --capabilities document: see "Capabilities" in the README for the
+/// schema
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    schema_version: u32,
+    version: &'static str,
+    platform: &'static str,
+    release: Option<String>,
+    #[serde(serialize_with = "by_name")]
+    features: &'a [Feature],
+}
+
+/// Features as an object keyed by name, in the order given
+fn by_name<S: Serializer>(features: &&[Feature], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(features.iter().map(|feature| (feature.name, feature)))
+}
+
+/// `features` as the --capabilities document
+pub fn to_json(features: &[Feature]) -> String {
+    let report = Report {
+        schema_version: SCHEMA_VERSION,
+        version: env!("CARGO_PKG_VERSION"),
+        platform: Platform::name(),
+        release: Platform::os_release(),
+        features,
+    };
+    serde_json::to_string(&report).expect("the capabilities report serializes")
+}
+
+/// The reason for a feature this target is never built with
+fn only_on(platforms: &str) -> String {
+    format!("only available on {}, not {}", platforms, Platform::name())
+}
+
+/// --cpu-limit, through RLIMIT_CPU
+pub fn cpu_limit() -> Feature {
+    if Platform::HAS_RLIMIT_AS {
+        Feature::supported("cpu_limit")
+    } else {
+        Feature::unsupported("cpu_limit", only_on("Linux, FreeBSD and DragonFly BSD"))
+    }
+}
+
+/// --mem-limit, through RLIMIT_AS (RLIMIT_DATA on the BSDs)
+pub fn mem_limit() -> Feature {
+    if Platform::HAS_RLIMIT_AS {
+        Feature::supported("mem_limit")
+    } else {
+        Feature::unsupported("mem_limit", only_on("Linux, FreeBSD and DragonFly BSD"))
+    }
+}
+
+/// PR_SET_PDEATHSIG, so COMMAND dies with a killed timeout
+pub fn pdeathsig() -> Feature {
+    if Platform::HAS_PRCTL {
+        Feature::supported("pdeathsig")
+    } else {
+        Feature::unsupported("pdeathsig", only_on("Linux"))
+    }
+}
+
+/// Exit notification through a pidfd rather than SIGCHLD
+pub fn pidfd() -> Feature {
+    if !Platform::IS_LINUX {
+        Feature::unsupported("pidfd", only_on("Linux"))
+    } else if Platform::capabilities().has_pidfd {
+        Feature::supported("pidfd")
+    } else {
+        Feature::unsupported("pidfd", too_old_or_blocked("pidfd_open", (5, 3)))
+    }
+}
+
+/// The clone3 system call, probed with an argument size it must reject
+pub fn clone3() -> Feature {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::errno::Errno;
+        use nix::libc;
+
+        let result = unsafe { libc::syscall(libc::SYS_clone3, std::ptr::null::<u8>(), 0usize) };
+        match Errno::result(result) {
+            Err(Errno::EINVAL) => Feature::supported("clone3"),
+            Err(Errno::ENOSYS) => Feature::unsupported("clone3", too_old("clone3", (5, 3))),
+            Err(errno) => {
+                Feature::unsupported("clone3", format!("clone3 failed: {}", errno.desc()))
+            }
+            // Not possible with no arguments, but a probe must not fork
+            Ok(_) => Feature::unsupported("clone3", "unexpected answer to the probe"),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    Feature::unsupported("clone3", only_on("Linux"))
+}
+
+pub fn signalfd() -> Feature {
+    if Platform::capabilities().has_signalfd {
+        Feature::supported("signalfd")
+    } else {
+        Feature::unsupported("signalfd", only_on("Linux"))
+    }
+}
+
+pub fn kqueue() -> Feature {
+    if Platform::capabilities().has_kqueue {
+        Feature::supported("kqueue")
+    } else {
+        Feature::unsupported("kqueue", only_on("macOS and the BSDs"))
+    }
+}
+
+/// A unified cgroup v2 hierarchy at /sys/fs/cgroup
+pub fn cgroup_v2() -> Feature {
+    if !Platform::IS_LINUX {
+        Feature::unsupported("cgroup_v2", only_on("Linux"))
+    } else if Platform::capabilities().has_cgroup_v2 {
+        Feature::supported("cgroup_v2")
+    } else {
+        Feature::unsupported(
+            "cgroup_v2",
+            "no cgroup v2 hierarchy is mounted at /sys/fs/cgroup",
+        )
+    }
+}
+
+/// The Landlock LSM, with the ABI level the kernel offers
+pub fn landlock() -> Feature {
+    #[cfg(target_os = "linux")]
+    {
+        use nix::errno::Errno;
+        use nix::libc;
+
+        /// Asks for the ABI version instead of creating a ruleset
+        const CREATE_RULESET_VERSION: libc::c_uint = 1;
+
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                std::ptr::null::<u8>(),
+                0usize,
+                CREATE_RULESET_VERSION,
+            )
+        };
+        match Errno::result(result) {
+            Ok(abi) => Feature::supported("landlock").with_detail(format!("ABI {}", abi)),
+            Err(Errno::ENOSYS) => Feature::unsupported("landlock", too_old("Landlock", (5, 13))),
+            Err(Errno::EOPNOTSUPP) => Feature::unsupported(
+                "landlock",
+                "built into the kernel but not enabled (see the lsm= boot parameter)",
+            ),
+            Err(errno) => Feature::unsupported(
+                "landlock",
+                format!("landlock_create_ruleset failed: {}", errno.desc()),
+            ),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    Feature::unsupported("landlock", only_on("Linux"))
+}
+
+/// --cpu-affinity
+pub fn cpu_affinity() -> Feature {
+    if Platform::IS_LINUX {
+        Feature::supported("cpu_affinity")
+    } else {
+        Feature::unsupported("cpu_affinity", only_on("Linux"))
+    }
+}
+
+/// --ionice
+pub fn ionice() -> Feature {
+    if Platform::IS_LINUX {
+        Feature::supported("ionice")
+    } else {
+        Feature::unsupported("ionice", only_on("Linux"))
+    }
+}
+
+/// --namespaced. Whether this user may create namespaces is only known
+/// by trying, which --capabilities does not do.
+pub fn namespaces() -> Feature {
+    if Platform::IS_LINUX {
+        Feature::supported("namespaces")
+    } else {
+        Feature::unsupported("namespaces", only_on("Linux"))
+    }
+}
+
+/// --boottime
+pub fn boottime() -> Feature {
+    if Platform::IS_LINUX {
+        Feature::supported("boottime")
+    } else {
+        Feature::unsupported("boottime", only_on("Linux"))
+    }
+}
+
+/// --pty
+pub fn pty() -> Feature {
+    if Platform::capabilities().has_pty {
+        Feature::supported("pty")
+    } else {
+        Feature::unsupported("pty", only_on("Unix"))
+    }
+}
+
+/// Windows job objects. timeout stops COMMAND by process ID rather than
+/// through a job; the detail says whether timeout itself already runs in
+/// one, as under many CI agents, which stops COMMAND's children with it.
+pub fn job_objects() -> Feature {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::JobObjects::IsProcessInJob;
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        let mut in_job = 0;
+        let probed = unsafe { IsProcessInJob(GetCurrentProcess(), 0, &mut in_job) } != 0;
+        Feature::supported("job_objects").with_detail(match (probed, in_job != 0) {
+            (false, _) => "could not tell whether timeout runs in a job object",
+            (true, true) => "timeout runs in a job object",
+            (true, false) => "timeout does not run in a job object",
+        })
+    }
+    #[cfg(not(windows))]
+    Feature::unsupported("job_objects", only_on("Windows"))
+}
+
+/// The running kernel's major and minor version, from e.g. `6.8.0-45-generic`
+fn kernel_version() -> Option<(u32, u32)> {
+    let release = Platform::os_release()?;
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// The reason for a system call this kernel lacks, `needed` being the
+/// version that added it
+fn too_old(what: &str, needed: (u32, u32)) -> String {
+    format!(
+        "{} needs Linux {}.{}, this is {}",
+        what,
+        needed.0,
+        needed.1,
+        Platform::os_release().unwrap_or_else(|| "an unknown release".to_string())
+    )
+}
+
+/// As `too_old`, unless the kernel is new enough and something such as a
+/// seccomp filter refused the call instead
+fn too_old_or_blocked(what: &str, needed: (u32, u32)) -> String {
+    match kernel_version() {
+        Some(version) if version >= needed => format!(
+            "{} is refused here although the kernel has it, e.g. by a seccomp filter",
+            what
+        ),
+        _ => too_old(what, needed),
+    }
+}
@@ -0,0 +1,261 @@
+// src/cli.rs
+// The command line and the defaults layered under it: TIMEOUT_OPTS,
+// TIMEOUT_DURATION and the other variables, the config file, and the
+// completion scripts generated from it
+
+use crate::args::Args;
+use crate::completions::CompletionShell;
+#[cfg(unix)]
+use crate::TimeoutSignal;
+use crate::{config, env_opts, parse_duration, TimeoutError, EXIT_CANCELED};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, ValueEnum};
+use clap_complete::generate_to;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+/// Write a completion script for every shell into `dir`, each under the
+/// name that shell looks for (timeout.bash, _timeout, timeout.fish, ...)
+pub fn write_completions(dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut cmd = Args::command();
+    for &shell in CompletionShell::value_variants() {
+        generate_to(shell, &mut cmd, "timeout", dir)?;
+    }
+    Ok(())
+}
+
+/// Apply TIMEOUT_OPTS, TIMEOUT_KILL_AFTER, TIMEOUT_SIGNAL and the config
+/// file, unless --no-config: their settings become defaults for the options
+/// the command line leaves out, and the command line is parsed again with
+/// them. The two single-purpose variables win over TIMEOUT_OPTS, and the
+/// environment wins over the file. A setting is dropped where a layer above
+/// it gives an option it conflicts with. Returns the file read, if any, and
+/// every setting the file may hold as now in effect.
+pub fn configure(
+    args: &mut Args,
+    matches: &ArgMatches,
+) -> Result<(Option<PathBuf>, config::Effective), TimeoutError> {
+    let var = |name: &str| std::env::var_os(name);
+    let invalid_env = |var: &str, reason: String| TimeoutError::InvalidEnv {
+        var: var.to_string(),
+        reason,
+    };
+    let cmd = Args::command();
+    let on_command_line = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+    let mut opts = match var(env_opts::VAR).filter(|value| !value.is_empty()) {
+        Some(value) => env_opts::split(&value.to_string_lossy())
+            .and_then(|words| env_opts::parse(&cmd, &words))
+            .map_err(|e| invalid_env(env_opts::VAR, e.0))?,
+        None => Vec::new(),
+    };
+    opts.retain(|opt| !conflicts_with_given(&cmd, &opt.id, on_command_line));
+    let opt = |id: &str| opts.iter().find(|opt| opt.id == id);
+    if let Some(duration) = opt("kill_after").and_then(|opt| opt.values.first()) {
+        parse_duration(duration)
+            .map_err(|e| invalid_env(env_opts::VAR, format!("--kill-after: {}", e)))?;
+    }
+
+    let mut env = config::environment(var);
+    if let Some(duration) = &env.kill_after {
+        parse_duration(duration).map_err(|e| invalid_env("TIMEOUT_KILL_AFTER", e.to_string()))?;
+    }
+    #[cfg(unix)]
+    if let Some(signal) = &env.signal {
+        TimeoutSignal::from_str_or_num(signal)
+            .map_err(|e| invalid_env("TIMEOUT_SIGNAL", e.to_string()))?;
+    }
+    let above_env = |id: &str| on_command_line(id) || opt(id).is_some();
+    for key in ["kill_after", "signal"] {
+        if conflicts_with_given(&cmd, key, above_env) {
+            env.unset(key);
+        }
+    }
+    // What TIMEOUT_KILL_AFTER and TIMEOUT_SIGNAL set, before TIMEOUT_OPTS
+    // fills in the rest
+    let single = [
+        ("kill_after", env.kill_after.clone()),
+        ("signal", env.signal.clone()),
+    ];
+    for opt in &opts {
+        if let Some(value) = opt.values.first() {
+            env.fill(&opt.id, value);
+        }
+    }
+
+    let no_config = args.no_config || opt("no_config").is_some();
+    let path = args.config.clone().or_else(|| {
+        opt("config")
+            .and_then(|opt| opt.values.first())
+            .map(PathBuf::from)
+    });
+    let file = if no_config {
+        None
+    } else {
+        config::load(path.as_deref(), var).map_err(|e| TimeoutError::InvalidConfig {
+            path: e.path.display().to_string(),
+            reason: e.reason,
+        })?
+    };
+
+    #[cfg(unix)]
+    let signal = args.signal.map(|signal| signal.to_string());
+    #[cfg(not(unix))]
+    let signal = args.signal.clone();
+    let cli = config::Settings {
+        kill_after: args.kill_after.clone(),
+        signal,
+        preserve_status: args.preserve_status.then_some(true),
+        verbose: (args.verbose > 0).then_some(true),
+        color: on_command_line("color").then(|| args.color.clone()),
+        metrics_file: args.metrics_file.clone(),
+    };
+
+    let mut settings = config::Settings::default();
+    if let Some(file) = &file {
+        let invalid = |key: &str, reason: String| TimeoutError::InvalidConfig {
+            path: file.path.display().to_string(),
+            reason: format!("{}: {}", key, reason),
+        };
+        if let Some(duration) = &file.settings.kill_after {
+            parse_duration(duration).map_err(|e| invalid("kill_after", e.to_string()))?;
+        }
+        #[cfg(unix)]
+        if let Some(signal) = &file.settings.signal {
+            TimeoutSignal::from_str_or_num(signal).map_err(|e| invalid("signal", e.to_string()))?;
+        }
+        settings = file.settings.clone();
+        let above_file = |id: &str| {
+            above_env(id)
+                || single
+                    .iter()
+                    .any(|(key, value)| *key == id && value.is_some())
+        };
+        for key in config::KEYS {
+            if conflicts_with_given(&cmd, key, above_file) {
+                settings.unset(key);
+            }
+        }
+    }
+    let effective = config::merge(&cli, &env, &settings);
+
+    // Later defaults replace earlier ones for the same option
+    let defaults: Vec<(String, Vec<String>)> = effective
+        .file_settings()
+        .into_iter()
+        .map(|(id, value)| (id.to_string(), vec![value]))
+        .chain(opts.into_iter().map(|opt| (opt.id, opt.values)))
+        .chain(
+            single
+                .into_iter()
+                .filter_map(|(id, value)| Some((id.to_string(), vec![value?]))),
+        )
+        .collect();
+    if !defaults.is_empty() {
+        let cmd = defaults.into_iter().fold(cmd, |cmd, (id, values)| {
+            cmd.mut_arg(id, |arg| arg.default_values(values))
+        });
+        *args = parse_args(&cmd).0;
+    }
+    Ok((file.map(|file| file.path), effective))
+}
+
+/// Whether an option `given` holds conflicts with the one with ID `id`,
+/// either way round
+fn conflicts_with_given(cmd: &clap::Command, id: &str, given: impl Fn(&str) -> bool) -> bool {
+    let given = |arg: &&clap::Arg| given(arg.get_id().as_str());
+    let Some(arg) = cmd.get_arguments().find(|arg| arg.get_id() == id) else {
+        return false;
+    };
+    cmd.get_arg_conflicts_with(arg).iter().any(given)
+        || cmd.get_arguments().filter(given).any(|other| {
+            cmd.get_arg_conflicts_with(other)
+                .iter()
+                .any(|conflict| conflict.get_id() == id)
+        })
+}
+
+/// Parse the command line, using TIMEOUT_DURATION when DURATION is left out.
+///
+/// Clap fills positionals left to right, so in `timeout cmd arg` the command
+/// lands in DURATION. When the first positional is missing or is not a valid
+/// duration, parse again with the default inserted in front. With --until
+/// the inserted DURATION is only a placeholder; the plan works out the real
+/// one from the deadline.
+///
+/// `cmd` is the Args command, with any defaults from a config file; the
+/// matches come back alongside, for where each value came from.
+pub fn parse_args(cmd: &clap::Command) -> (Args, ArgMatches) {
+    let argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let parse = |argv: &[std::ffi::OsString]| {
+        cmd.clone()
+            .try_get_matches_from(argv)
+            .and_then(|matches| Ok((Args::from_arg_matches(&matches)?, matches)))
+    };
+    let mut parsed = parse(&argv);
+
+    if let Ok((args, _)) = &parsed {
+        let has_duration = args
+            .duration
+            .as_deref()
+            .is_some_and(|d| parse_duration(d).is_ok());
+        if has_duration && args.until.is_some() {
+            Args::command()
+                .error(
+                    clap::error::ErrorKind::ArgumentConflict,
+                    "--until cannot be used with DURATION",
+                )
+                .exit();
+        }
+        if has_duration
+            || args.generate_completions.is_some()
+            || args.completions_dir.is_some()
+            || args.dump_config
+            || args.generate_man
+            || args.info
+            || args.capabilities
+            || args.list_signals()
+            || args.control().is_some()
+        {
+            return parsed.unwrap();
+        }
+    }
+
+    let until_given = argv
+        .iter()
+        .skip(1)
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--until" || arg.to_string_lossy().starts_with("--until="));
+    let default = if until_given {
+        Some("0".into())
+    } else {
+        std::env::var_os("TIMEOUT_DURATION")
+    };
+
+    if let Some(default) = default {
+        let mut with_default = argv.clone();
+        with_default.insert(1.min(argv.len()), default);
+        match parse(&with_default) {
+            Ok(parsed) => return parsed,
+            // --until has no DURATION to misplace, so its errors come from here
+            Err(e) if until_given => parsed = Err(e),
+            Err(_) => {}
+        }
+    }
+
+    parsed.unwrap_or_else(|e| {
+        // GNU timeout exits 125, not clap's usage status, for a bad signal
+        let bad_signal = e.kind() == clap::error::ErrorKind::ValueValidation
+            && matches!(
+                e.get(clap::error::ContextKind::InvalidArg),
+                Some(clap::error::ContextValue::String(arg)) if arg.starts_with("--signal")
+            );
+        if bad_signal {
+            let _ = e.print();
+            exit(EXIT_CANCELED);
+        }
+        e.exit()
+    })
+}
@@ -0,0 +1,163 @@
+// src/completions.rs
+// The shells --generate-completions and --completions-dir know: those
+// clap_complete supports, and Nushell, written here as an `extern`
+// declaration since clap_complete has no generator for it
+
+use clap::builder::PossibleValue;
+use clap::{Arg, ArgAction, Command, ValueEnum, ValueHint};
+use clap_complete::{Generator, Shell};
+use std::io::Write;
+
+/// A shell to write completions for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionShell {
+    Clap(Shell),
+    Nushell,
+}
+
+impl ValueEnum for CompletionShell {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            CompletionShell::Clap(Shell::Bash),
+            CompletionShell::Clap(Shell::Elvish),
+            CompletionShell::Clap(Shell::Fish),
+            CompletionShell::Clap(Shell::PowerShell),
+            CompletionShell::Clap(Shell::Zsh),
+            CompletionShell::Nushell,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        match self {
+            CompletionShell::Clap(shell) => shell.to_possible_value(),
+            CompletionShell::Nushell => Some(PossibleValue::new("nushell").alias("nu")),
+        }
+    }
+}
+
+impl Generator for CompletionShell {
+    fn file_name(&self, name: &str) -> String {
+        match self {
+            CompletionShell::Clap(shell) => shell.file_name(name),
+            CompletionShell::Nushell => format!("{}.nu", name),
+        }
+    }
+
+    fn generate(&self, cmd: &Command, buf: &mut dyn Write) {
+        match self {
+            CompletionShell::Clap(shell) => shell.generate(cmd, buf),
+            CompletionShell::Nushell => {
+                write_nushell(cmd, buf).expect("failed to write completion file")
+            }
+        }
+    }
+}
+
+/// A Nushell module declaring `cmd` as an `extern`, so Nushell completes
+/// its flags, their values where the choices are known, and its
+/// positionals. Load it with `use timeout.nu *`.
+fn write_nushell(cmd: &Command, buf: &mut dyn Write) -> std::io::Result<()> {
+    let name = cmd.get_bin_name().unwrap_or_else(|| cmd.get_name());
+    let visible = || cmd.get_arguments().filter(|arg| !arg.is_hide_set());
+
+    writeln!(buf, "module completions {{")?;
+    for arg in visible().filter(|arg| !arg.is_positional() && takes_value(arg)) {
+        let choices = choices(arg);
+        if choices.is_empty() {
+            continue;
+        }
+        writeln!(buf)?;
+        writeln!(buf, "  def \"{}\" [] {{", completer(name, arg))?;
+        writeln!(buf, "    [ {} ]", choices.join(" "))?;
+        writeln!(buf, "  }}")?;
+    }
+
+    writeln!(buf)?;
+    if let Some(about) = cmd.get_about() {
+        writeln!(buf, "  # {}", first_line(&about.to_string()))?;
+    }
+    writeln!(buf, "  export extern {} [", quote(name))?;
+    for arg in visible().filter(|arg| !arg.is_positional()) {
+        let mut flag = match (arg.get_long(), arg.get_short()) {
+            (Some(long), Some(short)) => format!("--{}(-{})", long, short),
+            (Some(long), None) => format!("--{}", long),
+            (None, Some(short)) => format!("-{}", short),
+            (None, None) => continue,
+        };
+        if takes_value(arg) {
+            flag += &format!(": {}", value_type(arg));
+            if !choices(arg).is_empty() {
+                flag += &format!("@{}", quote(&completer(name, arg)));
+            }
+        }
+        write_param(buf, &flag, arg)?;
+    }
+    for arg in visible().filter(|arg| arg.is_positional()) {
+        let id = arg.get_id().as_str().replace('_', "-");
+        let param = if matches!(arg.get_num_args(), Some(range) if range.max_values() > 1) {
+            format!("...{}: {}", id, value_type(arg))
+        } else if arg.is_required_set() {
+            format!("{}: {}", id, value_type(arg))
+        } else {
+            format!("{}?: {}", id, value_type(arg))
+        };
+        write_param(buf, &param, arg)?;
+    }
+    writeln!(buf, "  ]")?;
+    writeln!(buf, "}}")?;
+    writeln!(buf)?;
+    writeln!(buf, "export use completions *")
+}
+
+/// One parameter of the `extern`, with its help as a comment
+fn write_param(buf: &mut dyn Write, param: &str, arg: &Arg) -> std::io::Result<()> {
+    match arg.get_help() {
+        Some(help) => writeln!(buf, "    {} # {}", param, first_line(&help.to_string())),
+        None => writeln!(buf, "    {}", param),
+    }
+}
+
+/// Whether the flag takes a value rather than being a switch. One whose
+/// value is optional, like --dry-run[=json], is declared a switch, so
+/// Nushell does not take the next word for its value.
+fn takes_value(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Set | ArgAction::Append)
+        && arg
+            .get_num_args()
+            .is_some_and(|range| range.min_values() > 0)
+}
+
+/// The Nushell type for the arg's values
+fn value_type(arg: &Arg) -> &'static str {
+    match arg.get_value_hint() {
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath => "path",
+        _ => "string",
+    }
+}
+
+/// The name of the custom completer listing the arg's values
+fn completer(name: &str, arg: &Arg) -> String {
+    let flag = arg
+        .get_long()
+        .map_or_else(|| arg.get_id().to_string(), str::to_string);
+    format!("nu-complete {} {}", name, flag)
+}
+
+/// The values the arg accepts, quoted for Nushell; none when any is
+fn choices(arg: &Arg) -> Vec<String> {
+    arg.get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| quote(value.get_name()))
+        .collect()
+}
+
+/// A Nushell string literal
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// A help text's first line, to fit in a comment
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or_default().trim()
+}
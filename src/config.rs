@@ -0,0 +1,375 @@
+// src/config.rs
+// Default options from a TOML config file, for flags given to every run:
+// $TIMEOUT_CONFIG, else $XDG_CONFIG_HOME/timeout/config.toml, else
+// ~/.config/timeout/config.toml.
+
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The options a config file may set; any other key is an error
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    /// As --kill-after, e.g. "10s"
+    pub kill_after: Option<String>,
+    /// As --signal, e.g. "INT"
+    pub signal: Option<String>,
+    pub preserve_status: Option<bool>,
+    /// As a single -v
+    pub verbose: Option<bool>,
+    /// As --color: auto, always or never
+    pub color: Option<String>,
+    pub metrics_file: Option<String>,
+}
+
+/// The keys of `Settings`, which are also the IDs of the options they
+/// stand for
+pub const KEYS: [&str; 6] = [
+    "kill_after",
+    "signal",
+    "preserve_status",
+    "verbose",
+    "color",
+    "metrics_file",
+];
+
+impl Settings {
+    /// Forget the value for `key`, for a setting that cannot apply
+    pub fn unset(&mut self, key: &str) {
+        match key {
+            "kill_after" => self.kill_after = None,
+            "signal" => self.signal = None,
+            "preserve_status" => self.preserve_status = None,
+            "verbose" => self.verbose = None,
+            "color" => self.color = None,
+            "metrics_file" => self.metrics_file = None,
+            _ => {}
+        }
+    }
+
+    /// Set `key` from the value of the option it stands for, as clap would
+    /// take it as a default, unless it is already set
+    pub fn fill(&mut self, key: &str, value: &str) {
+        let text = || Some(value.to_string());
+        let flag = Some(value != "false" && value != "0");
+        match key {
+            "kill_after" if self.kill_after.is_none() => self.kill_after = text(),
+            "signal" if self.signal.is_none() => self.signal = text(),
+            "preserve_status" if self.preserve_status.is_none() => self.preserve_status = flag,
+            "verbose" if self.verbose.is_none() => self.verbose = flag,
+            "color" if self.color.is_none() => self.color = text(),
+            "metrics_file" if self.metrics_file.is_none() => self.metrics_file = text(),
+            _ => {}
+        }
+    }
+}
+
+/// A config file that was found and read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFile {
+    pub path: PathBuf,
+    pub settings: Settings,
+}
+
+/// Why a config file could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Where to look for a config file, in order, with whether it must exist.
+/// `var` looks up an environment variable; empty ones count as unset.
+pub fn search_path(var: impl Fn(&str) -> Option<OsString>) -> Vec<(PathBuf, bool)> {
+    let set = |name: &str| var(name).filter(|value| !value.is_empty());
+    if let Some(path) = set("TIMEOUT_CONFIG") {
+        return vec![(PathBuf::from(path), true)];
+    }
+    let mut paths = Vec::new();
+    // The XDG spec says to ignore a relative XDG_CONFIG_HOME
+    if let Some(dir) = set("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+    {
+        paths.push((dir.join("timeout").join("config.toml"), false));
+    }
+    if let Some(home) = set("HOME") {
+        let path = PathBuf::from(home)
+            .join(".config")
+            .join("timeout")
+            .join("config.toml");
+        if !paths.iter().any(|(p, _)| *p == path) {
+            paths.push((path, false));
+        }
+    }
+    paths
+}
+
+/// Read `explicit` (--config), or else the first file on the search path
+/// that exists. `None` when there is none.
+pub fn load(
+    explicit: Option<&Path>,
+    var: impl Fn(&str) -> Option<OsString>,
+) -> Result<Option<ConfigFile>, ConfigError> {
+    let candidates = match explicit {
+        Some(path) => vec![(path.to_path_buf(), true)],
+        None => search_path(var),
+    };
+    for (path, required) in candidates {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                let settings = parse(&path, &text)?;
+                return Ok(Some(ConfigFile { path, settings }));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !required => continue,
+            Err(e) => {
+                return Err(ConfigError {
+                    path,
+                    reason: e.to_string(),
+                })
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Parse the text of the config file at `path`. A syntax error or unknown
+/// key is reported with its line and column.
+pub fn parse(path: &Path, text: &str) -> Result<Settings, ConfigError> {
+    let error = |reason: String| ConfigError {
+        path: path.to_path_buf(),
+        reason,
+    };
+    let settings: Settings = toml::from_str(text).map_err(|e| {
+        let message = e.message().trim_end().to_string();
+        match e.span() {
+            Some(span) => {
+                let (line, column) = position(text, span.start);
+                error(format!("line {}, column {}: {}", line, column, message))
+            }
+            None => error(message),
+        }
+    })?;
+    if let Some(color) = &settings.color {
+        if !["auto", "always", "never"].contains(&color.as_str()) {
+            return Err(error(format!(
+                "color must be auto, always or never, not '{}'",
+                color
+            )));
+        }
+    }
+    Ok(settings)
+}
+
+/// 1-based line and column of byte `offset` in `text`
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
+
+/// The settings environment variables stand for: TIMEOUT_KILL_AFTER,
+/// TIMEOUT_SIGNAL, TIMEOUT_METRICS_FILE for metrics_file, and NO_COLOR or
+/// CLICOLOR_FORCE, which `auto` color follows, for color
+pub fn environment(var: impl Fn(&str) -> Option<OsString>) -> Settings {
+    let set = |name: &str| var(name).filter(|value| !value.is_empty());
+    let color_from_env =
+        set("NO_COLOR").is_some() || set("CLICOLOR_FORCE").is_some_and(|value| value != "0");
+    let text = |name: &str| set(name).map(|value| value.to_string_lossy().into_owned());
+    Settings {
+        kill_after: text("TIMEOUT_KILL_AFTER"),
+        signal: text("TIMEOUT_SIGNAL"),
+        metrics_file: text("TIMEOUT_METRICS_FILE"),
+        color: color_from_env.then(|| "auto".to_string()),
+        ..Settings::default()
+    }
+}
+
+/// Where a setting in effect came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Environment,
+    File,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::CommandLine => "command line",
+            Source::Environment => "environment",
+            Source::File => "config file",
+            Source::Default => "default",
+        })
+    }
+}
+
+/// A setting in effect; `value` is `None` for an option that is unset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved<T> {
+    pub value: Option<T>,
+    pub source: Source,
+}
+
+/// The value of the first layer that sets it, else `default`
+fn resolve<T: Clone>(
+    cli: &Option<T>,
+    env: &Option<T>,
+    file: &Option<T>,
+    default: Option<T>,
+) -> Resolved<T> {
+    [
+        (cli, Source::CommandLine),
+        (env, Source::Environment),
+        (file, Source::File),
+    ]
+    .into_iter()
+    .find_map(|(value, source)| {
+        value.clone().map(|value| Resolved {
+            value: Some(value),
+            source,
+        })
+    })
+    .unwrap_or(Resolved {
+        value: default,
+        source: Source::Default,
+    })
+}
+
+/// Every setting a config file may hold, as in effect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Effective {
+    pub kill_after: Resolved<String>,
+    pub signal: Resolved<String>,
+    pub preserve_status: Resolved<bool>,
+    pub verbose: Resolved<bool>,
+    pub color: Resolved<String>,
+    pub metrics_file: Resolved<String>,
+}
+
+/// Merge the layers: the command line over the environment over the config
+/// file over the built-in defaults
+pub fn merge(cli: &Settings, env: &Settings, file: &Settings) -> Effective {
+    Effective {
+        kill_after: resolve(&cli.kill_after, &env.kill_after, &file.kill_after, None),
+        signal: resolve(
+            &cli.signal,
+            &env.signal,
+            &file.signal,
+            cfg!(unix).then(|| "TERM".to_string()),
+        ),
+        preserve_status: resolve(
+            &cli.preserve_status,
+            &env.preserve_status,
+            &file.preserve_status,
+            Some(false),
+        ),
+        verbose: resolve(&cli.verbose, &env.verbose, &file.verbose, Some(false)),
+        color: resolve(
+            &cli.color,
+            &env.color,
+            &file.color,
+            Some("auto".to_string()),
+        ),
+        metrics_file: resolve(
+            &cli.metrics_file,
+            &env.metrics_file,
+            &file.metrics_file,
+            None,
+        ),
+    }
+}
+
+impl Effective {
+    /// The settings that came from the config file, by option ID, as
+    /// strings for clap to parse
+    pub fn file_settings(&self) -> Vec<(&'static str, String)> {
+        let mut settings = Vec::new();
+        let mut add = |id: &'static str, value: Option<String>, source: Source| {
+            if let (Some(value), Source::File) = (value, source) {
+                settings.push((id, value));
+            }
+        };
+        add(
+            "kill_after",
+            self.kill_after.value.clone(),
+            self.kill_after.source,
+        );
+        add("signal", self.signal.value.clone(), self.signal.source);
+        add(
+            "preserve_status",
+            self.preserve_status.value.map(|v| v.to_string()),
+            self.preserve_status.source,
+        );
+        add(
+            "verbose",
+            self.verbose.value.map(|v| u8::from(v).to_string()),
+            self.verbose.source,
+        );
+        add("color", self.color.value.clone(), self.color.source);
+        add(
+            "metrics_file",
+            self.metrics_file.value.clone(),
+            self.metrics_file.source,
+        );
+        settings
+    }
+
+    /// The settings as TOML, each with where it came from, for --dump-config
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let mut line = |key: &str, value: Option<String>, source: Source| match value {
+            Some(value) => out.push_str(&format!("{} = {}  # {}\n", key, value, source)),
+            None => out.push_str(&format!("# {} is not set\n", key)),
+        };
+        let quoted = |value: &Option<String>| value.as_deref().map(quote);
+        line(
+            "kill_after",
+            quoted(&self.kill_after.value),
+            self.kill_after.source,
+        );
+        line("signal", quoted(&self.signal.value), self.signal.source);
+        line(
+            "preserve_status",
+            self.preserve_status.value.map(|v| v.to_string()),
+            self.preserve_status.source,
+        );
+        line(
+            "verbose",
+            self.verbose.value.map(|v| v.to_string()),
+            self.verbose.source,
+        );
+        line("color", quoted(&self.color.value), self.color.source);
+        line(
+            "metrics_file",
+            quoted(&self.metrics_file.value),
+            self.metrics_file.source,
+        );
+        out
+    }
+}
+
+/// `value` as a TOML basic string
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,269 @@
+// src/deadline.rs
+// Absolute deadlines for --until, turned into a DURATION at startup
+
+use crate::logfile::civil_from_days;
+use crate::{parse_duration, TimeoutError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A calendar date as (year, month, day)
+type Date = (i64, u32, u32);
+
+/// A time of day as (hour, minute, second)
+type Clock = (u32, u32, u32);
+
+/// Resolve --until TIME to a point in time, given the current time `now`.
+///
+/// Accepts:
+/// - `+DURATION`: that long from now, e.g. `+2h`
+/// - `HH:MM[:SS]`: the next time the local clock shows it, today or tomorrow
+/// - `YYYY-MM-DDTHH:MM:SS[.frac][Z|±HH:MM]` (RFC 3339): local time unless
+///   `Z` or an offset is given
+pub fn parse_until(input: &str, now: SystemTime) -> Result<SystemTime, TimeoutError> {
+    let invalid = |reason: &str| TimeoutError::InvalidDeadline {
+        input: input.to_string(),
+        reason: reason.to_string(),
+    };
+    let s = input.trim();
+
+    if let Some(relative) = s.strip_prefix('+') {
+        return now
+            .checked_add(parse_duration(relative)?)
+            .ok_or_else(|| invalid("too far in the future"));
+    }
+
+    if let Some(clock) = parse_clock(s) {
+        let today = local_date(now).ok_or_else(|| invalid("local time is not available"))?;
+        let at = local_time(today, clock).ok_or_else(|| invalid("no such local time"))?;
+        if at > now {
+            return Ok(at);
+        }
+        let tomorrow = civil_from_days(days_from_civil(today) + 1);
+        return local_time(tomorrow, clock).ok_or_else(|| invalid("no such local time"));
+    }
+
+    let (date, rest) = s.split_once(['T', 't', ' ']).ok_or_else(|| {
+        if s.contains(':') {
+            invalid("invalid time of day")
+        } else {
+            invalid("expected an RFC 3339 time, HH:MM[:SS] or +DURATION")
+        }
+    })?;
+    let date = parse_date(date).ok_or_else(|| invalid("no such date"))?;
+
+    // The zone, if any, follows the time: `Z` or a `+HH:MM` / `-HH:MM` offset
+    let (time, offset) = if let Some(time) = rest.strip_suffix(['Z', 'z']) {
+        (time, Some(0))
+    } else if let Some(at) = rest.rfind(['+', '-']) {
+        let offset = parse_offset(&rest[at..]).ok_or_else(|| invalid("invalid UTC offset"))?;
+        (&rest[..at], Some(offset))
+    } else {
+        (rest, None)
+    };
+
+    let (time, nanos) = match time.split_once('.') {
+        Some((time, fraction)) => (
+            time,
+            parse_fraction(fraction).ok_or_else(|| invalid("invalid fraction of a second"))?,
+        ),
+        None => (time, 0),
+    };
+    let clock = parse_clock(time).ok_or_else(|| invalid("invalid time of day"))?;
+
+    let at = match offset {
+        Some(offset) => {
+            let secs = epoch_secs(date, clock) - offset;
+            u64::try_from(secs)
+                .ok()
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+                .ok_or_else(|| invalid("before 1970"))?
+        }
+        None => local_time(date, clock).ok_or_else(|| invalid("no such local time"))?,
+    };
+    Ok(at + Duration::from_nanos(nanos))
+}
+
+/// `HH:MM` or `HH:MM:SS`, with one- or two-digit fields
+fn parse_clock(s: &str) -> Option<Clock> {
+    let mut fields = s.split(':').map(|f| {
+        (!f.is_empty() && f.len() <= 2 && f.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| f.parse::<u32>().ok())
+            .flatten()
+    });
+    let hour = fields.next()??;
+    let minute = fields.next()??;
+    let second = fields.next().unwrap_or(Some(0))?;
+    if fields.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    Some((hour, minute, second))
+}
+
+/// `YYYY-MM-DD`, rejecting dates that do not exist such as February 30
+fn parse_date(s: &str) -> Option<Date> {
+    let mut fields = s.splitn(3, '-');
+    let year = fields
+        .next()
+        .filter(|y| y.len() == 4)?
+        .parse::<i64>()
+        .ok()?;
+    let month = fields
+        .next()
+        .filter(|m| m.len() == 2)?
+        .parse::<u32>()
+        .ok()?;
+    let day = fields
+        .next()
+        .filter(|d| d.len() == 2)?
+        .parse::<u32>()
+        .ok()?;
+    let date = (year, month, day);
+    ((1..=12).contains(&month) && civil_from_days(days_from_civil(date)) == date).then_some(date)
+}
+
+/// `+HH:MM` or `-HH:MM` as seconds east of UTC
+fn parse_offset(s: &str) -> Option<i64> {
+    let (sign, rest) = s.split_at(1);
+    let (hours, minutes) = rest.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    let (hours, minutes) = (hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?);
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    let secs = hours * 3600 + minutes * 60;
+    Some(if sign == "-" { -secs } else { secs })
+}
+
+/// Digits after the decimal point as nanoseconds; digits past the ninth
+/// are dropped
+fn parse_fraction(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let digits = &s[..s.len().min(9)];
+    Some(digits.parse::<u64>().ok()? * 10u64.pow(9 - digits.len() as u32))
+}
+
+/// Days since 1970-01-01 for a date in the proleptic Gregorian calendar
+/// (Howard Hinnant's algorithm, the inverse of `civil_from_days`)
+fn days_from_civil((year, month, day): Date) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let month = i64::from(month);
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Seconds since the epoch for a UTC date and time
+fn epoch_secs(date: Date, (hour, minute, second): Clock) -> i64 {
+    days_from_civil(date) * 86400 + i64::from(hour * 3600 + minute * 60 + second)
+}
+
+/// Today's date on the local clock
+#[cfg(unix)]
+fn local_date(now: SystemTime) -> Option<Date> {
+    let secs = now.duration_since(UNIX_EPOCH).ok()?.as_secs() as nix::libc::time_t;
+    let mut tm: nix::libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { nix::libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return None;
+    }
+    Some((
+        i64::from(tm.tm_year) + 1900,
+        (tm.tm_mon + 1) as u32,
+        tm.tm_mday as u32,
+    ))
+}
+
+/// The moment the local clock shows `clock` on `date`. mktime picks the
+/// right DST offset for that date, and moves times skipped by a DST
+/// change forward.
+#[cfg(unix)]
+fn local_time(date: Date, (hour, minute, second): Clock) -> Option<SystemTime> {
+    let mut tm: nix::libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = (date.0 - 1900) as _;
+    tm.tm_mon = date.1 as nix::libc::c_int - 1;
+    tm.tm_mday = date.2 as _;
+    tm.tm_hour = hour as _;
+    tm.tm_min = minute as _;
+    tm.tm_sec = second as _;
+    tm.tm_isdst = -1;
+    let secs = unsafe { nix::libc::mktime(&mut tm) };
+    let secs = u64::try_from(secs).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+#[cfg(windows)]
+fn system_time(
+    date: Date,
+    (hour, minute, second): Clock,
+) -> windows_sys::Win32::Foundation::SYSTEMTIME {
+    windows_sys::Win32::Foundation::SYSTEMTIME {
+        wYear: date.0 as u16,
+        wMonth: date.1 as u16,
+        wDayOfWeek: 0,
+        wDay: date.2 as u16,
+        wHour: hour as u16,
+        wMinute: minute as u16,
+        wSecond: second as u16,
+        wMilliseconds: 0,
+    }
+}
+
+/// Today's date on the local clock
+#[cfg(windows)]
+fn local_date(now: SystemTime) -> Option<Date> {
+    use windows_sys::Win32::System::Time::SystemTimeToTzSpecificLocalTime;
+    let secs = now.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let rem = (secs % 86400) as u32;
+    let utc = system_time(
+        civil_from_days((secs / 86400) as i64),
+        (rem / 3600, rem % 3600 / 60, rem % 60),
+    );
+    let mut local = utc;
+    if unsafe { SystemTimeToTzSpecificLocalTime(std::ptr::null(), &utc, &mut local) } == 0 {
+        return None;
+    }
+    Some((
+        i64::from(local.wYear),
+        u32::from(local.wMonth),
+        u32::from(local.wDay),
+    ))
+}
+
+/// The moment the local clock shows `clock` on `date`, using the time zone
+/// rules in force on that date
+#[cfg(windows)]
+fn local_time(date: Date, clock: Clock) -> Option<SystemTime> {
+    use windows_sys::Win32::System::Time::TzSpecificLocalTimeToSystemTime;
+    let local = system_time(date, clock);
+    let mut utc = local;
+    if unsafe { TzSpecificLocalTimeToSystemTime(std::ptr::null(), &local, &mut utc) } == 0 {
+        return None;
+    }
+    let secs = epoch_secs(
+        (
+            i64::from(utc.wYear),
+            u32::from(utc.wMonth),
+            u32::from(utc.wDay),
+        ),
+        (
+            u32::from(utc.wHour),
+            u32::from(utc.wMinute),
+            u32::from(utc.wSecond),
+        ),
+    );
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn local_date(_now: SystemTime) -> Option<Date> {
+    None
+}
+
+#[cfg(not(any(unix, windows)))]
+fn local_time(_date: Date, _clock: Clock) -> Option<SystemTime> {
+    None
+}
@@ -0,0 +1,78 @@
+// src/debug.rs
+// Event tracing for debugging timeout itself (TIMEOUT_DEBUG or -vvv, or
+// TIMEOUT_LOG=debug with the logging feature)
+
+use crate::logfile::rfc3339;
+use owo_colors::OwoColorize;
+use std::fmt;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime};
+
+/// When timeout started, for the `+<seconds>` of each event
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Whether TIMEOUT_DEBUG is set. Looked up once, so a disabled trace costs
+/// a couple of loads per event. With the logging feature the filter
+/// decides instead.
+#[cfg(not(feature = "logging"))]
+static REQUESTED: OnceLock<bool> = OnceLock::new();
+
+/// Whether TIMEOUT_DEBUG is set or -vvv was given
+#[cfg(not(feature = "logging"))]
+pub fn enabled() -> bool {
+    *REQUESTED.get_or_init(|| std::env::var_os("TIMEOUT_DEBUG").is_some())
+        || crate::logging::verbosity() >= 3
+}
+
+fn started() -> Instant {
+    *STARTED.get_or_init(Instant::now)
+}
+
+/// Start the clock for the `+<seconds>` of each event
+pub fn start() {
+    started();
+}
+
+/// One event as `Debug: <UTC time> +<seconds since start> <event>`
+pub struct Line<'a>(pub fmt::Arguments<'a>);
+
+impl fmt::Display for Line<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} +{:.6}s {}",
+            "Debug".magenta(),
+            rfc3339(SystemTime::now()),
+            started().elapsed().as_secs_f64(),
+            self.0
+        )
+    }
+}
+
+/// Print one event
+#[cfg(not(feature = "logging"))]
+pub fn emit(event: fmt::Arguments) {
+    crate::progress::clear();
+    anstream::eprintln!("{}", Line(event));
+}
+
+/// Trace an event when TIMEOUT_DEBUG is set or -vvv was given; the
+/// arguments are not even formatted otherwise
+#[cfg(not(feature = "logging"))]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::debug::enabled() {
+            $crate::debug::emit(format_args!($($arg)*));
+        }
+    };
+}
+
+/// Trace an event at debug level, from the module it happens in; the
+/// arguments are not even formatted unless TIMEOUT_LOG lets it through
+#[cfg(feature = "logging")]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::logging::event!(debug, "{}", $crate::debug::Line(format_args!($($arg)*)))
+    };
+}
+pub(crate) use debug;
@@ -0,0 +1,442 @@
+// src/driver.rs
+// Running COMMAND: one attempt under the platform backend, the --retries
+// loop around it, --pid watchdog mode, and the hooks and reports once it
+// has finished
+
+use crate::args::Args;
+use crate::hook::HookContext;
+use crate::logging::{detail, error, info, warning};
+use crate::plan::Plan;
+use crate::progress::Progress;
+use crate::retry::{RetryTrigger, TimeoutBudget};
+use crate::sink::Outcome;
+use crate::summary_format::human_duration;
+use crate::{platform, resolve, rusage};
+use crate::{TimeoutError, TimeoutMetrics, TimeoutReason};
+use crate::{EXIT_CANCELED, EXIT_INTERRUPTED, EXIT_TIMEDOUT};
+use owo_colors::OwoColorize;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Run the command, starting it again after each timeout while --retries
+/// allows, and return the exit code of the last attempt.
+///
+/// With --total-timeout, no attempt runs past the overall deadline: each
+/// gets DURATION or whatever is left of the budget, whichever is shorter.
+pub async fn run_command(
+    args: &Args,
+    plan: &Plan,
+    command: &str,
+    cmd_args: &[String],
+    iteration: u32,
+) -> Result<TimeoutMetrics, TimeoutError> {
+    if let Some(otel) = &plan.otel {
+        otel.start_run(
+            std::iter::once(command)
+                .chain(cmd_args.iter().map(String::as_str))
+                .map(String::from)
+                .collect(),
+        );
+    }
+
+    // Advisory pre-check so lookup failures are reported by the parent
+    // before forking; the child still does the real exec. Under --chroot
+    // only the child sees the PATH COMMAND is looked up on.
+    #[cfg(unix)]
+    if args.chroot().is_none() {
+        resolve::resolve_command(command)?;
+    }
+
+    let retry = &plan.retry;
+    let started = Instant::now();
+    let started_at = SystemTime::now();
+    let deadline = retry.total.map(|total| started + total);
+    let mut attempt = 1;
+    let (mut retries_timeout, mut retries_exit) = (0, 0);
+    loop {
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        let (duration, limited_by_total) = match remaining {
+            Some(left) if left < plan.duration => (left, true),
+            _ => (plan.duration, false),
+        };
+
+        if args.verbose() && retry.retries > 0 {
+            warning!(
+                "{}: Attempt {} of {} for command '{}'.",
+                "Retry".yellow(),
+                attempt,
+                retry.retries + 1,
+                command
+            );
+        }
+
+        let mut metrics = run_once(args, plan, duration, command, cmd_args).await?;
+        metrics.attempt = attempt;
+        metrics.iteration = iteration;
+        metrics.retries_timeout = retries_timeout;
+        metrics.retries_exit = retries_exit;
+        metrics.total_elapsed = started.elapsed();
+        metrics.started_at = started_at;
+
+        let total_expired =
+            limited_by_total && metrics.timeout_reason == Some(TimeoutReason::Duration);
+        if metrics.timed_out {
+            metrics.budget = Some(if total_expired {
+                TimeoutBudget::Total
+            } else {
+                TimeoutBudget::Attempt
+            });
+        }
+        if total_expired && !retry.clamp_last_attempt {
+            metrics.exit_code = EXIT_TIMEDOUT;
+        }
+        if let Some(output) = &plan.metrics {
+            output.write(&metrics, args.verbose());
+        }
+        if let Some(time) = &plan.time {
+            time.write(&metrics);
+        }
+        if args.verbose() {
+            info!("{}: {}.", "Summary".cyan(), metrics);
+            if let Some(usage) = metrics.usage.summary() {
+                info!("{}: {}.", "Resources".cyan(), usage);
+            }
+            if let Some(shutdown) = metrics.shutdown_summary() {
+                info!("{}: {}.", "Shutdown".cyan(), shutdown);
+            }
+        }
+
+        if total_expired {
+            if args.verbose() {
+                warning!(
+                    "{}: Total timeout ({:?}) expired.",
+                    "Timeout".red(),
+                    retry.total.unwrap_or_default()
+                );
+            }
+            return Ok(metrics);
+        }
+
+        let trigger = match retry.trigger(&metrics) {
+            Some(trigger) if retry.allows_retry(attempt) => trigger,
+            _ => return Ok(metrics),
+        };
+
+        let delay = retry.delay_after(attempt);
+        if let Some(deadline) = deadline {
+            if Instant::now() + delay >= deadline {
+                if args.verbose() {
+                    warning!(
+                        "{}: Total timeout would expire before the next attempt; not retrying.",
+                        "Retry".yellow()
+                    );
+                }
+                return Ok(metrics);
+            }
+        }
+        match trigger {
+            RetryTrigger::Timeout => retries_timeout += 1,
+            RetryTrigger::ExitCode(_) => retries_exit += 1,
+        }
+        if args.verbose() {
+            let why = match trigger {
+                RetryTrigger::Timeout => "timed out".to_string(),
+                RetryTrigger::ExitCode(code) => format!("exited with status {}", code),
+            };
+            warning!(
+                "{}: Command '{}' {}, retrying in {:?}.",
+                "Retry".yellow(),
+                command,
+                why,
+                delay
+            );
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = tokio::signal::ctrl_c() => {
+                metrics.exit_code = EXIT_INTERRUPTED;
+                return Ok(metrics);
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Watchdog mode: apply the plan to the existing process `pid` (--pid)
+#[cfg(unix)]
+pub async fn run_pid(args: &Args, plan: &Plan, pid: i32) -> i32 {
+    let result = platform::watch::watch_pid(
+        nix::unistd::Pid::from_raw(pid),
+        platform::watch::WatchOptions {
+            duration: plan.duration,
+            term_signal: plan.term_signal,
+            kill_after: plan.kill_after,
+            verbose: args.verbose(),
+            no_notify: args.no_notify(),
+            status_on_timeout: args.status_on_timeout,
+            poll: plan.poll_interval,
+            extend_step: plan.extend_step,
+            boottime: args.boottime(),
+            on_timeout: plan.on_timeout.as_ref(),
+            pre_kill: plan.pre_kill.as_ref(),
+            control_socket: args.control_socket.as_deref().map(Path::new),
+            control_socket_force: args.control_socket_force,
+        },
+    )
+    .await;
+
+    let result = result.map(|mut metrics| {
+        metrics.total_elapsed = metrics.elapsed;
+        if metrics.timed_out {
+            metrics.budget = Some(TimeoutBudget::Attempt);
+        }
+        if let Some(output) = &plan.metrics {
+            output.write(&metrics, args.verbose());
+        }
+        if let Some(time) = &plan.time {
+            time.write(&metrics);
+        }
+        if args.verbose() {
+            info!("{}: {}.", "Summary".cyan(), metrics);
+        }
+        metrics
+    });
+    finish(args, plan, &pid.to_string(), &[], result).await
+}
+
+/// Report how a command finished: print any error, run --on-success or
+/// --on-failure, apply --hook-failures-fatal and POST to --webhook.
+/// Returns timeout's exit code.
+pub async fn finish(
+    args: &Args,
+    plan: &Plan,
+    command: &str,
+    argv: &[String],
+    result: Result<TimeoutMetrics, TimeoutError>,
+) -> i32 {
+    let (code, metrics, error) = match result {
+        // --suppress-exit-code only ever applies to COMMAND's own status
+        Ok(metrics)
+            if !metrics.timed_out && plan.suppress_exit_codes.contains(&metrics.exit_code) =>
+        {
+            (0, Some(metrics), None)
+        }
+        Ok(metrics) => (metrics.exit_code, Some(metrics), None),
+        Err(e) => (e.exit_code(), None, Some(e.to_string())),
+    };
+    // With --quiet a log target stands in for stderr, unless it fails
+    let quiet = args.quiet > 0 && plan.sinks.iter().any(|sink| sink.is_log());
+    if let Some(error) = error.as_ref().filter(|_| !quiet) {
+        error!("{}: {}", "timeout".red(), error);
+    }
+    let timed_out = metrics.as_ref().is_some_and(|m| m.timed_out);
+
+    // Any --on-timeout hook has already run, before COMMAND was signalled
+    let mut failed = metrics
+        .as_ref()
+        .and_then(|m| m.hook_exit_code)
+        .filter(|&status| status != 0)
+        .map(|_| "--on-timeout");
+
+    let hook = if code == 0 && !timed_out {
+        &plan.on_success
+    } else {
+        &plan.on_failure
+    };
+    if let Some(hook) = hook {
+        let context = HookContext {
+            pid: metrics.as_ref().and_then(|m| m.pid),
+            pgid: metrics.as_ref().and_then(|m| m.pgid),
+            elapsed: metrics.as_ref().map(|m| m.elapsed).unwrap_or_default(),
+            command: metrics.as_ref().map_or(command, |m| m.command.as_str()),
+            exit_code: Some(code),
+            timed_out: Some(timed_out),
+            signal: None,
+        };
+        if hook.run(&context, args.verbose()).await != 0 {
+            failed = Some(hook.flag);
+        }
+    }
+
+    // The outcome is recorded whatever the hooks did, and cannot fail the run
+    let outcome = Outcome::new(command, argv, code, metrics.as_ref(), error.as_deref());
+    for sink in &plan.sinks {
+        let recorded = sink.record(&outcome, args.verbose()).await;
+        if let Some(error) = error
+            .as_ref()
+            .filter(|_| quiet && sink.is_log() && !recorded)
+        {
+            error!("{}: {}", "timeout".red(), error);
+        }
+    }
+
+    let code = match failed {
+        Some(flag) if plan.hook_failures_fatal => {
+            error!(
+                "{}: {} hook failed (--hook-failures-fatal)",
+                "timeout".red(),
+                flag
+            );
+            EXIT_CANCELED
+        }
+        _ => code,
+    };
+    if let Some(summary) = &plan.summary {
+        summary.write(&outcome, code, plan.duration);
+    }
+    if let Some(status) = &plan.status {
+        status.write(&outcome, code);
+    }
+    code
+}
+
+/// Run the command once under the platform backend
+async fn run_once(
+    args: &Args,
+    plan: &Plan,
+    duration: Duration,
+    command: &str,
+    cmd_args: &[String],
+) -> Result<TimeoutMetrics, TimeoutError> {
+    // --timestamps=remaining counts down to this attempt's own deadline
+    let mut output_rules = plan.output_rules();
+    if duration != plan.duration {
+        output_rules.overall_limit = Some(duration);
+    }
+
+    detail!("{}: {}", "Limits".cyan(), describe_limits(plan, duration));
+
+    // --simulate-timeout: the deadline passes as soon as COMMAND starts
+    let limit = if plan.simulate_timeout {
+        if args.verbose() {
+            info!(
+                "{}: simulating a timeout of command '{}' (--simulate-timeout)",
+                "Info".cyan(),
+                command
+            );
+        }
+        Duration::ZERO
+    } else {
+        duration
+    };
+
+    // Both stop as soon as the run is over
+    let _heartbeat = plan
+        .heartbeat
+        .as_ref()
+        .map(|heartbeat| heartbeat.start(command, limit));
+    let _progress = plan.progress.map(|interval| {
+        #[cfg(unix)]
+        let action = if args.no_notify() || plan.keep_running {
+            "timeout".to_string()
+        } else if args.stop_instead {
+            "SIGSTOP".to_string()
+        } else {
+            plan.term_signal.to_string()
+        };
+        #[cfg(not(unix))]
+        let action = "timeout".to_string();
+        Progress::start(limit, &action, plan.kill_after, interval)
+    });
+
+    #[cfg(any(unix, windows))]
+    let result = platform::run_with_timeout(
+        command,
+        cmd_args,
+        platform::RunOptions {
+            duration: limit,
+            kill_after: plan.kill_after,
+            preserve_status: args.preserve_status,
+            verbose: args.verbose(),
+            status_on_timeout: args.status_on_timeout,
+            output_rules,
+            redirects: plan.redirects.clone(),
+            pid_file: args.pid_file.as_deref().map(Path::new),
+            pid_file_force: args.pid_file_force,
+            on_timeout: plan.on_timeout.as_ref(),
+            pre_kill: plan.pre_kill.as_ref(),
+            notifier: plan.notifier.as_ref(),
+            #[cfg(unix)]
+            term_signal: plan.term_signal,
+            #[cfg(unix)]
+            foreground: args.foreground(),
+            #[cfg(unix)]
+            no_process_group: args.no_process_group(),
+            #[cfg(unix)]
+            set_session: args.set_session(),
+            #[cfg(unix)]
+            detect_stopped: args.detect_stopped(),
+            #[cfg(unix)]
+            no_notify: args.no_notify(),
+            #[cfg(unix)]
+            cpu_limit: plan.cpu_limit,
+            #[cfg(unix)]
+            mem_limit: plan.mem_limit,
+            #[cfg(unix)]
+            cpu_affinity: plan.cpu_affinity.as_deref(),
+            #[cfg(unix)]
+            io_priority: plan.io_priority,
+            #[cfg(unix)]
+            namespaced: args.namespaced(),
+            #[cfg(unix)]
+            chroot: args.chroot(),
+            #[cfg(unix)]
+            pty: args.pty(),
+            #[cfg(unix)]
+            extend_step: plan.extend_step,
+            #[cfg(unix)]
+            boottime: args.boottime(),
+            #[cfg(unix)]
+            control_socket: args.control_socket.as_deref().map(Path::new),
+            #[cfg(unix)]
+            control_socket_force: args.control_socket_force,
+            #[cfg(unix)]
+            stop_instead: args.stop_instead,
+            #[cfg(unix)]
+            embedded: None,
+            #[cfg(windows)]
+            no_window: args.no_window(),
+        },
+    )
+    .await;
+
+    #[cfg(not(any(unix, windows)))]
+    let result = {
+        let _ = (args, plan, command, cmd_args, limit);
+        error!("{}: Platform not supported", "Error".red());
+        Err(TimeoutError::FeatureNotSupported(format!(
+            "Platform {} not supported",
+            Platform::name()
+        )))
+    };
+
+    // Report the limit that was asked for, not the one simulated
+    result.map(|mut metrics| {
+        metrics.duration = duration;
+        metrics
+    })
+}
+
+/// The limits a run of COMMAND is under, for -vv, e.g. `duration 5s,
+/// kill-after 2s, signal SIGTERM, idle timeout none, ...`
+fn describe_limits(plan: &Plan, duration: Duration) -> String {
+    let time = |d: Option<Duration>| d.map_or_else(|| "none".to_string(), human_duration);
+    let bytes = |b: Option<u64>| b.map_or_else(|| "none".to_string(), rusage::binary_size);
+    format!(
+        "duration {}, kill-after {}, signal {}, idle timeout {}, max output {}, cpu {}, memory {}",
+        time(Some(duration).filter(|&d| d != Duration::MAX)),
+        time(plan.kill_after),
+        plan.signal_name(),
+        time(plan.idle_timeout),
+        bytes(plan.max_output),
+        plan.cpu_limit
+            .map_or_else(|| "none".to_string(), |s| format!("{}s", s)),
+        bytes(plan.mem_limit),
+    )
+}
+
+/// Run one command line, reporting errors and mapping them to exit codes
+pub async fn run_argv(args: &Args, plan: &Plan, argv: &[String], iteration: u32) -> i32 {
+    let result = run_command(args, plan, &argv[0], &argv[1..], iteration).await;
+    finish(args, plan, &argv[0], argv, result).await
+}
@@ -0,0 +1,108 @@
+// src/env_opts.rs
+// Default options from the environment: TIMEOUT_OPTS holds options as they
+// would be written on the command line, e.g. "-k 30 --color never". They
+// are parsed on their own and become defaults, so the command line wins.
+
+use clap::builder::Resettable;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgAction, Command};
+use std::fmt;
+
+/// The variable the options come from
+pub const VAR: &str = "TIMEOUT_OPTS";
+
+/// Why TIMEOUT_OPTS could not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptsError(pub String);
+
+impl fmt::Display for OptsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", VAR, self.0)
+    }
+}
+
+/// An option TIMEOUT_OPTS gives, by argument ID, with the values that make
+/// it a default: "true" for a flag, the count for -v, else as written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Opt {
+    pub id: String,
+    pub values: Vec<String>,
+}
+
+/// Split `value` into words the way sh would: quotes group, a backslash
+/// escapes, and no variables or globs are expanded
+pub fn split(value: &str) -> Result<Vec<String>, OptsError> {
+    shlex::split(value)
+        .ok_or_else(|| OptsError("unterminated quote or trailing backslash".to_string()))
+}
+
+/// Parse `words` as options of `cmd`. DURATION, COMMAND and anything else
+/// positional is an error, as are --help and --version.
+pub fn parse(cmd: &Command, words: &[String]) -> Result<Vec<Opt>, OptsError> {
+    let cmd = cmd.clone().mut_args(|arg| match arg.is_positional() {
+        true => arg
+            .required(false)
+            .required_unless_present(Resettable::Reset),
+        false => arg,
+    });
+    let argv = std::iter::once(cmd.get_name().to_string()).chain(words.iter().cloned());
+    let matches = cmd
+        .clone()
+        .try_get_matches_from(argv)
+        .map_err(|e| match e.kind() {
+            ErrorKind::DisplayHelp | ErrorKind::DisplayVersion => {
+                OptsError("--help and --version cannot be given here".to_string())
+            }
+            // The first line, without clap's "error: " and the usage after it
+            _ => OptsError(
+                e.render()
+                    .to_string()
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches("error: ")
+                    .to_string(),
+            ),
+        })?;
+
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let raw = |id: &str| -> Vec<String> {
+        matches
+            .get_raw(id)
+            .into_iter()
+            .flatten()
+            .map(|value| value.to_string_lossy().into_owned())
+            .collect()
+    };
+    if let Some(arg) = cmd
+        .get_positionals()
+        .find(|arg| given(arg.get_id().as_str()))
+    {
+        return Err(OptsError(format!(
+            "only options may be given here, not {} '{}'",
+            arg.get_value_names()
+                .and_then(|names| names.first())
+                .map_or_else(|| arg.get_id().to_string(), |name| name.to_string()),
+            raw(arg.get_id().as_str()).join(" ")
+        )));
+    }
+
+    Ok(cmd
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && given(arg.get_id().as_str()))
+        .map(|arg| {
+            let id = arg.get_id().as_str();
+            let values = match arg.get_action() {
+                ArgAction::SetTrue => vec!["true".to_string()],
+                ArgAction::SetFalse => vec!["false".to_string()],
+                ArgAction::Count => vec![matches.get_count(id).to_string()],
+                _ => raw(id),
+            };
+            Opt {
+                id: id.to_string(),
+                values,
+            }
+        })
+        .collect())
+}
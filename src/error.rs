@@ -0,0 +1,367 @@
+// src/error.rs
+// TimeoutError, every way a run can fail before or around COMMAND, and the
+// exit status each one maps to
+
+use crate::{EXIT_CANCELED, EXIT_CANNOT_INVOKE, EXIT_ENOENT};
+use thiserror::Error;
+
+/// Custom error types for timeout operations
+#[derive(Error, Debug)]
+pub enum TimeoutError {
+    #[cfg(unix)]
+    #[error("failed to fork process: {0}")]
+    ForkFailed(#[from] nix::Error),
+
+    #[error("failed to execute command '{cmd}': {source}")]
+    ExecFailed {
+        cmd: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("invalid duration '{input}': {reason}")]
+    InvalidDuration { input: String, reason: String },
+
+    #[error("invalid memory limit '{input}': {reason}")]
+    InvalidMemoryLimit { input: String, reason: String },
+
+    #[error("invalid output limit '{input}': {reason}")]
+    InvalidOutputLimit { input: String, reason: String },
+
+    #[error("invalid CPU limit '{input}': {reason}")]
+    InvalidCpuLimit { input: String, reason: String },
+
+    #[error("invalid CPU list '{input}': {reason}")]
+    InvalidCpuAffinity { input: String, reason: String },
+
+    #[error("invalid I/O priority '{input}': {reason}")]
+    InvalidIoPriority { input: String, reason: String },
+
+    #[error("invalid argument file '{path}': {reason}")]
+    InvalidArgFile { path: String, reason: String },
+
+    #[error("config file '{path}': {reason}")]
+    InvalidConfig { path: String, reason: String },
+
+    #[error("{var}: {reason}")]
+    InvalidEnv { var: String, reason: String },
+
+    #[error("invalid pattern '{pattern}': {reason}")]
+    InvalidPattern { pattern: String, reason: String },
+
+    #[error("invalid batch command {record} '{input}': {reason}")]
+    InvalidBatch {
+        record: usize,
+        input: String,
+        reason: &'static str,
+    },
+
+    #[error("cannot open log file '{path}': {source}")]
+    LogFileFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot write metrics to {target}: {source}")]
+    MetricsOutputFailed {
+        target: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("--pid-file {0} already exists; is another timeout still running? (use --pid-file-force to overwrite it)")]
+    PidFileExists(String),
+
+    #[error("cannot write --pid-file '{path}': {source}")]
+    PidFileFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot use fd {fd} for --status-fd: {source}")]
+    StatusFdFailed {
+        fd: i32,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot open --summary-output file '{path}': {source}")]
+    SummaryOutputFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot open --time-output file '{path}': {source}")]
+    TimeOutputFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot open redirect file '{path}': {source}")]
+    RedirectFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("{redirect} cannot be combined with {feature}, which pipes COMMAND's output through timeout")]
+    RedirectConflict {
+        redirect: &'static str,
+        feature: &'static str,
+    },
+
+    #[error("invalid exit code list '{input}': {reason}")]
+    InvalidExitCodes { input: String, reason: String },
+
+    #[error("invalid deadline '{input}': {reason}")]
+    InvalidDeadline { input: String, reason: String },
+
+    #[error("invalid webhook '{input}': {reason}")]
+    InvalidWebhook { input: String, reason: String },
+
+    #[error("invalid log target '{input}': {reason}")]
+    InvalidLogTarget { input: String, reason: String },
+
+    #[error("invalid --statsd setting '{input}': {reason}")]
+    InvalidStatsd { input: String, reason: String },
+
+    #[error("invalid --summary-format '{input}': {reason}")]
+    InvalidSummaryFormat { input: String, reason: String },
+
+    #[error("cannot export spans to '{endpoint}': {reason}")]
+    InvalidOtel { endpoint: String, reason: String },
+
+    #[error("unknown signal: {input}{}", did_you_mean(.suggestion))]
+    UnknownSignal {
+        input: String,
+        suggestion: Option<&'static str>,
+    },
+
+    #[error("failed to setup signal handler for {signal}: {source}")]
+    SignalSetupFailed {
+        signal: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[cfg(unix)]
+    #[error("control socket {0} already exists (use --control-socket-force to replace it)")]
+    ControlSocketExists(String),
+
+    #[cfg(unix)]
+    #[error("control socket {path}: {source}")]
+    ControlSocketFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[cfg(unix)]
+    #[error("no process with PID {0}")]
+    NoSuchProcess(i32),
+
+    #[cfg(unix)]
+    #[error("not permitted to signal PID {0} (run timeout as its owner or as root)")]
+    SignalNotPermitted(i32),
+
+    #[cfg(unix)]
+    #[error("failed to create output pipe: {0}")]
+    PipeFailed(std::io::Error),
+
+    #[cfg(unix)]
+    #[error("failed to set up pseudo-terminal: {0}")]
+    PtyFailed(std::io::Error),
+
+    #[cfg(unix)]
+    #[error("failed to create process group: {0}")]
+    ProcessGroupFailed(nix::Error),
+
+    #[cfg(unix)]
+    #[error("failed to send signal {signal} to process: {source}")]
+    SignalSendFailed {
+        signal: String,
+        #[source]
+        source: nix::Error,
+    },
+
+    #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+    #[error("failed to set resource limit: {0}")]
+    ResourceLimitFailed(nix::Error),
+
+    #[error("command not found: {0}")]
+    CommandNotFound(String),
+
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[cfg(not(target_os = "linux"))]
+    #[error("feature not supported on this platform: {0}")]
+    FeatureNotSupported(String),
+}
+
+/// Errors compare by variant, then by fields. `std::io::Error` has no
+/// equality of its own, so wrapped I/O errors compare by `ErrorKind`;
+/// `nix::Error` is an `Errno` and compares directly. The match lists every
+/// variant, so a new one cannot be left comparing unequal to itself.
+impl PartialEq for TimeoutError {
+    fn eq(&self, other: &Self) -> bool {
+        use TimeoutError::*;
+        if std::mem::discriminant(self) != std::mem::discriminant(other) {
+            return false;
+        }
+        let io_eq = |a: &std::io::Error, b: &std::io::Error| a.kind() == b.kind();
+        match self {
+            #[cfg(unix)]
+            ForkFailed(a) => matches!(other, ForkFailed(b) if a == b),
+            ExecFailed { cmd, source } => {
+                matches!(other, ExecFailed { cmd: cmd2, source: source2 } if cmd == cmd2 && io_eq(source, source2))
+            }
+            InvalidDuration { input, reason } => {
+                matches!(other, InvalidDuration { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidMemoryLimit { input, reason } => {
+                matches!(other, InvalidMemoryLimit { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidOutputLimit { input, reason } => {
+                matches!(other, InvalidOutputLimit { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidCpuLimit { input, reason } => {
+                matches!(other, InvalidCpuLimit { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidCpuAffinity { input, reason } => {
+                matches!(other, InvalidCpuAffinity { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidIoPriority { input, reason } => {
+                matches!(other, InvalidIoPriority { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidArgFile { path, reason } => {
+                matches!(other, InvalidArgFile { path: path2, reason: reason2 } if path == path2 && reason == reason2)
+            }
+            InvalidConfig { path, reason } => {
+                matches!(other, InvalidConfig { path: path2, reason: reason2 } if path == path2 && reason == reason2)
+            }
+            InvalidEnv { var, reason } => {
+                matches!(other, InvalidEnv { var: var2, reason: reason2 } if var == var2 && reason == reason2)
+            }
+            InvalidPattern { pattern, reason } => {
+                matches!(other, InvalidPattern { pattern: pattern2, reason: reason2 } if pattern == pattern2 && reason == reason2)
+            }
+            InvalidBatch {
+                record,
+                input,
+                reason,
+            } => {
+                matches!(other, InvalidBatch { record: record2, input: input2, reason: reason2 } if record == record2 && input == input2 && reason == reason2)
+            }
+            LogFileFailed { path, source } => {
+                matches!(other, LogFileFailed { path: path2, source: source2 } if path == path2 && io_eq(source, source2))
+            }
+            MetricsOutputFailed { target, source } => {
+                matches!(other, MetricsOutputFailed { target: target2, source: source2 } if target == target2 && io_eq(source, source2))
+            }
+            PidFileExists(a) => matches!(other, PidFileExists(b) if a == b),
+            PidFileFailed { path, source } => {
+                matches!(other, PidFileFailed { path: path2, source: source2 } if path == path2 && io_eq(source, source2))
+            }
+            StatusFdFailed { fd, source } => {
+                matches!(other, StatusFdFailed { fd: fd2, source: source2 } if fd == fd2 && io_eq(source, source2))
+            }
+            SummaryOutputFailed { path, source } => {
+                matches!(other, SummaryOutputFailed { path: path2, source: source2 } if path == path2 && io_eq(source, source2))
+            }
+            TimeOutputFailed { path, source } => {
+                matches!(other, TimeOutputFailed { path: path2, source: source2 } if path == path2 && io_eq(source, source2))
+            }
+            RedirectFailed { path, source } => {
+                matches!(other, RedirectFailed { path: path2, source: source2 } if path == path2 && io_eq(source, source2))
+            }
+            RedirectConflict { redirect, feature } => {
+                matches!(other, RedirectConflict { redirect: redirect2, feature: feature2 } if redirect == redirect2 && feature == feature2)
+            }
+            InvalidExitCodes { input, reason } => {
+                matches!(other, InvalidExitCodes { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidDeadline { input, reason } => {
+                matches!(other, InvalidDeadline { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidWebhook { input, reason } => {
+                matches!(other, InvalidWebhook { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidLogTarget { input, reason } => {
+                matches!(other, InvalidLogTarget { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidStatsd { input, reason } => {
+                matches!(other, InvalidStatsd { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidSummaryFormat { input, reason } => {
+                matches!(other, InvalidSummaryFormat { input: input2, reason: reason2 } if input == input2 && reason == reason2)
+            }
+            InvalidOtel { endpoint, reason } => {
+                matches!(other, InvalidOtel { endpoint: endpoint2, reason: reason2 } if endpoint == endpoint2 && reason == reason2)
+            }
+            UnknownSignal { input, suggestion } => {
+                matches!(other, UnknownSignal { input: input2, suggestion: suggestion2 } if input == input2 && suggestion == suggestion2)
+            }
+            SignalSetupFailed { signal, source } => {
+                matches!(other, SignalSetupFailed { signal: signal2, source: source2 } if signal == signal2 && io_eq(source, source2))
+            }
+            #[cfg(unix)]
+            ControlSocketExists(a) => matches!(other, ControlSocketExists(b) if a == b),
+            #[cfg(unix)]
+            ControlSocketFailed { path, source } => {
+                matches!(other, ControlSocketFailed { path: path2, source: source2 } if path == path2 && io_eq(source, source2))
+            }
+            #[cfg(unix)]
+            NoSuchProcess(a) => matches!(other, NoSuchProcess(b) if a == b),
+            #[cfg(unix)]
+            SignalNotPermitted(a) => matches!(other, SignalNotPermitted(b) if a == b),
+            #[cfg(unix)]
+            PipeFailed(a) => matches!(other, PipeFailed(b) if io_eq(a, b)),
+            #[cfg(unix)]
+            PtyFailed(a) => matches!(other, PtyFailed(b) if io_eq(a, b)),
+            #[cfg(unix)]
+            ProcessGroupFailed(a) => matches!(other, ProcessGroupFailed(b) if a == b),
+            #[cfg(unix)]
+            SignalSendFailed { signal, source } => {
+                matches!(other, SignalSendFailed { signal: signal2, source: source2 } if signal == signal2 && source == source2)
+            }
+            #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+            ResourceLimitFailed(a) => matches!(other, ResourceLimitFailed(b) if a == b),
+            CommandNotFound(a) => matches!(other, CommandNotFound(b) if a == b),
+            PermissionDenied(a) => matches!(other, PermissionDenied(b) if a == b),
+            #[cfg(not(target_os = "linux"))]
+            FeatureNotSupported(a) => matches!(other, FeatureNotSupported(b) if a == b),
+        }
+    }
+}
+
+/// ` (did you mean SIGHUP?)`, or nothing without a suggestion
+fn did_you_mean(suggestion: &Option<&str>) -> String {
+    suggestion
+        .map(|name| format!(" (did you mean {}?)", name))
+        .unwrap_or_default()
+}
+
+impl TimeoutError {
+    /// An unknown signal with no close match to suggest
+    #[cfg(unix)]
+    pub fn unknown_signal(input: &str) -> TimeoutError {
+        TimeoutError::UnknownSignal {
+            input: input.to_string(),
+            suggestion: None,
+        }
+    }
+
+    /// Exit status timeout itself should use when failing with this error
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TimeoutError::CommandNotFound(_) => EXIT_ENOENT,
+            TimeoutError::PermissionDenied(_) => EXIT_CANNOT_INVOKE,
+            _ => EXIT_CANCELED,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// src/every.rs
+// --every: COMMAND started again on an interval until --count, --until-failure
+// or --until-success says to stop
+
+use crate::args::Args;
+use crate::driver::run_argv;
+use crate::logging::info;
+use crate::plan::Plan;
+use crate::EXIT_INTERRUPTED;
+use owo_colors::OwoColorize;
+use std::time::{Duration, Instant};
+
+/// Watch mode: start the command every `interval` (start to start) until
+/// --count runs have finished, --until-failure / --until-success is
+/// satisfied, or Ctrl-C. Returns the status of the last run, or 130 when
+/// interrupted.
+pub async fn run_watch(args: &Args, plan: &Plan, argv: &[String], interval: Duration) -> i32 {
+    let (interrupt_tx, mut interrupted) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            let _ = interrupt_tx.send(true);
+        }
+    });
+
+    let mut iteration = 1;
+    loop {
+        let started = Instant::now();
+        let code = run_argv(args, plan, argv, iteration).await;
+
+        if args.verbose() {
+            info!(
+                "{}: Run {} of '{}' exited with status {}.",
+                "Watch".cyan(),
+                iteration,
+                argv.join(" "),
+                code
+            );
+        }
+
+        if *interrupted.borrow() {
+            return EXIT_INTERRUPTED;
+        }
+        if (args.until_failure && code != 0) || (args.until_success && code == 0) {
+            return code;
+        }
+        if args.count.is_some_and(|count| iteration >= count) {
+            return code;
+        }
+
+        let next = started + interval;
+        if args.verbose() {
+            info!(
+                "{}: Next run in {:?}.",
+                "Watch".cyan(),
+                next.saturating_duration_since(Instant::now())
+            );
+        }
+        tokio::select! {
+            _ = tokio::time::sleep_until(next.into()) => {}
+            _ = interrupted.changed() => return EXIT_INTERRUPTED,
+        }
+        iteration += 1;
+    }
+}
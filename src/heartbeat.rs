@@ -0,0 +1,133 @@
+// src/heartbeat.rs
+// --heartbeat: a line every INTERVAL while COMMAND runs, so CI systems that
+// kill jobs after minutes without output leave a quiet one alone
+
+use crate::summary_format::human_duration;
+use crate::units::parse_duration;
+use crate::TimeoutError;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// A parsed --heartbeat setting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heartbeat {
+    pub interval: Duration,
+    /// Printed instead of the usual line
+    pub text: Option<String>,
+    /// Print on stderr rather than stdout (--heartbeat-stderr)
+    pub stderr: bool,
+}
+
+impl Heartbeat {
+    /// Parse `INTERVAL[:TEXT]`, where INTERVAL is a DURATION above 0
+    pub fn parse(spec: &str, stderr: bool) -> Result<Heartbeat, TimeoutError> {
+        let (interval, text) = match spec.split_once(':') {
+            Some((interval, text)) => (interval, Some(text.to_string())),
+            None => (spec, None),
+        };
+        let interval = parse_duration(interval)?;
+        if interval.is_zero() {
+            return Err(TimeoutError::InvalidDuration {
+                input: spec.to_string(),
+                reason: "the heartbeat interval must be more than 0".to_string(),
+            });
+        }
+        Ok(Heartbeat {
+            interval,
+            text,
+            stderr,
+        })
+    }
+
+    /// Start printing for a run of `command` limited to `limit`
+    /// (`Duration::MAX` for none). The first line comes after one
+    /// INTERVAL; none come with --quiet.
+    pub fn start(&self, command: &str, limit: Duration) -> Beating {
+        if crate::logging::quietness() > 0 {
+            return Beating { task: None };
+        }
+        let heartbeat = self.clone();
+        let command = command.to_string();
+        let started = Instant::now();
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(heartbeat.interval);
+            // The first tick is immediate
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let line = match &heartbeat.text {
+                    Some(text) => text.clone(),
+                    None => line(&command, started.elapsed(), limit),
+                };
+                heartbeat.print(&line);
+            }
+        });
+        Beating { task: Some(task) }
+    }
+
+    /// Print `line`, first ending whatever line COMMAND left unfinished on
+    /// the same stream, as far as timeout forwards that stream itself
+    fn print(&self, line: &str) {
+        crate::progress::clear();
+        let newline = if crate::output::take_mid_line(self.stderr) {
+            "\n"
+        } else {
+            ""
+        };
+        let text = format!("{}{}\n", newline, line);
+        let _ = if self.stderr {
+            let mut stderr = std::io::stderr().lock();
+            stderr
+                .write_all(text.as_bytes())
+                .and_then(|_| stderr.flush())
+        } else {
+            let mut stdout = std::io::stdout().lock();
+            stdout
+                .write_all(text.as_bytes())
+                .and_then(|_| stdout.flush())
+        };
+    }
+}
+
+/// The usual line, e.g. `[timeout] still running ./backup.sh (12m 0s
+/// elapsed, 48m 0s remaining)`, or `(..., stopping it)` past the deadline
+fn line(command: &str, elapsed: Duration, limit: Duration) -> String {
+    let remaining = if limit == Duration::MAX {
+        "no time limit".to_string()
+    } else if elapsed >= limit {
+        "stopping it".to_string()
+    } else {
+        format!(
+            "{} remaining",
+            human_duration(whole_seconds(limit.saturating_sub(elapsed)))
+        )
+    };
+    format!(
+        "[timeout] still running {} ({} elapsed, {})",
+        command,
+        human_duration(whole_seconds(elapsed)),
+        remaining
+    )
+}
+
+/// `duration` to the nearest second, or to the millisecond below one
+fn whole_seconds(duration: Duration) -> Duration {
+    if duration < Duration::from_secs(1) {
+        return Duration::from_millis(duration.as_millis() as u64);
+    }
+    Duration::from_secs(duration.as_secs_f64().round() as u64)
+}
+
+/// Prints heartbeat lines until dropped
+pub struct Beating {
+    task: Option<JoinHandle<()>>,
+}
+
+impl Drop for Beating {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
@@ -0,0 +1,218 @@
+// src/hook.rs
+// Shell commands run around COMMAND (--on-timeout, --pre-kill, --on-success,
+// --on-failure)
+
+use crate::logging::{info, warning};
+use owo_colors::OwoColorize;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Exit code recorded when the hook is killed for overrunning its own limit
+const HOOK_TIMED_OUT: i32 = 124;
+
+/// Exit code recorded when the shell for the hook cannot be started
+const HOOK_NOT_RUN: i32 = 127;
+
+/// How long a --pre-kill hook may run; unlike the other hooks this is not
+/// affected by --hook-timeout
+pub const PRE_KILL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the placeholders in a hook stand for. A value that is not known
+/// when the hook runs, such as `{exit_code}` for --on-timeout, becomes an
+/// empty word.
+#[derive(Default)]
+pub struct HookContext<'a> {
+    pub pid: Option<u32>,
+    pub pgid: Option<u32>,
+    pub elapsed: Duration,
+    pub command: &'a str,
+    pub exit_code: Option<i32>,
+    pub timed_out: Option<bool>,
+    /// The signal about to be sent, for --on-timeout and --pre-kill
+    pub signal: Option<String>,
+}
+
+/// A shell command to run at some point in COMMAND's life
+#[derive(Debug, Clone)]
+pub struct Hook {
+    /// The option that gave it, for messages
+    pub flag: &'static str,
+    /// The command line as given, with `{pid}`, `{pgid}`, `{elapsed_ms}`,
+    /// `{command}`, `{signal}`, `{exit_code}` and `{timed_out}` placeholders,
+    /// which may also be written in capitals (`{PID}`)
+    pub template: String,
+    /// How long the hook may run before it is killed (--hook-timeout)
+    pub timeout: Duration,
+}
+
+impl Hook {
+    /// Substitute the placeholders, each quoted for the shell, in a single
+    /// pass so that a substituted value is never expanded again. Anything
+    /// else in braces, including mixed-case names, is left alone.
+    pub fn render(&self, context: &HookContext) -> String {
+        let mut out = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            rest = &rest[open..];
+            let value = rest.find('}').and_then(|close| {
+                let known = |value: Option<String>| value.unwrap_or_default();
+                let name = &rest[1..close];
+                let name = match name.bytes().any(|b| b.is_ascii_lowercase()) {
+                    true => name.to_string(),
+                    false => name.to_ascii_lowercase(),
+                };
+                let value = match name.as_str() {
+                    "pid" => known(context.pid.map(|pid| pid.to_string())),
+                    "pgid" => known(context.pgid.map(|pgid| pgid.to_string())),
+                    "elapsed_ms" => context.elapsed.as_millis().to_string(),
+                    "command" => context.command.to_string(),
+                    "signal" => known(context.signal.clone()),
+                    "exit_code" => known(context.exit_code.map(|code| code.to_string())),
+                    "timed_out" => known(context.timed_out.map(|t| t.to_string())),
+                    _ => return None,
+                };
+                Some((value, close))
+            });
+            match value {
+                Some((value, close)) => {
+                    out.push_str(&shell_quote(&value));
+                    rest = &rest[close + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Run the hook to completion, or until its time limit, and return its
+    /// exit code: 128+N if a signal ended it, 124 if it had to be killed
+    /// and 127 if it could not be started. A failing hook is reported but
+    /// otherwise changes nothing unless --hook-failures-fatal is given.
+    pub async fn run(&self, context: &HookContext<'_>, verbose: bool) -> i32 {
+        let script = self.render(context);
+        if verbose {
+            info!("{}: running {} hook: {}", "Hook".cyan(), self.flag, script);
+        }
+
+        let mut child = match shell(&script)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                warning!(
+                    "{}: failed to run {} hook: {}",
+                    "Warning".yellow(),
+                    self.flag,
+                    e
+                );
+                return HOOK_NOT_RUN;
+            }
+        };
+
+        match tokio::time::timeout(self.timeout, child.wait()).await {
+            Ok(Ok(status)) => {
+                let code = exit_code(status);
+                if code != 0 {
+                    warning!(
+                        "{}: {} hook exited with status {}",
+                        "Warning".yellow(),
+                        self.flag,
+                        code
+                    );
+                } else if verbose {
+                    info!("{}: {} hook exited with 0", "Hook".cyan(), self.flag);
+                }
+                code
+            }
+            Ok(Err(e)) => {
+                warning!(
+                    "{}: failed to wait for {} hook: {}",
+                    "Warning".yellow(),
+                    self.flag,
+                    e
+                );
+                HOOK_NOT_RUN
+            }
+            Err(_) => {
+                kill_group(&child);
+                let _ = child.kill().await;
+                warning!(
+                    "{}: {} hook still running after {:?}, killed it",
+                    "Warning".yellow(),
+                    self.flag,
+                    self.timeout
+                );
+                HOOK_TIMED_OUT
+            }
+        }
+    }
+}
+
+/// The hook leads a process group of its own, so that whatever it started
+/// can be killed with it and does not keep timeout's output open
+#[cfg(unix)]
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(script).process_group(0);
+    command
+}
+
+#[cfg(unix)]
+fn kill_group(child: &tokio::process::Child) {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+    if let Some(pid) = child.id() {
+        let _ = killpg(Pid::from_raw(pid as i32), Signal::SIGKILL);
+    }
+}
+
+#[cfg(windows)]
+fn kill_group(_child: &tokio::process::Child) {}
+
+/// cmd.exe takes the rest of its command line verbatim after /C, so the
+/// script is passed without the usual argument quoting
+#[cfg(windows)]
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.arg("/C").raw_arg(script);
+    command
+}
+
+#[cfg(unix)]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+    status
+        .code()
+        .or_else(|| status.signal().map(crate::signal::exit_code))
+        .unwrap_or(HOOK_NOT_RUN)
+}
+
+#[cfg(windows)]
+fn exit_code(status: std::process::ExitStatus) -> i32 {
+    status.code().unwrap_or(HOOK_NOT_RUN)
+}
+
+/// Quote `value` as a single `sh` word
+#[cfg(unix)]
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// Quote `value` as a single cmd.exe word. cmd has no way to escape `"`,
+/// `%` or `!` inside quotes, so those characters are dropped
+#[cfg(windows)]
+fn shell_quote(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .filter(|c| !matches!(c, '"' | '%' | '!'))
+        .collect();
+    format!("\"{}\"", value)
+}
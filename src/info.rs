@@ -0,0 +1,82 @@
+// src/info.rs
+// --info and --list-signals: what this machine and build support
+
+use crate::Platform;
+#[cfg(unix)]
+use crate::TimeoutSignal;
+use serde::Serialize;
+
+/// Print every signal --signal accepts here, one per line, e.g.
+/// `SIGHUP        1  Hangup`, for --list-signals
+#[cfg(unix)]
+pub fn print_signals() {
+    let width = TimeoutSignal::all()
+        .map(|sig| sig.as_str().len())
+        .max()
+        .unwrap_or_default();
+    for sig in TimeoutSignal::all() {
+        println!(
+            "{:<width$} {:>3}  {}",
+            sig.as_str(),
+            sig.0,
+            sig.description()
+        );
+    }
+}
+
+/// The `--info --json` object: the platform, then each capability by name
+#[derive(Debug, Serialize)]
+struct InfoJson {
+    platform: &'static str,
+    release: Option<String>,
+    #[serde(flatten)]
+    capabilities: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Print `Platform::capabilities()` for --info, as text or JSON
+pub fn print_info(json: bool) {
+    let capabilities = Platform::capabilities();
+    let release = Platform::os_release();
+
+    if json {
+        let info = InfoJson {
+            platform: Platform::name(),
+            release,
+            capabilities: capabilities
+                .list()
+                .into_iter()
+                .map(|(name, available)| (name.to_string(), available.into()))
+                .collect(),
+        };
+        println!(
+            "{}",
+            serde_json::to_string(&info).expect("the --info report serializes")
+        );
+        return;
+    }
+
+    println!(
+        "platform:   {}{}",
+        Platform::name(),
+        release
+            .as_deref()
+            .map(|r| format!(" {}", r))
+            .unwrap_or_default()
+    );
+    for (name, available) in capabilities.list() {
+        let detail = match (name, available, &release) {
+            ("pidfd", true, Some(release)) => format!(" ({} {})", Platform::name(), release),
+            _ => String::new(),
+        };
+        println!(
+            "{:<11} {}{}",
+            format!("{}:", name),
+            if available {
+                "available"
+            } else {
+                "not available"
+            },
+            detail
+        );
+    }
+}
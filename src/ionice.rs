@@ -0,0 +1,94 @@
+// src/ionice.rs
+// --ionice: COMMAND's I/O scheduling class and level, as ionice(1) sets
+// them, e.g. "best-effort:4" (Linux)
+
+use std::fmt;
+
+/// The highest level the realtime and best-effort classes take; lower
+/// levels are served first
+pub const MAX_LEVEL: u8 = 7;
+
+/// An I/O scheduling class, with the kernel's IOPRIO_CLASS_* number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoClass {
+    Realtime = 1,
+    BestEffort = 2,
+    /// Only gets disk time when no other process wants it; has no levels
+    Idle = 3,
+}
+
+/// A parsed --ionice value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoPriority {
+    pub class: IoClass,
+    /// 0 for idle
+    pub level: u8,
+}
+
+impl IoPriority {
+    /// Parse `CLASS[:LEVEL]`, where CLASS is `realtime`, `best-effort` or
+    /// `idle` (or the ionice class number 1, 2 or 3) and LEVEL is 0-7,
+    /// 4 when left out. `idle` takes no level. Fails with the reason.
+    pub fn parse(input: &str) -> Result<IoPriority, String> {
+        let (class, level) = match input.split_once(':') {
+            Some((class, level)) => (class, Some(level)),
+            None => (input, None),
+        };
+        let class = match class.trim().to_ascii_lowercase().as_str() {
+            "realtime" | "rt" | "1" => IoClass::Realtime,
+            "best-effort" | "be" | "2" => IoClass::BestEffort,
+            "idle" | "3" => IoClass::Idle,
+            other => {
+                return Err(format!(
+                    "unknown class '{}' (expected realtime, best-effort or idle)",
+                    other
+                ))
+            }
+        };
+        let level = match (class, level) {
+            (IoClass::Idle, Some(_)) => return Err("the idle class has no levels".to_string()),
+            (IoClass::Idle, None) => 0,
+            (_, None) => 4,
+            (_, Some(level)) => match level.trim().parse::<u8>() {
+                Ok(level) if level <= MAX_LEVEL => level,
+                _ => {
+                    return Err(format!(
+                        "level '{}' is not a number from 0 to {}",
+                        level.trim(),
+                        MAX_LEVEL
+                    ))
+                }
+            },
+        };
+        Ok(IoPriority { class, level })
+    }
+
+    /// The value ioprio_set takes: the class in the top bits, the level
+    /// below
+    pub fn value(&self) -> i32 {
+        ((self.class as i32) << 13) | i32::from(self.level)
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.class {
+            IoClass::Realtime => write!(f, "realtime:{}", self.level),
+            IoClass::BestEffort => write!(f, "best-effort:{}", self.level),
+            IoClass::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+/// Give the calling process `priority`; called in the child between fork
+/// and exec, so only COMMAND is affected. The realtime class needs
+/// CAP_SYS_ADMIN.
+#[cfg(target_os = "linux")]
+pub fn apply(priority: IoPriority) -> nix::Result<()> {
+    /// IOPRIO_WHO_PROCESS: `who` is a PID, 0 for the caller
+    const WHO_PROCESS: nix::libc::c_int = 1;
+
+    let result =
+        unsafe { nix::libc::syscall(nix::libc::SYS_ioprio_set, WHO_PROCESS, 0, priority.value()) };
+    nix::errno::Errno::result(result).map(drop)
+}
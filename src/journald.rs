@@ -0,0 +1,199 @@
+// src/journald.rs
+// Run outcomes as structured journal entries (--log journald), over
+// journald's native socket protocol
+
+use crate::debug::debug;
+use crate::logging::warning;
+use crate::sink::{Outcome, OutcomeSink};
+use owo_colors::OwoColorize;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Where journald listens for native protocol datagrams
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// The journal's socket, for --log journald
+#[derive(Debug)]
+pub struct Journal {
+    #[cfg(all(target_os = "linux", feature = "journald"))]
+    socket: std::os::unix::net::UnixDatagram,
+    path: PathBuf,
+    /// Set once a failed entry has been reported, so a journal that goes
+    /// away mid-run is warned about only once
+    warned: AtomicBool,
+}
+
+impl Journal {
+    /// Find the journal socket (TIMEOUT_JOURNAL_SOCKET overrides the usual
+    /// path). Without one, warn and return `None`: everything then goes to
+    /// stderr as usual.
+    pub fn open() -> Option<Journal> {
+        let path = std::env::var_os("TIMEOUT_JOURNAL_SOCKET")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(JOURNAL_SOCKET));
+        match Self::connect(path.clone()) {
+            Ok(journal) => Some(journal),
+            Err(reason) => {
+                warning!(
+                    "{}: --log journald: {}; logging to stderr instead",
+                    "Warning".yellow(),
+                    reason
+                );
+                None
+            }
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "journald"))]
+    fn connect(path: PathBuf) -> Result<Journal, String> {
+        use std::os::unix::net::UnixDatagram;
+
+        let socket = UnixDatagram::unbound().map_err(|e| e.to_string())?;
+        socket
+            .connect(&path)
+            .map_err(|e| format!("cannot connect to {}: {}", path.display(), e))?;
+        Ok(Journal {
+            socket,
+            path,
+            warned: AtomicBool::new(false),
+        })
+    }
+
+    #[cfg(all(not(target_os = "linux"), feature = "journald"))]
+    fn connect(_path: PathBuf) -> Result<Journal, String> {
+        Err(format!(
+            "there is no journal on {}",
+            crate::Platform::name()
+        ))
+    }
+
+    #[cfg(not(feature = "journald"))]
+    fn connect(_path: PathBuf) -> Result<Journal, String> {
+        Err("this timeout was built without the journald feature".to_string())
+    }
+
+    /// Write one entry; returns whether the journal took it
+    fn send(&self, fields: &[(String, String)]) -> bool {
+        let entry = encode(fields);
+        let result = self.write(&entry);
+        debug!(
+            "journal entry of {} bytes to {} = {:?}",
+            entry.len(),
+            self.path.display(),
+            result
+        );
+        if let Err(e) = &result {
+            if !self.warned.swap(true, Ordering::Relaxed) {
+                warning!(
+                    "{}: --log journald: cannot write to {}: {}",
+                    "Warning".yellow(),
+                    self.path.display(),
+                    e
+                );
+            }
+        }
+        result.is_ok()
+    }
+
+    /// A single datagram, or for entries too big for one, a sealed memfd
+    /// holding the entry passed as the datagram's only content
+    #[cfg(all(target_os = "linux", feature = "journald"))]
+    fn write(&self, entry: &[u8]) -> std::io::Result<()> {
+        match self.socket.send(entry) {
+            Ok(_) => Ok(()),
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(nix::libc::EMSGSIZE | nix::libc::ENOBUFS)
+                ) =>
+            {
+                self.write_memfd(entry)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    #[cfg(all(target_os = "linux", feature = "journald"))]
+    fn write_memfd(&self, entry: &[u8]) -> std::io::Result<()> {
+        use nix::fcntl::{fcntl, FcntlArg, SealFlag};
+        use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+        use nix::sys::socket::{sendmsg, ControlMessage, MsgFlags, UnixAddr};
+        use std::io::Write;
+        use std::os::fd::AsRawFd;
+
+        let memfd = memfd_create(
+            c"journal-entry",
+            MemFdCreateFlag::MFD_CLOEXEC | MemFdCreateFlag::MFD_ALLOW_SEALING,
+        )?;
+        let mut file = std::fs::File::from(memfd);
+        file.write_all(entry)?;
+        // journald only accepts a memfd nobody can change any more
+        fcntl(
+            file.as_raw_fd(),
+            FcntlArg::F_ADD_SEALS(
+                SealFlag::F_SEAL_SHRINK
+                    | SealFlag::F_SEAL_GROW
+                    | SealFlag::F_SEAL_WRITE
+                    | SealFlag::F_SEAL_SEAL,
+            ),
+        )?;
+        debug!("journal entry too big for a datagram, sent as a memfd");
+        sendmsg::<UnixAddr>(
+            self.socket.as_raw_fd(),
+            &[],
+            &[ControlMessage::ScmRights(&[file.as_raw_fd()])],
+            MsgFlags::empty(),
+            None,
+        )?;
+        Ok(())
+    }
+
+    #[cfg(not(all(target_os = "linux", feature = "journald")))]
+    fn write(&self, _entry: &[u8]) -> std::io::Result<()> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// One entry per run: MESSAGE, PRIORITY and SYSLOG_IDENTIFIER, then each
+/// outcome field upper-cased behind TIMEOUT_, e.g. TIMEOUT_EXIT_CODE
+impl OutcomeSink for Journal {
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        _verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        let mut fields = vec![
+            ("MESSAGE".to_string(), outcome.message()),
+            ("PRIORITY".to_string(), outcome.severity().to_string()),
+            ("SYSLOG_IDENTIFIER".to_string(), "timeout".to_string()),
+        ];
+        fields.extend(
+            outcome
+                .fields()
+                .into_iter()
+                .map(|(name, value)| (format!("TIMEOUT_{}", name.to_uppercase()), value)),
+        );
+        Box::pin(async move { self.send(&fields) })
+    }
+}
+
+/// The native protocol's encoding: `NAME=value` lines, except that a value
+/// containing a newline is written as `NAME`, a newline, its length as a
+/// 64-bit little-endian integer, the value and a newline
+fn encode(fields: &[(String, String)]) -> Vec<u8> {
+    let mut entry = Vec::new();
+    for (name, value) in fields {
+        entry.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            entry.push(b'\n');
+            entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            entry.push(b'=');
+        }
+        entry.extend_from_slice(value.as_bytes());
+        entry.push(b'\n');
+    }
+    entry
+}
@@ -0,0 +1,422 @@
+// src/lib.rs
+// The timeout command wired together: src/main.rs only calls run(), which
+// picks the mode and hands over to the module for it. The parsers and
+// formatters are public for the tests, benches and fuzz targets, and
+// TimeoutRunner for applications that run commands themselves.
+
+pub mod affinity;
+mod argfile;
+mod args;
+mod batch;
+mod capabilities;
+mod cli;
+mod completions;
+pub mod config;
+mod deadline;
+mod debug;
+mod driver;
+pub mod env_opts;
+mod error;
+mod every;
+mod heartbeat;
+mod hook;
+mod info;
+pub mod ionice;
+mod journald;
+mod logfile;
+mod logging;
+mod manpage;
+mod metrics;
+pub mod metrics_format;
+mod otel;
+mod output;
+mod pid_file;
+mod plan;
+mod platform;
+mod progress;
+pub mod progress_format;
+mod prom;
+pub mod prom_textfile;
+mod resolve;
+mod retry;
+#[cfg(unix)]
+mod runner;
+pub mod rusage;
+mod sdnotify;
+#[cfg(unix)]
+pub mod signal;
+mod sink;
+mod statsd;
+mod status_fd;
+mod stdio;
+mod summary;
+pub mod summary_format;
+mod syslog;
+pub mod time_format;
+mod time_output;
+pub mod units;
+mod webhook;
+
+use args::Args;
+use clap::CommandFactory;
+use clap_complete::generate;
+use debug::debug;
+pub use error::TimeoutError;
+use logfile::LogFile;
+use logging::{error, info, warning};
+use metrics::MetricsOutput;
+pub use metrics::{
+    timestamp, MetricsRecord, SentSignal, TimeoutMetrics, TimeoutReason, METRICS_SCHEMA_VERSION,
+};
+use owo_colors::OwoColorize;
+use plan::Plan;
+#[cfg(unix)]
+pub use platform::control::TimeoutHandle;
+pub use platform::{Platform, PlatformCapabilities};
+#[cfg(unix)]
+pub use runner::{TimeoutEvent, TimeoutRunner};
+use sdnotify::Notifier;
+#[cfg(unix)]
+pub use signal::TimeoutSignal;
+use status_fd::StatusFd;
+use std::io;
+use std::path::Path;
+use std::process::exit;
+use std::sync::Arc;
+use stdio::Redirects;
+use summary::SummaryOutput;
+use time_output::TimeOutput;
+use units::{parse_duration, parse_memory_limit, parse_output_limit};
+
+const EXIT_OUTPUT_LIMIT: i32 = 123;
+const EXIT_TIMEDOUT: i32 = 124;
+const EXIT_CANCELED: i32 = 125;
+const EXIT_CANNOT_INVOKE: i32 = 126;
+const EXIT_ENOENT: i32 = 127;
+/// 128 + SIGINT, for Ctrl-C while waiting between --retries attempts or
+/// --every runs
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Run timeout on the process's own arguments and exit with its status
+#[tokio::main]
+pub async fn run() {
+    logging::init();
+    debug!(
+        "timeout {} started as PID {}: {:?}",
+        env!("CARGO_PKG_VERSION"),
+        std::process::id(),
+        std::env::args().collect::<Vec<_>>()
+    );
+    let (mut args, matches) = cli::parse_args(&Args::command());
+    let configured = cli::configure(&mut args, &matches);
+    logging::set_color(&args.color);
+    logging::set_verbosity(args.verbose);
+    logging::set_quiet(args.quiet);
+    let (config_file, settings) = match configured {
+        Ok(configured) => configured,
+        Err(e) => {
+            error!("{}: {}", "timeout".red(), e);
+            exit(EXIT_CANCELED);
+        }
+    };
+
+    if args.dump_config {
+        match (&config_file, args.no_config) {
+            (_, true) => println!("# config files ignored (--no-config)"),
+            (Some(path), _) => println!("# config file: {}", path.display()),
+            (None, _) => println!("# no config file"),
+        }
+        print!("{}", settings.to_toml());
+        return;
+    }
+
+    // Handle shell completion generation
+    if let Some(shell) = args.generate_completions {
+        let mut cmd = Args::command();
+        generate(shell, &mut cmd, "timeout", &mut io::stdout());
+        return;
+    }
+
+    if let Some(dir) = &args.completions_dir {
+        if let Err(e) = cli::write_completions(dir) {
+            error!(
+                "{}: failed to write completions to {}: {}",
+                "Error".red(),
+                dir.display(),
+                e
+            );
+            exit(EXIT_CANCELED);
+        }
+        return;
+    }
+
+    if args.generate_man {
+        let written = match &args.output {
+            Some(path) => {
+                std::fs::File::create(path).and_then(|mut file| manpage::render(&mut file))
+            }
+            None => manpage::render(&mut io::stdout()),
+        };
+        if let Err(e) = written {
+            let target = match &args.output {
+                Some(path) => path.display().to_string(),
+                None => "stdout".to_string(),
+            };
+            error!(
+                "{}: failed to write man page to {}: {}",
+                "Error".red(),
+                target,
+                e
+            );
+            exit(EXIT_CANCELED);
+        }
+        return;
+    }
+
+    if args.info {
+        info::print_info(args.json);
+        return;
+    }
+
+    if args.capabilities {
+        println!("{}", capabilities::to_json(&capabilities::all()));
+        return;
+    }
+
+    #[cfg(unix)]
+    if args.list_signals {
+        info::print_signals();
+        return;
+    }
+
+    #[cfg(unix)]
+    if let Some((path, request)) = args.control().and_then(<[String]>::split_first) {
+        match platform::control::send_request(Path::new(path), request).await {
+            Ok(reply) => {
+                println!("{}", reply);
+                exit(if reply.starts_with("error") { 1 } else { 0 });
+            }
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+    }
+
+    // Show platform-specific warnings
+    if !Platform::IS_LINUX && (args.cpu_limit().is_some() || args.mem_limit().is_some()) {
+        warning!(
+            "{}: Running on {}. Some features may have limited support.",
+            "Warning".yellow(),
+            Platform::name()
+        );
+
+        // The same answer --capabilities gives
+        for (given, feature) in [
+            (args.cpu_limit().is_some(), capabilities::cpu_limit()),
+            (args.mem_limit().is_some(), capabilities::mem_limit()),
+        ] {
+            if given && !feature.supported {
+                error!(
+                    "{}: --{} is {}",
+                    "Error".red(),
+                    feature.name.replace('_', "-"),
+                    feature.why_not()
+                );
+                exit(EXIT_CANCELED);
+            }
+        }
+    }
+
+    let mut plan = match Plan::from_args(&args) {
+        Ok(plan) => plan,
+        Err(e) => {
+            error!("{}: {}", "timeout".red(), e);
+            exit(EXIT_CANCELED);
+        }
+    };
+
+    if let (Some(deadline), true) = (plan.deadline, args.verbose()) {
+        info!(
+            "{}: Running until {} ({:.1}s from now).",
+            "Deadline".cyan(),
+            logfile::rfc3339(deadline),
+            plan.duration.as_secs_f64()
+        );
+    }
+
+    args.args = match argfile::expand_arg_files(&args.args, 0) {
+        Ok(expanded) => expanded,
+        Err(e) => {
+            error!("{}: {}", "timeout".red(), e);
+            exit(EXIT_CANCELED);
+        }
+    };
+
+    for option in &plan.ignored {
+        warning!(
+            "Warning: {} option not supported on {}",
+            option,
+            Platform::name()
+        );
+    }
+
+    if args.foreground() && args.stdin.as_deref().is_some_and(|s| s != "inherit") {
+        warning!(
+            "Warning: --stdin {} replaces the TTY that --foreground lets COMMAND read from",
+            args.stdin.as_deref().unwrap_or_default()
+        );
+    }
+
+    // Connected up front, so an unreachable log target is reported once
+    // rather than after every run, and a metrics target that cannot be
+    // written fails before COMMAND starts
+    if args.dry_run().is_none() && !args.check {
+        plan.sinks = sink::open(&plan);
+        match plan
+            .metrics_target
+            .as_ref()
+            .map(|target| MetricsOutput::open(target, plan.metrics_format))
+            .transpose()
+        {
+            Ok(output) => plan.metrics = output,
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+        match plan
+            .summary_format
+            .as_ref()
+            .map(|template| SummaryOutput::open(template, plan.summary_output.as_deref()))
+            .transpose()
+        {
+            Ok(output) => plan.summary = output,
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+        match plan
+            .time_format
+            .as_deref()
+            .map(|format| TimeOutput::open(format, plan.time_output.as_deref()))
+            .transpose()
+        {
+            Ok(output) => plan.time = output,
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+        match plan.status_fd.map(StatusFd::open).transpose() {
+            Ok(status) => plan.status = status,
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    if let Some(pid) = args.pid {
+        exit(driver::run_pid(&args, &plan, pid).await);
+    }
+
+    // Opened before anything is forked so a bad path fails fast
+    if args.dry_run().is_none() && !args.check {
+        plan.notifier = plan
+            .sd_notify
+            .and_then(|mode| Notifier::open(mode, args.verbose()));
+        let opened = args
+            .log_file
+            .as_ref()
+            .map(|path| LogFile::open(path, args.log_append))
+            .transpose()
+            .and_then(|log| Ok((log, Redirects::open(&args)?)));
+        match opened {
+            Ok((log, redirects)) => {
+                plan.log_file = log.map(Arc::new);
+                plan.redirects = redirects;
+            }
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+    }
+
+    if let Some(jobs) = args.parallel {
+        let commands = match args.batch_delimiter() {
+            Some(delimiter) => match batch::read_batch(delimiter) {
+                Ok(commands) => commands,
+                Err(code) => exit(code),
+            },
+            None => {
+                let command = args.command.clone().expect("command is required");
+                let argv: Vec<String> = std::iter::once(command)
+                    .chain(args.args.iter().cloned())
+                    .collect();
+                vec![argv; jobs as usize]
+            }
+        };
+        exit(batch::run_parallel(Arc::new(args), Arc::new(plan), commands, jobs as usize).await);
+    }
+
+    if let Some(delimiter) = args.batch_delimiter() {
+        exit(batch::run_batch(&args, &plan, delimiter).await);
+    }
+
+    let command = args.command.as_ref().expect("command is required");
+
+    if let Some(format) = args.dry_run() {
+        let resolved = match args.chroot() {
+            Some(_) => Ok(Path::new(command).to_path_buf()),
+            None => resolve::resolve_command(command),
+        };
+        match resolved {
+            Ok(path) => {
+                plan.print(&args, format, &path, command, &args.args);
+                exit(0);
+            }
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(EXIT_CANCELED);
+            }
+        }
+    }
+
+    if args.check {
+        match resolve::resolve_command(command) {
+            Ok(path) => {
+                // Every option has been parsed and validated by now
+                if args.verbose() {
+                    info!(
+                        "{}: options are valid and '{}' resolves to {}",
+                        "Info".cyan(),
+                        command,
+                        path.display()
+                    );
+                }
+                exit(0);
+            }
+            Err(e) => {
+                error!("{}: {}", "timeout".red(), e);
+                exit(e.exit_code());
+            }
+        }
+    }
+
+    if let Some(interval) = plan.every {
+        let argv: Vec<String> = std::iter::once(command.clone())
+            .chain(args.args.iter().cloned())
+            .collect();
+        exit(every::run_watch(&args, &plan, &argv, interval).await);
+    }
+
+    let result = driver::run_command(&args, &plan, command, &args.args, 1).await;
+    let argv: Vec<String> = std::iter::once(command.clone())
+        .chain(args.args.iter().cloned())
+        .collect();
+    exit(driver::finish(&args, &plan, command, &argv, result).await);
+}
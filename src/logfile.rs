@@ -0,0 +1,107 @@
+// src/logfile.rs
+// Timestamped capture of the child's output for --log-file
+
+use crate::{TimeoutError, TimeoutMetrics};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Log file shared by the stdout and stderr pumps.
+///
+/// Each call writes one whole line under the lock, so lines from the two
+/// streams never interleave mid-line.
+#[derive(Debug)]
+pub struct LogFile {
+    file: Mutex<File>,
+}
+
+impl LogFile {
+    /// Open (or create) the log file, truncating unless `append` is set
+    pub fn open(path: &str, append: bool) -> Result<Self, TimeoutError> {
+        let mut options = OpenOptions::new();
+        options.create(true);
+        if append {
+            options.append(true);
+        } else {
+            options.write(true).truncate(true);
+        }
+
+        let file = options
+            .open(path)
+            .map_err(|e| TimeoutError::LogFileFailed {
+                path: path.to_string(),
+                source: e,
+            })?;
+        Ok(LogFile {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Write one line of output, tagged with its stream (`O` or `E`).
+    /// Non-UTF-8 bytes are rendered lossily.
+    pub fn line(&self, tag: &str, line: &[u8]) {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        self.write(&format!(
+            "{} [{}] {}\n",
+            rfc3339(SystemTime::now()),
+            tag,
+            String::from_utf8_lossy(line)
+        ));
+    }
+
+    /// Note how a timed-out command was stopped
+    pub fn record_timeout(&self, metrics: &TimeoutMetrics) {
+        let reason = metrics
+            .timeout_reason
+            .map(|r| r.as_str())
+            .unwrap_or("duration");
+        self.write(&format!(
+            "{} [timeout] {}: sent {}, exit code {}\n",
+            rfc3339(SystemTime::now()),
+            reason,
+            metrics.signal_name(),
+            metrics.exit_code
+        ));
+    }
+
+    fn write(&self, text: &str) {
+        let mut file = self.file.lock().unwrap();
+        let _ = file.write_all(text.as_bytes());
+    }
+}
+
+/// A time in UTC as RFC 3339 with milliseconds, e.g.
+/// `2025-11-26T09:30:00.123Z`
+pub fn rfc3339(time: SystemTime) -> String {
+    let now = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = now.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        now.subsec_millis()
+    )
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) date in the
+/// proleptic Gregorian calendar (Howard Hinnant's algorithm)
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
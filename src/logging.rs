@@ -0,0 +1,217 @@
+// src/logging.rs
+// timeout's messages on stderr: errors, warnings and the --verbose notes.
+// With the logging feature they are tracing events, printed as they always
+// were unless TIMEOUT_LOG filters them; without it they go straight to
+// stderr. Either way they are colored only as --color allows.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How many times -v was given
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+/// How many times -q was given
+static QUIET: AtomicU8 = AtomicU8::new(0);
+
+/// One message at `$level` (error, warn, info or debug), formatted like
+/// `format!`. The event's target is the module it comes from, so
+/// TIMEOUT_LOG can pick out e.g. `timeout::webhook`.
+macro_rules! event {
+    ($level:ident, $($arg:tt)*) => {{
+        #[cfg(feature = "logging")]
+        ::tracing::$level!("{}", format_args!($($arg)*));
+        #[cfg(not(feature = "logging"))]
+        {
+            $crate::progress::clear();
+            ::anstream::eprintln!($($arg)*);
+        }
+    }};
+}
+pub(crate) use event;
+
+/// timeout itself failed, or COMMAND could not be run. Left out by -qq.
+macro_rules! error {
+    ($($arg:tt)*) => {
+        if $crate::logging::quietness() < 2 {
+            $crate::logging::event!(error, $($arg)*)
+        }
+    };
+}
+pub(crate) use error;
+
+/// Something did not work out but timeout carries on, or a time limit was
+/// hit. Left out by -q.
+macro_rules! warning {
+    ($($arg:tt)*) => {
+        if $crate::logging::quietness() == 0 {
+            $crate::logging::event!(warn, $($arg)*)
+        }
+    };
+}
+pub(crate) use warning;
+
+/// --verbose notes and progress of batches, retries and watches. Left
+/// out by -q.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::logging::quietness() == 0 {
+            $crate::logging::event!(info, $($arg)*)
+        }
+    };
+}
+pub(crate) use info;
+
+/// -vv notes on each step: the limits worked out, every signal sent and
+/// every wait result. The arguments are not even formatted below -vv.
+macro_rules! detail {
+    ($($arg:tt)*) => {
+        if $crate::logging::verbosity() >= 2 {
+            $crate::logging::event!(info, $($arg)*)
+        }
+    };
+}
+pub(crate) use detail;
+
+/// Settle for the rest of the run whether messages are colored: as --color
+/// says, or for `auto` as NO_COLOR, CLICOLOR_FORCE and whether stderr is a
+/// terminal say. Deciding once also covers the messages of a forked child,
+/// which must not look the environment up. On Windows, coloring turns on
+/// the console's VT processing.
+pub fn set_color(when: &str) {
+    let choice = match when {
+        "always" => anstream::ColorChoice::Always,
+        "never" => anstream::ColorChoice::Never,
+        _ => anstream::AutoStream::choice(&std::io::stderr()),
+    };
+    choice.write_global();
+}
+
+/// 0 without -v, 1 for -v, 2 for -vv and 3 or more for -vvv
+pub fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Take the -v count from the command line. -vvv turns on the debug trace
+/// of TIMEOUT_DEBUG, unless TIMEOUT_LOG says otherwise.
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+    #[cfg(feature = "logging")]
+    if level >= 3 {
+        if let Some(filter) = DEFAULT_FILTER.get() {
+            let _ = filter.reload(tracing_subscriber::EnvFilter::new("info,timeout=debug"));
+        }
+    }
+}
+
+/// 0 without -q, 1 for -q and 2 or more for -qq. A plain load, so the
+/// forked child may read it too.
+pub fn quietness() -> u8 {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Take the -q count from the command line, then warn of a TIMEOUT_LOG
+/// that `init` could not use
+pub fn set_quiet(level: u8) {
+    QUIET.store(level, Ordering::Relaxed);
+    #[cfg(feature = "logging")]
+    if let Some(message) = INVALID_LOG.get() {
+        warning!("{}", message);
+    }
+}
+
+/// The filter in use when TIMEOUT_LOG does not set one, which -vvv widens
+#[cfg(feature = "logging")]
+static DEFAULT_FILTER: std::sync::OnceLock<
+    tracing_subscriber::reload::Handle<tracing_subscriber::EnvFilter, tracing_subscriber::Registry>,
+> = std::sync::OnceLock::new();
+
+/// The warning about an unusable TIMEOUT_LOG, held back by `init`
+#[cfg(feature = "logging")]
+static INVALID_LOG: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+
+/// Install the subscriber that prints each event's message, and nothing
+/// else, on stderr. TIMEOUT_LOG is an `EnvFilter` directive such as `warn`
+/// or `info,timeout::webhook=off`; by default everything is printed but
+/// debug events, which TIMEOUT_DEBUG turns on.
+#[cfg(feature = "logging")]
+pub fn init() {
+    use owo_colors::OwoColorize;
+    use tracing_subscriber::prelude::*;
+    use tracing_subscriber::{reload, EnvFilter};
+
+    let default = match std::env::var_os("TIMEOUT_DEBUG") {
+        Some(_) => "info,timeout=debug",
+        None => "info",
+    };
+    let spec = std::env::var("TIMEOUT_LOG").unwrap_or_default();
+    let (filter, invalid) = match EnvFilter::try_new(&spec) {
+        _ if spec.trim().is_empty() => (EnvFilter::new(default), None),
+        Ok(filter) => (filter, None),
+        Err(e) => (EnvFilter::new(default), Some(e)),
+    };
+    let given = invalid.is_none() && !spec.trim().is_empty();
+    let (filter, handle) = reload::Layer::new(filter);
+    if !given {
+        let _ = DEFAULT_FILTER.set(handle);
+    }
+    crate::debug::start();
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .event_format(MessageOnly)
+                .with_writer(anstream::stderr),
+        )
+        .init();
+    // Reported once the command line says whether to be quiet
+    if let Some(e) = invalid {
+        let _ = INVALID_LOG.set(format!(
+            "{}: ignoring TIMEOUT_LOG '{}': {}",
+            "Warning".yellow(),
+            spec,
+            e
+        ));
+    }
+}
+
+#[cfg(not(feature = "logging"))]
+pub fn init() {
+    crate::debug::start();
+}
+
+/// Formats an event as its message alone: timeout's messages carry their
+/// own `Warning:`-style prefixes and colors
+#[cfg(feature = "logging")]
+struct MessageOnly;
+
+#[cfg(feature = "logging")]
+impl<S, N> tracing_subscriber::fmt::FormatEvent<S, N> for MessageOnly
+where
+    S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    N: for<'a> tracing_subscriber::fmt::FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        _ctx: &tracing_subscriber::fmt::FmtContext<'_, S, N>,
+        mut writer: tracing_subscriber::fmt::format::Writer<'_>,
+        event: &tracing::Event<'_>,
+    ) -> std::fmt::Result {
+        // Out of the way of a --progress line
+        crate::progress::clear();
+        let mut message = Message(String::new());
+        event.record(&mut message);
+        writeln!(writer, "{}", message.0)
+    }
+}
+
+/// The `message` field of an event, unescaped
+#[cfg(feature = "logging")]
+struct Message(String);
+
+#[cfg(feature = "logging")]
+impl tracing::field::Visit for Message {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+// src/manpage.rs
+// troff man page for --generate-man
+
+use crate::args::Args;
+#[cfg(unix)]
+use crate::TimeoutSignal;
+use crate::{
+    EXIT_CANCELED, EXIT_CANNOT_INVOKE, EXIT_ENOENT, EXIT_INTERRUPTED, EXIT_OUTPUT_LIMIT,
+    EXIT_TIMEDOUT,
+};
+use clap::CommandFactory;
+use clap_mangen::roff::{bold, italic, roman, Roff};
+use clap_mangen::Man;
+use std::io::{self, Write};
+
+/// Environment variables timeout reads, with what they do
+const ENVIRONMENT: [(&str, &str); 8] = [
+    (
+        "TIMEOUT_DURATION",
+        "DURATION to use when it is left off the command line.",
+    ),
+    (
+        "TIMEOUT_METRICS",
+        "If set, print a record of each run to stderr when it ends, as a JSON object \
+         or in the --metrics-format chosen.",
+    ),
+    (
+        "TIMEOUT_METRICS_FILE",
+        "Append that record to this file instead, as --metrics-file does; the \
+         option takes precedence.",
+    ),
+    (
+        "TIMEOUT_DEBUG",
+        "If set, trace timeout's own event loop to stderr: every signal sent, every \
+         waitpid result and every wakeup, with timestamps, as -vvv does. The format is \
+         not stable.",
+    ),
+    (
+        "TIMEOUT_LOG",
+        "Filter timeout's own messages on stderr by level and module, e.g. error, off or \
+         info,timeout::platform=debug. By default everything but the debug trace is \
+         shown.",
+    ),
+    (
+        "NOTIFY_SOCKET",
+        "systemd's notification socket. With --sd-notify proxy, timeout notifies it on \
+         COMMAND's behalf and removes it, WATCHDOG_USEC and WATCHDOG_PID from COMMAND's \
+         environment.",
+    ),
+    (
+        "TIMEOUT_JOURNAL_SOCKET",
+        "Socket --log journald writes to, instead of /run/systemd/journal/socket.",
+    ),
+    (
+        "TIMEOUT_SYSLOG_SOCKET",
+        "Socket --log syslog writes to, instead of /dev/log.",
+    ),
+];
+
+/// Write the whole page: clap's NAME, SYNOPSIS, DESCRIPTION and OPTIONS,
+/// then the sections clap knows nothing about
+pub fn render(out: &mut dyn Write) -> io::Result<()> {
+    let man = Man::new(Args::command());
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    man.render_options_section(out)?;
+
+    let mut roff = Roff::new();
+    exit_status(&mut roff);
+    units(&mut roff);
+    signals(&mut roff);
+    environment(&mut roff);
+    platforms(&mut roff);
+    roff.to_writer(out)?;
+
+    man.render_version_section(out)
+}
+
+/// One `.TP` entry: a bold tag with an indented paragraph under it
+fn entry(roff: &mut Roff, tag: &str, text: &str) {
+    roff.control("TP", []).text([bold(tag)]).text([roman(text)]);
+}
+
+fn exit_status(roff: &mut Roff) {
+    roff.control("SH", ["EXIT STATUS"]);
+    roff.text([roman(
+        "If COMMAND finishes in time, its own exit status; 128+N if signal N ended it, \
+         but at most 255. Otherwise:",
+    )]);
+    let statuses = [
+        (
+            EXIT_TIMEDOUT,
+            "COMMAND timed out (DURATION, --idle-timeout, --kill-on-pattern, --until or \
+             --total-timeout) and neither --status nor --preserve-status was given",
+        ),
+        (EXIT_OUTPUT_LIMIT, "COMMAND was stopped by --max-output"),
+        (
+            EXIT_CANCELED,
+            "timeout itself failed, for example because an option was invalid",
+        ),
+        (EXIT_CANNOT_INVOKE, "COMMAND was found but could not be run"),
+        (EXIT_ENOENT, "COMMAND could not be found"),
+        (
+            EXIT_INTERRUPTED,
+            "interrupted with Ctrl-C between --retries attempts or --every runs",
+        ),
+        (
+            128 + 9,
+            "COMMAND was still running after --kill-after and was sent SIGKILL",
+        ),
+    ];
+    for (status, meaning) in statuses {
+        entry(roff, &status.to_string(), meaning);
+    }
+    roff.control("PP", []).text([roman(
+        "--success-on-pattern exits 0 when its pattern matches, and --pid exits 0 when the \
+         process ends in time.",
+    )]);
+}
+
+fn units(roff: &mut Roff) {
+    roff.control("SH", ["DURATIONS AND SIZES"]);
+    roff.text([
+        roman("A "),
+        italic("DURATION"),
+        roman(
+            " is a non-negative number, which may have a fraction, followed by an optional \
+             unit:",
+        ),
+    ]);
+    for (suffix, unit) in [
+        ("s", "seconds, the default"),
+        ("m", "minutes"),
+        ("h", "hours"),
+        ("d", "days"),
+    ] {
+        entry(roff, suffix, unit);
+    }
+    roff.control("PP", [])
+        .text([roman("For example 10, 2.5s, 5m or 1d.")]);
+    roff.control("PP", []).text([
+        roman("A "),
+        italic("SIZE"),
+        roman(
+            ", for --mem-limit and the --max-output options, is a number of bytes with an \
+             optional suffix in any case: K, M, G, T or P and KiB to PiB are powers of 1024, \
+             KB to PB powers of 1000. --mem-limit also takes a percentage of physical \
+             memory, such as 50%.",
+        ),
+    ]);
+}
+
+#[cfg(unix)]
+fn signals(roff: &mut Roff) {
+    roff.control("SH", ["SIGNALS"]);
+    roff.text([
+        roman("--signal accepts these names, with or without the "),
+        bold("SIG"),
+        roman(" prefix and in any case, or their numbers:"),
+    ]);
+    // The real-time signals are described as a range rather than listed
+    for signal in TimeoutSignal::all().filter(|s| !s.as_str().starts_with("SIGRT")) {
+        entry(
+            roff,
+            signal.as_str(),
+            &format!("{}, signal {}", signal.description(), signal.0),
+        );
+    }
+    #[cfg(target_os = "linux")]
+    roff.control("PP", []).text([
+        roman("On Linux, also the real-time signals "),
+        bold("SIGRTMIN+"),
+        italic("N"),
+        roman(" and "),
+        bold("SIGRTMAX-"),
+        italic("N"),
+        roman(format!(
+            " for N from 0 to 31 (numbers {} to {} here).",
+            nix::libc::SIGRTMIN(),
+            nix::libc::SIGRTMAX()
+        )),
+    ]);
+    roff.control("PP", []).text([roman(
+        "SIGUSR1 and SIGUSR2 sent to timeout itself extend and report the deadline \
+         (see --extend-step), and SIGINT and SIGTERM are passed on to COMMAND.",
+    )]);
+}
+
+#[cfg(not(unix))]
+fn signals(roff: &mut Roff) {
+    roff.control("SH", ["SIGNALS"]);
+    roff.text([roman(
+        "Windows has no signals: a timed-out COMMAND is ended with TerminateProcess, and \
+         Ctrl-C sent to timeout ends COMMAND the same way. A COMMAND that crashes with an \
+         exception exits 128+N for the signal Unix would have sent, e.g. 139 for \
+         STATUS_ACCESS_VIOLATION.",
+    )]);
+}
+
+fn environment(roff: &mut Roff) {
+    roff.control("SH", ["ENVIRONMENT"]);
+    for (name, meaning) in ENVIRONMENT {
+        entry(roff, name, meaning);
+    }
+}
+
+fn platforms(roff: &mut Roff) {
+    roff.control("SH", ["PLATFORM NOTES"]);
+    entry(
+        roff,
+        "Linux",
+        "Every option. COMMAND's exit is noticed through a pidfd, and PR_SET_PDEATHSIG \
+         kills it if timeout itself dies.",
+    );
+    entry(
+        roff,
+        "FreeBSD, DragonFly BSD",
+        "Every option, with --mem-limit applied as RLIMIT_DATA. There is no \
+         PR_SET_PDEATHSIG, so COMMAND outlives a killed timeout.",
+    );
+    entry(
+        roff,
+        "macOS, OpenBSD, NetBSD",
+        "As the BSDs, but --cpu-limit and --mem-limit are not enforced.",
+    );
+    entry(
+        roff,
+        "Windows",
+        "No --foreground, --pty, resource limits, --pid, --stop-instead or control \
+         socket, and COMMAND is always ended with TerminateProcess whatever --signal says. \
+         --no-window (Windows only) starts a console COMMAND without a console.",
+    );
+}
@@ -0,0 +1,476 @@
+// src/metrics.rs
+// The TIMEOUT_METRICS record of each run, and where it goes: stderr, a
+// file (--metrics-file) or an inherited descriptor (--metrics-fd), in the
+// --metrics-format chosen
+
+use crate::args::Args;
+use crate::logfile;
+use crate::logging::{info, warning};
+use crate::metrics_format::{self, MetricsFormat};
+use crate::retry::TimeoutBudget;
+use crate::rusage::ResourceUsage;
+use crate::{TimeoutError, TimeoutSignal, EXIT_OUTPUT_LIMIT, EXIT_TIMEDOUT};
+use owo_colors::OwoColorize;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Which limit caused the command to be stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutReason {
+    /// The overall DURATION expired
+    Duration,
+    /// No output was seen for the --idle-timeout period
+    Idle,
+    /// The command wrote more than a --max-output limit allows
+    #[serde(rename = "output")]
+    OutputLimit,
+    /// An output line matched --kill-on-pattern
+    KillPattern,
+    /// An output line matched --success-on-pattern
+    SuccessPattern,
+}
+
+impl TimeoutReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimeoutReason::Duration => "duration",
+            TimeoutReason::Idle => "idle",
+            TimeoutReason::OutputLimit => "output",
+            TimeoutReason::KillPattern => "kill_pattern",
+            TimeoutReason::SuccessPattern => "success_pattern",
+        }
+    }
+
+    /// Exit status used when neither --status nor --preserve-status applies
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TimeoutReason::Duration | TimeoutReason::Idle | TimeoutReason::KillPattern => {
+                EXIT_TIMEDOUT
+            }
+            TimeoutReason::OutputLimit => EXIT_OUTPUT_LIMIT,
+            TimeoutReason::SuccessPattern => 0,
+        }
+    }
+
+    /// Whether this counts as the command timing out (a success match does not)
+    pub fn is_timeout(&self) -> bool {
+        *self != TimeoutReason::SuccessPattern
+    }
+}
+
+impl fmt::Display for TimeoutReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Version of the TIMEOUT_METRICS JSON, bumped whenever a field is renamed,
+/// removed or changes meaning. 2 added `schema_version`, `argv`,
+/// `started_at` and `pid`, and made every string properly escaped.
+pub const METRICS_SCHEMA_VERSION: u32 = 2;
+
+/// Timeout metrics for observability, serialized as the TIMEOUT_METRICS
+/// JSON object
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeoutMetrics {
+    pub command: String,
+    /// COMMAND and its arguments; empty with --pid
+    pub argv: Vec<String>,
+    #[serde(rename = "duration_ms", serialize_with = "limit_ms")]
+    pub duration: Duration,
+    pub timed_out: bool,
+    pub timeout_reason: Option<TimeoutReason>,
+    pub matched_line: Option<String>,
+    pub exit_code: i32,
+    #[cfg(unix)]
+    #[serde(rename = "signal", serialize_with = "signal_name")]
+    pub signal_sent: Option<TimeoutSignal>,
+    #[cfg(not(unix))]
+    #[serde(rename = "signal", serialize_with = "signal_name")]
+    pub signal_sent: Option<String>,
+    #[serde(rename = "elapsed_ms", serialize_with = "millis")]
+    pub elapsed: Duration,
+    pub kill_after_used: bool,
+    pub cpu_limit: Option<u64>,
+    pub memory_limit: Option<u64>,
+    pub stopped_detected: bool,
+    pub platform: &'static str,
+    /// Which run of the command this was, counting from 1 (see --retries)
+    pub attempt: u32,
+    /// Which watch-mode run this attempt belongs to, counting from 1 (see --every)
+    pub iteration: u32,
+    /// Retries so far caused by timeouts
+    pub retries_timeout: u32,
+    /// Retries so far caused by --retry-on-exit statuses
+    pub retries_exit: u32,
+    /// Time since the first attempt started
+    #[serde(rename = "total_elapsed_ms", serialize_with = "millis")]
+    pub total_elapsed: Duration,
+    /// Which time limit stopped the command, if one did
+    pub budget: Option<TimeoutBudget>,
+    /// Times SIGUSR1 or the control socket extended the deadline
+    pub extensions: u32,
+    /// The existing process given with --pid, when timeout did not start
+    /// the command itself
+    pub watched_pid: Option<i32>,
+    /// Exit code of the --on-timeout hook, if it ran
+    pub hook_exit_code: Option<i32>,
+    /// When the first attempt started
+    #[serde(serialize_with = "timestamp")]
+    pub started_at: SystemTime,
+    /// COMMAND's PID and process group, for the --on-success and
+    /// --on-failure hooks that run after it has been reaped
+    pub pid: Option<u32>,
+    #[serde(skip)]
+    pub pgid: Option<u32>,
+    /// What COMMAND used, once it has been reaped
+    #[serde(flatten)]
+    pub usage: ResourceUsage,
+    /// Every signal timeout sent COMMAND, SIGCONT nudges included, in order
+    pub signals_sent: Vec<SentSignal>,
+    /// Whether it came to SIGKILL (on Windows, TerminateProcess after
+    /// --kill-after)
+    pub kill_signal_sent: bool,
+    /// When the first signal meant to stop COMMAND went out, since it started
+    #[serde(rename = "term_signal_sent_at_ms", serialize_with = "opt_millis")]
+    pub term_signal_sent_at: Option<Duration>,
+    /// When COMMAND was reaped, since it started; null if it was left running
+    #[serde(rename = "child_exited_at_ms", serialize_with = "opt_millis")]
+    pub child_exited_at: Option<Duration>,
+    /// How long COMMAND took to exit after that first signal
+    #[serde(rename = "shutdown_latency_ms", serialize_with = "opt_millis")]
+    pub shutdown_latency: Option<Duration>,
+}
+
+/// A signal timeout sent COMMAND, and when
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SentSignal {
+    /// e.g. SIGTERM, or TERMINATE for TerminateProcess on Windows
+    pub signal: String,
+    /// Since COMMAND started
+    #[serde(rename = "offset_ms", serialize_with = "millis")]
+    pub offset: Duration,
+}
+
+/// The TIMEOUT_METRICS object: the metrics between the schema version and
+/// whether timeout ran COMMAND or watched a --pid
+#[derive(Serialize)]
+pub struct MetricsRecord<'a> {
+    schema_version: u32,
+    #[serde(flatten)]
+    metrics: &'a TimeoutMetrics,
+    mode: &'static str,
+}
+
+impl TimeoutMetrics {
+    /// Name of the signal sent to the command, or "none"
+    pub fn signal_name(&self) -> &str {
+        #[cfg(unix)]
+        return self.signal_sent.map(|s| s.as_str()).unwrap_or("none");
+        #[cfg(not(unix))]
+        return self.signal_sent.as_deref().unwrap_or("none");
+    }
+
+    /// Record the signals sent to COMMAND and, when it was reaped
+    /// (`exited`), how long it took to go after the first one that was
+    /// meant to stop it. Call this once `elapsed` is final.
+    pub fn record_shutdown(&mut self, signals: Vec<SentSignal>, exited: bool) {
+        self.term_signal_sent_at = signals
+            .iter()
+            .find(|s| s.signal != "SIGCONT")
+            .map(|s| s.offset);
+        self.child_exited_at = exited.then_some(self.elapsed);
+        self.shutdown_latency = self
+            .child_exited_at
+            .zip(self.term_signal_sent_at)
+            .map(|(exited, sent)| exited.saturating_sub(sent));
+        self.signals_sent = signals;
+    }
+
+    /// The --verbose line, e.g. `child exited 3.4s after SIGTERM`, or `None`
+    /// when COMMAND was not signalled or not reaped
+    pub fn shutdown_summary(&self) -> Option<String> {
+        let latency = self.shutdown_latency?;
+        let first = self.signals_sent.iter().find(|s| s.signal != "SIGCONT")?;
+        Some(format!(
+            "child exited {:.1}s after {}",
+            latency.as_secs_f64(),
+            first.signal
+        ))
+    }
+
+    /// The metrics as a single-line JSON object
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.record()).expect("metrics serialize to JSON")
+    }
+
+    pub fn record(&self) -> MetricsRecord<'_> {
+        MetricsRecord {
+            schema_version: METRICS_SCHEMA_VERSION,
+            metrics: self,
+            mode: if self.watched_pid.is_some() {
+                "pid"
+            } else {
+                "command"
+            },
+        }
+    }
+}
+
+/// A Duration as whole milliseconds
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// A Duration as whole milliseconds, or null
+fn opt_millis<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => millis(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// DURATION in milliseconds, or null for no limit
+fn limit_ms<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    match *duration {
+        Duration::MAX => serializer.serialize_none(),
+        duration => millis(&duration, serializer),
+    }
+}
+
+/// The signal's name, or "none"
+fn signal_name<S: Serializer, T: Serialize>(
+    signal: &Option<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match signal {
+        Some(signal) => signal.serialize(serializer),
+        None => serializer.serialize_str("none"),
+    }
+}
+
+/// An RFC 3339 timestamp in UTC, e.g. "2025-11-26T09:30:00.123Z"
+pub fn timestamp<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&logfile::rfc3339(*time))
+}
+
+/// One-line summary, e.g. `Command 'sleep' timed out after 5.032s (limit:
+/// 5s), sent SIGTERM, exit code 124`
+impl fmt::Display for TimeoutMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = match self.timeout_reason {
+            Some(TimeoutReason::Duration) => "timed out".to_string(),
+            Some(reason) => format!("was stopped ({})", reason),
+            None => "exited".to_string(),
+        };
+        write!(
+            f,
+            "Command '{}' {} after {:.3}s",
+            self.command,
+            outcome,
+            self.elapsed.as_secs_f64()
+        )?;
+        if self.duration == Duration::MAX {
+            write!(f, " (no limit)")?;
+        } else {
+            write!(f, " (limit: {:?})", self.duration)?;
+        }
+        match self.extensions {
+            0 => {}
+            1 => write!(f, ", extended once")?,
+            n => write!(f, ", extended {} times", n)?,
+        }
+        if self.signal_sent.is_some() {
+            write!(f, ", sent {}", self.signal_name())?;
+        }
+        write!(f, ", exit code {}", self.exit_code)?;
+        if let Some(cpu) = self.cpu_limit {
+            write!(f, ", CPU limit {}s", cpu)?;
+        }
+        if let Some(mem) = self.memory_limit {
+            write!(f, ", memory limit {} bytes", mem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Where metrics records are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsTarget {
+    Stderr,
+    File(String),
+    #[cfg(unix)]
+    Fd(i32),
+}
+
+impl MetricsTarget {
+    /// --metrics-file or --metrics-fd, then TIMEOUT_METRICS_FILE, then
+    /// stderr if TIMEOUT_METRICS is set; `None` when metrics are off
+    pub fn from_args(args: &Args) -> Option<MetricsTarget> {
+        #[cfg(unix)]
+        if let Some(fd) = args.metrics_fd {
+            return Some(MetricsTarget::Fd(fd));
+        }
+        if let Some(path) = &args.metrics_file {
+            return Some(MetricsTarget::File(path.clone()));
+        }
+        match std::env::var("TIMEOUT_METRICS_FILE") {
+            Ok(path) if !path.is_empty() => Some(MetricsTarget::File(path)),
+            _ => std::env::var_os("TIMEOUT_METRICS").map(|_| MetricsTarget::Stderr),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            MetricsTarget::Stderr => "stderr".to_string(),
+            MetricsTarget::File(path) => format!("file {}", path),
+            #[cfg(unix)]
+            MetricsTarget::Fd(fd) => format!("fd {}", fd),
+        }
+    }
+}
+
+/// An opened metrics target. Each record is a single write of one whole
+/// line, and files are opened with O_APPEND, so records from timeouts
+/// sharing a file never interleave.
+#[derive(Debug)]
+pub struct MetricsOutput {
+    target: MetricsTarget,
+    format: MetricsFormat,
+    /// `None` for stderr
+    file: Option<Mutex<File>>,
+    /// Whether the CSV header has been written, for targets that are not
+    /// regular files and so cannot be checked for being empty
+    header_written: AtomicBool,
+}
+
+impl MetricsOutput {
+    /// Open the target now, so a path that cannot be created or a
+    /// descriptor that is not open fails the run before COMMAND starts
+    /// rather than losing its record at the end
+    pub fn open(
+        target: &MetricsTarget,
+        format: MetricsFormat,
+    ) -> Result<MetricsOutput, TimeoutError> {
+        let failed = |source| TimeoutError::MetricsOutputFailed {
+            target: target.describe(),
+            source,
+        };
+        let file = match target {
+            MetricsTarget::Stderr => None,
+            MetricsTarget::File(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(failed)?,
+            ),
+            #[cfg(unix)]
+            MetricsTarget::Fd(fd) => Some(inherited(*fd).map_err(failed)?),
+        };
+        Ok(MetricsOutput {
+            target: target.clone(),
+            format,
+            file: file.map(Mutex::new),
+            header_written: AtomicBool::new(false),
+        })
+    }
+
+    /// Write one record. A failure now is reported but cannot change the
+    /// exit code.
+    pub fn write(&self, metrics: &TimeoutMetrics, verbose: bool) {
+        let result = match &self.file {
+            None => {
+                eprint!("{}", self.render(metrics, None));
+                Ok(())
+            }
+            Some(file) => {
+                // Held across the emptiness check and the write, so two
+                // runs of --every cannot both see an empty file
+                let mut file = file.lock().unwrap();
+                let line = self.render(metrics, Some(&file));
+                file.write_all(line.as_bytes())
+            }
+        };
+        match result {
+            Ok(()) if verbose => info!(
+                "{}: metrics written to {} as {}",
+                "Info".cyan(),
+                self.target.describe(),
+                self.format.as_str()
+            ),
+            Ok(()) => {}
+            Err(e) => warning!(
+                "{}: cannot write metrics to {}: {}",
+                "Warning".yellow(),
+                self.target.describe(),
+                e
+            ),
+        }
+    }
+
+    /// The record as one line in the chosen format, after a CSV header
+    /// row if `file` is a new or empty file, or if it is not a regular file
+    /// and no header has gone to it yet
+    fn render(&self, metrics: &TimeoutMetrics, file: Option<&File>) -> String {
+        let fields = || {
+            let record = serde_json::to_value(metrics.record()).expect("metrics serialize to JSON");
+            metrics_format::fields(record)
+        };
+        match self.format {
+            MetricsFormat::Json => format!("{}\n", metrics.to_json()),
+            MetricsFormat::Logfmt => format!("{}\n", metrics_format::logfmt(&fields())),
+            MetricsFormat::Csv => {
+                let fields = fields();
+                let record = metrics_format::csv_record(&fields);
+                let header = match file.and_then(|f| f.metadata().ok()).filter(|m| m.is_file()) {
+                    Some(meta) => meta.len() == 0,
+                    None => !self.header_written.swap(true, Ordering::Relaxed),
+                };
+                if header {
+                    format!("{}\n{}\n", metrics_format::csv_header(&fields), record)
+                } else {
+                    format!("{}\n", record)
+                }
+            }
+        }
+    }
+}
+
+/// A close-on-exec duplicate of the descriptor `fd`, provided it is open
+/// for writing and was inherited. Everything timeout opens itself is
+/// close-on-exec, so that flag tells a number the caller set up apart from
+/// one of timeout's own descriptors.
+#[cfg(unix)]
+pub fn inherited(fd: i32) -> std::io::Result<File> {
+    use nix::libc::{fcntl, FD_CLOEXEC, F_GETFD, F_GETFL, O_ACCMODE, O_RDONLY};
+    use std::io::{Error, ErrorKind};
+    use std::os::fd::BorrowedFd;
+
+    let fd_flags = unsafe { fcntl(fd, F_GETFD) };
+    if fd_flags < 0 || fd_flags & FD_CLOEXEC != 0 {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "no such inherited descriptor",
+        ));
+    }
+    if unsafe { fcntl(fd, F_GETFL) } & O_ACCMODE == O_RDONLY {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "not open for writing",
+        ));
+    }
+    // SAFETY: fcntl has just confirmed that fd is open, and it is only
+    // borrowed long enough to duplicate it
+    let owned = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?;
+    Ok(File::from(owned))
+}
@@ -0,0 +1,131 @@
+// src/metrics_format.rs
+// The shapes a metrics record can be written in (--metrics-format): JSON
+// for jq, logfmt for log indexers, CSV for spreadsheets
+
+use serde_json::Value;
+
+/// How each metrics record is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricsFormat {
+    /// One JSON object per line
+    Json,
+    /// `key=value` pairs, quoted where needed
+    Logfmt,
+    /// RFC 4180 rows, after a header row at the top of the file
+    Csv,
+}
+
+impl MetricsFormat {
+    pub fn parse(format: &str) -> MetricsFormat {
+        match format {
+            "logfmt" => MetricsFormat::Logfmt,
+            "csv" => MetricsFormat::Csv,
+            _ => MetricsFormat::Json,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricsFormat::Json => "json",
+            MetricsFormat::Logfmt => "logfmt",
+            MetricsFormat::Csv => "csv",
+        }
+    }
+}
+
+/// The fields of a serialized record, in declaration order, which is also
+/// the CSV column order. Anything but an object has no fields.
+pub fn fields(record: Value) -> Vec<(String, Value)> {
+    match record {
+        Value::Object(map) => map.into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A field's value as flat text: strings as they are, numbers and booleans
+/// as JSON writes them, lists and objects as compact JSON; `None` for null
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// One logfmt line, without the newline. Null fields are written as
+/// `key=` with nothing after the `=`.
+pub fn logfmt(fields: &[(String, Value)]) -> String {
+    fields
+        .iter()
+        .map(|(key, value)| match text(value) {
+            Some(text) => format!("{}={}", key, logfmt_value(&text)),
+            None => format!("{}=", key),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `s` as a logfmt value: bare if it is non-empty and holds no space, `=`,
+/// `"` or control character, otherwise in double quotes with `\` and `"`
+/// backslash-escaped and control characters written as `\n`, `\r`, `\t`
+/// or `\u00XX`
+pub fn logfmt_value(s: &str) -> String {
+    let bare = !s.is_empty()
+        && !s
+            .chars()
+            .any(|c| c == ' ' || c == '=' || c == '"' || c.is_control());
+    if bare {
+        return s.to_string();
+    }
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The CSV header row naming each field, without the line ending
+pub fn csv_header(fields: &[(String, Value)]) -> String {
+    csv_row(fields.iter().map(|(key, _)| key.clone()))
+}
+
+/// One CSV record of the fields' values, without the line ending. Null
+/// fields are empty.
+pub fn csv_record(fields: &[(String, Value)]) -> String {
+    csv_row(
+        fields
+            .iter()
+            .map(|(_, value)| text(value).unwrap_or_default()),
+    )
+}
+
+fn csv_row(cells: impl Iterator<Item = String>) -> String {
+    cells
+        .map(|cell| csv_field(&cell))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// `s` as an RFC 4180 field: in double quotes, with `"` doubled, if it
+/// holds a comma, quote or line break, or starts or ends with whitespace
+/// that a reader might trim; otherwise as it is
+pub fn csv_field(s: &str) -> String {
+    let quote = s.contains([',', '"', '\n', '\r'])
+        || s.starts_with(char::is_whitespace)
+        || s.ends_with(char::is_whitespace);
+    if quote {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
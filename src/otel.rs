@@ -0,0 +1,436 @@
+// src/otel.rs
+// One OpenTelemetry span per run, exported over OTLP/HTTP as JSON (--otel),
+// as a child of the caller's TRACEPARENT and the parent of COMMAND's
+
+use crate::debug::debug;
+use crate::logging::{info, warning};
+use crate::metrics_format;
+use crate::sink::{Outcome, OutcomeSink};
+use crate::TimeoutError;
+use owo_colors::OwoColorize;
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where spans go when OTEL_EXPORTER_OTLP_ENDPOINT is not set
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318";
+
+/// The longest an export may hold up timeout's exit. minreq counts in
+/// whole seconds.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The only OTLP protocol timeout speaks
+const PROTOCOL: &str = "http/json";
+
+/// OTLP's SPAN_KIND_INTERNAL and STATUS_CODE_ERROR
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// A W3C trace context, as carried by TRACEPARENT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceParent {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub flags: u8,
+}
+
+impl TraceParent {
+    /// Parse `VERSION-TRACEID-SPANID-FLAGS`. Versions after 00 may add
+    /// fields, which are ignored; all-zero IDs are invalid.
+    pub fn parse(header: &str) -> Option<TraceParent> {
+        let mut fields = header.trim().split('-');
+        let version = fields.next()?;
+        let (trace_id, span_id, flags) = (fields.next()?, fields.next()?, fields.next()?);
+        if lower_hex(version, 2)? == "ff" || (version == "00" && fields.next().is_some()) {
+            return None;
+        }
+        let parent = TraceParent {
+            trace_id: u128::from_str_radix(lower_hex(trace_id, 32)?, 16).ok()?,
+            span_id: u64::from_str_radix(lower_hex(span_id, 16)?, 16).ok()?,
+            flags: u8::from_str_radix(lower_hex(flags, 2)?, 16).ok()?,
+        };
+        (parent.trace_id != 0 && parent.span_id != 0).then_some(parent)
+    }
+}
+
+/// `s` if it is `len` lower-case hex digits, as trace context fields are
+fn lower_hex(s: &str, len: usize) -> Option<&str> {
+    (s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))).then_some(s)
+}
+
+impl std::fmt::Display for TraceParent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+/// A run's span from when it started until its outcome is recorded
+#[derive(Debug)]
+struct Span {
+    id: u64,
+    started: SystemTime,
+}
+
+/// The --otel exporter: where spans go, and the spans of runs under way
+#[derive(Debug)]
+pub struct Otel {
+    /// The traces URL spans are POSTed to
+    pub endpoint: String,
+    headers: Vec<(String, String)>,
+    service_name: String,
+    /// The caller's context (TRACEPARENT), which each run's span is a
+    /// child of
+    pub parent: Option<TraceParent>,
+    /// The trace runs belong to: the parent's, or a new one
+    trace_id: u128,
+    /// Runs started and not yet recorded, by argv. Identical command lines
+    /// running at once (--parallel) are matched up first in, first out.
+    running: Mutex<HashMap<Vec<String>, Vec<Span>>>,
+}
+
+impl Otel {
+    /// Read the OTLP settings and the caller's TRACEPARENT from the
+    /// environment: OTEL_EXPORTER_OTLP_TRACES_ENDPOINT as is, or
+    /// OTEL_EXPORTER_OTLP_ENDPOINT with /v1/traces added (default
+    /// http://localhost:4318), OTEL_EXPORTER_OTLP_(TRACES_)HEADERS and
+    /// OTEL_SERVICE_NAME
+    pub fn from_env(verbose: bool) -> Result<Otel, TimeoutError> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let endpoint = match var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT") {
+            Some(url) => url,
+            None => format!(
+                "{}/v1/traces",
+                var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                    .as_deref()
+                    .unwrap_or(DEFAULT_ENDPOINT)
+                    .trim_end_matches('/')
+            ),
+        };
+        check_endpoint(&endpoint)?;
+
+        let protocol = var("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL")
+            .or_else(|| var("OTEL_EXPORTER_OTLP_PROTOCOL"));
+        if let Some(protocol) = protocol.filter(|p| p != PROTOCOL) {
+            warning!(
+                "{}: --otel: OTLP protocol {} is not supported; sending {}",
+                "Warning".yellow(),
+                protocol,
+                PROTOCOL
+            );
+        }
+
+        let parent = var("TRACEPARENT").and_then(|header| {
+            let parent = TraceParent::parse(&header);
+            if parent.is_none() && verbose {
+                info!(
+                    "{}: --otel: ignoring TRACEPARENT '{}', which is not a W3C trace context",
+                    "Info".cyan(),
+                    header
+                );
+            }
+            parent
+        });
+
+        let mut headers = Vec::new();
+        for name in [
+            "OTEL_EXPORTER_OTLP_HEADERS",
+            "OTEL_EXPORTER_OTLP_TRACES_HEADERS",
+        ] {
+            headers.extend(var(name).as_deref().map(parse_headers).unwrap_or_default());
+        }
+
+        Ok(Otel {
+            endpoint,
+            headers,
+            service_name: var("OTEL_SERVICE_NAME").unwrap_or_else(|| "timeout".to_string()),
+            parent,
+            trace_id: parent.map_or_else(random_trace_id, |p| p.trace_id),
+            running: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Open a span for the run of `argv` about to start, and pass it to
+    /// COMMAND as its TRACEPARENT so that anything it traces links up
+    pub fn start_run(&self, argv: Vec<String>) {
+        let span = Span {
+            id: random_span_id(),
+            started: SystemTime::now(),
+        };
+        let context = TraceParent {
+            trace_id: self.trace_id,
+            span_id: span.id,
+            flags: self.parent.map_or(1, |p| p.flags),
+        };
+        debug!("otel: run of {:?} is span {}", argv, context);
+        std::env::set_var("TRACEPARENT", context.to_string());
+        self.running
+            .lock()
+            .unwrap()
+            .entry(argv)
+            .or_default()
+            .push(span);
+    }
+
+    /// The span started for the outcome's argv, or a fresh one covering the
+    /// run if none was (--pid)
+    fn finish_run(&self, outcome: &Outcome) -> Span {
+        let argv = outcome.argv;
+        let mut running = self.running.lock().unwrap();
+        let span = running
+            .get_mut(argv)
+            .filter(|spans| !spans.is_empty())
+            .map(|spans| spans.remove(0));
+        if running.get(argv).is_some_and(Vec::is_empty) {
+            running.remove(argv);
+        }
+        span.unwrap_or_else(|| Span {
+            id: random_span_id(),
+            started: outcome.started(),
+        })
+    }
+
+    /// The OTLP/JSON export request for one run: its span is named after
+    /// the command, carries every TIMEOUT_METRICS field as a `timeout.*`
+    /// attribute, and has status Error if COMMAND timed out or could not be
+    /// run
+    fn request(&self, outcome: &Outcome) -> Value {
+        let span = self.finish_run(outcome);
+        let mut attributes = vec![
+            attribute("process.command", &json!(outcome.command)),
+            attribute("timeout.exit_code", &json!(outcome.exit_code)),
+            attribute("timeout.timed_out", &json!(outcome.timed_out())),
+        ];
+        if let Some(metrics) = outcome.metrics {
+            let record = serde_json::to_value(metrics.record()).expect("metrics serialize to JSON");
+            attributes.extend(
+                metrics_format::fields(record)
+                    .into_iter()
+                    .filter(|(key, value)| {
+                        !value.is_null() && !matches!(key.as_str(), "exit_code" | "timed_out")
+                    })
+                    .map(|(key, value)| attribute(&format!("timeout.{}", key), &value)),
+            );
+        }
+        if let Some(error) = outcome.error {
+            attributes.push(attribute("timeout.error", &json!(error)));
+        }
+
+        let mut otlp_span = json!({
+            "traceId": format!("{:032x}", self.trace_id),
+            "spanId": format!("{:016x}", span.id),
+            "name": outcome.command,
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": unix_nanos(span.started).to_string(),
+            "endTimeUnixNano": unix_nanos(outcome.ended).to_string(),
+            "attributes": attributes,
+        });
+        if let Some(parent) = self.parent {
+            otlp_span["parentSpanId"] = json!(format!("{:016x}", parent.span_id));
+        }
+        if outcome.timed_out() || outcome.error.is_some() {
+            let message = outcome.error.unwrap_or("timed out");
+            otlp_span["status"] = json!({ "code": STATUS_CODE_ERROR, "message": message });
+        }
+
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", &json!(self.service_name))],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": "timeout", "version": env!("CARGO_PKG_VERSION") },
+                    "spans": [otlp_span],
+                }],
+            }],
+        })
+    }
+
+    /// POST the export request, giving up after EXPORT_TIMEOUT
+    async fn export(&self, body: String, verbose: bool) -> bool {
+        let (endpoint, headers) = (self.endpoint.clone(), self.headers.clone());
+        let post = tokio::task::spawn_blocking(move || post(&endpoint, &headers, &body));
+        let result = match tokio::time::timeout(EXPORT_TIMEOUT, post).await {
+            Ok(joined) => joined.unwrap_or_else(|e| Err(e.to_string())),
+            Err(_) => Err(format!("no response within {:?}", EXPORT_TIMEOUT)),
+        };
+        match result {
+            Ok(status) => {
+                if verbose {
+                    info!(
+                        "{}: exported span to {}: {}",
+                        "Info".cyan(),
+                        self.endpoint,
+                        status
+                    );
+                }
+                true
+            }
+            Err(reason) => {
+                warning!(
+                    "{}: --otel export to {} failed: {}",
+                    "Warning".yellow(),
+                    self.endpoint,
+                    reason
+                );
+                false
+            }
+        }
+    }
+}
+
+/// Export failures are reported on stderr and never change the exit code
+impl OutcomeSink for Otel {
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(async move {
+            let body = self.request(outcome).to_string();
+            self.export(body, verbose).await
+        })
+    }
+
+    fn is_log(&self) -> bool {
+        false
+    }
+}
+
+/// One OTLP KeyValue. Integers are strings, as OTLP/JSON writes int64, and
+/// lists of strings are array values; anything else is its JSON text.
+fn attribute(key: &str, value: &Value) -> Value {
+    let value = match value {
+        Value::Bool(b) => json!({ "boolValue": b }),
+        Value::Number(n) if n.is_i64() || n.is_u64() => json!({ "intValue": n.to_string() }),
+        Value::Number(n) => json!({ "doubleValue": n }),
+        Value::String(s) => json!({ "stringValue": s }),
+        Value::Array(items) if items.iter().all(Value::is_string) => json!({
+            "arrayValue": {
+                "values": items
+                    .iter()
+                    .map(|item| json!({ "stringValue": item }))
+                    .collect::<Vec<_>>(),
+            },
+        }),
+        other => json!({ "stringValue": other.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+/// `name=value,...` as OTEL_EXPORTER_OTLP_HEADERS gives them, with
+/// percent-encoded values
+fn parse_headers(list: &str) -> Vec<(String, String)> {
+    list.split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), percent_decode(value.trim())))
+        .filter(|(name, _)| !name.is_empty())
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Only http:// is built in; https:// needs the webhook-tls feature, which
+/// brings in minreq's TLS
+fn check_endpoint(url: &str) -> Result<(), TimeoutError> {
+    let reason = if cfg!(not(feature = "otel")) {
+        "this timeout was built without the otel feature"
+    } else if url.starts_with("http://") {
+        return Ok(());
+    } else if url.starts_with("https://") {
+        if cfg!(feature = "webhook-tls") {
+            return Ok(());
+        }
+        "https:// endpoints need timeout built with the webhook-tls feature"
+    } else {
+        "expected an http:// or https:// OTLP endpoint"
+    };
+    Err(TimeoutError::InvalidOtel {
+        endpoint: url.to_string(),
+        reason: reason.to_string(),
+    })
+}
+
+/// Blocking POST of an OTLP/JSON request; returns the status line
+#[cfg(feature = "otel")]
+fn post(endpoint: &str, headers: &[(String, String)], body: &str) -> Result<String, String> {
+    let mut request = minreq::post(endpoint)
+        .with_header("Content-Type", "application/json")
+        .with_timeout(EXPORT_TIMEOUT.as_secs())
+        .with_body(body);
+    for (name, value) in headers {
+        request = request.with_header(name, value);
+    }
+    match request.send() {
+        Ok(response) if (200..300).contains(&response.status_code) => Ok(format!(
+            "{} {}",
+            response.status_code, response.reason_phrase
+        )),
+        Ok(response) => Err(format!(
+            "{} {}",
+            response.status_code, response.reason_phrase
+        )),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(not(feature = "otel"))]
+fn post(_endpoint: &str, _headers: &[(String, String)], _body: &str) -> Result<String, String> {
+    Err("this timeout was built without the otel feature".to_string())
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+/// 64 random bits, never zero, from the standard library's randomly keyed
+/// hasher
+fn random_u64() -> u64 {
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(unix_nanos(SystemTime::now()));
+        let n = hasher.finish();
+        if n != 0 {
+            return n;
+        }
+    }
+}
+
+fn random_trace_id() -> u128 {
+    (u128::from(random_u64()) << 64) | u128::from(random_u64())
+}
+
+fn random_span_id() -> u64 {
+    random_u64()
+}
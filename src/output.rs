@@ -0,0 +1,441 @@
+// src/output.rs
+// Forwarding and watching of the child's stdout/stderr when they are piped
+
+use crate::logfile::LogFile;
+use crate::TimeoutReason;
+use regex::bytes::Regex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use tokio::time::Instant;
+
+/// How long to keep forwarding once the child has exited, in case a
+/// background grandchild still holds the pipes open
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest line buffered for matching and logging; longer lines are
+/// handled in pieces of this size
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// Whether the last output forwarded to our stdout, or stderr, stopped
+/// short of a newline. Shared by every run, as they share the streams.
+static STDOUT_MID_LINE: AtomicBool = AtomicBool::new(false);
+static STDERR_MID_LINE: AtomicBool = AtomicBool::new(false);
+
+/// Whether a line of timeout's own about to go to stdout (or `stderr`)
+/// should start with a newline, to keep off the end of a partial line of
+/// COMMAND's; the line then ends the partial one. Only known while the
+/// output is piped through timeout.
+pub fn take_mid_line(stderr: bool) -> bool {
+    match stderr {
+        true => STDERR_MID_LINE.swap(false, Ordering::Relaxed),
+        false => STDOUT_MID_LINE.swap(false, Ordering::Relaxed),
+    }
+}
+
+/// What `--timestamps` puts in front of each output line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampMode {
+    /// Time since the command started
+    Elapsed,
+    /// Time since the command started and time left before DURATION
+    Remaining,
+}
+
+impl TimestampMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimestampMode::Elapsed => "elapsed",
+            TimestampMode::Remaining => "remaining",
+        }
+    }
+}
+
+/// Rules applied to the child's output while it runs
+#[derive(Debug, Clone, Default)]
+pub struct OutputRules {
+    /// Stop the command after this long without any output
+    pub idle_timeout: Option<Duration>,
+    /// Stop the command once stdout and stderr together exceed this many bytes
+    pub max_output: Option<u64>,
+    /// Stop the command once stdout alone exceeds this many bytes
+    pub max_stdout: Option<u64>,
+    /// Stop the command once stderr alone exceeds this many bytes
+    pub max_stderr: Option<u64>,
+    /// Stop the command as on timeout when an output line matches
+    pub kill_pattern: Option<Regex>,
+    /// Exit 0 as soon as an output line matches
+    pub success_pattern: Option<Regex>,
+    /// Leave the command running instead of stopping it when it times out
+    /// or `success_pattern` matches (--no-kill)
+    pub keep_running: bool,
+    /// Prefix each forwarded line with a timestamp
+    pub timestamps: Option<TimestampMode>,
+    /// Overall limit, for `TimestampMode::Remaining`
+    pub overall_limit: Option<Duration>,
+    /// Also write each output line, timestamped, to this file
+    pub log_file: Option<Arc<LogFile>>,
+}
+
+impl OutputRules {
+    /// Whether any rule needs the child's stdout/stderr to go through pipes
+    pub fn needs_pipes(&self) -> bool {
+        self.idle_timeout.is_some()
+            || self.has_byte_limit()
+            || self.has_pattern()
+            || self.timestamps.is_some()
+            || self.log_file.is_some()
+    }
+
+    fn has_byte_limit(&self) -> bool {
+        self.max_output.is_some() || self.max_stdout.is_some() || self.max_stderr.is_some()
+    }
+
+    fn has_pattern(&self) -> bool {
+        self.kill_pattern.is_some() || self.success_pattern.is_some()
+    }
+}
+
+/// Splits a byte stream into lines of at most `MAX_LINE_LEN` bytes
+#[derive(Default)]
+struct LineSplitter {
+    line: Vec<u8>,
+}
+
+impl LineSplitter {
+    /// Add data, calling `emit` for each line it completes
+    fn feed(&mut self, data: &[u8], mut emit: impl FnMut(&[u8])) {
+        for piece in data.split_inclusive(|&b| b == b'\n') {
+            let mut piece = piece;
+            while !piece.is_empty() {
+                let take = piece.len().min(MAX_LINE_LEN - self.line.len());
+                self.line.extend_from_slice(&piece[..take]);
+                piece = &piece[take..];
+                if self.line.ends_with(b"\n") || self.line.len() == MAX_LINE_LEN {
+                    emit(&self.line);
+                    self.line.clear();
+                }
+            }
+        }
+    }
+
+    /// Emit a final unterminated line, if any
+    fn finish(&mut self, mut emit: impl FnMut(&[u8])) {
+        if !self.line.is_empty() {
+            emit(&self.line);
+            self.line.clear();
+        }
+    }
+}
+
+/// Counters shared by the stdout and stderr pumps
+struct OutputState {
+    last_output: Instant,
+    forwarded: u64,
+    over_limit: bool,
+    /// First byte-limit or pattern rule that fired
+    event: Option<TimeoutReason>,
+    matched_line: Option<String>,
+}
+
+impl OutputState {
+    /// Record a rule firing; only the first one counts
+    fn fire(&mut self, reason: TimeoutReason, notify: &Notify) {
+        if self.event.is_none() {
+            self.event = Some(reason);
+            notify.notify_one();
+        }
+    }
+}
+
+struct Shared {
+    started: Instant,
+    state: Mutex<OutputState>,
+    rules: OutputRules,
+    fired: Notify,
+}
+
+impl Shared {
+    /// Line prefix for `--timestamps`, e.g. `[00:04:13.2] `
+    fn timestamp(&self, mode: TimestampMode) -> String {
+        let elapsed = self.started.elapsed();
+        match (mode, self.rules.overall_limit) {
+            (TimestampMode::Remaining, Some(limit)) => format!(
+                "[{} | {} left] ",
+                clock(elapsed),
+                clock(limit.saturating_sub(elapsed))
+            ),
+            _ => format!("[{}] ", clock(elapsed)),
+        }
+    }
+
+    /// Check one complete output line against the pattern rules
+    fn scan_line(&self, line: &[u8]) {
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        let reason = if self
+            .rules
+            .kill_pattern
+            .as_ref()
+            .is_some_and(|re| re.is_match(line))
+        {
+            TimeoutReason::KillPattern
+        } else if self
+            .rules
+            .success_pattern
+            .as_ref()
+            .is_some_and(|re| re.is_match(line))
+        {
+            TimeoutReason::SuccessPattern
+        } else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state.event.is_none() {
+            state.matched_line = Some(String::from_utf8_lossy(line).into_owned());
+            state.fire(reason, &self.fired);
+        }
+    }
+}
+
+/// Forwards the child's piped output to our own stdout/stderr and checks
+/// it against the output rules.
+pub struct OutputWatcher {
+    shared: Arc<Shared>,
+    pumps: Vec<tokio::task::JoinHandle<()>>,
+}
+
+impl OutputWatcher {
+    /// Start forwarding the child's stdout and stderr
+    pub fn start<O, E>(rules: OutputRules, stdout: O, stderr: E) -> Self
+    where
+        O: AsyncRead + Unpin + Send + 'static,
+        E: AsyncRead + Unpin + Send + 'static,
+    {
+        let (max_stdout, max_stderr) = (rules.max_stdout, rules.max_stderr);
+        let shared = Arc::new(Shared {
+            started: Instant::now(),
+            state: Mutex::new(OutputState {
+                last_output: Instant::now(),
+                forwarded: 0,
+                over_limit: false,
+                event: None,
+                matched_line: None,
+            }),
+            rules,
+            fired: Notify::new(),
+        });
+        let pumps = vec![
+            tokio::spawn(pump(
+                stdout,
+                tokio::io::stdout(),
+                &STDOUT_MID_LINE,
+                "O",
+                max_stdout,
+                Arc::clone(&shared),
+            )),
+            tokio::spawn(pump(
+                stderr,
+                tokio::io::stderr(),
+                &STDERR_MID_LINE,
+                "E",
+                max_stderr,
+                Arc::clone(&shared),
+            )),
+        ];
+
+        OutputWatcher { shared, pumps }
+    }
+
+    /// Resolves once an output rule says the command should be stopped
+    pub async fn triggered(&self) -> TimeoutReason {
+        tokio::select! {
+            _ = self.idle() => TimeoutReason::Idle,
+            reason = self.fired() => reason,
+        }
+    }
+
+    /// The output line that matched a pattern rule, if one did
+    pub fn matched_line(&self) -> Option<String> {
+        self.shared.state.lock().unwrap().matched_line.clone()
+    }
+
+    async fn idle(&self) {
+        let Some(idle) = self.shared.rules.idle_timeout else {
+            return std::future::pending().await;
+        };
+
+        loop {
+            let last = self.shared.state.lock().unwrap().last_output;
+            tokio::time::sleep_until(last + idle).await;
+            if self.shared.state.lock().unwrap().last_output == last {
+                return;
+            }
+        }
+    }
+
+    async fn fired(&self) -> TimeoutReason {
+        let rules = &self.shared.rules;
+        if !rules.has_byte_limit() && !rules.has_pattern() {
+            return std::future::pending().await;
+        }
+
+        // notify_one keeps a permit, so a rule firing between the check and
+        // the await is not lost
+        loop {
+            if let Some(reason) = self.shared.state.lock().unwrap().event {
+                return reason;
+            }
+            self.shared.fired.notified().await;
+        }
+    }
+
+    /// Forward whatever output is left after the child has exited
+    pub async fn finish(self) {
+        let aborts: Vec<AbortHandle> = self.pumps.iter().map(|p| p.abort_handle()).collect();
+        let drain = async {
+            for pump in self.pumps {
+                let _ = pump.await;
+            }
+        };
+
+        if tokio::time::timeout(DRAIN_TIMEOUT, drain).await.is_err() {
+            for abort in aborts {
+                abort.abort();
+            }
+        }
+    }
+}
+
+/// Resolves when the overall duration expires or an output rule fires
+#[cfg(windows)]
+pub async fn wait_for_timeout(duration: Duration, output: Option<&OutputWatcher>) -> TimeoutReason {
+    match output {
+        Some(output) => tokio::select! {
+            _ = tokio::time::sleep(duration) => TimeoutReason::Duration,
+            reason = output.triggered() => reason,
+        },
+        None => {
+            tokio::time::sleep(duration).await;
+            TimeoutReason::Duration
+        }
+    }
+}
+
+/// Format a duration as `HH:MM:SS.t`
+fn clock(d: Duration) -> String {
+    let secs = d.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}.{}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        d.subsec_millis() / 100
+    )
+}
+
+/// Insert `prefix` at the start of every line in `data`. Carriage returns
+/// are passed through, so progress-bar repaints keep their line's prefix.
+fn add_timestamps(data: &[u8], prefix: &str, at_line_start: &mut bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + prefix.len());
+    for piece in data.split_inclusive(|&b| b == b'\n') {
+        if *at_line_start {
+            out.extend_from_slice(prefix.as_bytes());
+        }
+        out.extend_from_slice(piece);
+        *at_line_start = piece.ends_with(b"\n");
+    }
+    out
+}
+
+/// Copy one stream until EOF, recording when data was last seen and in
+/// `mid_line` whether it ended a line, enforcing the byte limits, scanning
+/// lines for the pattern rules and logging them under `tag`.
+///
+/// Bytes up to a limit are still forwarded; after that the stream is read
+/// and discarded so the child never blocks on a full pipe. Write errors are
+/// ignored for the same reason.
+async fn pump<R, W>(
+    mut reader: R,
+    mut writer: W,
+    mid_line: &'static AtomicBool,
+    tag: &'static str,
+    stream_limit: Option<u64>,
+    shared: Arc<Shared>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let scan = shared.rules.has_pattern();
+    let log = shared.rules.log_file.clone();
+    let mut buf = vec![0u8; 8192];
+    let mut scanned = LineSplitter::default();
+    let mut logged = LineSplitter::default();
+    let mut stream_total = 0u64;
+    let mut at_line_start = true;
+    loop {
+        let n = match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+
+        let allowed = {
+            let mut state = shared.state.lock().unwrap();
+            state.last_output = Instant::now();
+
+            if state.over_limit {
+                0
+            } else {
+                let mut allowed = n as u64;
+                if let Some(limit) = stream_limit {
+                    allowed = allowed.min(limit.saturating_sub(stream_total));
+                }
+                if let Some(limit) = shared.rules.max_output {
+                    allowed = allowed.min(limit.saturating_sub(state.forwarded));
+                }
+                stream_total += n as u64;
+                state.forwarded += allowed;
+
+                if allowed < n as u64 {
+                    state.over_limit = true;
+                    state.fire(TimeoutReason::OutputLimit, &shared.fired);
+                }
+                allowed as usize
+            }
+        };
+
+        if allowed > 0 {
+            let written = match shared.rules.timestamps {
+                Some(mode) => {
+                    let prefix = shared.timestamp(mode);
+                    let stamped = add_timestamps(&buf[..allowed], &prefix, &mut at_line_start);
+                    writer.write_all(&stamped).await
+                }
+                None => writer.write_all(&buf[..allowed]).await,
+            };
+            if written.is_ok() {
+                let _ = writer.flush().await;
+                mid_line.store(!buf[..allowed].ends_with(b"\n"), Ordering::Relaxed);
+            }
+        }
+
+        if let Some(log) = &log {
+            logged.feed(&buf[..allowed], |line| log.line(tag, line));
+        }
+        if scan {
+            scanned.feed(&buf[..n], |line| shared.scan_line(line));
+        }
+    }
+
+    if let Some(log) = &log {
+        logged.finish(|line| log.line(tag, line));
+    }
+    if scan {
+        scanned.finish(|line| shared.scan_line(line));
+    }
+}
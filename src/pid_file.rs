@@ -0,0 +1,64 @@
+// src/pid_file.rs
+// --pid-file: COMMAND's PID (and on Unix its process group) in a file for
+// supervisors, there for as long as COMMAND runs
+
+use crate::TimeoutError;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// A --pid-file, removed when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl PidFile {
+    /// Create `path`, which must not exist yet unless `force` allows
+    /// overwriting it: a leftover file suggests another timeout is still
+    /// running, or one was killed before it could clean up
+    pub fn create(path: &Path, force: bool) -> Result<PidFile, TimeoutError> {
+        let mut options = OpenOptions::new();
+        options.write(true);
+        if force {
+            options.create(true).truncate(true);
+        } else {
+            options.create_new(true);
+        }
+        match options.open(path) {
+            Ok(file) => Ok(PidFile {
+                path: path.to_path_buf(),
+                file,
+            }),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                Err(TimeoutError::PidFileExists(path.display().to_string()))
+            }
+            Err(source) => Err(TimeoutError::PidFileFailed {
+                path: path.display().to_string(),
+                source,
+            }),
+        }
+    }
+
+    /// Write COMMAND's PID and, when there is one, its process group ID,
+    /// each on a line of its own
+    pub fn write(&mut self, pid: u32, pgid: Option<u32>) -> Result<(), TimeoutError> {
+        let text = match pgid {
+            Some(pgid) => format!("{}\n{}\n", pid, pgid),
+            None => format!("{}\n", pid),
+        };
+        self.file
+            .write_all(text.as_bytes())
+            .map_err(|source| TimeoutError::PidFileFailed {
+                path: self.path.display().to_string(),
+                source,
+            })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,1026 @@
+// src/plan.rs
+// Execution plan: parsed and validated settings for a timeout invocation
+
+use crate::affinity;
+use crate::args::Args;
+use crate::deadline;
+use crate::heartbeat::Heartbeat;
+use crate::hook::{Hook, PRE_KILL_TIMEOUT};
+use crate::ionice::IoPriority;
+use crate::logfile::{rfc3339, LogFile};
+use crate::metrics::{MetricsOutput, MetricsTarget};
+use crate::metrics_format::MetricsFormat;
+use crate::otel::Otel;
+use crate::output::{OutputRules, TimestampMode};
+use crate::retry::{ExitCodes, RetryBackoff, RetryPolicy};
+use crate::sdnotify::{Notifier, SdNotify};
+use crate::sink::{LogTarget, OutcomeSink};
+use crate::statsd::StatsdTarget;
+use crate::status_fd::StatusFd;
+use crate::stdio::Redirects;
+use crate::summary::SummaryOutput;
+use crate::summary_format::Template;
+use crate::syslog::SyslogFormat;
+use crate::time_format;
+use crate::time_output::TimeOutput;
+use crate::units::format_size;
+use crate::webhook::Webhook;
+use crate::{
+    parse_duration, parse_memory_limit, parse_output_limit, Platform, TimeoutError, EXIT_TIMEDOUT,
+};
+use regex::bytes::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[cfg(unix)]
+use crate::TimeoutSignal;
+#[cfg(unix)]
+use nix::sys::signal::Signal;
+
+/// Everything timeout needs to know before it starts a command.
+///
+/// Built once from the command line so that the normal run, batch mode and
+/// `--dry-run` all share the same parsing and validation.
+#[derive(Debug)]
+pub struct Plan {
+    /// Overall limit; `Duration::MAX` when only the idle timeout applies
+    pub duration: Duration,
+    /// Wall-clock time given with --until, from which `duration` was worked out
+    pub deadline: Option<SystemTime>,
+    #[cfg(unix)]
+    pub term_signal: TimeoutSignal,
+    /// How far SIGUSR1 pushes the deadline back; None with --forward-signals
+    #[cfg(unix)]
+    pub extend_step: Option<Duration>,
+    /// How often --pid checks for exit without a pidfd
+    #[cfg(unix)]
+    pub poll_interval: Duration,
+    /// How often the --progress line is redrawn; None without --progress
+    pub progress: Option<Duration>,
+    pub kill_after: Option<Duration>,
+    pub idle_timeout: Option<Duration>,
+    pub max_output: Option<u64>,
+    pub max_output_stdout: Option<u64>,
+    pub max_output_stderr: Option<u64>,
+    pub kill_pattern: Option<Regex>,
+    pub success_pattern: Option<Regex>,
+    pub keep_running: bool,
+    /// Time COMMAND out as soon as it starts (--simulate-timeout)
+    pub simulate_timeout: bool,
+    /// Run when COMMAND times out, before it is signalled (--on-timeout)
+    pub on_timeout: Option<Hook>,
+    /// Run after any --on-timeout hook, just before the signal (--pre-kill)
+    pub pre_kill: Option<Hook>,
+    /// Run once COMMAND has been reaped, depending on how it did
+    pub on_success: Option<Hook>,
+    pub on_failure: Option<Hook>,
+    /// Exit 125 when a hook fails (--hook-failures-fatal)
+    pub hook_failures_fatal: bool,
+    /// Where to POST a report of each run (--webhook)
+    pub webhook: Option<Webhook>,
+    /// Where run outcomes are recorded besides stderr (--log)
+    pub log: Vec<LogTarget>,
+    pub syslog_format: SyslogFormat,
+    /// Where to send StatsD datapoints for each run (--statsd)
+    pub statsd: Option<StatsdTarget>,
+    /// The span exporter, which also names each run's span to COMMAND
+    /// (--otel)
+    pub otel: Option<Arc<Otel>>,
+    /// The node_exporter textfile to rewrite after each run
+    /// (--prom-textfile)
+    pub prom_textfile: Option<String>,
+    /// The log targets that could be reached, --webhook, --statsd, --otel
+    /// and --prom-textfile; connected just before running
+    pub sinks: Vec<Arc<dyn OutcomeSink>>,
+    /// Where each run's metrics record goes, if anywhere
+    pub metrics_target: Option<MetricsTarget>,
+    pub metrics_format: MetricsFormat,
+    /// Opened along with the sinks
+    pub metrics: Option<MetricsOutput>,
+    /// The line printed at the end of each run (--summary-format)
+    pub summary_format: Option<Template>,
+    /// Where that line goes; stderr when `None` (--summary-output)
+    pub summary_output: Option<String>,
+    /// Opened along with the metrics target
+    pub summary: Option<SummaryOutput>,
+    /// How to lay out the report of each run, if --time is on
+    pub time_format: Option<String>,
+    /// Where that report goes; stderr when `None` (--time-output)
+    pub time_output: Option<String>,
+    /// Opened along with the metrics target
+    pub time: Option<TimeOutput>,
+    /// The descriptor that gets how each run ended (--status-fd)
+    pub status_fd: Option<i32>,
+    /// Checked along with the metrics target
+    pub status: Option<StatusFd>,
+    /// Who talks to systemd (--sd-notify)
+    pub sd_notify: Option<SdNotify>,
+    /// Taken from the environment just before running, in proxy mode only
+    pub notifier: Option<Arc<Notifier>>,
+    pub timestamps: Option<TimestampMode>,
+    pub heartbeat: Option<Heartbeat>,
+    pub retry: RetryPolicy,
+    /// COMMAND exit statuses that timeout exits 0 for instead
+    /// (--suppress-exit-code)
+    pub suppress_exit_codes: HashSet<i32>,
+    /// Interval between run starts in watch mode (--every)
+    pub every: Option<Duration>,
+    /// Opened separately, just before running, so --dry-run never creates it
+    pub log_file: Option<Arc<LogFile>>,
+    /// Opened alongside the log file
+    pub redirects: Redirects,
+    pub cpu_limit: Option<u64>,
+    pub mem_limit: Option<u64>,
+    /// CPUs COMMAND may run on (--cpu-affinity), sorted
+    pub cpu_affinity: Option<Vec<usize>>,
+    /// COMMAND's I/O scheduling class and level (--ionice)
+    pub io_priority: Option<IoPriority>,
+    /// Options given on the command line that this platform ignores
+    pub ignored: Vec<&'static str>,
+}
+
+/// The plan `--dry-run` shows, built once for both formats: serialized as
+/// the `--dry-run=json` object, where keys keep this order and every
+/// setting is present, null when unset, and displayed as the text plan.
+#[derive(Debug, Serialize)]
+struct DryRun<'a> {
+    command: String,
+    argv: &'a [&'a str],
+    duration_ms: Option<u128>,
+    deadline: Option<String>,
+    boottime: bool,
+    kill_after_ms: Option<u128>,
+    idle_timeout_ms: Option<u128>,
+    max_output_bytes: Option<u64>,
+    max_output_stdout_bytes: Option<u64>,
+    max_output_stderr_bytes: Option<u64>,
+    kill_pattern: Option<&'a str>,
+    success_pattern: Option<&'a str>,
+    keep_running: bool,
+    simulate_timeout: bool,
+    simulated_steps: Vec<String>,
+    on_timeout: Option<&'a str>,
+    on_timeout_timeout_ms: Option<u128>,
+    pre_kill: Option<&'a str>,
+    on_success: Option<&'a str>,
+    on_failure: Option<&'a str>,
+    hook_timeout_ms: Option<u128>,
+    hook_failures_fatal: bool,
+    webhook: Option<&'a str>,
+    webhook_on: Vec<&'a str>,
+    /// Header values are often credentials, so only names are shown
+    webhook_headers: Vec<&'a str>,
+    log: Vec<String>,
+    /// Whether a --log target is syslog, which is all --syslog-format
+    /// applies to
+    #[serde(skip)]
+    syslog: bool,
+    syslog_format: &'a str,
+    statsd: Option<DryRunStatsd<'a>>,
+    otel: Option<DryRunOtel<'a>>,
+    prom_textfile: Option<&'a str>,
+    metrics: Option<String>,
+    metrics_format: &'a str,
+    summary_format: Option<&'a str>,
+    summary_output: Option<&'a str>,
+    time_format: Option<&'a str>,
+    time_output: Option<&'a str>,
+    status_fd: Option<i32>,
+    sd_notify: Option<&'a str>,
+    timestamps: Option<&'a str>,
+    heartbeat_ms: Option<u128>,
+    heartbeat_text: Option<&'a str>,
+    heartbeat_stderr: bool,
+    retries: u32,
+    retry_on_exit: Option<String>,
+    retry_delay_ms: u128,
+    retry_backoff: &'a str,
+    retry_max_delay_ms: u128,
+    total_timeout_ms: Option<u128>,
+    clamp_last_attempt: bool,
+    suppress_exit_codes: Vec<i32>,
+    every_ms: Option<u128>,
+    count: Option<u32>,
+    signal: &'a str,
+    kill_signal: Option<&'a str>,
+    extend_step_ms: Option<u128>,
+    cpu_limit_secs: Option<u64>,
+    memory_limit_bytes: Option<u64>,
+    cpu_affinity: Option<String>,
+    io_priority: Option<String>,
+    namespaced: bool,
+    chroot: Option<String>,
+    new_session: bool,
+    backend: String,
+    ignored: &'a [&'static str],
+}
+
+/// `--statsd` in the dry-run object
+#[derive(Debug, Serialize)]
+struct DryRunStatsd<'a> {
+    address: &'a str,
+    prefix: &'a str,
+    tags: bool,
+}
+
+/// `--otel` in the dry-run object
+#[derive(Debug, Serialize)]
+struct DryRunOtel<'a> {
+    endpoint: &'a str,
+    traceparent: Option<String>,
+}
+
+impl Plan {
+    /// Parse and validate all timing, signal and limit options
+    pub fn from_args(args: &Args) -> Result<Plan, TimeoutError> {
+        let (mut duration, deadline) = match &args.until {
+            Some(until) => {
+                let now = SystemTime::now();
+                let deadline = deadline::parse_until(until, now)?;
+                let budget = deadline
+                    .duration_since(now)
+                    .ok()
+                    .filter(|budget| !budget.is_zero())
+                    .ok_or_else(|| TimeoutError::InvalidDeadline {
+                        input: until.to_string(),
+                        reason: format!("{} has already passed", rfc3339(deadline)),
+                    })?;
+                (budget, Some(deadline))
+            }
+            None => {
+                let duration_str = args.duration.as_ref().expect("duration is required");
+                (parse_duration(duration_str)?, None)
+            }
+        };
+
+        #[cfg(unix)]
+        let term_signal = args
+            .signal
+            .unwrap_or_else(|| TimeoutSignal::from(Signal::SIGTERM));
+
+        #[cfg(unix)]
+        let extend_step = match &args.extend_step {
+            _ if args.forward_signals => None,
+            Some(step) => Some(parse_duration(step)?),
+            None => Some(Duration::from_secs(300)),
+        };
+
+        #[cfg(unix)]
+        let poll_interval = match &args.poll_interval {
+            Some(interval) => parse_duration(interval)?.max(Duration::from_millis(1)),
+            None => Duration::from_millis(100),
+        };
+
+        let heartbeat = args
+            .heartbeat
+            .as_deref()
+            .map(|spec| Heartbeat::parse(spec, args.heartbeat_stderr))
+            .transpose()?;
+
+        let progress = match &args.progress_interval {
+            _ if !args.progress => None,
+            Some(interval) => Some(parse_duration(interval)?.max(Duration::from_millis(10))),
+            None => Some(Duration::from_millis(500)),
+        };
+
+        #[cfg(unix)]
+        let ignored = Vec::new();
+        #[cfg(not(unix))]
+        let ignored = if args.signal.is_some() {
+            vec!["--signal"]
+        } else {
+            Vec::new()
+        };
+
+        let kill_after = match &args.kill_after {
+            Some(ka) => Some(parse_duration(ka)?),
+            None => None,
+        };
+
+        let idle_timeout = match &args.idle_timeout {
+            Some(idle) => Some(parse_duration(idle)?),
+            None => None,
+        };
+
+        let parse_limit =
+            |limit: &Option<String>| limit.as_deref().map(parse_output_limit).transpose();
+        let max_output = parse_limit(&args.max_output)?;
+        let max_output_stdout = parse_limit(&args.max_output_stdout)?;
+        let max_output_stderr = parse_limit(&args.max_output_stderr)?;
+
+        let compile = |pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(|p| {
+                    Regex::new(p).map_err(|e| TimeoutError::InvalidPattern {
+                        pattern: p.to_string(),
+                        reason: e.to_string(),
+                    })
+                })
+                .transpose()
+        };
+        let kill_pattern = compile(&args.kill_on_pattern)?;
+        let success_pattern = compile(&args.success_on_pattern)?;
+
+        check_redirect_conflicts(args)?;
+
+        let hook_timeout = match &args.hook_timeout {
+            Some(timeout) => parse_duration(timeout)?,
+            None => Duration::from_secs(30),
+        };
+        let hook = |flag, template: &Option<String>| {
+            template.as_ref().map(|template| Hook {
+                flag,
+                template: template.clone(),
+                timeout: hook_timeout,
+            })
+        };
+        let mut on_timeout = hook("--on-timeout", &args.on_timeout);
+        if let (Some(hook), Some(timeout)) = (&mut on_timeout, &args.on_timeout_timeout) {
+            hook.timeout = parse_duration(timeout)?;
+        }
+        let pre_kill = args.pre_kill.as_ref().map(|template| Hook {
+            flag: "--pre-kill",
+            template: template.clone(),
+            timeout: PRE_KILL_TIMEOUT,
+        });
+        let on_success = hook("--on-success", &args.on_success);
+        let on_failure = hook("--on-failure", &args.on_failure);
+        let webhook = args
+            .webhook
+            .as_deref()
+            .map(|url| Webhook::new(url, &args.webhook_header, &args.webhook_on))
+            .transpose()?;
+
+        let log = args
+            .log
+            .iter()
+            .map(|target| LogTarget::parse(target))
+            .collect::<Result<Vec<_>, _>>()?;
+        let statsd = args
+            .statsd
+            .as_deref()
+            .map(|address| {
+                StatsdTarget::new(address, args.statsd_prefix.as_deref(), args.statsd_tags)
+            })
+            .transpose()?;
+        let otel = match args.otel {
+            true => Some(Arc::new(Otel::from_env(args.verbose())?)),
+            false => None,
+        };
+
+        let retry = RetryPolicy {
+            retries: args.retries.unwrap_or(0),
+            on_exit: args
+                .retry_on_exit
+                .as_deref()
+                .map(ExitCodes::parse)
+                .transpose()?,
+            delay: match &args.retry_delay {
+                Some(delay) => parse_duration(delay)?,
+                None => Duration::from_secs(1),
+            },
+            backoff: match args.retry_backoff.as_deref() {
+                Some("exponential") => RetryBackoff::Exponential,
+                _ => RetryBackoff::Fixed,
+            },
+            max_delay: match &args.retry_max_delay {
+                Some(max) => parse_duration(max)?,
+                None => Duration::from_secs(300),
+            },
+            total: match &args.total_timeout {
+                Some(total) => Some(parse_duration(total)?),
+                None => None,
+            },
+            clamp_last_attempt: args.clamp_last_attempt,
+        };
+
+        // With an idle timeout, a DURATION of 0 means no overall limit
+        if duration.is_zero() && idle_timeout.is_some() {
+            duration = Duration::MAX;
+        }
+
+        let mem_limit = match &args.mem_limit() {
+            // RLIMIT_AS of 0 kills COMMAND on its first allocation
+            Some(mem) => match parse_memory_limit(mem)? {
+                0 => {
+                    return Err(TimeoutError::InvalidMemoryLimit {
+                        input: mem.to_string(),
+                        reason: "use --no-mem-limit to explicitly disable the limit".to_string(),
+                    })
+                }
+                bytes => Some(bytes),
+            },
+            None => None,
+        };
+
+        let cpu_affinity = match &args.cpu_affinity {
+            Some(list) => {
+                let cpus = affinity::parse_cpu_list(list).map_err(|reason| {
+                    TimeoutError::InvalidCpuAffinity {
+                        input: list.clone(),
+                        reason,
+                    }
+                })?;
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let _ = cpus;
+                    return Err(TimeoutError::FeatureNotSupported(format!(
+                        "--cpu-affinity is {}",
+                        crate::capabilities::cpu_affinity().why_not()
+                    )));
+                }
+                #[cfg(target_os = "linux")]
+                Some(cpus)
+            }
+            None => None,
+        };
+
+        let io_priority = match &args.ionice {
+            Some(input) => {
+                let priority =
+                    IoPriority::parse(input).map_err(|reason| TimeoutError::InvalidIoPriority {
+                        input: input.clone(),
+                        reason,
+                    })?;
+                #[cfg(not(target_os = "linux"))]
+                {
+                    let _ = priority;
+                    return Err(TimeoutError::FeatureNotSupported(format!(
+                        "--ionice is {}",
+                        crate::capabilities::ionice().why_not()
+                    )));
+                }
+                #[cfg(target_os = "linux")]
+                Some(priority)
+            }
+            None => None,
+        };
+
+        Ok(Plan {
+            duration,
+            deadline,
+            #[cfg(unix)]
+            term_signal,
+            #[cfg(unix)]
+            extend_step,
+            #[cfg(unix)]
+            poll_interval,
+            progress,
+            kill_after,
+            idle_timeout,
+            max_output,
+            max_output_stdout,
+            max_output_stderr,
+            kill_pattern,
+            success_pattern,
+            keep_running: args.no_kill,
+            simulate_timeout: args.simulate_timeout,
+            on_timeout,
+            pre_kill,
+            on_success,
+            on_failure,
+            hook_failures_fatal: args.hook_failures_fatal,
+            webhook,
+            log,
+            syslog_format: match args.syslog_format.as_deref() {
+                Some("rfc3164") => SyslogFormat::Rfc3164,
+                _ => SyslogFormat::Rfc5424,
+            },
+            statsd,
+            otel,
+            prom_textfile: args.prom_textfile.clone(),
+            sinks: Vec::new(),
+            metrics_target: MetricsTarget::from_args(args),
+            metrics_format: args
+                .metrics_format
+                .as_deref()
+                .map_or(MetricsFormat::Json, MetricsFormat::parse),
+            metrics: None,
+            summary_format: match &args.summary_format {
+                Some(format) => Some(Template::parse(format).map_err(|reason| {
+                    TimeoutError::InvalidSummaryFormat {
+                        input: format.clone(),
+                        reason,
+                    }
+                })?),
+                None => None,
+            },
+            summary_output: args.summary_output.clone(),
+            summary: None,
+            time_format: (args.time || args.time_format.is_some() || args.time_output.is_some())
+                .then(|| {
+                    args.time_format
+                        .clone()
+                        .unwrap_or_else(|| time_format::DEFAULT_FORMAT.to_string())
+                }),
+            time_output: args.time_output.clone(),
+            status_fd: args.status_fd(),
+            status: None,
+            time: None,
+            sd_notify: args.sd_notify.as_deref().map(SdNotify::parse),
+            notifier: None,
+            timestamps: args.timestamps.as_deref().map(|mode| match mode {
+                "remaining" => TimestampMode::Remaining,
+                _ => TimestampMode::Elapsed,
+            }),
+            heartbeat,
+            retry,
+            suppress_exit_codes: args.suppress_exit_code.iter().copied().collect(),
+            every: match &args.every {
+                Some(every) => Some(parse_duration(every)?),
+                None => None,
+            },
+            log_file: None,
+            redirects: Redirects::default(),
+            cpu_limit: args.cpu_limit(),
+            cpu_affinity,
+            io_priority,
+            mem_limit,
+            ignored,
+        })
+    }
+
+    /// Overall limit, or None when only the idle timeout applies
+    pub fn overall_limit(&self) -> Option<Duration> {
+        (self.duration != Duration::MAX).then_some(self.duration)
+    }
+
+    /// Rules the child's output is checked against while it runs
+    pub fn output_rules(&self) -> OutputRules {
+        OutputRules {
+            idle_timeout: self.idle_timeout,
+            max_output: self.max_output,
+            max_stdout: self.max_output_stdout,
+            max_stderr: self.max_output_stderr,
+            kill_pattern: self.kill_pattern.clone(),
+            success_pattern: self.success_pattern.clone(),
+            keep_running: self.keep_running,
+            timestamps: self.timestamps,
+            overall_limit: self.overall_limit(),
+            log_file: self.log_file.clone(),
+        }
+    }
+
+    /// --suppress-exit-code statuses in order, for --dry-run
+    fn sorted_suppress_exit_codes(&self) -> Vec<i32> {
+        let mut codes: Vec<i32> = self.suppress_exit_codes.iter().copied().collect();
+        codes.sort_unstable();
+        codes
+    }
+
+    /// Name of the signal sent when the duration expires
+    pub fn signal_name(&self) -> &'static str {
+        #[cfg(unix)]
+        return self.term_signal.as_str();
+        #[cfg(not(unix))]
+        return "TERMINATE";
+    }
+
+    /// How far SIGUSR1 pushes the deadline back, or None when it is not
+    /// handled (--forward-signals, or no SIGUSR1 on this platform)
+    pub fn extend_step(&self) -> Option<Duration> {
+        #[cfg(unix)]
+        return self.extend_step;
+        #[cfg(not(unix))]
+        return None;
+    }
+
+    /// Name of the signal sent once kill-after expires, if any
+    pub fn kill_signal_name(&self) -> Option<&'static str> {
+        self.kill_after.map(|_| {
+            if Platform::IS_WINDOWS {
+                "TERMINATE"
+            } else {
+                "SIGKILL"
+            }
+        })
+    }
+
+    /// What --simulate-timeout makes timeout do once COMMAND starts, in
+    /// order, for --dry-run
+    fn simulated_steps(&self, args: &Args) -> Vec<String> {
+        let mut steps = Vec::new();
+        if self.on_timeout.is_some() {
+            steps.push("run the --on-timeout hook".to_string());
+        }
+        #[cfg(unix)]
+        let stop_instead = args.stop_instead;
+        #[cfg(not(unix))]
+        let stop_instead = false;
+        if self.keep_running {
+            steps.push("leave COMMAND running".to_string());
+        } else if stop_instead {
+            steps.push("send SIGSTOP and leave COMMAND stopped".to_string());
+        } else {
+            if self.pre_kill.is_some() {
+                steps.push("run the --pre-kill hook".to_string());
+            }
+            if !args.no_notify() {
+                steps.push(format!("send {}", self.signal_name()));
+            }
+            if let (Some(kill_after), Some(kill_signal)) =
+                (self.kill_after, self.kill_signal_name())
+            {
+                steps.push(format!(
+                    "send {} after {} ms if COMMAND is still running",
+                    kill_signal,
+                    kill_after.as_millis()
+                ));
+            }
+        }
+        if args.preserve_status && !self.keep_running && !stop_instead {
+            steps.push("exit with COMMAND's status".to_string());
+        } else {
+            steps.push(format!(
+                "exit {}",
+                args.status_on_timeout.unwrap_or(EXIT_TIMEDOUT)
+            ));
+        }
+        steps
+    }
+
+    /// The plan as `--dry-run` shows it, for COMMAND found at
+    /// `command_path` and run as `argv`
+    fn dry_run<'a>(&'a self, args: &Args, command_path: &Path, argv: &'a [&'a str]) -> DryRun<'a> {
+        let millis = |d: Option<Duration>| d.map(|d| d.as_millis());
+        fn hook_template(hook: &Option<Hook>) -> Option<&str> {
+            hook.as_ref().map(|hook| hook.template.as_str())
+        }
+        DryRun {
+            command: command_path.display().to_string(),
+            argv,
+            duration_ms: millis(self.overall_limit()),
+            deadline: self.deadline.map(rfc3339),
+            boottime: args.boottime(),
+            kill_after_ms: millis(self.kill_after),
+            idle_timeout_ms: millis(self.idle_timeout),
+            max_output_bytes: self.max_output,
+            max_output_stdout_bytes: self.max_output_stdout,
+            max_output_stderr_bytes: self.max_output_stderr,
+            kill_pattern: self.kill_pattern.as_ref().map(Regex::as_str),
+            success_pattern: self.success_pattern.as_ref().map(Regex::as_str),
+            keep_running: self.keep_running,
+            simulate_timeout: self.simulate_timeout,
+            simulated_steps: if self.simulate_timeout {
+                self.simulated_steps(args)
+            } else {
+                Vec::new()
+            },
+            on_timeout: hook_template(&self.on_timeout),
+            on_timeout_timeout_ms: millis(self.on_timeout.as_ref().map(|hook| hook.timeout)),
+            pre_kill: hook_template(&self.pre_kill),
+            on_success: hook_template(&self.on_success),
+            on_failure: hook_template(&self.on_failure),
+            hook_timeout_ms: millis(
+                self.on_success
+                    .as_ref()
+                    .or(self.on_failure.as_ref())
+                    .map(|hook| hook.timeout),
+            ),
+            hook_failures_fatal: self.hook_failures_fatal,
+            webhook: self.webhook.as_ref().map(|webhook| webhook.url.as_str()),
+            webhook_on: self
+                .webhook
+                .as_ref()
+                .map(Webhook::describe_events)
+                .unwrap_or_default(),
+            webhook_headers: self
+                .webhook
+                .iter()
+                .flat_map(|webhook| webhook.headers.iter().map(|(name, _)| name.as_str()))
+                .collect(),
+            log: self.log.iter().map(LogTarget::describe).collect(),
+            syslog: self
+                .log
+                .iter()
+                .any(|target| matches!(target, LogTarget::Syslog(_))),
+            syslog_format: self.syslog_format.as_str(),
+            statsd: self.statsd.as_ref().map(|statsd| DryRunStatsd {
+                address: &statsd.address,
+                prefix: &statsd.prefix,
+                tags: statsd.tags,
+            }),
+            otel: self.otel.as_ref().map(|otel| DryRunOtel {
+                endpoint: &otel.endpoint,
+                traceparent: otel.parent.map(|parent| parent.to_string()),
+            }),
+            prom_textfile: self.prom_textfile.as_deref(),
+            metrics: self.metrics_target.as_ref().map(MetricsTarget::describe),
+            metrics_format: self.metrics_format.as_str(),
+            summary_format: self.summary_format.as_ref().map(Template::as_str),
+            summary_output: self.summary_output.as_deref(),
+            time_format: self.time_format.as_deref(),
+            time_output: self.time_output.as_deref(),
+            status_fd: self.status_fd,
+            sd_notify: self.sd_notify.map(|mode| mode.as_str()),
+            timestamps: self.timestamps.map(|t| t.as_str()),
+            heartbeat_ms: millis(self.heartbeat.as_ref().map(|h| h.interval)),
+            heartbeat_text: self.heartbeat.as_ref().and_then(|h| h.text.as_deref()),
+            heartbeat_stderr: self.heartbeat.as_ref().is_some_and(|h| h.stderr),
+            retries: self.retry.retries,
+            retry_on_exit: self.retry.on_exit.as_ref().map(ExitCodes::describe),
+            retry_delay_ms: self.retry.delay.as_millis(),
+            retry_backoff: self.retry.backoff.as_str(),
+            retry_max_delay_ms: self.retry.max_delay.as_millis(),
+            total_timeout_ms: millis(self.retry.total),
+            clamp_last_attempt: self.retry.clamp_last_attempt,
+            suppress_exit_codes: self.sorted_suppress_exit_codes(),
+            every_ms: millis(self.every),
+            count: args.count,
+            signal: self.signal_name(),
+            kill_signal: self.kill_signal_name(),
+            extend_step_ms: millis(self.extend_step()),
+            cpu_limit_secs: self.cpu_limit,
+            memory_limit_bytes: self.mem_limit,
+            cpu_affinity: self.cpu_affinity.as_deref().map(affinity::format_cpu_list),
+            io_priority: self.io_priority.map(|priority| priority.to_string()),
+            namespaced: args.namespaced(),
+            chroot: args.chroot().map(|dir| dir.display().to_string()),
+            new_session: args.set_session(),
+            backend: crate::platform::backend_description(
+                args.foreground() || args.no_process_group(),
+                args.detect_stopped(),
+                args.pty(),
+                args.no_window(),
+            ),
+            ignored: &self.ignored,
+        }
+    }
+
+    /// Print the plan for `--dry-run`, as either `text` or `json`
+    pub fn print(
+        &self,
+        args: &Args,
+        format: &str,
+        command_path: &Path,
+        command: &str,
+        cmd_args: &[String],
+    ) {
+        let argv: Vec<&str> = std::iter::once(command)
+            .chain(cmd_args.iter().map(String::as_str))
+            .collect();
+        let plan = self.dry_run(args, command_path, &argv);
+        if format == "json" {
+            println!(
+                "{}",
+                serde_json::to_string(&plan).expect("the dry-run plan serializes")
+            );
+        } else {
+            print!("{}", plan);
+        }
+    }
+}
+
+/// The text plan: a `name: value` line for each setting in effect, with
+/// sizes in the largest unit that holds them exactly. The command, DURATION,
+/// the signal and the backend are always shown.
+impl fmt::Display for DryRun<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut line = |label: &str, value: &dyn fmt::Display| {
+            writeln!(f, "{:<14} {}", format!("{}:", label), value)
+        };
+        let ms =
+            |ms: Option<u128>| ms.map_or_else(|| "none".to_string(), |ms| format!("{} ms", ms));
+
+        line("command", &self.command)?;
+        line("argv", &format!("{:?}", self.argv))?;
+        line("duration", &ms(self.duration_ms))?;
+        if let Some(deadline) = &self.deadline {
+            line("deadline", deadline)?;
+        }
+        if self.boottime {
+            line("clock", &"boottime")?;
+        }
+        if self.kill_after_ms.is_some() {
+            line("kill-after", &ms(self.kill_after_ms))?;
+        }
+        if self.idle_timeout_ms.is_some() {
+            line("idle timeout", &ms(self.idle_timeout_ms))?;
+        }
+        for (label, bytes) in [
+            ("max output", self.max_output_bytes),
+            ("max stdout", self.max_output_stdout_bytes),
+            ("max stderr", self.max_output_stderr_bytes),
+        ] {
+            if let Some(bytes) = bytes {
+                line(label, &format_size(bytes))?;
+            }
+        }
+        if let Some(pattern) = self.kill_pattern {
+            line("kill pattern", &pattern)?;
+        }
+        if let Some(pattern) = self.success_pattern {
+            line("success", &pattern)?;
+        }
+        if self.keep_running {
+            line("keep running", &"yes")?;
+        }
+        if self.simulate_timeout {
+            line("simulated", &self.simulated_steps.join(", then "))?;
+        }
+        let pre_kill_ms = Some(PRE_KILL_TIMEOUT.as_millis());
+        for (label, hook, limit) in [
+            ("on timeout", self.on_timeout, self.on_timeout_timeout_ms),
+            ("pre kill", self.pre_kill, pre_kill_ms),
+            ("on success", self.on_success, self.hook_timeout_ms),
+            ("on failure", self.on_failure, self.hook_timeout_ms),
+        ] {
+            if let Some(hook) = hook {
+                line(label, &format!("{} (at most {})", hook, ms(limit)))?;
+            }
+        }
+        if self.hook_failures_fatal {
+            line("hook failures", &"fatal")?;
+        }
+        if let Some(url) = self.webhook {
+            let mut webhook = format!("{} on {}", url, self.webhook_on.join(", "));
+            if !self.webhook_headers.is_empty() {
+                webhook += &format!(", with headers {}", self.webhook_headers.join(", "));
+            }
+            line("webhook", &webhook)?;
+        }
+        if !self.log.is_empty() {
+            line("log", &self.log.join(", "))?;
+        }
+        if self.syslog {
+            line("syslog format", &self.syslog_format)?;
+        }
+        if let Some(statsd) = &self.statsd {
+            line(
+                "statsd",
+                &format!(
+                    "{}, prefix {}{}",
+                    statsd.address,
+                    statsd.prefix,
+                    if statsd.tags { ", with tags" } else { "" }
+                ),
+            )?;
+        }
+        if let Some(otel) = &self.otel {
+            match &otel.traceparent {
+                Some(parent) => line("otel", &format!("{}, child of {}", otel.endpoint, parent))?,
+                None => line("otel", &otel.endpoint)?,
+            }
+        }
+        if let Some(path) = self.prom_textfile {
+            line("prom textfile", &path)?;
+        }
+        if let Some(metrics) = &self.metrics {
+            line("metrics", metrics)?;
+            line("metrics format", &self.metrics_format)?;
+        }
+        if let Some(template) = self.summary_format {
+            match self.summary_output {
+                Some(path) => line("summary", &format!("file {}, {}", path, template))?,
+                None => line("summary", &format!("stderr, {}", template))?,
+            }
+        }
+        if self.time_format.is_some() {
+            match self.time_output {
+                Some(path) => line("time", &format!("file {}", path))?,
+                None => line("time", &"stderr")?,
+            }
+        }
+        if let Some(fd) = self.status_fd {
+            line("status fd", &fd)?;
+        }
+        if let Some(mode) = self.sd_notify {
+            line("sd-notify", &mode)?;
+        }
+        if let Some(mode) = self.timestamps {
+            line("timestamps", &mode)?;
+        }
+        if let Some(interval) = self.heartbeat_ms {
+            line(
+                "heartbeat",
+                &format!(
+                    "every {} ms to {}{}",
+                    interval,
+                    if self.heartbeat_stderr {
+                        "stderr"
+                    } else {
+                        "stdout"
+                    },
+                    self.heartbeat_text
+                        .map(|text| format!(": {}", text))
+                        .unwrap_or_default()
+                ),
+            )?;
+        }
+        if let Some(every) = self.every_ms {
+            line(
+                "every",
+                &format!(
+                    "{} ms, {}",
+                    every,
+                    self.count
+                        .map(|n| format!("{} runs", n))
+                        .unwrap_or_else(|| "until interrupted".to_string())
+                ),
+            )?;
+        }
+        if self.retries > 0 {
+            line(
+                "retries",
+                &format!(
+                    "{} ({} ms {} delay, at most {} ms)",
+                    self.retries, self.retry_delay_ms, self.retry_backoff, self.retry_max_delay_ms
+                ),
+            )?;
+        }
+        if let Some(codes) = &self.retry_on_exit {
+            line("retry on exit", codes)?;
+        }
+        if self.total_timeout_ms.is_some() {
+            line(
+                "total timeout",
+                &format!(
+                    "{}{}",
+                    ms(self.total_timeout_ms),
+                    if self.clamp_last_attempt {
+                        " (last attempt clamped)"
+                    } else {
+                        ""
+                    }
+                ),
+            )?;
+        }
+        if !self.suppress_exit_codes.is_empty() {
+            let codes: Vec<String> = self
+                .suppress_exit_codes
+                .iter()
+                .map(i32::to_string)
+                .collect();
+            line("suppress exit", &codes.join(", "))?;
+        }
+        line("signal", &self.signal)?;
+        if let Some(signal) = self.kill_signal {
+            line("kill signal", &signal)?;
+        }
+        if self.extend_step_ms.is_some() {
+            line("extend step", &ms(self.extend_step_ms))?;
+        }
+        if let Some(secs) = self.cpu_limit_secs {
+            line("cpu limit", &format!("{} s", secs))?;
+        }
+        if let Some(bytes) = self.memory_limit_bytes {
+            line("memory limit", &format_size(bytes))?;
+        }
+        if let Some(cpus) = &self.cpu_affinity {
+            line("cpu affinity", cpus)?;
+        }
+        if let Some(priority) = &self.io_priority {
+            line("io priority", priority)?;
+        }
+        if self.namespaced {
+            line("namespaces", &"mount, network, uts, ipc")?;
+        }
+        if let Some(dir) = &self.chroot {
+            line("chroot", dir)?;
+        }
+        if self.new_session {
+            line("session", &"new, without a controlling terminal")?;
+        }
+        line("backend", &self.backend)?;
+        if !self.ignored.is_empty() {
+            line("ignored", &self.ignored.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Redirecting output to files and piping it through timeout are exclusive;
+/// reject the combination rather than silently dropping either
+fn check_redirect_conflicts(args: &Args) -> Result<(), TimeoutError> {
+    let redirect = if args.stdout.is_some() {
+        "--stdout"
+    } else if args.stderr.is_some() {
+        "--stderr"
+    } else if args.merge_output {
+        "--merge-output"
+    } else if args.quiet_child {
+        "--quiet-child"
+    } else {
+        return Ok(());
+    };
+
+    let piped = [
+        ("--idle-timeout", args.idle_timeout.is_some()),
+        ("--max-output", args.max_output.is_some()),
+        ("--max-output-stdout", args.max_output_stdout.is_some()),
+        ("--max-output-stderr", args.max_output_stderr.is_some()),
+        ("--kill-on-pattern", args.kill_on_pattern.is_some()),
+        ("--success-on-pattern", args.success_on_pattern.is_some()),
+        ("--timestamps", args.timestamps.is_some()),
+        ("--log-file", args.log_file.is_some()),
+    ];
+    match piped.iter().find(|(_, set)| *set) {
+        Some((feature, _)) => Err(TimeoutError::RedirectConflict { redirect, feature }),
+        None => Ok(()),
+    }
+}
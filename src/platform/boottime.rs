@@ -0,0 +1,76 @@
+// src/platform/boottime.rs
+// --boottime: DURATION counted on CLOCK_BOOTTIME, which unlike the
+// monotonic clock behind Instant keeps going while the machine is suspended
+// (Linux)
+
+use nix::libc;
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::time::Duration;
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+/// Time since boot, time spent suspended included
+pub fn now() -> io::Result<Duration> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32))
+}
+
+/// Sleep for `duration` of CLOCK_BOOTTIME, on a timerfd the runtime
+/// watches. A suspend in the middle counts towards it, so the sleep ends
+/// on resume if it should have ended while the machine was asleep.
+pub async fn sleep_boottime(duration: Duration) -> io::Result<()> {
+    // An all-zero it_value would disarm the timer instead
+    if duration.is_zero() {
+        return Ok(());
+    }
+    let fd = unsafe {
+        libc::timerfd_create(libc::CLOCK_BOOTTIME, libc::TFD_NONBLOCK | libc::TFD_CLOEXEC)
+    };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let spec = libc::itimerspec {
+        it_interval: libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        it_value: libc::timespec {
+            tv_sec: duration.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+            tv_nsec: duration.subsec_nanos() as _,
+        },
+    };
+    if unsafe { libc::timerfd_settime(fd.as_raw_fd(), 0, &spec, std::ptr::null_mut()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fd = AsyncFd::with_interest(fd, Interest::READABLE)?;
+    loop {
+        let mut guard = fd.readable().await?;
+        let mut expirations = [0u8; 8];
+        let read = guard.try_io(|fd| {
+            let n = unsafe {
+                libc::read(
+                    fd.get_ref().as_raw_fd(),
+                    expirations.as_mut_ptr().cast(),
+                    expirations.len(),
+                )
+            };
+            if n == -1 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        });
+        if let Ok(result) = read {
+            return result;
+        }
+    }
+}
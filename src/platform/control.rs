@@ -0,0 +1,637 @@
+// src/platform/control.rs
+// The running command's deadline, which SIGUSR1, SIGUSR2 and the
+// --control-socket can adjust
+
+use crate::debug::debug;
+use crate::logging::{info, warning};
+use crate::output::OutputWatcher;
+use crate::{parse_duration, TimeoutError, TimeoutReason, TimeoutSignal};
+use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::signal::unix::{signal, Signal as SignalStream, SignalKind};
+use tokio::sync::{mpsc, oneshot, watch, Notify};
+use tokio::task::JoinHandle;
+
+/// A second SIGUSR2 this soon after the first expires the deadline at once
+const EXPIRE_WINDOW: Duration = Duration::from_secs(1);
+
+/// A request line read from the control socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    /// `status`: reply with the command, PID and time left as JSON
+    Status,
+    /// `extend DURATION`: push the deadline back
+    Extend(Duration),
+    /// `expire`: time out now, going through --signal and --kill-after
+    Expire,
+    /// `kill`: send SIGKILL now
+    Kill,
+}
+
+impl Request {
+    fn parse(line: &str) -> Result<Request, String> {
+        let mut words = line.split_whitespace();
+        let request = match (words.next(), words.next()) {
+            (Some("status"), None) => Request::Status,
+            (Some("extend"), Some(amount)) => {
+                Request::Extend(parse_duration(amount).map_err(|e| e.to_string())?)
+            }
+            (Some("extend"), None) => return Err("extend needs a DURATION".to_string()),
+            (Some("expire"), None) => Request::Expire,
+            (Some("kill"), None) => Request::Kill,
+            _ => {
+                return Err(format!(
+                    "unknown request '{}' (expected status, extend DURATION, expire or kill)",
+                    line.trim()
+                ))
+            }
+        };
+        match words.next() {
+            Some(extra) => Err(format!("unexpected '{}' after the request", extra)),
+            None => Ok(request),
+        }
+    }
+}
+
+/// A parsed request waiting for the deadline loop to act on it and reply
+struct Pending {
+    request: Request,
+    reply: oneshot::Sender<String>,
+}
+
+/// The Unix domain socket given with --control-socket.
+///
+/// Each connection may send any number of request lines and gets one reply
+/// line per request. The socket file is removed again when this is dropped,
+/// so on every way out of the run short of timeout itself being killed.
+pub struct ControlSocket {
+    path: PathBuf,
+    listener: JoinHandle<()>,
+    requests: mpsc::Receiver<Pending>,
+}
+
+impl ControlSocket {
+    /// Listen on `path`, which must not exist yet unless `force` allows
+    /// replacing it. Only the owner may connect.
+    pub fn bind(path: &Path, force: bool) -> Result<ControlSocket, TimeoutError> {
+        let failed = |source| TimeoutError::ControlSocketFailed {
+            path: path.display().to_string(),
+            source,
+        };
+        if path.symlink_metadata().is_ok() {
+            if !force {
+                return Err(TimeoutError::ControlSocketExists(
+                    path.display().to_string(),
+                ));
+            }
+            std::fs::remove_file(path).map_err(failed)?;
+        }
+
+        let listener = bind_private(path).map_err(failed)?;
+
+        let (sender, requests) = mpsc::channel(16);
+        let listener = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, sender.clone()));
+            }
+        });
+
+        Ok(ControlSocket {
+            path: path.to_path_buf(),
+            listener,
+            requests,
+        })
+    }
+}
+
+/// Bind a socket at `path` that only the owner can connect to. It is bound
+/// inside a new 0700 directory beside `path`, made 0600 while nobody else
+/// can reach it, and only then renamed into place, so there is no moment
+/// when it is open to everyone and the process umask is left alone.
+fn bind_private(path: &Path) -> std::io::Result<UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let dir = parent.join(format!(".timeout-{}", std::process::id()));
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let private = dir.join("socket");
+    let bound = UnixListener::bind(&private).and_then(|listener| {
+        std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&private, path)?;
+        Ok(listener)
+    });
+    if bound.is_err() {
+        let _ = std::fs::remove_file(&private);
+    }
+    let _ = std::fs::remove_dir(&dir);
+    bound
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.listener.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Answer one connection's requests until it closes
+async fn serve(stream: UnixStream, requests: mpsc::Sender<Pending>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match Request::parse(&line) {
+            Ok(request) => {
+                let (reply, answer) = oneshot::channel();
+                let _ = requests.send(Pending { request, reply }).await;
+                answer
+                    .await
+                    .unwrap_or_else(|_| "error: command has finished".to_string())
+            }
+            Err(e) => format!("error: {}", e),
+        };
+        if writer
+            .write_all(format!("{}\n", reply).as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Send one request to a running timeout's --control-socket and return its
+/// reply, for `timeout --control PATH REQUEST`
+pub async fn send_request(path: &Path, request: &[String]) -> Result<String, TimeoutError> {
+    let failed = |source| TimeoutError::ControlSocketFailed {
+        path: path.display().to_string(),
+        source,
+    };
+    let mut stream = UnixStream::connect(path).await.map_err(failed)?;
+    stream
+        .write_all(format!("{}\n", request.join(" ")).as_bytes())
+        .await
+        .map_err(failed)?;
+    stream.shutdown().await.map_err(failed)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).await.map_err(failed)?;
+    Ok(reply.trim_end().to_string())
+}
+
+/// Extends, queries or cancels a `TimeoutRunner`'s run from elsewhere in
+/// the application, as the control socket's `extend`, `status` and `kill`
+/// requests do for the binary. Clones act on the same run.
+#[derive(Debug, Clone)]
+pub struct TimeoutHandle {
+    requests: mpsc::Sender<Pending>,
+    /// When the deadline passes; None without an overall limit
+    expiry: watch::Receiver<Option<Instant>>,
+    cancel: Arc<Notify>,
+}
+
+/// The deadline's side of a `TimeoutHandle`
+pub struct HandleRequests {
+    requests: mpsc::Receiver<Pending>,
+    expiry: watch::Sender<Option<Instant>>,
+}
+
+impl TimeoutHandle {
+    /// A handle on a run limited to `limit`, and the side of it its
+    /// deadline keeps
+    pub(crate) fn new(limit: Duration) -> (TimeoutHandle, HandleRequests) {
+        let (sender, requests) = mpsc::channel(16);
+        let (expiry, watched) = watch::channel(expiry_of(Instant::now(), limit));
+        let handle = TimeoutHandle {
+            requests: sender,
+            expiry: watched,
+            cancel: Arc::new(Notify::new()),
+        };
+        (handle, HandleRequests { requests, expiry })
+    }
+
+    /// Push the deadline back by `amount`, replacing the timer that was
+    /// running. False without an overall limit, or once it has passed: a
+    /// call made while COMMAND is being stopped returns when it has been.
+    pub async fn extend(&self, amount: Duration) -> bool {
+        let (reply, answer) = oneshot::channel();
+        let pending = Pending {
+            request: Request::Extend(amount),
+            reply,
+        };
+        if self.requests.send(pending).await.is_err() {
+            return false;
+        }
+        answer.await.is_ok_and(|reply| reply.starts_with("ok"))
+    }
+
+    /// Time left before the deadline, or None without an overall limit.
+    /// Counted from when the handle was made until COMMAND starts, and
+    /// zero once the deadline has passed.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expiry
+            .borrow()
+            .map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Send COMMAND the term signal, and SIGKILL after the runner's
+    /// `kill_after` if set, then wait for it to exit. The run itself must
+    /// be awaited for that to happen.
+    pub async fn cancel(&self) {
+        self.cancel.notify_one();
+        // The deadline drops its side once COMMAND has been waited for
+        let mut expiry = self.expiry.clone();
+        while expiry.changed().await.is_ok() {}
+    }
+
+    /// Resolves once `cancel` has been called, for the runner
+    pub(crate) fn cancelled(&self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let cancel = self.cancel.clone();
+        Box::pin(async move { cancel.notified().await })
+    }
+}
+
+/// When a deadline of `limit` from `started` passes, or None without an
+/// overall limit
+fn expiry_of(started: Instant, limit: Duration) -> Option<Instant> {
+    (limit != Duration::MAX)
+        .then(|| started.checked_add(limit))
+        .flatten()
+}
+
+/// The reply to `status`; the times are null without an overall limit
+#[derive(Debug, Serialize)]
+struct StatusReply<'a> {
+    command: &'a str,
+    pid: i32,
+    elapsed_ms: u128,
+    remaining_ms: Option<u128>,
+    limit_ms: Option<u128>,
+    extensions: u32,
+}
+
+/// When DURATION runs out for the current command.
+///
+/// While the command runs, SIGUSR1 pushes the deadline back by the
+/// `--extend-step` and SIGUSR2 prints the time left; a second SIGUSR2
+/// within a second cuts the run short. With `--forward-signals` both are
+/// passed on to the command instead and the deadline stays fixed. The
+/// `--control-socket`, if any, is served from here too.
+pub struct Deadline {
+    command: String,
+    started: Instant,
+    /// CLOCK_BOOTTIME when the countdown started, with --boottime
+    #[cfg(target_os = "linux")]
+    boottime: Option<Duration>,
+    /// DURATION plus any extensions; `Duration::MAX` when there is no
+    /// overall limit
+    limit: Duration,
+    /// None with --forward-signals
+    step: Option<Duration>,
+    extensions: u32,
+    last_report: Option<Instant>,
+    /// Set by a `kill` request: skip --signal and --kill-after
+    kill: bool,
+    verbose: bool,
+    /// None for a `TimeoutRunner`, as the host application owns them
+    usr1: Option<SignalStream>,
+    usr2: Option<SignalStream>,
+    control: Option<ControlSocket>,
+    /// A `TimeoutRunner`'s handle, which stands in for the control socket
+    handle: Option<HandleRequests>,
+}
+
+impl Deadline {
+    /// Start counting down `limit`, on CLOCK_BOOTTIME with `boottime`.
+    /// Call this before forking, so a control signal sent as soon as the
+    /// command starts cannot kill timeout.
+    pub fn start(
+        command: &str,
+        limit: Duration,
+        step: Option<Duration>,
+        boottime: bool,
+        verbose: bool,
+        control: Option<ControlSocket>,
+    ) -> std::io::Result<Deadline> {
+        #[cfg(not(target_os = "linux"))]
+        let _ = boottime;
+        Ok(Deadline {
+            command: command.to_string(),
+            started: Instant::now(),
+            #[cfg(target_os = "linux")]
+            boottime: boottime.then(super::boottime::now).transpose()?,
+            limit,
+            step,
+            extensions: 0,
+            last_report: None,
+            kill: false,
+            verbose,
+            usr1: Some(signal(SignalKind::user_defined1())?),
+            usr2: Some(signal(SignalKind::user_defined2())?),
+            control,
+            handle: None,
+        })
+    }
+
+    /// Start counting down `limit` for a `TimeoutRunner`, without taking
+    /// over SIGUSR1 and SIGUSR2 from the application running it
+    pub fn embedded(command: &str, limit: Duration, handle: Option<HandleRequests>) -> Deadline {
+        let started = Instant::now();
+        if let Some(handle) = &handle {
+            handle.expiry.send_replace(expiry_of(started, limit));
+        }
+        Deadline {
+            command: command.to_string(),
+            started,
+            #[cfg(target_os = "linux")]
+            boottime: None,
+            limit,
+            step: None,
+            extensions: 0,
+            last_report: None,
+            kill: false,
+            verbose: false,
+            usr1: None,
+            usr2: None,
+            control: None,
+            handle,
+        }
+    }
+
+    /// How many times SIGUSR1 or the control socket extended the deadline
+    pub fn extensions(&self) -> u32 {
+        self.extensions
+    }
+
+    /// Whether a `kill` request asked for SIGKILL straight away
+    pub fn kill_requested(&self) -> bool {
+        self.kill
+    }
+
+    /// Time since the countdown started, suspend included with --boottime
+    fn elapsed(&self) -> Duration {
+        #[cfg(target_os = "linux")]
+        if let Some(started) = self.boottime {
+            if let Ok(now) = super::boottime::now() {
+                return now.saturating_sub(started);
+            }
+        }
+        self.started.elapsed()
+    }
+
+    /// Time left before the deadline, or None without an overall limit
+    fn remaining(&self) -> Option<Duration> {
+        (self.limit != Duration::MAX).then(|| self.limit.saturating_sub(self.elapsed()))
+    }
+
+    /// Resolves once the deadline passes or an output rule fires, handling
+    /// control signals and requests in the meantime. SIGUSR1 and SIGUSR2
+    /// go on to `child`, or to `group` if given, when they are not ours to
+    /// consume.
+    pub async fn wait(
+        &mut self,
+        output: Option<&OutputWatcher>,
+        child: Pid,
+        group: Option<Pid>,
+    ) -> TimeoutReason {
+        let forward = |sig: Signal| {
+            let sig = TimeoutSignal::from(sig);
+            let _ = match group {
+                None => sig.send_to_process(child),
+                Some(group) => sig.send_to_group(group),
+            };
+        };
+
+        loop {
+            // Re-armed on every pass, so an extension takes effect at once
+            let at = self.started.checked_add(self.limit);
+            #[cfg(target_os = "linux")]
+            let boottime_left = self.boottime.and(self.remaining());
+            let expiry = async {
+                #[cfg(target_os = "linux")]
+                if let Some(left) = boottime_left {
+                    match super::boottime::sleep_boottime(left).await {
+                        Ok(()) => return,
+                        Err(e) => debug!(
+                            "CLOCK_BOOTTIME timer failed, using the monotonic clock: {}",
+                            e
+                        ),
+                    }
+                }
+                match at {
+                    Some(at) => tokio::time::sleep_until(at.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let triggered = async {
+                match output {
+                    Some(output) => output.triggered().await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            let expire = tokio::select! {
+                _ = expiry => {
+                    debug!("select: expiry arm fired, limit {:?}", self.limit);
+                    return TimeoutReason::Duration;
+                }
+                reason = triggered => {
+                    debug!("select: output arm fired ({})", reason);
+                    return reason;
+                }
+                Some(()) = next_delivery(&mut self.usr1) => {
+                    debug!("select: SIGUSR1 arm fired");
+                    match self.step {
+                        Some(step) => {
+                            self.extend(step, "SIGUSR1");
+                            false
+                        }
+                        None => {
+                            forward(Signal::SIGUSR1);
+                            false
+                        }
+                    }
+                }
+                Some(()) = next_delivery(&mut self.usr2) => {
+                    debug!("select: SIGUSR2 arm fired");
+                    match self.step {
+                        Some(_) => self.report(),
+                        None => {
+                            forward(Signal::SIGUSR2);
+                            false
+                        }
+                    }
+                }
+                Some(pending) = next_request(&mut self.control) => {
+                    debug!("select: control socket arm fired with {:?}", pending.request);
+                    let (expire, reply) = self.answer(pending.request, child, "control socket");
+                    let _ = pending.reply.send(reply);
+                    expire
+                }
+                pending = next_handle_request(&mut self.handle) => {
+                    debug!("select: handle arm fired with {:?}", pending.request);
+                    let (expire, reply) = self.answer(pending.request, child, "handle");
+                    let _ = pending.reply.send(reply);
+                    expire
+                }
+            };
+            if expire {
+                return TimeoutReason::Duration;
+            }
+        }
+    }
+
+    /// Act on a control socket request; returns whether the deadline should
+    /// expire now, and the reply line
+    fn answer(&mut self, request: Request, child: Pid, source: &str) -> (bool, String) {
+        match request {
+            Request::Status => (false, self.status(child)),
+            Request::Extend(amount) => {
+                if self.extend(amount, source) {
+                    let left = self.remaining().unwrap_or_default();
+                    (false, format!("ok: {:.1}s left", left.as_secs_f64()))
+                } else {
+                    (false, "error: no overall limit to extend".to_string())
+                }
+            }
+            Request::Expire | Request::Kill => {
+                self.kill = request == Request::Kill;
+                if self.verbose {
+                    warning!(
+                        "{}: {} requested for command '{}' via {}",
+                        "Expire".red(),
+                        if self.kill { "kill" } else { "expiry" },
+                        self.command,
+                        source
+                    );
+                }
+                (true, "ok".to_string())
+            }
+        }
+    }
+
+    /// Reply to `status`, e.g. `{"command":"sleep","pid":4242,...}`
+    fn status(&self, child: Pid) -> String {
+        let status = StatusReply {
+            command: &self.command,
+            pid: child.as_raw(),
+            elapsed_ms: self.elapsed().as_millis(),
+            remaining_ms: self.remaining().map(|d| d.as_millis()),
+            limit_ms: (self.limit != Duration::MAX).then_some(self.limit.as_millis()),
+            extensions: self.extensions,
+        };
+        serde_json::to_string(&status).expect("the status reply serializes")
+    }
+
+    /// Push the deadline back by `amount`; false without an overall limit
+    fn extend(&mut self, amount: Duration, source: &str) -> bool {
+        if self.limit == Duration::MAX {
+            if self.verbose {
+                info!(
+                    "{}: {} ignored, command '{}' has no overall limit to extend",
+                    "Extend".cyan(),
+                    source,
+                    self.command
+                );
+            }
+            return false;
+        }
+
+        self.limit = self.limit.saturating_add(amount);
+        self.extensions += 1;
+        if let Some(handle) = &self.handle {
+            handle
+                .expiry
+                .send_replace(expiry_of(self.started, self.limit));
+        }
+        crate::progress::extend(amount);
+        if self.verbose {
+            info!(
+                "{}: deadline for command '{}' extended by {:?} via {}, {:.1}s left",
+                "Extend".cyan(),
+                self.command,
+                amount,
+                source,
+                self.remaining().unwrap_or_default().as_secs_f64()
+            );
+        }
+        true
+    }
+
+    /// Print the time left; returns true on a second SIGUSR2 within
+    /// `EXPIRE_WINDOW`, meaning the deadline should expire now
+    fn report(&mut self) -> bool {
+        let now = Instant::now();
+        if self
+            .last_report
+            .is_some_and(|last| now.duration_since(last) < EXPIRE_WINDOW)
+        {
+            if self.verbose {
+                warning!(
+                    "{}: second SIGUSR2 within {:?}, expiring the deadline for command '{}' now",
+                    "Expire".red(),
+                    EXPIRE_WINDOW,
+                    self.command
+                );
+            }
+            return true;
+        }
+        self.last_report = Some(now);
+
+        match self.remaining() {
+            Some(left) => info!(
+                "{}: {:.1}s left before command '{}' times out",
+                "Remaining".cyan(),
+                left.as_secs_f64(),
+                self.command
+            ),
+            None => info!(
+                "{}: command '{}' has no overall limit",
+                "Remaining".cyan(),
+                self.command
+            ),
+        }
+        false
+    }
+}
+
+/// The next control socket request, or never without a socket
+async fn next_request(control: &mut Option<ControlSocket>) -> Option<Pending> {
+    match control {
+        Some(control) => control.requests.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// The next request from a `TimeoutRunner`'s handle. Never without one, or
+/// once every copy of it is gone.
+async fn next_handle_request(handle: &mut Option<HandleRequests>) -> Pending {
+    if let Some(handle) = handle {
+        if let Some(pending) = handle.requests.recv().await {
+            return pending;
+        }
+    }
+    std::future::pending().await
+}
+
+/// The next delivery of a signal, or never without a stream for it
+pub async fn next_delivery(stream: &mut Option<SignalStream>) -> Option<()> {
+    match stream {
+        Some(stream) => stream.recv().await,
+        None => std::future::pending().await,
+    }
+}
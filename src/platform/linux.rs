@@ -0,0 +1,102 @@
+// src/platform/linux.rs
+// --namespaced: COMMAND in private mount, network, UTS and IPC namespaces,
+// so it has no network, cannot change the host's mounts or hostname and
+// shares no System V IPC with it (Linux)
+
+use super::unix::cloexec_pipe;
+use crate::TimeoutError;
+use nix::errno::Errno;
+use nix::libc;
+use nix::sched::{unshare, CloneFlags};
+use nix::sys::wait::waitpid;
+use nix::unistd::{fork, ForkResult};
+use std::fs::File;
+use std::io::{Read, Write};
+
+/// The namespaces COMMAND gets a new one of
+pub const NAMESPACES: CloneFlags = CloneFlags::CLONE_NEWNS
+    .union(CloneFlags::CLONE_NEWNET)
+    .union(CloneFlags::CLONE_NEWUTS)
+    .union(CloneFlags::CLONE_NEWIPC);
+
+const EXIT_CANCELED: i32 = 125;
+
+/// `fork()`, with the child in new mount, network, UTS and IPC namespaces
+/// as `clone(2)` with those flags would start it. The child calls
+/// `unshare(2)` right after `fork(2)` instead: glibc's fork keeps malloc
+/// usable in the child of a multi-threaded process, which a raw clone does
+/// not. Without CAP_SYS_ADMIN the child first enters a new user namespace
+/// that maps only the caller's own user and group. The parent hears over a
+/// pipe whether that worked before this returns, and reaps a child that
+/// could not get its namespaces; `PermissionDenied` when neither way is
+/// allowed.
+///
+/// # Safety
+///
+/// As for `fork()`.
+pub unsafe fn namespace_fork() -> Result<ForkResult, TimeoutError> {
+    let (read, write) = cloexec_pipe()?;
+    match fork()? {
+        ForkResult::Child => {
+            drop(read);
+            if let Err(errno) = enter_namespaces() {
+                let _ = File::from(write).write_all(&(errno as i32).to_ne_bytes());
+                libc::_exit(EXIT_CANCELED);
+            }
+            Ok(ForkResult::Child)
+        }
+        ForkResult::Parent { child } => {
+            drop(write);
+            // End of file once the child has its namespaces
+            let mut report = Vec::new();
+            let _ = File::from(read).read_to_end(&mut report);
+            let Ok(errno) = <[u8; 4]>::try_from(report.as_slice()) else {
+                return Ok(ForkResult::Parent { child });
+            };
+            let _ = waitpid(child, None);
+            Err(match Errno::from_raw(i32::from_ne_bytes(errno)) {
+                errno @ (Errno::EPERM | Errno::EACCES) => TimeoutError::PermissionDenied(format!(
+                    "--namespaced needs CAP_SYS_ADMIN or unprivileged user namespaces ({})",
+                    errno.desc()
+                )),
+                errno => TimeoutError::ForkFailed(errno),
+            })
+        }
+    }
+}
+
+/// Move the calling process into new namespaces, through a user namespace
+/// of its own if it may not create them directly
+fn enter_namespaces() -> Result<(), Errno> {
+    let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+    if let Err(denied) = unshare(NAMESPACES) {
+        if denied != Errno::EPERM {
+            return Err(denied);
+        }
+        unshare(NAMESPACES | CloneFlags::CLONE_NEWUSER).map_err(|_| denied)?;
+        // Until mapped, COMMAND would run as the overflow user. timeout
+        // made itself undumpable, which leaves /proc/self owned by root;
+        // the child turns that back on before exec anyway.
+        unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 1) };
+        let write = |path: &str, text: String| {
+            std::fs::write(path, text)
+                .map_err(|e| Errno::from_raw(e.raw_os_error().unwrap_or(libc::EPERM)))
+        };
+        write("/proc/self/setgroups", "deny".to_string())?;
+        write("/proc/self/uid_map", format!("{} {} 1", uid, uid))?;
+        write("/proc/self/gid_map", format!("{} {} 1", gid, gid))?;
+    }
+    // The new mount namespace starts with copies of the host's mounts,
+    // which may still be shared with it; make them private so nothing
+    // mounted inside shows up outside
+    let result = unsafe {
+        libc::mount(
+            c"none".as_ptr(),
+            c"/".as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        )
+    };
+    Errno::result(result).map(drop)
+}
@@ -1,15 +1,251 @@
 // src/platform/mod.rs
 // Platform abstraction layer for timeout command
 
+#[cfg(target_os = "linux")]
+pub mod boottime;
+
+#[cfg(unix)]
+pub mod control;
+
+#[cfg(target_os = "linux")]
+pub mod linux;
+
+#[cfg(unix)]
+pub mod monitor;
+
+#[cfg(unix)]
+pub mod pty;
+
 #[cfg(unix)]
 pub mod unix;
 
+#[cfg(unix)]
+pub mod watch;
+
 #[cfg(windows)]
 pub mod windows;
 
+use crate::hook::Hook;
+use crate::output::OutputRules;
+use crate::sdnotify::Notifier;
+use crate::stdio::Redirects;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::ionice::IoPriority;
+#[cfg(unix)]
+use crate::runner::Embedded;
+#[cfg(unix)]
+use crate::TimeoutSignal;
+
+/// How `run_with_timeout` runs COMMAND, gathered from the plan and the
+/// command line. The fields are the options of the same names; those a
+/// backend has no use for are left out of its build.
+pub struct RunOptions<'a> {
+    /// DURATION, or zero for --simulate-timeout
+    pub duration: Duration,
+    pub kill_after: Option<Duration>,
+    pub preserve_status: bool,
+    pub verbose: bool,
+    pub status_on_timeout: Option<i32>,
+    pub output_rules: OutputRules,
+    pub redirects: Redirects,
+    pub pid_file: Option<&'a Path>,
+    pub pid_file_force: bool,
+    pub on_timeout: Option<&'a Hook>,
+    pub pre_kill: Option<&'a Hook>,
+    pub notifier: Option<&'a Arc<Notifier>>,
+    #[cfg(unix)]
+    pub term_signal: TimeoutSignal,
+    #[cfg(unix)]
+    pub foreground: bool,
+    #[cfg(unix)]
+    pub no_process_group: bool,
+    #[cfg(unix)]
+    pub set_session: bool,
+    #[cfg(unix)]
+    pub detect_stopped: bool,
+    #[cfg(unix)]
+    pub no_notify: bool,
+    #[cfg(unix)]
+    pub cpu_limit: Option<u64>,
+    #[cfg(unix)]
+    pub mem_limit: Option<u64>,
+    #[cfg(unix)]
+    pub cpu_affinity: Option<&'a [usize]>,
+    #[cfg(unix)]
+    pub io_priority: Option<IoPriority>,
+    #[cfg(unix)]
+    pub namespaced: bool,
+    #[cfg(unix)]
+    pub chroot: Option<&'a Path>,
+    #[cfg(unix)]
+    pub pty: bool,
+    #[cfg(unix)]
+    pub extend_step: Option<Duration>,
+    #[cfg(unix)]
+    pub boottime: bool,
+    #[cfg(unix)]
+    pub control_socket: Option<&'a Path>,
+    #[cfg(unix)]
+    pub control_socket_force: bool,
+    #[cfg(unix)]
+    pub stop_instead: bool,
+    /// Set when a `TimeoutRunner` runs COMMAND inside another application
+    #[cfg(unix)]
+    pub embedded: Option<Embedded>,
+    #[cfg(windows)]
+    pub no_window: bool,
+}
+
 // Re-export the platform-specific run function under a common name
 #[cfg(unix)]
-pub use unix::run_with_timeout;
+pub use unix::{backend_description, pidfd_available, run_with_timeout};
 
 #[cfg(windows)]
-pub use windows::run_with_timeout;
+pub use windows::{backend_description, run_with_timeout};
+
+/// Platform detection helper
+pub struct Platform;
+
+impl Platform {
+    pub const IS_LINUX: bool = cfg!(target_os = "linux");
+    pub const IS_MACOS: bool = cfg!(target_os = "macos");
+    pub const IS_FREEBSD: bool = cfg!(target_os = "freebsd");
+    pub const IS_OPENBSD: bool = cfg!(target_os = "openbsd");
+    pub const IS_NETBSD: bool = cfg!(target_os = "netbsd");
+    pub const IS_DRAGONFLY: bool = cfg!(target_os = "dragonfly");
+    pub const IS_WINDOWS: bool = cfg!(windows);
+
+    pub const HAS_PRCTL: bool = cfg!(target_os = "linux");
+    pub const HAS_RLIMIT_AS: bool = cfg!(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "dragonfly"
+    ));
+
+    pub fn name() -> &'static str {
+        if Self::IS_LINUX {
+            "Linux"
+        } else if Self::IS_MACOS {
+            "macOS"
+        } else if Self::IS_FREEBSD {
+            "FreeBSD"
+        } else if Self::IS_OPENBSD {
+            "OpenBSD"
+        } else if Self::IS_NETBSD {
+            "NetBSD"
+        } else if Self::IS_DRAGONFLY {
+            "DragonFly BSD"
+        } else if Self::IS_WINDOWS {
+            "Windows"
+        } else {
+            "Unknown"
+        }
+    }
+
+    /// Total physical memory in bytes, if the system reports it
+    pub fn total_memory() -> Option<u64> {
+        #[cfg(unix)]
+        {
+            use nix::libc::{sysconf, _SC_PAGE_SIZE, _SC_PHYS_PAGES};
+            let (pages, page_size) = unsafe { (sysconf(_SC_PHYS_PAGES), sysconf(_SC_PAGE_SIZE)) };
+            if pages <= 0 || page_size <= 0 {
+                return None;
+            }
+            (pages as u64).checked_mul(page_size as u64)
+        }
+        #[cfg(windows)]
+        {
+            use windows_sys::Win32::System::SystemInformation::{
+                GlobalMemoryStatusEx, MEMORYSTATUSEX,
+            };
+            let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+            status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+            if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+                return None;
+            }
+            Some(status.ullTotalPhys)
+        }
+        #[cfg(not(any(unix, windows)))]
+        None
+    }
+
+    /// Kernel or OS release, e.g. `6.8.0-45-generic`
+    pub fn os_release() -> Option<String> {
+        #[cfg(unix)]
+        {
+            let mut uts: nix::libc::utsname = unsafe { std::mem::zeroed() };
+            if unsafe { nix::libc::uname(&mut uts) } == -1 {
+                return None;
+            }
+            let release = unsafe { std::ffi::CStr::from_ptr(uts.release.as_ptr()) };
+            Some(release.to_string_lossy().into_owned())
+        }
+        #[cfg(not(unix))]
+        None
+    }
+
+    /// What this machine supports. Compile-time facts are fixed per
+    /// target; pidfd and cgroup v2 support are probed each call.
+    pub fn capabilities() -> PlatformCapabilities {
+        #[cfg(unix)]
+        let has_pidfd = pidfd_available();
+        #[cfg(not(unix))]
+        let has_pidfd = false;
+
+        PlatformCapabilities {
+            has_prctl: Self::HAS_PRCTL,
+            has_rlimit_as: Self::HAS_RLIMIT_AS,
+            has_signalfd: cfg!(target_os = "linux"),
+            has_pidfd,
+            has_cgroup_v2: Self::IS_LINUX
+                && std::path::Path::new("/sys/fs/cgroup/cgroup.controllers").exists(),
+            has_kqueue: cfg!(any(
+                target_os = "macos",
+                target_os = "freebsd",
+                target_os = "openbsd",
+                target_os = "netbsd",
+                target_os = "dragonfly"
+            )),
+            has_pty: cfg!(unix),
+        }
+    }
+}
+
+/// Kernel and OS features timeout can use on this machine, from
+/// `Platform::capabilities()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlatformCapabilities {
+    /// `prctl(PR_SET_PDEATHSIG)`, so the command dies with timeout (Linux)
+    pub has_prctl: bool,
+    /// An address-space or data rlimit for --mem-limit
+    pub has_rlimit_as: bool,
+    /// signalfd (Linux)
+    pub has_signalfd: bool,
+    /// `pidfd_open`, for exit notification without SIGCHLD (Linux 5.3+)
+    pub has_pidfd: bool,
+    /// A unified cgroup v2 hierarchy at /sys/fs/cgroup (Linux)
+    pub has_cgroup_v2: bool,
+    /// kqueue (macOS and the BSDs)
+    pub has_kqueue: bool,
+    /// Pseudo-terminals for --pty
+    pub has_pty: bool,
+}
+
+impl PlatformCapabilities {
+    /// Each capability by its --info name, in display order
+    pub fn list(&self) -> [(&'static str, bool); 7] {
+        [
+            ("pidfd", self.has_pidfd),
+            ("signalfd", self.has_signalfd),
+            ("cgroup_v2", self.has_cgroup_v2),
+            ("kqueue", self.has_kqueue),
+            ("prctl", self.has_prctl),
+            ("rlimit_as", self.has_rlimit_as),
+            ("pty", self.has_pty),
+        ]
+    }
+}
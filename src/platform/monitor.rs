@@ -0,0 +1,110 @@
+// src/platform/monitor.rs
+// Child exit notification sources for the Unix backend
+
+use std::future::Future;
+use std::pin::Pin;
+use tokio::signal::unix::{signal, Signal as SignalStream, SignalKind};
+
+#[cfg(target_os = "linux")]
+use crate::TimeoutSignal;
+#[cfg(target_os = "linux")]
+use nix::errno::Errno;
+#[cfg(target_os = "linux")]
+use nix::unistd::Pid;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+#[cfg(target_os = "linux")]
+use tokio::io::unix::AsyncFd;
+
+/// Source of "the child changed state" wakeups.
+///
+/// A wakeup only means the child *may* be ready to reap; callers still
+/// confirm with `waitpid`.
+pub trait ChildMonitor: Send {
+    /// Resolves once the child has changed state
+    fn changed(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>>;
+}
+
+/// Fallback monitor driven by the process-wide SIGCHLD stream.
+///
+/// Also reports stops and continues, so it is the one to use with
+/// `--detect-stopped`.
+pub struct SigchldMonitor {
+    stream: SignalStream,
+}
+
+impl SigchldMonitor {
+    /// Register for SIGCHLD. Call this before forking so that an early
+    /// child exit is not missed.
+    pub fn new() -> std::io::Result<Self> {
+        Ok(SigchldMonitor {
+            stream: signal(SignalKind::child())?,
+        })
+    }
+}
+
+impl ChildMonitor for SigchldMonitor {
+    fn changed(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async move {
+            let _ = self.stream.recv().await;
+        })
+    }
+}
+
+/// Monitor backed by a pidfd (Linux 5.3+).
+///
+/// The descriptor becomes readable when the child exits, and is tied to
+/// that exact process, so there is no race with unrelated SIGCHLDs or PID
+/// reuse. It does not report stops.
+#[cfg(target_os = "linux")]
+pub struct PidfdMonitor {
+    fd: AsyncFd<OwnedFd>,
+}
+
+#[cfg(target_os = "linux")]
+impl PidfdMonitor {
+    /// Open a pidfd for `pid`. Fails with ENOSYS on kernels without
+    /// `pidfd_open`, in which case callers should fall back to SIGCHLD.
+    pub fn new(pid: Pid) -> std::io::Result<Self> {
+        let raw = unsafe {
+            nix::libc::syscall(
+                nix::libc::SYS_pidfd_open,
+                pid.as_raw(),
+                nix::libc::PIDFD_NONBLOCK,
+            )
+        };
+        if raw < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw as i32) };
+        Ok(PidfdMonitor {
+            fd: AsyncFd::new(fd)?,
+        })
+    }
+
+    /// Send `signal` through the pidfd, so it cannot reach an unrelated
+    /// process that has since been given the same PID
+    pub fn send_signal(&self, signal: TimeoutSignal) -> nix::Result<()> {
+        let res = unsafe {
+            nix::libc::syscall(
+                nix::libc::SYS_pidfd_send_signal,
+                self.fd.as_raw_fd(),
+                signal.0,
+                std::ptr::null::<nix::libc::siginfo_t>(),
+                0,
+            )
+        };
+        Errno::result(res).map(drop)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ChildMonitor for PidfdMonitor {
+    fn changed(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send + '_>> {
+        Box::pin(async move {
+            // A pidfd stays readable once the process has exited, so the
+            // readiness is never cleared here.
+            let _ = self.fd.readable().await;
+        })
+    }
+}
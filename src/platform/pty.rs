@@ -0,0 +1,164 @@
+// src/platform/pty.rs
+// Pseudo-terminal for --pty: the child runs on the slave side while timeout
+// relays between the master and its own stdin/stdout
+
+use crate::output::{OutputRules, OutputWatcher};
+use crate::TimeoutError;
+use nix::pty::{openpty, Winsize};
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg, Termios};
+use std::fs::File;
+use std::io::IsTerminal;
+use std::os::fd::{AsFd, AsRawFd, OwnedFd, RawFd};
+use tokio::io::AsyncWriteExt;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::JoinHandle;
+
+/// End-of-file character for the slave's line discipline, sent once our own
+/// stdin runs out so a reading child sees EOF too
+const CTRL_D: u8 = 0x04;
+
+/// A freshly opened pseudo-terminal, sized like timeout's own terminal
+pub struct Pty {
+    master: OwnedFd,
+    slave: OwnedFd,
+}
+
+impl Pty {
+    pub fn open() -> Result<Pty, TimeoutError> {
+        let size = terminal_size();
+        let pty = openpty(size.as_ref(), None).map_err(|e| TimeoutError::PtyFailed(e.into()))?;
+        for fd in [&pty.master, &pty.slave] {
+            super::unix::set_cloexec(fd).map_err(TimeoutError::PtyFailed)?;
+        }
+        Ok(Pty {
+            master: pty.master,
+            slave: pty.slave,
+        })
+    }
+
+    /// Make the slave the controlling terminal and stdin/stdout/stderr of
+    /// the calling process. Called in the forked child; returns false on
+    /// failure.
+    pub fn attach_in_child(&self) -> bool {
+        let slave = self.slave.as_raw_fd();
+        unsafe {
+            nix::libc::setsid() != -1
+                && nix::libc::ioctl(slave, nix::libc::TIOCSCTTY as _, 0) != -1
+                && (0..3).all(|fd| nix::libc::dup2(slave, fd) != -1)
+        }
+    }
+
+    /// Start relaying in the parent: the child's output goes through an
+    /// `OutputWatcher` as one stream, our stdin is copied to the child and
+    /// window size changes are passed on. `terminal` is switched to raw
+    /// mode so keystrokes (including Ctrl-C) reach the child unchanged.
+    pub fn start(
+        self,
+        rules: OutputRules,
+        terminal: Option<&TerminalGuard>,
+    ) -> Result<(OutputWatcher, PtySession), TimeoutError> {
+        drop(self.slave);
+
+        let reader = self.master.try_clone().map_err(TimeoutError::PtyFailed)?;
+        let writer = self.master.try_clone().map_err(TimeoutError::PtyFailed)?;
+        let output = OutputWatcher::start(
+            rules,
+            tokio::fs::File::from_std(File::from(reader)),
+            tokio::io::empty(),
+        );
+
+        if let Some(terminal) = terminal {
+            terminal.make_raw();
+        }
+
+        let mut writer = tokio::fs::File::from_std(File::from(writer));
+        let stdin = tokio::spawn(async move {
+            let _ = tokio::io::copy(&mut tokio::io::stdin(), &mut writer).await;
+            let _ = writer.write_all(&[CTRL_D]).await;
+            let _ = writer.flush().await;
+        });
+
+        let mut winch =
+            signal(SignalKind::window_change()).map_err(|e| TimeoutError::SignalSetupFailed {
+                signal: "SIGWINCH".to_string(),
+                source: e,
+            })?;
+        let master = self.master;
+        let resize = tokio::spawn(async move {
+            while winch.recv().await.is_some() {
+                if let Some(size) = terminal_size() {
+                    set_window_size(master.as_raw_fd(), &size);
+                }
+            }
+        });
+
+        Ok((
+            output,
+            PtySession {
+                tasks: vec![stdin, resize],
+            },
+        ))
+    }
+}
+
+/// Background relays for a running --pty child; dropping it stops them
+pub struct PtySession {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// The attributes of the terminal on our stdin, saved before the command
+/// starts and put back when dropped.
+///
+/// Held for the whole run so every way out, including errors and the
+/// SIGKILL path, leaves the user's shell as it was: out of our raw mode
+/// with --pty, and without whatever a killed --foreground child changed.
+pub struct TerminalGuard {
+    saved: Termios,
+}
+
+impl TerminalGuard {
+    /// Save the terminal's attributes; `None` if stdin is not a terminal
+    pub fn save() -> Option<TerminalGuard> {
+        let stdin = std::io::stdin();
+        if !stdin.is_terminal() {
+            return None;
+        }
+        let saved = tcgetattr(stdin.as_fd()).ok()?;
+        Some(TerminalGuard { saved })
+    }
+
+    fn make_raw(&self) {
+        let mut raw = self.saved.clone();
+        cfmakeraw(&mut raw);
+        let _ = tcsetattr(std::io::stdin().as_fd(), SetArg::TCSANOW, &raw);
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // TCSADRAIN lets output already written to the terminal finish first
+        let _ = tcsetattr(std::io::stdin().as_fd(), SetArg::TCSADRAIN, &self.saved);
+    }
+}
+
+/// Window size of whichever of our standard streams is a terminal
+fn terminal_size() -> Option<Winsize> {
+    (0..3).find_map(|fd| {
+        let mut size: Winsize = unsafe { std::mem::zeroed() };
+        (unsafe { nix::libc::ioctl(fd, nix::libc::TIOCGWINSZ, &mut size) } != -1).then_some(size)
+    })
+}
+
+fn set_window_size(fd: RawFd, size: &Winsize) {
+    unsafe {
+        nix::libc::ioctl(fd, nix::libc::TIOCSWINSZ, size);
+    }
+}
@@ -1,14 +1,30 @@
 // src/platform/unix.rs
 // Unix-specific timeout implementation using fork() and signals
 
-use crate::{Platform, TimeoutError, TimeoutMetrics, TimeoutSignal};
+use super::control::{next_delivery, ControlSocket, Deadline};
+use super::monitor::{ChildMonitor, SigchldMonitor};
+use super::pty::{Pty, TerminalGuard};
+use super::RunOptions;
+use crate::debug::debug;
+use crate::hook::HookContext;
+use crate::logging::{detail, error, info, warning};
+use crate::output::OutputWatcher;
+use crate::pid_file::PidFile;
+use crate::runner::{cancelled, Events, TimeoutEvent};
+use crate::rusage::ResourceUsage;
+use crate::signal::SignalsInFlight;
+use crate::{Platform, SentSignal, TimeoutError, TimeoutMetrics, TimeoutReason, TimeoutSignal};
+use nix::errno::Errno;
 use nix::sys::signal::Signal;
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
-use nix::unistd::{fork, setpgid, ForkResult, Pid};
+use nix::unistd::{chdir, chroot, fork, getpid, getsid, pipe, setpgid, ForkResult, Pid};
 use owo_colors::OwoColorize;
+use std::os::fd::{AsRawFd, OwnedFd};
 use std::os::unix::process::CommandExt;
+use std::path::Path;
 use std::process::{exit, Command};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::net::unix::pipe::Receiver;
 use tokio::signal::unix::{signal, SignalKind};
 
 // Platform-specific imports
@@ -18,47 +34,313 @@ use nix::libc::{prctl, PR_SET_DUMPABLE, PR_SET_PDEATHSIG};
 #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
 use nix::sys::resource::{setrlimit, Resource};
 
-const EXIT_TIMEDOUT: i32 = 124;
+#[cfg(target_os = "linux")]
+use super::monitor::PidfdMonitor;
+
 const EXIT_CANCELED: i32 = 125;
 const EXIT_CANNOT_INVOKE: i32 = 126;
 const EXIT_ENOENT: i32 = 127;
 
+/// How far short of RLIMIT_CPU the CPU time wait4 reports may be when the
+/// kernel enforces it
+const CPU_TICK: Duration = Duration::from_millis(20);
+
 /// Helper to determine exit code on timeout
 fn timeout_exit_code(
     child_code: i32,
+    reason: TimeoutReason,
     preserve_status: bool,
     status_on_timeout: Option<i32>,
 ) -> i32 {
-    if let Some(custom_status) = status_on_timeout {
+    if !reason.is_timeout() {
+        reason.exit_code()
+    } else if let Some(custom_status) = status_on_timeout {
         custom_status
     } else if preserve_status {
         child_code
     } else {
-        EXIT_TIMEDOUT
+        reason.exit_code()
+    }
+}
+
+/// Pick the child exit notification source at runtime.
+///
+/// A pidfd is preferred where the kernel supports it; SIGCHLD is used
+/// otherwise, and always for `--detect-stopped` since pidfds do not report
+/// stops.
+#[allow(unused_variables)]
+fn select_monitor(
+    child_pid: Pid,
+    sigchld: SigchldMonitor,
+    detect_stopped: bool,
+) -> Box<dyn ChildMonitor> {
+    #[cfg(target_os = "linux")]
+    if !detect_stopped {
+        if let Ok(pidfd) = PidfdMonitor::new(child_pid) {
+            return Box::new(pidfd);
+        }
+    }
+
+    Box::new(sigchld)
+}
+
+/// Whether timeout started its own session, e.g. under setsid(1)
+fn is_session_leader() -> bool {
+    getsid(None).is_ok_and(|sid| sid == getpid())
+}
+
+/// Keep `fd` from being inherited across exec
+pub(super) fn set_cloexec(fd: &OwnedFd) -> std::io::Result<()> {
+    if unsafe { nix::libc::fcntl(fd.as_raw_fd(), nix::libc::F_SETFD, nix::libc::FD_CLOEXEC) } == -1
+    {
+        return Err(std::io::Error::last_os_error());
     }
+    Ok(())
+}
+
+/// Create a pipe whose ends are not inherited across exec, so commands run
+/// by `--parallel` do not keep each other's output pipes open
+pub(super) fn cloexec_pipe() -> Result<(OwnedFd, OwnedFd), TimeoutError> {
+    let (read, write) = pipe().map_err(|e| TimeoutError::PipeFailed(e.into()))?;
+    for fd in [&read, &write] {
+        set_cloexec(fd).map_err(TimeoutError::PipeFailed)?;
+    }
+    Ok((read, write))
+}
+
+/// Make `dir` the root directory, then move into it so the working
+/// directory is not left outside. COMMAND is then looked up on PATH inside
+/// `dir`.
+fn enter_chroot(dir: &Path) -> nix::Result<()> {
+    chroot(dir)?;
+    chdir("/")
+}
+
+/// The signals `in_flight` has sent, timed from when COMMAND started
+fn sent_signals(in_flight: &SignalsInFlight, start_time: Instant) -> Vec<SentSignal> {
+    in_flight
+        .log()
+        .iter()
+        .map(|(signal, at)| SentSignal {
+            signal: signal.as_str().to_string(),
+            offset: at.saturating_duration_since(start_time),
+        })
+        .collect()
+}
+
+/// waitpid() that also returns what the child used, when it has terminated.
+/// nix has no wrapper for wait4().
+fn wait4(pid: Pid, flags: WaitPidFlag) -> nix::Result<(WaitStatus, Option<ResourceUsage>)> {
+    let mut status = 0;
+    let mut usage: nix::libc::rusage = unsafe { std::mem::zeroed() };
+    let res = unsafe { nix::libc::wait4(pid.as_raw(), &mut status, flags.bits(), &mut usage) };
+    match Errno::result(res)? {
+        0 => Ok((WaitStatus::StillAlive, None)),
+        pid => {
+            // `Signal` has no real-time signals, so a child they ended is
+            // passed on as the exit status a shell would give it
+            let status = if nix::libc::WIFSIGNALED(status)
+                && Signal::try_from(nix::libc::WTERMSIG(status)).is_err()
+            {
+                WaitStatus::Exited(
+                    Pid::from_raw(pid),
+                    crate::signal::exit_code(nix::libc::WTERMSIG(status)),
+                )
+            } else {
+                WaitStatus::from_raw(Pid::from_raw(pid), status)?
+            };
+            let usage = matches!(status, WaitStatus::Exited(..) | WaitStatus::Signaled(..))
+                .then(|| ResourceUsage::from(&usage));
+            Ok((status, usage))
+        }
+    }
+}
+
+/// Wait until `child_pid` itself changes state and return its status,
+/// filling in `usage` once it has terminated.
+///
+/// With the SIGCHLD monitor a wakeup may belong to a sibling started by
+/// `--parallel`, so keep waiting while our child is still alive.
+async fn wait_for_child(
+    monitor: &mut dyn ChildMonitor,
+    child_pid: Pid,
+    flags: WaitPidFlag,
+    usage: &mut ResourceUsage,
+) -> nix::Result<WaitStatus> {
+    loop {
+        monitor.changed().await;
+        let status = wait4(child_pid, flags | WaitPidFlag::WNOHANG);
+        debug!(
+            "wait4({}, {:?}) = {:?}",
+            child_pid,
+            flags | WaitPidFlag::WNOHANG,
+            status
+        );
+        match status {
+            Ok((WaitStatus::StillAlive, _)) => continue,
+            Ok((status, collected)) => {
+                if let Some(collected) = collected {
+                    *usage = collected;
+                }
+                detail!("{}: {}", "Wait".cyan(), describe_wait(&status));
+                return Ok(status);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A wait result for -vv, e.g. `PID 4242 was killed by SIGTERM`
+fn describe_wait(status: &WaitStatus) -> String {
+    match *status {
+        WaitStatus::Exited(pid, code) => format!("PID {} exited with status {}", pid, code),
+        WaitStatus::Signaled(pid, signal, core) => format!(
+            "PID {} was killed by {}{}",
+            pid,
+            signal,
+            if core { " (core dumped)" } else { "" }
+        ),
+        WaitStatus::Stopped(pid, signal) => format!("PID {} was stopped by {}", pid, signal),
+        WaitStatus::Continued(pid) => format!("PID {} was continued", pid),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Reaps COMMAND if `run_with_timeout` leaves before waiting for it, through
+/// an error returned with `?` or a panic, so it does not linger as a zombie
+/// while timeout goes on to --retries, --every or other --parallel jobs.
+/// Disarmed once COMMAND has been waited for: its PID may then belong to
+/// someone else. Nothing can reap it if timeout itself is SIGKILLed; init
+/// inherits it then.
+struct Reaper {
+    pid: Pid,
+    armed: bool,
+}
+
+impl Reaper {
+    fn new(pid: Pid) -> Reaper {
+        Reaper { pid, armed: true }
+    }
+
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for Reaper {
+    fn drop(&mut self) {
+        if self.armed {
+            let status = waitpid(self.pid, Some(WaitPidFlag::WNOHANG));
+            debug!("reaper: waitpid({}, WNOHANG) = {:?}", self.pid, status);
+        }
+    }
+}
+
+/// With TIMEOUT_TEST_PANIC_AFTER_FORK set, wait for COMMAND to exit without
+/// reaping it and then panic, so tests can watch the Reaper at work. Debug
+/// builds only.
+#[cfg(all(target_os = "linux", debug_assertions))]
+fn panic_after_fork_for_tests(child_pid: Pid) {
+    use nix::sys::wait::{waitid, Id};
+
+    if std::env::var_os("TIMEOUT_TEST_PANIC_AFTER_FORK").is_some() {
+        let _ = waitid(
+            Id::Pid(child_pid),
+            WaitPidFlag::WEXITED | WaitPidFlag::WNOWAIT,
+        );
+        panic!("TIMEOUT_TEST_PANIC_AFTER_FORK");
+    }
+}
+
+/// Whether this kernel supports pidfds, checked by opening one for ourselves
+pub fn pidfd_available() -> bool {
+    #[cfg(target_os = "linux")]
+    return PidfdMonitor::new(nix::unistd::getpid()).is_ok();
+    #[cfg(not(target_os = "linux"))]
+    return false;
+}
+
+/// Human-readable description of how a command would be run, for --dry-run
+pub fn backend_description(
+    process_only: bool,
+    detect_stopped: bool,
+    pty: bool,
+    _no_window: bool,
+) -> String {
+    let monitor = if !detect_stopped && Platform::capabilities().has_pidfd {
+        "pidfd"
+    } else {
+        "sigchld"
+    };
+
+    let target = if process_only {
+        "process"
+    } else {
+        "process group"
+    };
+    format!(
+        "unix fork/exec, {} exit notification, signals to {}{}",
+        monitor,
+        target,
+        if pty { ", on a pseudo-terminal" } else { "" }
+    )
 }
 
-#[allow(clippy::too_many_arguments)]
 pub async fn run_with_timeout(
     command: &str,
     args: &[String],
-    duration: Duration,
-    term_signal: TimeoutSignal,
-    kill_after: Option<Duration>,
-    foreground: bool,
-    preserve_status: bool,
-    verbose: bool,
-    detect_stopped: bool,
-    no_notify: bool,
-    status_on_timeout: Option<i32>,
-    cpu_limit: Option<u64>,
-    mem_limit: Option<u64>,
-) -> Result<i32, TimeoutError> {
+    options: RunOptions<'_>,
+) -> Result<TimeoutMetrics, TimeoutError> {
+    let RunOptions {
+        duration,
+        term_signal,
+        kill_after,
+        foreground,
+        no_process_group,
+        set_session,
+        preserve_status,
+        verbose,
+        detect_stopped,
+        no_notify,
+        status_on_timeout,
+        cpu_limit,
+        mem_limit,
+        cpu_affinity,
+        io_priority,
+        namespaced,
+        chroot,
+        output_rules,
+        redirects,
+        pty,
+        extend_step,
+        boottime,
+        control_socket,
+        control_socket_force,
+        pid_file,
+        pid_file_force,
+        stop_instead,
+        on_timeout,
+        pre_kill,
+        notifier,
+        embedded,
+    } = options;
+    // Run inside another application, which keeps its signal handlers
+    let embedded_run = embedded.is_some();
+    let (events, mut cancel, handle) = match embedded {
+        Some(embedded) => (embedded.events, embedded.cancel, embedded.handle),
+        None => (Events::default(), None, None),
+    };
     let start_time = Instant::now();
     let mut metrics = TimeoutMetrics {
         command: command.to_string(),
+        argv: std::iter::once(command)
+            .chain(args.iter().map(String::as_str))
+            .map(String::from)
+            .collect(),
         duration,
         timed_out: false,
+        timeout_reason: None,
+        matched_line: None,
         exit_code: 0,
         signal_sent: None,
         elapsed: Duration::ZERO,
@@ -67,43 +349,169 @@ pub async fn run_with_timeout(
         memory_limit: mem_limit,
         stopped_detected: false,
         platform: Platform::name(),
+        attempt: 1,
+        iteration: 1,
+        retries_timeout: 0,
+        retries_exit: 0,
+        total_elapsed: Duration::ZERO,
+        budget: None,
+        extensions: 0,
+        watched_pid: None,
+        hook_exit_code: None,
+        started_at: SystemTime::now(),
+        pid: None,
+        pgid: None,
+        usage: ResourceUsage::default(),
+        signals_sent: Vec::new(),
+        kill_signal_sent: false,
+        term_signal_sent_at: None,
+        child_exited_at: None,
+        shutdown_latency: None,
     };
 
+    if verbose && kill_after.is_some() && !term_signal.is_catchable() {
+        warning!(
+            "{}: --signal {} cannot be caught, so --kill-after has no grace period to give",
+            "Warning".yellow(),
+            term_signal
+        );
+    }
+
     // Linux-specific: Disable core dumps
     #[cfg(target_os = "linux")]
-    unsafe {
-        prctl(PR_SET_DUMPABLE, 0);
+    if !embedded_run {
+        unsafe {
+            prctl(PR_SET_DUMPABLE, 0);
+        }
     }
 
-    if !foreground {
-        setpgid(Pid::from_raw(0), Pid::from_raw(0)).map_err(TimeoutError::ProcessGroupFailed)?;
+    // Dropped last, so the terminal is restored however this function returns
+    let terminal = if pty || foreground {
+        TerminalGuard::save()
+    } else {
+        None
+    };
+
+    // Signals go to COMMAND alone when it shares timeout's process group
+    let process_only = foreground || no_process_group;
+
+    // With --pty the child gets its own session instead, and timeout stays
+    // in the foreground so it can read and configure the real terminal. A
+    // session leader, as under setsid(1), already leads its own process
+    // group and may not move to another one: setpgid fails with EPERM.
+    if !process_only && !pty {
+        if is_session_leader() {
+            debug!("already a session leader, so already in a group of our own");
+        } else {
+            setpgid(Pid::from_raw(0), Pid::from_raw(0))
+                .map_err(TimeoutError::ProcessGroupFailed)?;
+        }
     }
 
-    let mut sigchld = signal(SignalKind::child()).map_err(|e| TimeoutError::SignalSetupFailed {
+    // Registered before fork so an early exit is not missed if we end up
+    // falling back to SIGCHLD
+    let sigchld = SigchldMonitor::new().map_err(|e| TimeoutError::SignalSetupFailed {
         signal: "SIGCHLD".to_string(),
         source: e,
     })?;
 
-    let child_pid = match unsafe { fork() }? {
+    let control = control_socket
+        .map(|path| ControlSocket::bind(path, control_socket_force))
+        .transpose()?;
+    let mut pid_file = pid_file
+        .map(|path| PidFile::create(path, pid_file_force))
+        .transpose()?;
+
+    // SIGUSR1 and SIGUSR2 too, as their default action would kill timeout
+    let mut deadline = if embedded_run {
+        Deadline::embedded(command, duration, handle)
+    } else {
+        Deadline::start(command, duration, extend_step, boottime, verbose, control).map_err(
+            |e| TimeoutError::SignalSetupFailed {
+                signal: "SIGUSR1/SIGUSR2".to_string(),
+                source: e,
+            },
+        )?
+    };
+
+    // A pty carries the output itself, so no pipes are needed alongside it
+    let pty = if pty { Some(Pty::open()?) } else { None };
+    let pipes = if pty.is_none() && output_rules.needs_pipes() {
+        Some((cloexec_pipe()?, cloexec_pipe()?))
+    } else {
+        None
+    };
+
+    // With --namespaced the child is in its namespaces by the time this
+    // returns, or has already been reaped
+    #[cfg(target_os = "linux")]
+    let forked = if namespaced {
+        unsafe { super::linux::namespace_fork() }?
+    } else {
+        unsafe { fork() }?
+    };
+    #[cfg(not(target_os = "linux"))]
+    let forked = {
+        let _ = namespaced;
+        unsafe { fork() }?
+    };
+    let child_pid = match forked {
         ForkResult::Parent { child } => child,
         ForkResult::Child => {
             // === Child process setup ===
+            // Messages here use anstream's eprintln! rather than the logging
+            // macros: another thread may have held the subscriber's locks at
+            // fork(). The color choice was settled before forking, and
+            // -q and -qq are honoured by hand.
+            let quiet = crate::logging::quietness();
 
-            // Linux-specific: Setup PR_SET_PDEATHSIG
+            // Route output through timeout; the pipe fds themselves are
+            // close-on-exec, only the dup2'd copies survive
+            if let Some(((_, stdout_w), (_, stderr_w))) = &pipes {
+                if unsafe { nix::libc::dup2(stdout_w.as_raw_fd(), 1) } == -1
+                    || unsafe { nix::libc::dup2(stderr_w.as_raw_fd(), 2) } == -1
+                {
+                    exit(EXIT_CANCELED);
+                }
+            }
+            if !redirects.apply_in_child() {
+                exit(EXIT_CANCELED);
+            }
+            // The child leads its own session on the pty, so signals to its
+            // process group reach everything it starts there
+            if pty.as_ref().is_some_and(|pty| !pty.attach_in_child()) {
+                exit(EXIT_CANCELED);
+            }
+
+            // With --stop-instead the child leads its own session: otherwise
+            // our exit would orphan its stopped process group, and the kernel
+            // sends such groups SIGHUP and SIGCONT. --set-session asks for one
+            // outright.
+            if (stop_instead || set_session)
+                && pty.is_none()
+                && unsafe { nix::libc::setsid() } == -1
+            {
+                exit(EXIT_CANCELED);
+            }
+
+            // Linux-specific: Setup PR_SET_PDEATHSIG, unless the command is
+            // meant to outlive us with --no-kill or --stop-instead
             #[cfg(target_os = "linux")]
+            if !output_rules.keep_running
+                && !stop_instead
+                && unsafe { prctl(PR_SET_PDEATHSIG, Signal::SIGKILL as i32) } == -1
+                && quiet == 0
             {
-                if unsafe { prctl(PR_SET_PDEATHSIG, Signal::SIGKILL as i32) } == -1 {
-                    eprintln!("{}: failed to set parent death signal", "Warning".yellow());
-                }
+                anstream::eprintln!("{}: failed to set parent death signal", "Warning".yellow());
             }
 
             // BSD/macOS: Warning about missing orphan prevention
             #[cfg(not(target_os = "linux"))]
             if verbose {
-                eprintln!(
-                    "{}: orphan prevention (PR_SET_PDEATHSIG) not available on {}",
+                anstream::eprintln!(
+                    "{}: orphan prevention (PR_SET_PDEATHSIG) is {}",
                     "Note".cyan(),
-                    Platform::name()
+                    crate::capabilities::pdeathsig().why_not()
                 );
             }
 
@@ -111,8 +519,13 @@ pub async fn run_with_timeout(
             #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
             {
                 if let Some(cpu_secs) = cpu_limit {
-                    if let Err(e) = setrlimit(Resource::RLIMIT_CPU, cpu_secs, cpu_secs) {
-                        eprintln!("{}: failed to set CPU limit: {}", "Warning".yellow(), e);
+                    match setrlimit(Resource::RLIMIT_CPU, cpu_secs, cpu_secs) {
+                        Err(e) if quiet == 0 => anstream::eprintln!(
+                            "{}: failed to set CPU limit: {}",
+                            "Warning".yellow(),
+                            e
+                        ),
+                        _ => {}
                     }
                 }
 
@@ -125,8 +538,13 @@ pub async fn run_with_timeout(
                     #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
                     let resource = Resource::RLIMIT_DATA;
 
-                    if let Err(e) = setrlimit(resource, mem_bytes, mem_bytes) {
-                        eprintln!("{}: failed to set memory limit: {}", "Warning".yellow(), e);
+                    match setrlimit(resource, mem_bytes, mem_bytes) {
+                        Err(e) if quiet == 0 => anstream::eprintln!(
+                            "{}: failed to set memory limit: {}",
+                            "Warning".yellow(),
+                            e
+                        ),
+                        _ => {}
                     }
                 }
             }
@@ -134,15 +552,64 @@ pub async fn run_with_timeout(
             // macOS/OpenBSD/NetBSD: Warning about resource limits
             #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly")))]
             {
-                if cpu_limit.is_some() || mem_limit.is_some() {
-                    eprintln!(
-                        "{}: resource limits not fully supported on {}",
+                if (cpu_limit.is_some() || mem_limit.is_some()) && quiet == 0 {
+                    anstream::eprintln!(
+                        "{}: resource limits are {}",
                         "Warning".yellow(),
-                        Platform::name()
+                        crate::capabilities::cpu_limit().why_not()
                     );
                 }
             }
 
+            // Linux-specific: keep COMMAND to the --cpu-affinity CPUs
+            #[cfg(target_os = "linux")]
+            if let Some(cpus) = cpu_affinity {
+                if let Err(e) = crate::affinity::apply(cpus) {
+                    if quiet < 2 {
+                        anstream::eprintln!(
+                            "{}: cannot set CPU affinity to {}: {}",
+                            "timeout".red(),
+                            crate::affinity::format_cpu_list(cpus),
+                            e
+                        );
+                    }
+                    exit(EXIT_CANCELED);
+                }
+            }
+            // Linux-specific: the --ionice class and level
+            #[cfg(target_os = "linux")]
+            if let Some(priority) = io_priority {
+                if let Err(e) = crate::ionice::apply(priority) {
+                    if quiet < 2 {
+                        anstream::eprintln!(
+                            "{}: cannot set I/O priority to {}: {}",
+                            "timeout".red(),
+                            priority,
+                            e
+                        );
+                    }
+                    exit(EXIT_CANCELED);
+                }
+            }
+            // The plan rejects --cpu-affinity and --ionice elsewhere
+            #[cfg(not(target_os = "linux"))]
+            let _ = (cpu_affinity, io_priority);
+
+            // Last, as everything above may still need the real root
+            if let Some(dir) = chroot {
+                if let Err(e) = enter_chroot(dir) {
+                    let error = TimeoutError::PermissionDenied(format!(
+                        "cannot chroot to {}: {}",
+                        dir.display(),
+                        e.desc()
+                    ));
+                    if quiet < 2 {
+                        anstream::eprintln!("{}: {}", "timeout".red(), error);
+                    }
+                    exit(error.exit_code());
+                }
+            }
+
             let _ = unsafe {
                 nix::sys::signal::signal(Signal::SIGTTIN, nix::sys::signal::SigHandler::SigDfl)
             };
@@ -165,62 +632,109 @@ pub async fn run_with_timeout(
             };
 
             // If we get here, exec failed
-            eprintln!(
-                "{}: failed to run command '{}': {}",
-                "Error".red(),
-                command,
-                error
-            );
+            if quiet < 2 {
+                anstream::eprintln!(
+                    "{}: failed to run command '{}': {}",
+                    "Error".red(),
+                    command,
+                    error
+                );
+            }
             exit(exit_code);
         }
     };
 
     // === Parent process ===
+    debug!("fork() = {}", child_pid);
+    let mut reaper = Reaper::new(child_pid);
+    // A child that starts its own session leads a group numbered after
+    // it, which getpgid may not show yet if it has not got that far.
+    // Otherwise it is in timeout's group, which it inherited.
+    let pgid = if set_session || stop_instead || pty.is_some() {
+        Some(child_pid)
+    } else {
+        nix::unistd::getpgid(Some(child_pid)).ok()
+    };
+    let group = if process_only { None } else { pgid };
+    let mut in_flight = SignalsInFlight::new(child_pid, group);
+    #[cfg(all(target_os = "linux", debug_assertions))]
+    panic_after_fork_for_tests(child_pid);
+    metrics.pid = Some(child_pid.as_raw() as u32);
+    events.send(TimeoutEvent::Started {
+        pid: child_pid.as_raw() as u32,
+    });
+    metrics.pgid = pgid.map(|pgid| pgid.as_raw() as u32);
+    if let Some(pid_file) = &mut pid_file {
+        if let Err(e) = pid_file.write(child_pid.as_raw() as u32, metrics.pgid) {
+            // Whoever asked for the file cannot find COMMAND without it
+            let _ = in_flight.send_now(TimeoutSignal::from(Signal::SIGKILL));
+            let _ = waitpid(child_pid, None);
+            reaper.disarm();
+            return Err(e);
+        }
+    }
+    // Fed until this function returns, however COMMAND ends
+    let _watchdog = notifier.and_then(|notifier| notifier.started(child_pid.as_raw() as u32));
 
-    let mut sigint =
-        signal(SignalKind::interrupt()).map_err(|e| TimeoutError::SignalSetupFailed {
-            signal: "SIGINT".to_string(),
-            source: e,
-        })?;
+    let mut pty_session = None;
+    let output = match (pty, pipes) {
+        (Some(pty), _) => {
+            let (output, session) = pty.start(output_rules.clone(), terminal.as_ref())?;
+            pty_session = Some(session);
+            Some(output)
+        }
+        (None, Some(((stdout_r, stdout_w), (stderr_r, stderr_w)))) => {
+            drop((stdout_w, stderr_w));
+            let stdout = Receiver::from_owned_fd(stdout_r).map_err(TimeoutError::PipeFailed)?;
+            let stderr = Receiver::from_owned_fd(stderr_r).map_err(TimeoutError::PipeFailed)?;
+            Some(OutputWatcher::start(output_rules.clone(), stdout, stderr))
+        }
+        (None, None) => None,
+    };
 
-    let mut sigterm =
-        signal(SignalKind::terminate()).map_err(|e| TimeoutError::SignalSetupFailed {
-            signal: "SIGTERM".to_string(),
-            source: e,
-        })?;
+    let mut monitor = select_monitor(child_pid, sigchld, detect_stopped);
 
-    let mut wait_flags = WaitPidFlag::WNOHANG;
+    // Passed on to COMMAND; an application running it keeps its own
+    let listen = |kind: SignalKind, name: &str| {
+        (!embedded_run)
+            .then(|| signal(kind))
+            .transpose()
+            .map_err(|e| TimeoutError::SignalSetupFailed {
+                signal: name.to_string(),
+                source: e,
+            })
+    };
+    let mut sigint = listen(SignalKind::interrupt(), "SIGINT")?;
+    let mut sigterm = listen(SignalKind::terminate(), "SIGTERM")?;
+
+    let mut wait_flags = WaitPidFlag::empty();
     if detect_stopped {
         wait_flags |= WaitPidFlag::WUNTRACED;
     }
 
+    let mut usage = ResourceUsage::default();
     let exit_code = tokio::select! {
-        _ = sigchld.recv() => {
+        status = wait_for_child(monitor.as_mut(), child_pid, wait_flags, &mut usage) => {
+            debug!("select: child arm fired with {:?}", status);
             metrics.elapsed = start_time.elapsed();
 
-            match waitpid(child_pid, Some(wait_flags)) {
+            match status {
                 Ok(WaitStatus::Stopped(_, sig)) if detect_stopped => {
                     metrics.stopped_detected = true;
                     if verbose {
-                        eprintln!("{}: process stopped by signal {}", "Info".blue(), sig);
+                        info!("{}: process stopped by signal {}", "Info".blue(), sig);
                     }
 
-                    if !foreground {
-                        let _ = TimeoutSignal(Signal::SIGCONT).send_to_group(child_pid);
-                    } else {
-                        let _ = TimeoutSignal(Signal::SIGCONT).send_to_process(child_pid);
-                    }
+                    let _ = in_flight.send_now(TimeoutSignal::from(Signal::SIGCONT));
 
-                    match waitpid(child_pid, None) {
+                    match wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage).await {
                         Ok(WaitStatus::Exited(_, code)) => {
                             metrics.exit_code = code;
-                            metrics.log();
                             code
                         }
                         Ok(WaitStatus::Signaled(_, sig, _)) => {
-                            let code = 128 + sig as i32;
+                            let code = crate::signal::exit_code(sig as i32);
                             metrics.exit_code = code;
-                            metrics.log();
                             code
                         }
                         _ => EXIT_CANCELED,
@@ -228,133 +742,245 @@ pub async fn run_with_timeout(
                 }
                 Ok(WaitStatus::Exited(_, code)) => {
                     metrics.exit_code = code;
-                    metrics.log();
                     code
                 }
                 Ok(WaitStatus::Signaled(_, sig, _)) => {
-                    let code = 128 + sig as i32;
+                    let code = crate::signal::exit_code(sig as i32);
                     metrics.exit_code = code;
-                    metrics.log();
                     code
                 }
-                Ok(WaitStatus::StillAlive) => {
-                    match waitpid(child_pid, None) {
-                        Ok(WaitStatus::Exited(_, code)) => {
-                            metrics.exit_code = code;
-                            metrics.log();
-                            code
-                        }
-                        Ok(WaitStatus::Signaled(_, sig, _)) => {
-                            let code = 128 + sig as i32;
-                            metrics.exit_code = code;
-                            metrics.log();
-                            code
-                        }
-                        _ => EXIT_CANCELED,
-                    }
-                }
                 _ => EXIT_CANCELED,
             }
         }
 
-        _ = tokio::time::sleep(duration) => {
-            metrics.timed_out = true;
+        reason = deadline.wait(output.as_ref(), child_pid, group) => {
+            debug!("select: deadline arm fired ({})", reason);
+            crate::progress::expired();
+            metrics.extensions = deadline.extensions();
+            // A `kill` request on the control socket skips straight to SIGKILL
+            let (term_signal, kill_after, no_notify) = if deadline.kill_requested() {
+                (TimeoutSignal::from(Signal::SIGKILL), None, false)
+            } else {
+                (term_signal, kill_after, no_notify)
+            };
+            // The signal the hooks are told about: with --no-notify, the
+            // first one COMMAND gets is the SIGKILL after --kill-after
+            let next_signal = if no_notify { TimeoutSignal::from(Signal::SIGKILL) } else { term_signal };
+            metrics.timed_out = reason.is_timeout();
+            metrics.timeout_reason = Some(reason);
+            match reason {
+                TimeoutReason::OutputLimit => events.send(TimeoutEvent::ResourceLimitExceeded {
+                    resource: "output".to_string(),
+                }),
+                TimeoutReason::SuccessPattern => {}
+                _ => events.send(TimeoutEvent::TimedOut {
+                    elapsed: start_time.elapsed(),
+                }),
+            }
+            metrics.matched_line = output.as_ref().and_then(|o| o.matched_line());
+            if let Some(notifier) = notifier.filter(|_| reason.is_timeout()) {
+                notifier.stopping();
+            }
+
+            if verbose {
+                if let Some(line) = &metrics.matched_line {
+                    info!("{}: {} matched: {}", "Pattern".cyan(), reason, line);
+                }
+            }
+
+            // The hook gets to look at COMMAND while it is still running
+            if let Some(hook) = on_timeout.filter(|_| reason.is_timeout() && !deadline.kill_requested()) {
+                let context = HookContext {
+                    pid: metrics.pid,
+                    pgid: metrics.pgid,
+                    elapsed: start_time.elapsed(),
+                    command,
+                    signal: Some(next_signal.to_string()),
+                    ..Default::default()
+                };
+                metrics.hook_exit_code = Some(hook.run(&context, verbose).await);
+            }
+
+            // --no-kill leaves the command to be reaped by init once we exit;
+            // an explicit `kill` on the control socket still goes ahead
+            let leave_running = output_rules.keep_running
+                && !deadline.kill_requested()
+                && matches!(reason, TimeoutReason::Duration | TimeoutReason::Idle | TimeoutReason::SuccessPattern);
+            if leave_running {
+                metrics.elapsed = start_time.elapsed();
+                if reason == TimeoutReason::SuccessPattern {
+                    if verbose {
+                        info!("{}: leaving command '{}' running (--no-kill)", "Info".cyan(), command);
+                    }
+                    metrics.exit_code = 0;
+                } else {
+                    let pgid = nix::unistd::getpgid(Some(child_pid)).unwrap_or(child_pid);
+                    warning!(
+                        "{}: command '{}' timed out ({}), leaving it running (--no-kill): PID {}, process group {}",
+                        "Timeout".red(),
+                        command,
+                        reason,
+                        child_pid,
+                        pgid
+                    );
+                    metrics.exit_code = status_on_timeout.unwrap_or(reason.exit_code());
+                    if let Some(log) = &output_rules.log_file {
+                        log.record_timeout(&metrics);
+                    }
+                }
+                metrics.record_shutdown(sent_signals(&in_flight, start_time), false);
+                return Ok(metrics);
+            }
+
+            // --stop-instead freezes the command where it is for a debugger
+            if stop_instead && !deadline.kill_requested() && reason.is_timeout() {
+                let stop = TimeoutSignal::from(Signal::SIGSTOP);
+                in_flight.send_now(stop)?;
+                events.signal_sent(stop);
+                crate::progress::sent("SIGSTOP");
+                metrics.signal_sent = Some(stop);
+                metrics.elapsed = start_time.elapsed();
+                warning!(
+                    "{}: command '{}' timed out ({}), PID {}: process group suspended; resume with kill -CONT -{} or inspect with gdb -p {}",
+                    "Timeout".red(),
+                    command,
+                    reason,
+                    child_pid,
+                    child_pid,
+                    child_pid
+                );
+                metrics.exit_code = status_on_timeout.unwrap_or(reason.exit_code());
+                metrics.record_shutdown(sent_signals(&in_flight, start_time), false);
+                if let Some(log) = &output_rules.log_file {
+                    log.record_timeout(&metrics);
+                }
+                return Ok(metrics);
+            }
+
+            if verbose && reason == TimeoutReason::Idle {
+                warning!("{}: no output from command '{}' for {:?}", "Idle".red(), command, output_rules.idle_timeout.unwrap_or_default());
+            }
+            if reason == TimeoutReason::OutputLimit {
+                error!("{}: output limit exceeded, stopping command '{}'", "timeout".red(), command);
+            }
+
+            // Its status is only reported: COMMAND is signalled regardless
+            if let Some(hook) = pre_kill.filter(|_| reason.is_timeout()) {
+                let context = HookContext {
+                    pid: metrics.pid,
+                    pgid: metrics.pgid,
+                    elapsed: start_time.elapsed(),
+                    command,
+                    signal: Some(next_signal.to_string()),
+                    ..Default::default()
+                };
+                hook.run(&context, verbose).await;
+            }
 
             // Send initial signal unless --no-notify is specified
             if !no_notify {
                 metrics.signal_sent = Some(term_signal);
 
                 if verbose {
-                    eprintln!("{}: sending signal {} to command '{}'", "Timeout".red(), term_signal, command);
+                    warning!("{}: sending signal {} to command '{}'", "Timeout".red(), term_signal, command);
                 }
 
-                if foreground {
-                    term_signal.send_to_process(child_pid)?;
-                } else {
-                    term_signal.send_to_group(child_pid)?;
+                if in_flight.send(term_signal)? {
+                    events.signal_sent(term_signal);
                 }
+                crate::progress::sent(&term_signal.to_string());
 
-                if !foreground {
-                    let _ = TimeoutSignal(Signal::SIGCONT).send_to_group(child_pid);
+                if !process_only {
+                    let _ = in_flight.send_now(TimeoutSignal::from(Signal::SIGCONT));
                 }
             } else if verbose {
-                eprintln!("{}: skipping initial signal (--no-notify), will send SIGKILL after grace period", "Info".cyan());
+                info!("{}: skipping initial signal (--no-notify), will send SIGKILL after grace period", "Info".cyan());
             }
 
             if let Some(ka_duration) = kill_after {
                 metrics.kill_after_used = true;
 
-                tokio::select! {
-                    _ = sigchld.recv() => {
-                        metrics.elapsed = start_time.elapsed();
+                let grace = tokio::time::sleep(ka_duration);
+                tokio::pin!(grace);
+                loop {
+                    tokio::select! {
+                        status = wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage) => {
+                            debug!("select: child arm fired during the kill-after grace period with {:?}", status);
+                            metrics.elapsed = start_time.elapsed();
+
+                            let code = match status {
+                                Ok(WaitStatus::Exited(_, c)) => {
+                                    timeout_exit_code(c, reason, preserve_status, status_on_timeout)
+                                }
+                                Ok(WaitStatus::Signaled(_, sig, _)) => {
+                                    timeout_exit_code(crate::signal::exit_code(sig as i32), reason, preserve_status, status_on_timeout)
+                                }
+                                _ => timeout_exit_code(reason.exit_code(), reason, false, status_on_timeout),
+                            };
+
+                            metrics.exit_code = code;
+                            break code;
+                        }
 
-                        let code = match waitpid(child_pid, Some(WaitPidFlag::WNOHANG)) {
-                            Ok(WaitStatus::Exited(_, c)) => {
-                                timeout_exit_code(c, preserve_status, status_on_timeout)
+                        _ = &mut grace => {
+                            debug!("select: kill-after arm fired after {:?}", ka_duration);
+                            if verbose {
+                                warning!("{}: sending signal SIGKILL to command '{}'", "Kill".bright_red(), command);
                             }
-                            Ok(WaitStatus::Signaled(_, sig, _)) => {
-                                timeout_exit_code(128 + sig as i32, preserve_status, status_on_timeout)
+
+                            if in_flight.send(TimeoutSignal::from(Signal::SIGKILL))? {
+                                events.send(TimeoutEvent::KillSent);
                             }
-                            _ => status_on_timeout.unwrap_or(EXIT_TIMEDOUT),
-                        };
+                            crate::progress::sent("SIGKILL");
 
-                        metrics.exit_code = code;
-                        metrics.log();
-                        code
-                    }
+                            let _ = wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage).await;
+                            metrics.elapsed = start_time.elapsed();
+                            let code = if reason.is_timeout() { 128 + 9 } else { reason.exit_code() };
+                            metrics.exit_code = code;
 
-                    _ = tokio::time::sleep(ka_duration) => {
-                        if verbose {
-                            eprintln!("{}: sending signal SIGKILL to command '{}'", "Kill".bright_red(), command);
+                            break code;
                         }
 
-                        let kill_sig = TimeoutSignal(Signal::SIGKILL);
-                        if foreground {
-                            kill_sig.send_to_process(child_pid)?;
-                        } else {
-                            kill_sig.send_to_group(child_pid)?;
+                        // Passed on while COMMAND has its grace period, unless
+                        // it is the signal timeout has only just sent
+                        Some(()) = next_delivery(&mut sigterm) => {
+                            debug!("select: SIGTERM arm fired during the kill-after grace period");
+                            in_flight.send(TimeoutSignal::from(Signal::SIGTERM))?;
                         }
 
-                        let _ = sigchld.recv().await;
-                        metrics.elapsed = start_time.elapsed();
-                        metrics.exit_code = 128 + 9;
-                        metrics.log();
-
-                        128 + 9
+                        Some(()) = next_delivery(&mut sigint) => {
+                            debug!("select: SIGINT arm fired during the kill-after grace period");
+                            in_flight.send(TimeoutSignal::from(Signal::SIGINT))?;
+                        }
                     }
                 }
             } else {
-                let _ = sigchld.recv().await;
+                let status = wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage).await;
                 metrics.elapsed = start_time.elapsed();
 
-                let code = match waitpid(child_pid, None) {
+                let code = match status {
                     Ok(WaitStatus::Exited(_, c)) => {
-                        timeout_exit_code(c, preserve_status, status_on_timeout)
+                        timeout_exit_code(c, reason, preserve_status, status_on_timeout)
                     }
                     Ok(WaitStatus::Signaled(_, sig, _)) => {
-                        timeout_exit_code(128 + sig as i32, preserve_status, status_on_timeout)
+                        timeout_exit_code(crate::signal::exit_code(sig as i32), reason, preserve_status, status_on_timeout)
                     }
-                    _ => status_on_timeout.unwrap_or(EXIT_TIMEDOUT),
+                    _ => timeout_exit_code(reason.exit_code(), reason, false, status_on_timeout),
                 };
 
                 metrics.exit_code = code;
-                metrics.log();
                 code
             }
         }
 
-        _ = sigint.recv() => {
+        Some(()) = next_delivery(&mut sigint) => {
+            debug!("select: SIGINT arm fired");
             metrics.elapsed = start_time.elapsed();
 
-            let sig = TimeoutSignal(Signal::SIGINT);
-            if foreground {
-                sig.send_to_process(child_pid)?;
-            } else {
-                sig.send_to_group(child_pid)?;
-            }
+            let sig = TimeoutSignal::from(Signal::SIGINT);
+            in_flight.send(sig)?;
 
-            let _ = sigchld.recv().await;
-            let code = match waitpid(child_pid, None) {
+            let code = match wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage).await {
                 Ok(WaitStatus::Exited(_, c)) => c,
                 Ok(WaitStatus::Signaled(_, _, _)) => 128 + 2,
                 _ => 128 + 2,
@@ -362,22 +988,17 @@ pub async fn run_with_timeout(
 
             metrics.exit_code = code;
             metrics.signal_sent = Some(sig);
-            metrics.log();
             code
         }
 
-        _ = sigterm.recv() => {
+        Some(()) = next_delivery(&mut sigterm) => {
+            debug!("select: SIGTERM arm fired");
             metrics.elapsed = start_time.elapsed();
 
-            let sig = TimeoutSignal(Signal::SIGTERM);
-            if foreground {
-                sig.send_to_process(child_pid)?;
-            } else {
-                sig.send_to_group(child_pid)?;
-            }
+            let sig = TimeoutSignal::from(Signal::SIGTERM);
+            in_flight.send(sig)?;
 
-            let _ = sigchld.recv().await;
-            let code = match waitpid(child_pid, None) {
+            let code = match wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage).await {
                 Ok(WaitStatus::Exited(_, c)) => c,
                 Ok(WaitStatus::Signaled(_, _, _)) => 128 + 15,
                 _ => 128 + 15,
@@ -385,10 +1006,82 @@ pub async fn run_with_timeout(
 
             metrics.exit_code = code;
             metrics.signal_sent = Some(sig);
-            metrics.log();
+            code
+        }
+
+        // A TimeoutRunner's application gave up on COMMAND: stop it as on
+        // timeout, but with its own status
+        () = cancelled(&mut cancel) => {
+            debug!("select: cancellation arm fired");
+            if in_flight.send(term_signal)? {
+                events.signal_sent(term_signal);
+            }
+            metrics.signal_sent = Some(term_signal);
+
+            let waited = wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage);
+            let status = match kill_after {
+                Some(grace) => match tokio::time::timeout(grace, waited).await {
+                    Ok(status) => status,
+                    Err(_) => {
+                        debug!("cancellation: kill-after passed after {:?}", grace);
+                        if in_flight.send(TimeoutSignal::from(Signal::SIGKILL))? {
+                            events.send(TimeoutEvent::KillSent);
+                        }
+                        wait_for_child(monitor.as_mut(), child_pid, WaitPidFlag::empty(), &mut usage).await
+                    }
+                },
+                None => waited.await,
+            };
+            metrics.elapsed = start_time.elapsed();
+
+            let code = match status {
+                Ok(WaitStatus::Exited(_, c)) => c,
+                Ok(WaitStatus::Signaled(_, sig, _)) => crate::signal::exit_code(sig as i32),
+                _ => EXIT_CANCELED,
+            };
+            metrics.exit_code = code;
             code
         }
     };
 
-    Ok(exit_code)
+    // Every way out of the select above has waited for COMMAND
+    reaper.disarm();
+    metrics.exit_code = exit_code;
+    metrics.extensions = deadline.extensions();
+    metrics.usage = usage;
+    metrics.record_shutdown(sent_signals(&in_flight, start_time), true);
+    metrics.kill_signal_sent = metrics.signals_sent.iter().any(|s| s.signal == "SIGKILL");
+    // The kernel sends SIGXCPU or SIGKILL once RLIMIT_CPU is used up, and
+    // the CPU time, short of the limit by a clock tick at most, shows that
+    // is what killed COMMAND
+    let cpu_used = usage
+        .user_cpu
+        .zip(usage.sys_cpu)
+        .map(|(user, sys)| user + sys);
+    let killed_by_kernel = !metrics.timed_out
+        && !metrics.kill_signal_sent
+        && [Signal::SIGXCPU, Signal::SIGKILL]
+            .iter()
+            .any(|&sig| exit_code == crate::signal::exit_code(sig as i32));
+    if let (Some(limit), Some(used), true) = (cpu_limit, cpu_used, killed_by_kernel) {
+        if used + CPU_TICK >= Duration::from_secs(limit) {
+            events.send(TimeoutEvent::ResourceLimitExceeded {
+                resource: "cpu".to_string(),
+            });
+        }
+    }
+    events.send(TimeoutEvent::Exited { code: exit_code });
+
+    if let Some(output) = output {
+        output.finish().await;
+    }
+    drop(pty_session);
+
+    if let Some(log) = &output_rules.log_file {
+        if metrics.timed_out {
+            log.record_timeout(&metrics);
+        }
+    }
+
+    Ok(metrics)
 }
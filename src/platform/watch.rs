@@ -0,0 +1,301 @@
+// src/platform/watch.rs
+// Watchdog mode: a time limit for a process timeout did not start (--pid)
+
+use super::control::{ControlSocket, Deadline};
+use crate::debug::debug;
+use crate::hook::{Hook, HookContext};
+use crate::logging::{info, warning};
+use crate::rusage::ResourceUsage;
+use crate::{Platform, TimeoutError, TimeoutMetrics, TimeoutSignal};
+use nix::errno::Errno;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use owo_colors::OwoColorize;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(target_os = "linux")]
+use super::monitor::{ChildMonitor, PidfdMonitor};
+
+/// The watched process. It is not our child, so it cannot be reaped or
+/// waited for; exit is seen through a pidfd where the kernel has them, and
+/// otherwise by polling `kill(pid, 0)`.
+struct Watched {
+    pid: Pid,
+    #[cfg(target_os = "linux")]
+    pidfd: Option<PidfdMonitor>,
+    poll: Duration,
+}
+
+impl Watched {
+    /// Check that `pid` exists and that we may signal it, so both problems
+    /// are reported up front rather than at the deadline
+    fn open(pid: Pid, poll: Duration) -> Result<Watched, TimeoutError> {
+        match kill(pid, None) {
+            Ok(()) => {}
+            Err(Errno::ESRCH) => return Err(TimeoutError::NoSuchProcess(pid.as_raw())),
+            Err(Errno::EPERM) => return Err(TimeoutError::SignalNotPermitted(pid.as_raw())),
+            Err(e) => {
+                return Err(TimeoutError::SignalSendFailed {
+                    signal: "0".to_string(),
+                    source: e,
+                })
+            }
+        }
+        Ok(Watched {
+            pid,
+            #[cfg(target_os = "linux")]
+            pidfd: PidfdMonitor::new(pid).ok(),
+            poll,
+        })
+    }
+
+    /// How exit is detected, for --verbose
+    fn method(&self) -> String {
+        #[cfg(target_os = "linux")]
+        if self.pidfd.is_some() {
+            return "pidfd".to_string();
+        }
+        format!("polling every {:?}", self.poll)
+    }
+
+    /// Resolves once the process has exited
+    async fn exited(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(pidfd) = &mut self.pidfd {
+            return pidfd.changed().await;
+        }
+        // A zombie still counts as running until its own parent reaps it
+        loop {
+            tokio::time::sleep(self.poll).await;
+            let alive = kill(self.pid, None);
+            debug!("kill({}, 0) = {:?}", self.pid, alive);
+            if alive == Err(Errno::ESRCH) {
+                return;
+            }
+        }
+    }
+
+    /// Send `signal`; a process that has already exited is not an error
+    fn signal(&self, signal: TimeoutSignal) -> Result<(), TimeoutError> {
+        #[cfg(target_os = "linux")]
+        let result = match &self.pidfd {
+            Some(pidfd) => pidfd.send_signal(signal),
+            None => {
+                Errno::result(unsafe { nix::libc::kill(self.pid.as_raw(), signal.0) }).map(drop)
+            }
+        };
+        #[cfg(not(target_os = "linux"))]
+        let result =
+            Errno::result(unsafe { nix::libc::kill(self.pid.as_raw(), signal.0) }).map(drop);
+        debug!(
+            "signal {} to watched PID {} = {:?}",
+            signal, self.pid, result
+        );
+
+        match result {
+            Ok(()) | Err(Errno::ESRCH) => Ok(()),
+            Err(Errno::EPERM) => Err(TimeoutError::SignalNotPermitted(self.pid.as_raw())),
+            Err(e) => Err(TimeoutError::SignalSendFailed {
+                signal: signal.as_str().to_string(),
+                source: e,
+            }),
+        }
+    }
+}
+
+/// The watched process's name for messages, from /proc where there is one
+fn process_name(pid: Pid) -> String {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_else(|_| format!("PID {}", pid))
+}
+
+/// How `watch_pid` watches the process, from the options of the same names
+pub struct WatchOptions<'a> {
+    pub duration: Duration,
+    pub term_signal: TimeoutSignal,
+    pub kill_after: Option<Duration>,
+    pub verbose: bool,
+    pub no_notify: bool,
+    pub status_on_timeout: Option<i32>,
+    /// --poll-interval, where there is no pidfd to wait on
+    pub poll: Duration,
+    pub extend_step: Option<Duration>,
+    pub boottime: bool,
+    pub on_timeout: Option<&'a Hook>,
+    pub pre_kill: Option<&'a Hook>,
+    pub control_socket: Option<&'a Path>,
+    pub control_socket_force: bool,
+}
+
+/// Apply DURATION to the existing process `pid`: if it is still running
+/// at the deadline, send `term_signal` and then, after `kill_after`,
+/// SIGKILL. Its exit status belongs to its parent, so an exit in time is
+/// reported as 0.
+pub async fn watch_pid(
+    pid: Pid,
+    options: WatchOptions<'_>,
+) -> Result<TimeoutMetrics, TimeoutError> {
+    let WatchOptions {
+        duration,
+        term_signal,
+        kill_after,
+        verbose,
+        no_notify,
+        status_on_timeout,
+        poll,
+        extend_step,
+        boottime,
+        on_timeout,
+        pre_kill,
+        control_socket,
+        control_socket_force,
+    } = options;
+    let start_time = Instant::now();
+    let mut target = Watched::open(pid, poll)?;
+    let command = process_name(pid);
+    let mut metrics = TimeoutMetrics {
+        command: command.clone(),
+        argv: Vec::new(),
+        duration,
+        timed_out: false,
+        timeout_reason: None,
+        matched_line: None,
+        exit_code: 0,
+        signal_sent: None,
+        elapsed: Duration::ZERO,
+        kill_after_used: false,
+        cpu_limit: None,
+        memory_limit: None,
+        stopped_detected: false,
+        platform: Platform::name(),
+        attempt: 1,
+        iteration: 1,
+        retries_timeout: 0,
+        retries_exit: 0,
+        total_elapsed: Duration::ZERO,
+        budget: None,
+        extensions: 0,
+        watched_pid: Some(pid.as_raw()),
+        hook_exit_code: None,
+        started_at: SystemTime::now(),
+        pid: Some(pid.as_raw() as u32),
+        pgid: nix::unistd::getpgid(Some(pid))
+            .ok()
+            .map(|pgid| pgid.as_raw() as u32),
+        // Not our child, so there is nothing to wait4() for
+        usage: ResourceUsage::default(),
+        signals_sent: Vec::new(),
+        kill_signal_sent: false,
+        term_signal_sent_at: None,
+        child_exited_at: None,
+        shutdown_latency: None,
+    };
+
+    if verbose {
+        info!(
+            "{}: watching PID {} ('{}') using {}",
+            "Info".cyan(),
+            pid,
+            command,
+            target.method()
+        );
+    }
+
+    let control = control_socket
+        .map(|path| ControlSocket::bind(path, control_socket_force))
+        .transpose()?;
+    let mut deadline = Deadline::start(&command, duration, extend_step, boottime, verbose, control)
+        .map_err(|e| TimeoutError::SignalSetupFailed {
+            signal: "SIGUSR1/SIGUSR2".to_string(),
+            source: e,
+        })?;
+
+    let reason = tokio::select! {
+        _ = target.exited() => {
+            debug!("select: exit arm fired for PID {}", pid);
+            metrics.elapsed = start_time.elapsed();
+            metrics.extensions = deadline.extensions();
+            if verbose {
+                info!("{}: PID {} exited before the deadline", "Info".green(), pid);
+            }
+            return Ok(metrics);
+        }
+        reason = deadline.wait(None, pid, None) => {
+            debug!("select: deadline arm fired ({})", reason);
+            reason
+        }
+    };
+
+    metrics.extensions = deadline.extensions();
+    metrics.timed_out = true;
+    metrics.timeout_reason = Some(reason);
+
+    // A `kill` request on the control socket skips straight to SIGKILL
+    let (term_signal, kill_after, no_notify) = if deadline.kill_requested() {
+        (TimeoutSignal::from(Signal::SIGKILL), None, false)
+    } else {
+        (term_signal, kill_after, no_notify)
+    };
+    let next_signal = if no_notify {
+        TimeoutSignal::from(Signal::SIGKILL)
+    } else {
+        term_signal
+    };
+    let context = HookContext {
+        pid: metrics.pid,
+        pgid: metrics.pgid,
+        elapsed: start_time.elapsed(),
+        command: &command,
+        signal: Some(next_signal.to_string()),
+        ..Default::default()
+    };
+
+    if let Some(hook) = on_timeout.filter(|_| !deadline.kill_requested()) {
+        metrics.hook_exit_code = Some(hook.run(&context, verbose).await);
+    }
+    // Its status is only reported: the process is signalled regardless
+    if let Some(hook) = pre_kill {
+        let context = HookContext {
+            elapsed: start_time.elapsed(),
+            ..context
+        };
+        hook.run(&context, verbose).await;
+    }
+
+    if !no_notify {
+        if verbose {
+            warning!(
+                "{}: sending signal {} to PID {} ('{}')",
+                "Timeout".red(),
+                term_signal,
+                pid,
+                command
+            );
+        }
+        target.signal(term_signal)?;
+        metrics.signal_sent = Some(term_signal);
+    }
+
+    metrics.exit_code = status_on_timeout.unwrap_or(reason.exit_code());
+    if let Some(grace) = kill_after {
+        metrics.kill_after_used = true;
+        tokio::select! {
+            _ = target.exited() => debug!("select: exit arm fired during the kill-after grace period"),
+            _ = tokio::time::sleep(grace) => {
+                debug!("select: kill-after arm fired after {:?}", grace);
+                if verbose {
+                    warning!("{}: sending signal SIGKILL to PID {} ('{}')", "Kill".bright_red(), pid, command);
+                }
+                target.signal(TimeoutSignal::from(Signal::SIGKILL))?;
+                target.exited().await;
+                metrics.exit_code = 128 + Signal::SIGKILL as i32;
+            }
+        }
+    } else {
+        target.exited().await;
+    }
+    metrics.elapsed = start_time.elapsed();
+    Ok(metrics)
+}
@@ -1,31 +1,172 @@
 // src/platform/windows.rs
 // Windows-specific timeout implementation using tokio async processes
 
-use crate::{Platform, TimeoutError, TimeoutMetrics};
+use super::RunOptions;
+use crate::debug::debug;
+use crate::hook::HookContext;
+use crate::logging::{detail, error, info, warning};
+use crate::output::{wait_for_timeout, OutputWatcher};
+use crate::pid_file::PidFile;
+use crate::rusage::ResourceUsage;
+use crate::{Platform, SentSignal, TimeoutError, TimeoutMetrics, TimeoutReason};
 use owo_colors::OwoColorize;
-use std::time::{Duration, Instant};
+use std::process::Stdio;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::process::Command as TokioCommand;
+use windows_sys::Win32::Foundation::{CloseHandle, DuplicateHandle, DUPLICATE_SAME_ACCESS, HANDLE};
+use windows_sys::Win32::System::Threading::{GetCurrentProcess, CREATE_NO_WINDOW};
 
-const EXIT_TIMEDOUT: i32 = 124;
 const EXIT_CANCELED: i32 = 125;
 const EXIT_CANNOT_INVOKE: i32 = 126;
 const EXIT_ENOENT: i32 = 127;
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Human-readable description of how a command would be run, for --dry-run
+pub fn backend_description(
+    _foreground: bool,
+    _detect_stopped: bool,
+    _pty: bool,
+    no_window: bool,
+) -> String {
+    format!(
+        "windows tokio process, TerminateProcess on timeout{}",
+        if no_window { ", without a console" } else { "" }
+    )
+}
+
+/// Exit code for a command that ended without one of its own, as a
+/// TerminateProcess can leave it. Only put down to the timeout when timeout
+/// terminated it for one (the usual exit code or --status and
+/// --preserve-status then apply); a command terminated from outside is an
+/// error.
+fn unreported_exit_code(reason: Option<TimeoutReason>, terminated: bool, interrupted: bool) -> i32 {
+    match reason {
+        Some(reason) if terminated => reason.exit_code(),
+        _ if interrupted => EXIT_INTERRUPTED,
+        _ => EXIT_CANCELED,
+    }
+}
+
+/// The exit code a Unix shell would report for a command ended by an
+/// exception, whose NTSTATUS Windows gives as its exit code: 128 plus the
+/// number of the signal Unix would have sent it. Any other code is COMMAND's
+/// own and is left alone.
+fn windows_exception_to_exit_code(code: u32) -> i32 {
+    const SIGINT: i32 = 2;
+    const SIGILL: i32 = 4;
+    const SIGTRAP: i32 = 5;
+    const SIGABRT: i32 = 6;
+    const SIGFPE: i32 = 8;
+    const SIGSEGV: i32 = 11;
+    let signal = match code {
+        0xC000013A => SIGINT,  // STATUS_CONTROL_C_EXIT
+        0xC000001D => SIGILL,  // STATUS_ILLEGAL_INSTRUCTION
+        0xC0000096 => SIGILL,  // STATUS_PRIVILEGED_INSTRUCTION
+        0x80000003 => SIGTRAP, // STATUS_BREAKPOINT
+        0xC0000409 => SIGABRT, // STATUS_STACK_BUFFER_OVERRUN, also raised by abort()
+        0xC000008E => SIGFPE,  // STATUS_FLOAT_DIVIDE_BY_ZERO
+        0xC0000094 => SIGFPE,  // STATUS_INTEGER_DIVIDE_BY_ZERO
+        0xC0000095 => SIGFPE,  // STATUS_INTEGER_OVERFLOW
+        0xC0000005 => SIGSEGV, // STATUS_ACCESS_VIOLATION
+        0xC00000FD => SIGSEGV, // STATUS_STACK_OVERFLOW
+        0xC0000006 => SIGSEGV, // STATUS_IN_PAGE_ERROR
+        _ => return code as i32,
+    };
+    128 + signal
+}
+
+/// A handle of timeout's own to COMMAND, which stays valid after tokio has
+/// waited for it and closed its handle, so what COMMAND used can still be
+/// read
+struct ProcessHandle(HANDLE);
+
+impl ProcessHandle {
+    fn duplicate(child: &tokio::process::Child) -> Option<ProcessHandle> {
+        let source = child.raw_handle()? as HANDLE;
+        let mut handle: HANDLE = 0;
+        let duplicated = unsafe {
+            DuplicateHandle(
+                GetCurrentProcess(),
+                source,
+                GetCurrentProcess(),
+                &mut handle,
+                0,
+                0,
+                DUPLICATE_SAME_ACCESS,
+            )
+        };
+        (duplicated != 0).then_some(ProcessHandle(handle))
+    }
+}
+
+impl Drop for ProcessHandle {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// A TerminateProcess sent now, for the metrics' signals_sent and -vv
+fn terminated_at(start_time: Instant, pid: Option<u32>) -> SentSignal {
+    if let Some(pid) = pid {
+        detail!("{}: sent TERMINATE to process {}", "Signal".cyan(), pid);
+    }
+    crate::progress::sent("TERMINATE");
+    SentSignal {
+        signal: "TERMINATE".to_string(),
+        offset: start_time.elapsed(),
+    }
+}
+
+/// Wait for the next deadline: the initial timeout (returning why it fired),
+/// then the end of the kill-after grace period, then short re-checks
+async fn next_deadline(
+    duration: Duration,
+    kill_phase_end: Option<Instant>,
+    initial_timeout_expired: bool,
+    output: Option<&OutputWatcher>,
+) -> Option<TimeoutReason> {
+    if !initial_timeout_expired {
+        return Some(wait_for_timeout(duration, output).await);
+    }
+
+    match kill_phase_end {
+        Some(end) => tokio::time::sleep_until(end.into()).await,
+        None => tokio::time::sleep(Duration::from_millis(100)).await,
+    }
+    None
+}
 
-#[allow(clippy::too_many_arguments)]
 pub async fn run_with_timeout(
     command: &str,
     args: &[String],
-    duration: Duration,
-    kill_after: Option<Duration>,
-    preserve_status: bool,
-    verbose: bool,
-    status_on_timeout: Option<i32>,
-) -> Result<i32, TimeoutError> {
+    options: RunOptions<'_>,
+) -> Result<TimeoutMetrics, TimeoutError> {
+    let RunOptions {
+        duration,
+        kill_after,
+        preserve_status,
+        verbose,
+        status_on_timeout,
+        output_rules,
+        redirects,
+        no_window,
+        pid_file,
+        pid_file_force,
+        on_timeout,
+        pre_kill,
+        notifier,
+    } = options;
     let start_time = Instant::now();
     let mut metrics = TimeoutMetrics {
         command: command.to_string(),
+        argv: std::iter::once(command)
+            .chain(args.iter().map(String::as_str))
+            .map(String::from)
+            .collect(),
         duration,
         timed_out: false,
+        timeout_reason: None,
+        matched_line: None,
         exit_code: 0,
         signal_sent: None,
         elapsed: Duration::ZERO,
@@ -34,6 +175,24 @@ pub async fn run_with_timeout(
         memory_limit: None,
         stopped_detected: false,
         platform: Platform::name(),
+        attempt: 1,
+        iteration: 1,
+        retries_timeout: 0,
+        retries_exit: 0,
+        total_elapsed: Duration::ZERO,
+        budget: None,
+        extensions: 0,
+        watched_pid: None,
+        hook_exit_code: None,
+        started_at: SystemTime::now(),
+        pid: None,
+        pgid: None,
+        usage: ResourceUsage::default(),
+        signals_sent: Vec::new(),
+        kill_signal_sent: false,
+        term_signal_sent_at: None,
+        child_exited_at: None,
+        shutdown_latency: None,
     };
 
     // Setup Ctrl+C handling for the timeout process itself
@@ -44,9 +203,37 @@ pub async fn run_with_timeout(
             source: e,
         })?;
 
+    let mut pid_file = pid_file
+        .map(|path| PidFile::create(path, pid_file_force))
+        .transpose()?;
+
     // Spawn the child command
     let mut cmd = TokioCommand::new(command);
     cmd.args(args);
+    // A console program started this way gets no console; GUI programs
+    // ignore the flag
+    if no_window {
+        cmd.creation_flags(CREATE_NO_WINDOW);
+    }
+    if output_rules.needs_pipes() {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let [stdin, stdout, stderr] =
+        redirects
+            .child_stdio()
+            .map_err(|e| TimeoutError::ExecFailed {
+                cmd: command.to_string(),
+                source: e,
+            })?;
+    if let Some(stdin) = stdin {
+        cmd.stdin(stdin);
+    }
+    if let Some(stdout) = stdout {
+        cmd.stdout(stdout);
+    }
+    if let Some(stderr) = stderr {
+        cmd.stderr(stderr);
+    }
 
     let mut child = cmd.spawn().map_err(|e| {
         let exit_code = match e.kind() {
@@ -54,7 +241,7 @@ pub async fn run_with_timeout(
             std::io::ErrorKind::PermissionDenied => EXIT_CANNOT_INVOKE,
             _ => EXIT_CANNOT_INVOKE,
         };
-        eprintln!(
+        error!(
             "{}: failed to execute command '{}': {}",
             "Error".red(),
             command,
@@ -66,10 +253,28 @@ pub async fn run_with_timeout(
         }
     })?;
 
+    let output = match (child.stdout.take(), child.stderr.take()) {
+        (Some(stdout), Some(stderr)) => {
+            Some(OutputWatcher::start(output_rules.clone(), stdout, stderr))
+        }
+        _ => None,
+    };
+
     let child_pid = child.id();
+    let process = ProcessHandle::duplicate(&child);
+    metrics.pid = child_pid;
+    metrics.pgid = child_pid;
+    if let (Some(pid_file), Some(pid)) = (&mut pid_file, child_pid) {
+        if let Err(e) = pid_file.write(pid, None) {
+            // Whoever asked for the file cannot find COMMAND without it
+            let _ = child.kill().await;
+            return Err(e);
+        }
+    }
+    let _watchdog = notifier.and_then(|notifier| notifier.started(child_pid.unwrap_or_default()));
     if verbose {
         if let Some(pid) = child_pid {
-            eprintln!(
+            info!(
                 "{}: Started command '{}' with PID {}.",
                 "Info".cyan(),
                 command,
@@ -84,102 +289,217 @@ pub async fn run_with_timeout(
 
     let mut initial_timeout_expired = false;
     let mut final_terminate_sent = false;
+    // Ctrl+C made timeout terminate the command
+    let mut interrupted = false;
+    // Every TerminateProcess, for the metrics
+    let mut signals = Vec::new();
+    let mut reaped = false;
+    // Phase 2 ends kill_after after the initial timeout actually fired,
+    // which may be before DURATION when the idle timeout triggers
+    let mut kill_phase_end = None;
 
-    loop {
-        // Determine the next timeout based on current state
-        let timeout_future = if !initial_timeout_expired {
-            // Phase 1: Wait for the initial timeout duration
-            tokio::time::sleep(timeout_duration)
-        } else if !final_terminate_sent && !kill_after_duration.is_zero() {
-            // Phase 2: Wait for the kill_after duration
-            let kill_phase_end = start_time + timeout_duration + kill_after_duration;
-            let remaining = kill_phase_end.saturating_duration_since(Instant::now());
-            tokio::time::sleep(remaining)
+    let exit_code = loop {
+        let phase_end = if !final_terminate_sent && !kill_after_duration.is_zero() {
+            kill_phase_end
         } else {
-            // Wait briefly for process to exit after termination
-            tokio::time::sleep(Duration::from_millis(100))
+            None
         };
 
         tokio::select! {
-            _ = timeout_future => {
-                if !initial_timeout_expired {
+            reason = next_deadline(timeout_duration, phase_end, initial_timeout_expired, output.as_ref()) => {
+                debug!("select: deadline arm fired ({:?})", reason);
+                if let Some(reason) = reason {
                     // Initial timeout has expired
+                    metrics.matched_line = output.as_ref().and_then(|o| o.matched_line());
+                    if let Some(notifier) = notifier.filter(|_| reason.is_timeout()) {
+                        notifier.stopping();
+                    }
                     if verbose {
-                        eprintln!("{}: Initial timeout ({:?}) expired.", "Timeout".red(), timeout_duration);
+                        if let Some(line) = &metrics.matched_line {
+                            info!("{}: {} matched: {}", "Pattern".cyan(), reason, line);
+                        }
+                    }
+
+                    // The hook gets to look at COMMAND while it is still running
+                    if let (Some(hook), true) = (on_timeout, reason.is_timeout()) {
+                        let context = HookContext {
+                            pid: metrics.pid,
+                            pgid: metrics.pgid,
+                            elapsed: start_time.elapsed(),
+                            command,
+                            signal: Some("TERMINATE".to_string()),
+                            ..Default::default()
+                        };
+                        metrics.hook_exit_code = Some(hook.run(&context, verbose).await);
+                    }
+
+                    if reason == TimeoutReason::SuccessPattern && output_rules.keep_running {
+                        if verbose {
+                            info!("{}: Leaving command '{}' running (--no-kill).", "Info".cyan(), command);
+                        }
+                        metrics.timeout_reason = Some(reason);
+                        metrics.elapsed = start_time.elapsed();
+                        metrics.exit_code = 0;
+                        return Ok(metrics);
+                    }
+
+                    // Dropping `child` does not terminate it, so returning is enough
+                    if matches!(reason, TimeoutReason::Duration | TimeoutReason::Idle) && output_rules.keep_running {
+                        warning!(
+                            "{}: Command '{}' timed out ({}), leaving it running (--no-kill): PID {}.",
+                            "Timeout".red(),
+                            command,
+                            reason,
+                            child_pid.map(|pid| pid.to_string()).unwrap_or_else(|| "unknown".to_string())
+                        );
+                        metrics.timed_out = true;
+                        metrics.timeout_reason = Some(reason);
+                        metrics.elapsed = start_time.elapsed();
+                        metrics.exit_code = status_on_timeout.unwrap_or(reason.exit_code());
+                        if let Some(log) = &output_rules.log_file {
+                            log.record_timeout(&metrics);
+                        }
+                        return Ok(metrics);
+                    }
+
+                    if verbose {
+                        match reason {
+                            TimeoutReason::Idle => warning!("{}: No output for {:?}.", "Idle".red(), output_rules.idle_timeout.unwrap_or_default()),
+                            TimeoutReason::Duration => warning!("{}: Initial timeout ({:?}) expired.", "Timeout".red(), timeout_duration),
+                            TimeoutReason::OutputLimit
+                            | TimeoutReason::KillPattern
+                            | TimeoutReason::SuccessPattern => {}
+                        }
+                    }
+                    if reason == TimeoutReason::OutputLimit {
+                        error!("{}: output limit exceeded, stopping command '{}'", "timeout".red(), command);
+                    }
+                    // Its status is only reported: COMMAND is terminated regardless
+                    if let (Some(hook), true) = (pre_kill, reason.is_timeout()) {
+                        let context = HookContext {
+                            pid: metrics.pid,
+                            pgid: metrics.pgid,
+                            elapsed: start_time.elapsed(),
+                            command,
+                            signal: Some("TERMINATE".to_string()),
+                            ..Default::default()
+                        };
+                        hook.run(&context, verbose).await;
                     }
                     initial_timeout_expired = true;
-                    metrics.timed_out = true;
+                    crate::progress::expired();
+                    kill_phase_end = Some(Instant::now() + kill_after_duration);
+                    metrics.timed_out = reason.is_timeout();
+                    metrics.timeout_reason = Some(reason);
                     metrics.signal_sent = Some("TERMINATE".to_string());
 
                     if kill_after_duration.is_zero() {
                         // No grace period, terminate immediately
                         if verbose {
-                            eprintln!("{}: Terminating process (no kill-after grace period).", "Info".cyan());
+                            info!("{}: Terminating process (no kill-after grace period).", "Info".cyan());
                         }
-                        if let Err(e) = child.kill().await {
-                            eprintln!("{}: Failed to terminate child process: {}", "Error".red(), e);
+                        let result = child.kill().await;
+                        debug!("TerminateProcess({:?}) = {:?}", child_pid, result);
+                        if let Err(e) = result {
+                            error!("{}: Failed to terminate child process: {}", "Error".red(), e);
                         }
+                        signals.push(terminated_at(start_time, child_pid));
                         final_terminate_sent = true;
                     }
                     // If kill_after is non-zero, continue to next iteration
                 } else if !final_terminate_sent {
                     // Kill-after duration has expired
                     if verbose {
-                        eprintln!("{}: Kill-after duration ({:?}) expired. Sending final terminate.", "Kill".bright_red(), kill_after_duration);
+                        warning!("{}: Kill-after duration ({:?}) expired. Sending final terminate.", "Kill".bright_red(), kill_after_duration);
                     }
                     metrics.kill_after_used = true;
-                    if let Err(e) = child.kill().await {
-                        eprintln!("{}: Failed to terminate child process: {}", "Error".red(), e);
+                    let result = child.kill().await;
+                    debug!("TerminateProcess({:?}) = {:?}", child_pid, result);
+                    if let Err(e) = result {
+                        error!("{}: Failed to terminate child process: {}", "Error".red(), e);
                     }
+                    signals.push(terminated_at(start_time, child_pid));
                     final_terminate_sent = true;
                 }
             }
 
             result = child.wait() => {
+                debug!("select: child arm fired with {:?}", result);
                 match result {
                     Ok(status) => {
                         metrics.elapsed = start_time.elapsed();
-                        let code = status.code().unwrap_or(EXIT_CANCELED);
+                        reaped = true;
+                        if let Some(process) = &process {
+                            metrics.usage = ResourceUsage::of_process(process.0);
+                        }
+                        let code = match status.code() {
+                            Some(code) => windows_exception_to_exit_code(code as u32),
+                            None => {
+                                debug!("no exit code for {:?}", child_pid);
+                                unreported_exit_code(metrics.timeout_reason, final_terminate_sent, interrupted)
+                            }
+                        };
 
                         if verbose {
-                            eprintln!("{}: Child exited with code {}.", "Info".green(), code);
+                            info!("{}: Child exited with code {}.", "Info".green(), code);
                         }
 
                         // Determine final exit code
-                        metrics.exit_code = if metrics.timed_out {
-                            if let Some(custom_status) = status_on_timeout {
-                                custom_status
-                            } else if preserve_status {
-                                code
-                            } else {
-                                EXIT_TIMEDOUT
+                        metrics.exit_code = match metrics.timeout_reason {
+                            Some(reason) if !reason.is_timeout() => reason.exit_code(),
+                            Some(reason) => {
+                                if let Some(custom_status) = status_on_timeout {
+                                    custom_status
+                                } else if preserve_status {
+                                    code
+                                } else {
+                                    reason.exit_code()
+                                }
                             }
-                        } else {
-                            code
+                            None => code,
                         };
 
-                        metrics.log();
-                        return Ok(metrics.exit_code);
+                        break metrics.exit_code;
                     }
                     Err(e) => {
-                        eprintln!("{}: Error waiting for child: {}", "Error".red(), e);
+                        error!("{}: Error waiting for child: {}", "Error".red(), e);
                         metrics.elapsed = start_time.elapsed();
                         metrics.exit_code = EXIT_CANCELED;
-                        metrics.log();
-                        return Ok(EXIT_CANCELED);
+                        break EXIT_CANCELED;
                     }
                 }
             }
 
             _ = ctrl_c_stream.recv() => {
+                debug!("select: Ctrl+C arm fired");
                 if verbose {
-                    eprintln!("{}: Received Ctrl+C for timeout process. Terminating child.", "Signal".yellow());
+                    warning!("{}: Received Ctrl+C for timeout process. Terminating child.", "Signal".yellow());
                 }
-                if let Err(e) = child.kill().await {
-                    eprintln!("{}: Failed to terminate child process on Ctrl+C: {}", "Error".red(), e);
+                interrupted = true;
+                let result = child.kill().await;
+                debug!("TerminateProcess({:?}) = {:?}", child_pid, result);
+                if let Err(e) = result {
+                    error!("{}: Failed to terminate child process on Ctrl+C: {}", "Error".red(), e);
                 }
+                signals.push(terminated_at(start_time, child_pid));
                 // Continue loop to wait for child exit
             }
         }
+    };
+
+    metrics.exit_code = exit_code;
+    metrics.kill_signal_sent = metrics.kill_after_used;
+    metrics.record_shutdown(signals, reaped);
+
+    if let Some(output) = output {
+        output.finish().await;
+    }
+
+    if let Some(log) = &output_rules.log_file {
+        if metrics.timed_out {
+            log.record_timeout(&metrics);
+        }
     }
+
+    Ok(metrics)
 }
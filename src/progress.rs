@@ -0,0 +1,177 @@
+// src/progress.rs
+// --progress: a self-updating line on stderr counting down to the deadline,
+// redrawn with \r and erased before any other message of timeout's
+
+use crate::progress_format::{self, ProgressLine, Stage};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// The run being shown, if any
+static STATE: Mutex<Option<State>> = Mutex::new(None);
+
+/// How many characters of the line are on the terminal, 0 for none
+static DRAWN: AtomicUsize = AtomicUsize::new(0);
+
+struct State {
+    started: Instant,
+    limit: Option<Duration>,
+    action: String,
+    kill_after: Option<Duration>,
+    expired: Option<Instant>,
+    /// The last signal sent after the deadline, and when
+    sent: Option<(String, Instant)>,
+}
+
+/// Shows the line until dropped, which erases it
+pub struct Progress {
+    task: Option<JoinHandle<()>>,
+}
+
+impl Progress {
+    /// Start redrawing the line every `interval` for a run of COMMAND
+    /// limited to `limit` (`Duration::MAX` for none). Nothing is shown
+    /// when stderr is not a terminal, with --quiet, or while another run
+    /// already has the line.
+    pub fn start(
+        limit: Duration,
+        action: &str,
+        kill_after: Option<Duration>,
+        interval: Duration,
+    ) -> Progress {
+        let idle = Progress { task: None };
+        if !std::io::stderr().is_terminal() || crate::logging::quietness() > 0 {
+            return idle;
+        }
+        let mut state = STATE.lock().unwrap();
+        if state.is_some() {
+            return idle;
+        }
+        *state = Some(State {
+            started: Instant::now(),
+            limit: Some(limit).filter(|&l| l != Duration::MAX),
+            action: action.to_string(),
+            kill_after,
+            expired: None,
+            sent: None,
+        });
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            loop {
+                ticks.tick().await;
+                draw();
+            }
+        });
+        Progress { task: Some(task) }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        let Some(task) = self.task.take() else {
+            return;
+        };
+        task.abort();
+        let mut state = STATE.lock().unwrap();
+        *state = None;
+        erase();
+    }
+}
+
+/// Erase the line, if it is showing, so a message can be printed in its
+/// place. The next redraw brings it back below the message.
+pub fn clear() {
+    if DRAWN.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let _state = STATE.lock().unwrap();
+    erase();
+}
+
+/// The deadline was pushed back by `amount`
+pub fn extend(amount: Duration) {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.limit = state.limit.map(|limit| limit.saturating_add(amount));
+    }
+}
+
+/// The deadline passed, or an output rule or control request ended the
+/// run early
+pub fn expired() {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.expired.get_or_insert_with(Instant::now);
+    }
+}
+
+/// `signal` went to COMMAND to stop it
+pub fn sent(signal: &str) {
+    if let Some(state) = STATE.lock().unwrap().as_mut() {
+        state.expired.get_or_insert_with(Instant::now);
+        state.sent = Some((signal.to_string(), Instant::now()));
+    }
+}
+
+fn draw() {
+    let state = STATE.lock().unwrap();
+    let Some(state) = state.as_ref() else {
+        return;
+    };
+    let stage = match (&state.sent, state.expired) {
+        (Some((signal, at)), _) => Stage::Stopping {
+            signal: Some(signal),
+            since: at.elapsed(),
+        },
+        (None, Some(at)) => Stage::Stopping {
+            signal: None,
+            since: at.elapsed(),
+        },
+        (None, None) => Stage::Running,
+    };
+    let line = progress_format::render(
+        &ProgressLine {
+            elapsed: state.started.elapsed(),
+            limit: state.limit,
+            action: &state.action,
+            kill_after: state.kill_after,
+            stage,
+        },
+        terminal_width(),
+    );
+    let mut stderr = std::io::stderr().lock();
+    // Spaces rather than an erase-line sequence, which older Windows
+    // consoles do not understand
+    let drawn = DRAWN.swap(line.chars().count(), Ordering::Relaxed);
+    let padding = drawn.saturating_sub(line.chars().count());
+    let _ = write!(stderr, "\r{}{}", line, " ".repeat(padding));
+    let _ = stderr.flush();
+}
+
+/// Blank out whatever `draw` left on the line. Call with STATE locked.
+fn erase() {
+    let drawn = DRAWN.swap(0, Ordering::Relaxed);
+    if drawn > 0 {
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{}\r", " ".repeat(drawn));
+        let _ = stderr.flush();
+    }
+}
+
+/// Columns of the terminal stderr is on: its window size, else COLUMNS,
+/// else 80
+fn terminal_width() -> usize {
+    #[cfg(unix)]
+    {
+        let mut size: nix::libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { nix::libc::ioctl(2, nix::libc::TIOCGWINSZ, &mut size) } != -1 && size.ws_col > 0
+        {
+            return size.ws_col as usize;
+        }
+    }
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns| columns > 0)
+        .unwrap_or(80)
+}
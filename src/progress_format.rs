@@ -0,0 +1,87 @@
+// src/progress_format.rs
+// The --progress line, e.g. "⏱ 03:12 / 10:00, SIGTERM in 06:48" while
+// COMMAND runs and "⏱ 10:03 / 10:00, sent SIGTERM, killing in 00:07" once
+// it is being stopped
+
+use std::time::Duration;
+
+/// Where the run is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage<'a> {
+    /// Counting down to the deadline
+    Running,
+    /// The deadline has passed. `signal` is the last one sent, `since` ago;
+    /// with none sent yet (--no-notify, or an --on-timeout hook still
+    /// running) `since` counts from the deadline.
+    Stopping {
+        signal: Option<&'a str>,
+        since: Duration,
+    },
+}
+
+/// What the line is made from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressLine<'a> {
+    pub elapsed: Duration,
+    /// The time limit; `None` for no limit
+    pub limit: Option<Duration>,
+    /// What happens at the deadline: a signal name, or `timeout` when
+    /// nothing is sent then
+    pub action: &'a str,
+    pub kill_after: Option<Duration>,
+    pub stage: Stage<'a>,
+}
+
+/// The line for `line`, cut to fit `width` terminal columns. One column
+/// is kept spare, as some terminals draw the clock sign two wide.
+pub fn render(line: &ProgressLine, width: usize) -> String {
+    let mut text = format!("⏱ {}", clock(line.elapsed));
+    if let Some(limit) = line.limit {
+        text.push_str(&format!(" / {}", clock(limit)));
+    }
+    match line.stage {
+        Stage::Running => match line.limit {
+            Some(limit) => text.push_str(&format!(
+                ", {} in {}",
+                line.action,
+                clock_up(limit.saturating_sub(line.elapsed))
+            )),
+            None => text.push_str(", no time limit"),
+        },
+        Stage::Stopping { signal, since } => {
+            match signal {
+                Some(signal) => text.push_str(&format!(", sent {}", signal)),
+                None => text.push_str(", timed out"),
+            }
+            match line.kill_after {
+                Some(grace) if !matches!(signal, Some("SIGKILL" | "TERMINATE")) => text.push_str(
+                    &format!(", killing in {}", clock_up(grace.saturating_sub(since))),
+                ),
+                _ if signal.is_some() => text.push_str(", waiting for exit"),
+                _ => {}
+            }
+        }
+    }
+    text.chars().take(width.saturating_sub(2)).collect()
+}
+
+/// `MM:SS`, or `H:MM:SS` from an hour on, rounded down
+pub fn clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 3600 {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    } else {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    }
+}
+
+/// As `clock`, but rounded up, so a countdown reads 00:00 only once it
+/// has run out
+fn clock_up(duration: Duration) -> String {
+    let whole = Duration::from_secs(duration.as_secs());
+    clock(if duration > whole {
+        whole + Duration::from_secs(1)
+    } else {
+        whole
+    })
+}
@@ -0,0 +1,102 @@
+// src/prom.rs
+// --prom-textfile: rewrite a node_exporter textfile-collector file after
+// each run
+
+use crate::logging::{info, warning};
+use crate::prom_textfile::{self, LastRun};
+use crate::sink::{Outcome, OutcomeSink};
+use owo_colors::OwoColorize;
+use std::fs;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// A --prom-textfile PATH
+#[derive(Debug)]
+pub struct PromTextfile {
+    pub path: String,
+    /// Held across each read-modify-write, so runs of one timeout (--every,
+    /// --parallel) do not lose each other's counts
+    lock: Mutex<()>,
+}
+
+impl PromTextfile {
+    pub fn new(path: &str) -> PromTextfile {
+        PromTextfile {
+            path: path.to_string(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Read the counts already in the file, add this run and replace the
+    /// file by renaming a new one over it, so the collector never reads
+    /// half a file. The temporary file sits next to PATH, without the .prom
+    /// extension the collector looks for.
+    fn update(&self, outcome: &Outcome) -> Result<(), String> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut counts = match fs::read_to_string(&self.path) {
+            Ok(text) => prom_textfile::parse_runs_total(&text),
+            Err(e) if e.kind() == ErrorKind::NotFound => Default::default(),
+            Err(e) => return Err(format!("cannot read it: {}", e)),
+        };
+        let run = LastRun {
+            command: outcome.command,
+            outcome: outcome.name(),
+            timestamp: outcome
+                .ended
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs_f64(),
+            duration: outcome.metrics.map(|m| m.total_elapsed.as_secs_f64()),
+            exit_code: outcome.exit_code,
+            timed_out: outcome.timed_out(),
+        };
+        prom_textfile::merge(&mut counts, &run);
+
+        let temp = format!("{}.{}.tmp", self.path, std::process::id());
+        let written = fs::write(&temp, prom_textfile::render(&run, &counts))
+            .and_then(|()| fs::rename(&temp, &self.path));
+        written.map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            e.to_string()
+        })
+    }
+}
+
+impl OutcomeSink for PromTextfile {
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(async move {
+            match self.update(outcome) {
+                Ok(()) => {
+                    if verbose {
+                        info!(
+                            "{}: wrote Prometheus metrics to {}",
+                            "Info".cyan(),
+                            self.path
+                        );
+                    }
+                    true
+                }
+                Err(e) => {
+                    warning!(
+                        "{}: --prom-textfile {}: {}",
+                        "Warning".yellow(),
+                        self.path,
+                        e
+                    );
+                    false
+                }
+            }
+        })
+    }
+
+    fn is_log(&self) -> bool {
+        false
+    }
+}
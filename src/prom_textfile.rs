@@ -0,0 +1,168 @@
+// src/prom_textfile.rs
+// The Prometheus exposition-format file --prom-textfile keeps for
+// node_exporter's textfile collector: gauges for the last run and a
+// timeout_runs_total counter carried over from the file already there
+
+use std::collections::BTreeMap;
+
+/// The outcomes timeout_runs_total counts
+pub const OUTCOMES: [&str; 4] = ["success", "failure", "timeout", "exec_error"];
+
+/// timeout_runs_total so far, by command and outcome label
+pub type RunCounts = BTreeMap<(String, String), f64>;
+
+/// What the gauges describe: the run that just finished
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastRun<'a> {
+    pub command: &'a str,
+    /// One of OUTCOMES
+    pub outcome: &'a str,
+    /// When it ended, in seconds since the Unix epoch
+    pub timestamp: f64,
+    /// `None` when COMMAND could not be run
+    pub duration: Option<f64>,
+    pub exit_code: i32,
+    pub timed_out: bool,
+}
+
+/// `value` escaped for a label value: backslash, double quote and line feed
+pub fn escape_label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str(r"\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str(r"\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The timeout_runs_total samples of an existing file. Comments, other
+/// metrics and samples that do not parse are skipped, so a damaged file
+/// starts its counts again rather than stopping the write.
+pub fn parse_runs_total(text: &str) -> RunCounts {
+    let mut counts = RunCounts::new();
+    for line in text.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("timeout_runs_total{") else {
+            continue;
+        };
+        let Some((labels, rest)) = parse_labels(rest) else {
+            continue;
+        };
+        // The value, then perhaps a timestamp
+        let Some(value) = rest
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse::<f64>().ok())
+            .filter(|v| v.is_finite() && *v >= 0.0)
+        else {
+            continue;
+        };
+        let label = |name: &str| {
+            labels
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        };
+        if let (Some(command), Some(outcome)) = (label("command"), label("outcome")) {
+            *counts.entry((command, outcome)).or_insert(0.0) += value;
+        }
+    }
+    counts
+}
+
+/// Parse `name="value",...}` up to and including the closing brace,
+/// returning the unescaped labels and what follows
+fn parse_labels(mut s: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let mut labels = Vec::new();
+    loop {
+        s = s.trim_start();
+        if let Some(rest) = s.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+        let (name, rest) = s.split_once('=')?;
+        let quoted = rest.trim_start().strip_prefix('"')?;
+        let mut chars = quoted.char_indices();
+        let mut value = String::new();
+        let end = loop {
+            match chars.next()? {
+                (i, '"') => break i,
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (_, c) => value.push(c),
+            }
+        };
+        labels.push((name.trim().to_string(), value));
+        s = quoted[end + 1..].trim_start();
+        s = s.strip_prefix(',').unwrap_or(s);
+    }
+}
+
+/// Count `run` in `counts`, making sure every outcome of its command has a
+/// sample so rate() sees the first of each
+pub fn merge(counts: &mut RunCounts, run: &LastRun) {
+    for outcome in OUTCOMES {
+        counts
+            .entry((run.command.to_string(), outcome.to_string()))
+            .or_insert(0.0);
+    }
+    *counts
+        .entry((run.command.to_string(), run.outcome.to_string()))
+        .or_insert(0.0) += 1.0;
+}
+
+/// The whole file: the last run's gauges, labelled with its command, and
+/// every timeout_runs_total sample in `counts`
+pub fn render(run: &LastRun, counts: &RunCounts) -> String {
+    let command = format!("command=\"{}\"", escape_label(run.command));
+    let mut gauges = vec![
+        (
+            "timeout_last_run_timestamp_seconds",
+            "When the last run ended, in seconds since the Unix epoch",
+            run.timestamp,
+        ),
+        (
+            "timeout_last_run_exit_code",
+            "timeout's exit code for the last run",
+            f64::from(run.exit_code),
+        ),
+        (
+            "timeout_last_run_timed_out",
+            "1 if the last run hit its time limit",
+            f64::from(u8::from(run.timed_out)),
+        ),
+    ];
+    if let Some(duration) = run.duration {
+        gauges.insert(
+            1,
+            (
+                "timeout_last_run_duration_seconds",
+                "How long the last run took",
+                duration,
+            ),
+        );
+    }
+
+    let mut text = String::new();
+    for (name, help, value) in gauges {
+        text.push_str(&format!(
+            "# HELP {0} {1}\n# TYPE {0} gauge\n{0}{{{2}}} {3}\n",
+            name, help, command, value
+        ));
+    }
+    text.push_str("# HELP timeout_runs_total Runs by how they ended\n");
+    text.push_str("# TYPE timeout_runs_total counter\n");
+    for ((command, outcome), value) in counts {
+        text.push_str(&format!(
+            "timeout_runs_total{{command=\"{}\",outcome=\"{}\"}} {}\n",
+            escape_label(command),
+            escape_label(outcome),
+            value
+        ));
+    }
+    text
+}
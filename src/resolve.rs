@@ -0,0 +1,126 @@
+// src/resolve.rs
+// PATH resolution for COMMAND, used for --check and the pre-fork check
+
+use crate::TimeoutError;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Search path used when PATH is unset, matching glibc's execvp default
+const DEFAULT_PATH: &str = "/usr/local/bin:/usr/bin:/bin";
+
+/// Outcome of checking a single candidate path
+enum Candidate {
+    Executable,
+    NotExecutable,
+    Missing,
+}
+
+#[cfg(unix)]
+fn check_candidate(path: &Path) -> Candidate {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let meta = match path.metadata() {
+        Ok(meta) => meta,
+        Err(_) => return Candidate::Missing,
+    };
+
+    // Directories pass access(X_OK) but exec fails with EACCES
+    if meta.is_dir() {
+        return Candidate::NotExecutable;
+    }
+
+    let c_path = match CString::new(path.as_os_str().as_bytes()) {
+        Ok(p) => p,
+        Err(_) => return Candidate::Missing,
+    };
+    if unsafe { nix::libc::access(c_path.as_ptr(), nix::libc::X_OK) } == 0 {
+        Candidate::Executable
+    } else {
+        Candidate::NotExecutable
+    }
+}
+
+#[cfg(not(unix))]
+fn check_candidate(path: &Path) -> Candidate {
+    match path.metadata() {
+        Ok(meta) if meta.is_dir() => Candidate::NotExecutable,
+        Ok(_) => Candidate::Executable,
+        Err(_) => Candidate::Missing,
+    }
+}
+
+/// File names to try for `name` in a single directory
+#[cfg(windows)]
+fn candidate_names(name: &str) -> Vec<String> {
+    if Path::new(name).extension().is_some() {
+        return vec![name.to_string()];
+    }
+    let exts = env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    exts.split(';')
+        .filter(|ext| !ext.is_empty())
+        .map(|ext| format!("{}{}", name, ext))
+        .collect()
+}
+
+#[cfg(not(windows))]
+fn candidate_names(name: &str) -> Vec<String> {
+    vec![name.to_string()]
+}
+
+fn has_separator(command: &str) -> bool {
+    if cfg!(windows) {
+        command.contains('/') || command.contains('\\')
+    } else {
+        command.contains('/')
+    }
+}
+
+/// Resolve COMMAND the way exec would: names containing a slash are used
+/// as-is, anything else is searched for in PATH.
+///
+/// Returns `CommandNotFound` (127) when no candidate exists, and
+/// `PermissionDenied` (126) when a candidate exists but cannot be executed
+/// (including directories). Both list the candidates that were tried.
+pub fn resolve_command(command: &str) -> Result<PathBuf, TimeoutError> {
+    let mut candidates = Vec::new();
+
+    if has_separator(command) {
+        candidates.push(PathBuf::from(command));
+    } else {
+        let path_var = env::var_os("PATH").unwrap_or_else(|| DEFAULT_PATH.into());
+        for dir in env::split_paths(&path_var) {
+            // An empty PATH entry means the current directory
+            let dir = if dir.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                dir
+            };
+            for name in candidate_names(command) {
+                candidates.push(dir.join(name));
+            }
+        }
+    }
+
+    let mut denied = false;
+    for candidate in &candidates {
+        match check_candidate(candidate) {
+            Candidate::Executable => return Ok(candidate.clone()),
+            Candidate::NotExecutable => denied = true,
+            Candidate::Missing => {}
+        }
+    }
+
+    let tried = candidates
+        .iter()
+        .map(|c| c.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let detail = format!("{} (tried: {})", command, tried);
+
+    if denied {
+        Err(TimeoutError::PermissionDenied(detail))
+    } else {
+        Err(TimeoutError::CommandNotFound(detail))
+    }
+}
@@ -0,0 +1,185 @@
+// src/retry.rs
+// When and how long to wait before running a timed-out command again
+
+use crate::{TimeoutError, TimeoutMetrics};
+use serde::Serialize;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+/// How the delay grows between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryBackoff {
+    /// Wait the same delay every time
+    Fixed,
+    /// Double the delay after each attempt, up to the cap
+    Exponential,
+}
+
+impl RetryBackoff {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RetryBackoff::Fixed => "fixed",
+            RetryBackoff::Exponential => "exponential",
+        }
+    }
+}
+
+/// Exit statuses listed with --retry-on-exit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitCodes {
+    /// `any`: every nonzero status
+    Any,
+    /// Codes and inclusive ranges, e.g. `1,100-120`
+    List(Vec<RangeInclusive<i32>>),
+}
+
+impl ExitCodes {
+    /// Parse `any` or a comma-separated list of codes and `LOW-HIGH`
+    /// ranges, each between 1 and 255
+    pub fn parse(s: &str) -> Result<Self, TimeoutError> {
+        let invalid = |reason: String| TimeoutError::InvalidExitCodes {
+            input: s.to_string(),
+            reason,
+        };
+        if s.eq_ignore_ascii_case("any") {
+            return Ok(ExitCodes::Any);
+        }
+
+        let code = |part: &str| -> Result<i32, TimeoutError> {
+            match part.trim().parse::<i32>() {
+                Ok(code) if (1..=255).contains(&code) => Ok(code),
+                Ok(code) => Err(invalid(format!("{} is not between 1 and 255", code))),
+                Err(_) => Err(invalid(format!("'{}' is not an exit code", part.trim()))),
+            }
+        };
+
+        let mut ranges = Vec::new();
+        for part in s.split(',') {
+            let range = match part.split_once('-') {
+                Some((low, high)) => {
+                    let (low, high) = (code(low)?, code(high)?);
+                    if low > high {
+                        return Err(invalid(format!("range {}-{} is backwards", low, high)));
+                    }
+                    low..=high
+                }
+                None => {
+                    let code = code(part)?;
+                    code..=code
+                }
+            };
+            ranges.push(range);
+        }
+        Ok(ExitCodes::List(ranges))
+    }
+
+    pub fn contains(&self, code: i32) -> bool {
+        match self {
+            ExitCodes::Any => code != 0,
+            ExitCodes::List(ranges) => ranges.iter().any(|r| r.contains(&code)),
+        }
+    }
+
+    /// Canonical form for --dry-run, e.g. `1,100-120`
+    pub fn describe(&self) -> String {
+        match self {
+            ExitCodes::Any => "any".to_string(),
+            ExitCodes::List(ranges) => ranges
+                .iter()
+                .map(|r| {
+                    if r.start() == r.end() {
+                        r.start().to_string()
+                    } else {
+                        format!("{}-{}", r.start(), r.end())
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    }
+}
+
+/// Why an attempt is followed by another one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryTrigger {
+    /// The attempt timed out
+    Timeout,
+    /// The attempt exited with a status listed in --retry-on-exit
+    ExitCode(i32),
+}
+
+/// Which time limit stopped a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutBudget {
+    /// The attempt's own DURATION, --idle-timeout or another per-attempt limit
+    Attempt,
+    /// --total-timeout, the deadline shared by all attempts
+    Total,
+}
+
+impl TimeoutBudget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeoutBudget::Attempt => "attempt",
+            TimeoutBudget::Total => "total",
+        }
+    }
+}
+
+/// Settings from --retries, --retry-on-exit, --retry-delay, --retry-backoff,
+/// --retry-max-delay, --total-timeout and --clamp-last-attempt
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Extra attempts after the first, whatever triggers them; 0 disables
+    /// retrying
+    pub retries: u32,
+    /// Exit statuses that also trigger a retry, besides timing out
+    pub on_exit: Option<ExitCodes>,
+    pub delay: Duration,
+    pub backoff: RetryBackoff,
+    /// Upper bound for exponential delays
+    pub max_delay: Duration,
+    /// Deadline for all attempts and delays together
+    pub total: Option<Duration>,
+    /// Give an attempt cut short by `total` the exit status of an ordinary
+    /// timeout, instead of always 124
+    pub clamp_last_attempt: bool,
+}
+
+impl RetryPolicy {
+    /// Whether another attempt may follow attempt number `attempt` (from 1)
+    pub fn allows_retry(&self, attempt: u32) -> bool {
+        attempt <= self.retries
+    }
+
+    /// What about a finished attempt calls for a retry, if anything;
+    /// stopping on --success-on-pattern never does
+    pub fn trigger(&self, metrics: &TimeoutMetrics) -> Option<RetryTrigger> {
+        if metrics.timed_out {
+            return Some(RetryTrigger::Timeout);
+        }
+        match &self.on_exit {
+            Some(codes)
+                if metrics.timeout_reason.is_none() && codes.contains(metrics.exit_code) =>
+            {
+                Some(RetryTrigger::ExitCode(metrics.exit_code))
+            }
+            _ => None,
+        }
+    }
+
+    /// How long to wait after attempt number `attempt` (from 1)
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            RetryBackoff::Fixed => self.delay,
+            RetryBackoff::Exponential => {
+                let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+                self.delay
+                    .checked_mul(factor)
+                    .unwrap_or(Duration::MAX)
+                    .min(self.max_delay)
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+// src/runner.rs
+// TimeoutRunner: the Unix backend as a library, for applications that run
+// a command under a time limit themselves and watch its events as they
+// happen
+
+use crate::output::OutputRules;
+use crate::platform::control::{HandleRequests, TimeoutHandle};
+use crate::platform::{self, RunOptions};
+use crate::stdio::Redirects;
+use crate::{TimeoutError, TimeoutSignal};
+use nix::sys::signal::Signal;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Something that happened to a command run by a `TimeoutRunner`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeoutEvent {
+    /// The command is running as `pid`
+    Started { pid: u32 },
+    /// The time limit passed `elapsed` after the command started
+    TimedOut { elapsed: Duration },
+    /// The command was sent `signal`, on timeout or when cancelled
+    SignalSent { signal: TimeoutSignal },
+    /// The command was sent SIGKILL
+    KillSent,
+    /// The command has finished; `code` is the status the run returns
+    Exited { code: i32 },
+    /// The command was stopped for going over its limit on `resource`:
+    /// `cpu` time or `output`
+    ResourceLimitExceeded { resource: String },
+}
+
+/// Where the backend sends `TimeoutEvent`s; nowhere for the timeout binary
+#[derive(Debug, Clone, Default)]
+pub struct Events(Option<UnboundedSender<TimeoutEvent>>);
+
+impl Events {
+    /// Report `event`, whether or not anyone is still listening
+    pub fn send(&self, event: TimeoutEvent) {
+        if let Some(sender) = &self.0 {
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Report that `signal` was sent: `KillSent` for SIGKILL
+    pub fn signal_sent(&self, signal: TimeoutSignal) {
+        self.send(match signal.as_signal() {
+            Some(Signal::SIGKILL) => TimeoutEvent::KillSent,
+            _ => TimeoutEvent::SignalSent { signal },
+        });
+    }
+}
+
+/// A future that cancels the run when it resolves
+pub type Cancel = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// What a `TimeoutRunner` changes about a run. The backend leaves the
+/// process's own state alone: no handlers for SIGINT, SIGTERM, SIGUSR1 or
+/// SIGUSR2, no new process group and no change to whether it can dump
+/// core.
+pub struct Embedded {
+    pub events: Events,
+    pub cancel: Option<Cancel>,
+    pub handle: Option<HandleRequests>,
+}
+
+/// Resolves once the run is cancelled; never without a way to cancel it
+pub async fn cancelled(cancel: &mut Option<Cancel>) {
+    match cancel {
+        Some(cancel) => cancel.as_mut().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Runs a command with a time limit, as the timeout binary does, from
+/// within an application on a tokio runtime.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), timeout::TimeoutError> {
+/// use std::time::Duration;
+/// use timeout::TimeoutRunner;
+///
+/// let code = TimeoutRunner::new("make", Duration::from_secs(600))
+///     .args(["-j8"])
+///     .kill_after(Duration::from_secs(10))
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The command's signals go to it alone, as with `--no-process-group`,
+/// and it inherits the application's stdin, stdout and stderr. The exit
+/// status is the one the binary would exit with: 124 on timeout, 137 after
+/// SIGKILL, otherwise the command's own.
+pub struct TimeoutRunner {
+    command: String,
+    args: Vec<String>,
+    duration: Duration,
+    signal: TimeoutSignal,
+    kill_after: Option<Duration>,
+    preserve_status: bool,
+    cpu_limit: Option<u64>,
+    max_output: Option<u64>,
+    cancel: Option<Cancel>,
+    handle: Option<(TimeoutHandle, HandleRequests)>,
+}
+
+impl TimeoutRunner {
+    /// Run `command` for at most `duration`; `Duration::MAX` sets no limit
+    pub fn new(command: impl Into<String>, duration: Duration) -> TimeoutRunner {
+        TimeoutRunner {
+            command: command.into(),
+            args: Vec::new(),
+            duration,
+            signal: TimeoutSignal::from(Signal::SIGTERM),
+            kill_after: None,
+            preserve_status: false,
+            cpu_limit: None,
+            max_output: None,
+            cancel: None,
+            handle: None,
+        }
+    }
+
+    /// The command's arguments
+    pub fn args<I, S>(mut self, args: I) -> TimeoutRunner
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// The signal sent on timeout, SIGTERM unless set (--signal)
+    pub fn signal(mut self, signal: TimeoutSignal) -> TimeoutRunner {
+        self.signal = signal;
+        self
+    }
+
+    /// Send SIGKILL this long after the first signal if the command is
+    /// still running (--kill-after)
+    pub fn kill_after(mut self, kill_after: Duration) -> TimeoutRunner {
+        self.kill_after = Some(kill_after);
+        self
+    }
+
+    /// On timeout, return the command's own status instead of 124
+    /// (--preserve-status)
+    pub fn preserve_status(mut self, preserve_status: bool) -> TimeoutRunner {
+        self.preserve_status = preserve_status;
+        self
+    }
+
+    /// Limit the command to this many seconds of CPU time, where the
+    /// platform can (--cpu-limit)
+    pub fn cpu_limit(mut self, secs: u64) -> TimeoutRunner {
+        self.cpu_limit = Some(secs);
+        self
+    }
+
+    /// Stop the command once its stdout and stderr together pass this many
+    /// bytes (--max-output). Its output then goes through the runner.
+    pub fn max_output(mut self, bytes: u64) -> TimeoutRunner {
+        self.max_output = Some(bytes);
+        self
+    }
+
+    /// Cancel the run once `cancel` resolves: the command is sent the
+    /// term signal, and SIGKILL after `kill_after` if one is set, and the
+    /// run returns its status once it has exited. With a tokio_util
+    /// `CancellationToken`, pass `token.cancelled_owned()`; tokio_util
+    /// itself is not a dependency.
+    pub fn with_cancellation(
+        mut self,
+        cancel: impl Future<Output = ()> + Send + 'static,
+    ) -> TimeoutRunner {
+        self.cancel = Some(Box::pin(cancel));
+        self
+    }
+
+    /// A handle on the run, to extend its deadline, see the time left or
+    /// cancel it while it goes on. Every call returns a copy of the same
+    /// handle; take one before starting the run.
+    pub fn handle(&mut self) -> TimeoutHandle {
+        let duration = self.duration;
+        let (handle, _) = self
+            .handle
+            .get_or_insert_with(|| TimeoutHandle::new(duration));
+        handle.clone()
+    }
+
+    /// Run the command and return its exit status
+    pub async fn run(self) -> Result<i32, TimeoutError> {
+        self.start(Events::default()).await
+    }
+
+    /// Run the command, returning the run alongside a handle on it
+    pub fn run_with_handle(
+        mut self,
+    ) -> (
+        impl Future<Output = Result<i32, TimeoutError>>,
+        TimeoutHandle,
+    ) {
+        let handle = self.handle();
+        (self.run(), handle)
+    }
+
+    /// Run the command, returning the run alongside a stream of its
+    /// events. The stream ends once the run is over; events are kept
+    /// until read, so the receiver may be dropped or read at leisure.
+    pub fn run_with_events(
+        self,
+    ) -> (
+        impl Future<Output = Result<i32, TimeoutError>>,
+        UnboundedReceiver<TimeoutEvent>,
+    ) {
+        let (sender, events) = unbounded_channel();
+        (self.start(Events(Some(sender))), events)
+    }
+
+    async fn start(self, events: Events) -> Result<i32, TimeoutError> {
+        // Cancelled by whichever comes first, the future or the handle
+        let (handle_cancel, handle) = match self.handle {
+            Some((handle, requests)) => (Some(handle.cancelled()), Some(requests)),
+            None => (None, None),
+        };
+        let cancel = match (self.cancel, handle_cancel) {
+            (Some(cancel), Some(handle_cancel)) => Some(Box::pin(async move {
+                tokio::select! {
+                    () = cancel => {}
+                    () = handle_cancel => {}
+                }
+            }) as Cancel),
+            (cancel, handle_cancel) => cancel.or(handle_cancel),
+        };
+        let options = RunOptions {
+            duration: self.duration,
+            kill_after: self.kill_after,
+            preserve_status: self.preserve_status,
+            verbose: false,
+            status_on_timeout: None,
+            output_rules: OutputRules {
+                max_output: self.max_output,
+                ..OutputRules::default()
+            },
+            redirects: Redirects::default(),
+            pid_file: None,
+            pid_file_force: false,
+            on_timeout: None,
+            pre_kill: None,
+            notifier: None,
+            term_signal: self.signal,
+            foreground: false,
+            no_process_group: true,
+            set_session: false,
+            detect_stopped: false,
+            no_notify: false,
+            cpu_limit: self.cpu_limit,
+            mem_limit: None,
+            cpu_affinity: None,
+            io_priority: None,
+            namespaced: false,
+            chroot: None,
+            pty: false,
+            extend_step: None,
+            boottime: false,
+            control_socket: None,
+            control_socket_force: false,
+            stop_instead: false,
+            embedded: Some(Embedded {
+                events,
+                cancel,
+                handle,
+            }),
+        };
+        let metrics = platform::run_with_timeout(&self.command, &self.args, options).await?;
+        Ok(metrics.exit_code)
+    }
+}
@@ -0,0 +1,135 @@
+// src/rusage.rs
+// What COMMAND used, from the struct rusage wait4() returns when it is
+// reaped (Unix), or GetProcessTimes and GetProcessMemoryInfo (Windows)
+
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+/// COMMAND's resource usage, part of the metrics record. Every field is
+/// `None` (null) when it is not known: with --pid, or when COMMAND was left
+/// running. Windows reports only the CPU times and peak RSS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceUsage {
+    /// Peak resident set size
+    pub max_rss_bytes: Option<u64>,
+    #[serde(rename = "user_cpu_ms", serialize_with = "opt_millis")]
+    pub user_cpu: Option<Duration>,
+    #[serde(rename = "sys_cpu_ms", serialize_with = "opt_millis")]
+    pub sys_cpu: Option<Duration>,
+    /// Page faults served without I/O
+    pub minor_faults: Option<u64>,
+    /// Page faults that needed I/O
+    pub major_faults: Option<u64>,
+    /// Block reads and writes that hit the disk
+    pub block_input_ops: Option<u64>,
+    pub block_output_ops: Option<u64>,
+}
+
+impl ResourceUsage {
+    /// The --verbose line, e.g. `peak rss 812 MiB, cpu 42.1s user / 3.0s
+    /// sys`, or `None` when nothing was collected
+    pub fn summary(&self) -> Option<String> {
+        let (user, sys) = (self.user_cpu?, self.sys_cpu?);
+        Some(format!(
+            "peak rss {}, cpu {:.1}s user / {:.1}s sys",
+            self.max_rss_bytes
+                .map_or("unknown".to_string(), binary_size),
+            user.as_secs_f64(),
+            sys.as_secs_f64()
+        ))
+    }
+}
+
+/// `bytes` in the largest binary unit that keeps it at 1 or more, e.g.
+/// `812 MiB` or `1.5 GiB`
+pub fn binary_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1} {}", size, UNITS[unit])
+    } else {
+        format!("{:.0} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(unix)]
+impl From<&nix::libc::rusage> for ResourceUsage {
+    fn from(usage: &nix::libc::rusage) -> ResourceUsage {
+        let time = |tv: nix::libc::timeval| {
+            Duration::new(tv.tv_sec.max(0) as u64, (tv.tv_usec.max(0) as u32) * 1000)
+        };
+        let count = |n: nix::libc::c_long| Some(n.max(0) as u64);
+        // ru_maxrss is in bytes on macOS and KiB everywhere else
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        let max_rss = usage.ru_maxrss.max(0) as u64;
+        #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+        let max_rss = (usage.ru_maxrss.max(0) as u64).saturating_mul(1024);
+        ResourceUsage {
+            max_rss_bytes: Some(max_rss),
+            user_cpu: Some(time(usage.ru_utime)),
+            sys_cpu: Some(time(usage.ru_stime)),
+            minor_faults: count(usage.ru_minflt),
+            major_faults: count(usage.ru_majflt),
+            block_input_ops: count(usage.ru_inblock),
+            block_output_ops: count(usage.ru_oublock),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl ResourceUsage {
+    /// What the process behind `handle` used, from GetProcessTimes and
+    /// GetProcessMemoryInfo, with its peak working set as the peak RSS.
+    /// Windows does not tell minor from major page faults or count block
+    /// I/O, so those stay unknown.
+    pub fn of_process(handle: windows_sys::Win32::Foundation::HANDLE) -> ResourceUsage {
+        use windows_sys::Win32::Foundation::FILETIME;
+        use windows_sys::Win32::System::ProcessStatus::{
+            GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+        };
+        use windows_sys::Win32::System::Threading::GetProcessTimes;
+
+        // In 100ns ticks
+        let time = |ft: FILETIME| {
+            Duration::from_nanos(
+                (((ft.dwHighDateTime as u64) << 32) | ft.dwLowDateTime as u64).saturating_mul(100),
+            )
+        };
+        let zero = FILETIME {
+            dwLowDateTime: 0,
+            dwHighDateTime: 0,
+        };
+        let (mut creation, mut exit, mut kernel, mut user) = (zero, zero, zero, zero);
+        let times =
+            unsafe { GetProcessTimes(handle, &mut creation, &mut exit, &mut kernel, &mut user) }
+                != 0;
+        let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+        counters.cb = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        let memory = unsafe { GetProcessMemoryInfo(handle, &mut counters, counters.cb) } != 0;
+        ResourceUsage {
+            max_rss_bytes: memory.then_some(counters.PeakWorkingSetSize as u64),
+            user_cpu: times.then(|| time(user)),
+            sys_cpu: times.then(|| time(kernel)),
+            ..ResourceUsage::default()
+        }
+    }
+}
+
+/// A Duration as whole milliseconds, or null
+fn opt_millis<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serializer.serialize_u64(duration.as_millis() as u64),
+        None => serializer.serialize_none(),
+    }
+}
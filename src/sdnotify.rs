@@ -0,0 +1,204 @@
+// src/sdnotify.rs
+// systemd's notification protocol, for timeout as a service's main process
+// (--sd-notify)
+
+use crate::debug::debug;
+use crate::logging::{info, warning};
+use owo_colors::OwoColorize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Who talks to systemd (--sd-notify)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SdNotify {
+    /// COMMAND inherits NOTIFY_SOCKET and speaks for itself, as it would
+    /// without the option
+    Passthrough,
+    /// timeout sends READY=1, WATCHDOG=1 and STOPPING=1 on COMMAND's behalf
+    Proxy,
+}
+
+impl SdNotify {
+    pub fn parse(mode: &str) -> SdNotify {
+        match mode {
+            "proxy" => SdNotify::Proxy,
+            _ => SdNotify::Passthrough,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SdNotify::Passthrough => "passthrough",
+            SdNotify::Proxy => "proxy",
+        }
+    }
+}
+
+/// The service manager's notification socket, in proxy mode
+#[derive(Debug)]
+pub struct Notifier {
+    #[cfg(target_os = "linux")]
+    socket: std::os::unix::net::UnixDatagram,
+    #[cfg(target_os = "linux")]
+    address: std::os::unix::net::SocketAddr,
+    /// How often to send WATCHDOG=1: half of WATCHDOG_USEC, as sd_notify(3)
+    /// recommends
+    watchdog: Option<Duration>,
+}
+
+impl Notifier {
+    /// Set up the mode given with --sd-notify. In proxy mode this takes
+    /// NOTIFY_SOCKET, WATCHDOG_USEC and WATCHDOG_PID out of the environment,
+    /// so COMMAND does not notify systemd as well, and returns the socket;
+    /// `None` when there is nothing to notify, which --verbose explains.
+    pub fn open(mode: SdNotify, verbose: bool) -> Option<Arc<Notifier>> {
+        let socket = std::env::var_os("NOTIFY_SOCKET");
+        match (mode, &socket) {
+            (_, None) => {
+                if verbose {
+                    info!(
+                        "{}: --sd-notify {}: NOTIFY_SOCKET is not set, so there is no service manager to notify",
+                        "Info".cyan(),
+                        mode.as_str()
+                    );
+                }
+                return None;
+            }
+            (SdNotify::Passthrough, Some(socket)) => {
+                if verbose {
+                    info!(
+                        "{}: passing NOTIFY_SOCKET={} through to COMMAND",
+                        "Info".cyan(),
+                        socket.to_string_lossy()
+                    );
+                }
+                return None;
+            }
+            (SdNotify::Proxy, Some(_)) => {}
+        }
+
+        let watchdog = watchdog_interval();
+        for name in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+            std::env::remove_var(name);
+        }
+        Self::connect(socket.unwrap_or_default(), watchdog, verbose).map(Arc::new)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn connect(
+        path: std::ffi::OsString,
+        watchdog: Option<Duration>,
+        verbose: bool,
+    ) -> Option<Notifier> {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        // A leading '@' names a socket in the abstract namespace
+        let address = match path.as_bytes().strip_prefix(b"@") {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(&path),
+        };
+        let opened = address.and_then(|address| Ok((UnixDatagram::unbound()?, address)));
+        match opened {
+            Ok((socket, address)) => {
+                if verbose {
+                    info!(
+                        "{}: notifying systemd at {}{}",
+                        "Info".cyan(),
+                        path.to_string_lossy(),
+                        watchdog
+                            .map(|every| format!(", watchdog every {:?}", every))
+                            .unwrap_or_default()
+                    );
+                }
+                Some(Notifier {
+                    socket,
+                    address,
+                    watchdog,
+                })
+            }
+            Err(e) => {
+                warning!(
+                    "{}: --sd-notify: cannot use NOTIFY_SOCKET {}: {}",
+                    "Warning".yellow(),
+                    path.to_string_lossy(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// systemd only runs on Linux, so elsewhere proxy mode has nothing to do
+    #[cfg(not(target_os = "linux"))]
+    fn connect(
+        _path: std::ffi::OsString,
+        _watchdog: Option<Duration>,
+        verbose: bool,
+    ) -> Option<Notifier> {
+        if verbose {
+            info!(
+                "{}: --sd-notify proxy has no effect on {}",
+                "Info".cyan(),
+                crate::Platform::name()
+            );
+        }
+        None
+    }
+
+    /// Send one notification, e.g. "READY=1". Failures are traced but not
+    /// reported: systemd may simply have stopped listening.
+    #[cfg(target_os = "linux")]
+    pub fn send(&self, state: &str) {
+        let result = self.socket.send_to_addr(state.as_bytes(), &self.address);
+        debug!("sd_notify({}) = {:?}", state, result);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn send(&self, state: &str) {
+        debug!("sd_notify({}) skipped", state);
+    }
+
+    /// Tell systemd the service is up, once COMMAND has been started, and
+    /// keep its watchdog fed until the returned guard is dropped
+    pub fn started(self: &Arc<Self>, pid: u32) -> Option<Watchdog> {
+        self.send(&format!("READY=1\nMAINPID={}", std::process::id()));
+        debug!("sd_notify: COMMAND is PID {}", pid);
+        let every = self.watchdog?;
+        let notifier = Arc::clone(self);
+        Some(Watchdog(tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(every).await;
+                notifier.send("WATCHDOG=1");
+            }
+        })))
+    }
+
+    /// Tell systemd the service is going down, when COMMAND times out
+    pub fn stopping(&self) {
+        self.send("STOPPING=1");
+    }
+}
+
+/// Sends WATCHDOG=1 in the background; dropping it stops
+pub struct Watchdog(JoinHandle<()>);
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Half of WATCHDOG_USEC, if the watchdog is enabled and meant for this
+/// process rather than one of its ancestors
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse() != Ok(std::process::id()) {
+            return None;
+        }
+    }
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
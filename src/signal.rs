@@ -0,0 +1,429 @@
+// src/signal.rs
+// Signals timeout sends, parsed from names like TERM, SIGRTMIN+3 or numbers (Unix)
+
+use crate::debug::debug;
+use crate::logging::detail;
+use crate::TimeoutError;
+use nix::errno::Errno;
+use nix::libc::{kill, killpg};
+use nix::sys::signal::Signal;
+use nix::unistd::{getpgrp, getpid, Pid};
+use owo_colors::OwoColorize;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How long a signal sent to COMMAND counts as in flight, during which
+/// sending it again is skipped
+pub const COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Exit status for a process that signal `sig` ended: 128+N, clamped to 255
+/// because an exit status has only 8 bits and shells would wrap anything
+/// above it. No signal is numbered that high on the platforms timeout runs
+/// on today (Linux's SIGRTMAX is 64, exit status 192).
+pub fn exit_code(sig: nix::libc::c_int) -> i32 {
+    (128 + sig).min(255)
+}
+
+/// Type-safe signal wrapper (Unix only)
+///
+/// Holds the raw signal number, since `Signal` cannot represent the
+/// real-time signals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeoutSignal(pub nix::libc::c_int);
+
+/// Build `[prefix0, prefix1, ...]` name tables for the real-time signals
+#[cfg(target_os = "linux")]
+macro_rules! rt_names {
+    ($prefix:literal; $($n:literal)*) => { [$(concat!($prefix, $n)),*] };
+}
+
+#[cfg(target_os = "linux")]
+const RTMIN_NAMES: [&str; 32] = rt_names!("SIGRTMIN+";
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31);
+#[cfg(target_os = "linux")]
+const RTMAX_NAMES: [&str; 32] = rt_names!("SIGRTMAX-";
+    0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31);
+
+/// How similar a mistyped signal name must be to a real one for the error
+/// to suggest it
+const SIGNAL_SUGGESTION_THRESHOLD: f64 = 0.7;
+
+/// Signals accepted by name or number, besides the real-time ones
+const NAMED_SIGNALS: [Signal; 9] = [
+    Signal::SIGHUP,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGKILL,
+    Signal::SIGTERM,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+    Signal::SIGALRM,
+    Signal::SIGCONT,
+];
+
+impl From<Signal> for TimeoutSignal {
+    fn from(sig: Signal) -> Self {
+        TimeoutSignal(sig as nix::libc::c_int)
+    }
+}
+
+impl TimeoutSignal {
+    pub fn from_str_or_num(s: &str) -> Result<Self, TimeoutError> {
+        #[cfg(target_os = "linux")]
+        if let Some(sig) = Self::parse_realtime(s)? {
+            return Ok(sig);
+        }
+
+        let upper = s.to_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        if let Ok(number) = name.parse::<nix::libc::c_int>() {
+            return Self::try_from(number).map_err(|_| TimeoutError::unknown_signal(s));
+        }
+        Self::all()
+            .find(|sig| sig.as_str().strip_prefix("SIG") == Some(name))
+            .ok_or_else(|| TimeoutError::UnknownSignal {
+                input: s.to_string(),
+                suggestion: Self::closest_name(name),
+            })
+    }
+
+    /// The named signal most like a misspelt `name` (upper case, without
+    /// `SIG`) by Jaro-Winkler similarity, if any is close enough to suggest
+    fn closest_name(name: &str) -> Option<&'static str> {
+        NAMED_SIGNALS
+            .iter()
+            .map(|sig| (sig.as_str(), strsim::jaro_winkler(name, &sig.as_str()[3..])))
+            .filter(|(_, similarity)| *similarity >= SIGNAL_SUGGESTION_THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(name, _)| name)
+    }
+
+    /// Every signal `from_str_or_num` accepts on this platform, named
+    /// signals first, then (on Linux) SIGRTMIN through SIGRTMAX
+    pub fn all() -> impl Iterator<Item = TimeoutSignal> {
+        let named = NAMED_SIGNALS.into_iter().map(TimeoutSignal::from);
+        #[cfg(target_os = "linux")]
+        let named = named.chain((nix::libc::SIGRTMIN()..=nix::libc::SIGRTMAX()).map(TimeoutSignal));
+        named
+    }
+
+    /// Parse `SIGRTMIN+N` or `SIGRTMAX-N` (N from 0 to 31, `SIG` optional);
+    /// `Ok(None)` if `s` is not a real-time signal name at all
+    #[cfg(target_os = "linux")]
+    fn parse_realtime(s: &str) -> Result<Option<Self>, TimeoutError> {
+        let upper = s.to_uppercase();
+        let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+        let (base, offset, sign) = if let Some(offset) = name.strip_prefix("RTMIN") {
+            (nix::libc::SIGRTMIN(), offset, '+')
+        } else if let Some(offset) = name.strip_prefix("RTMAX") {
+            (nix::libc::SIGRTMAX(), offset, '-')
+        } else {
+            return Ok(None);
+        };
+
+        let n = match offset.strip_prefix(sign) {
+            None if offset.is_empty() => 0,
+            Some(n) => n
+                .parse::<nix::libc::c_int>()
+                .ok()
+                .filter(|n| (0..32).contains(n))
+                .ok_or_else(|| TimeoutError::unknown_signal(s))?,
+            None => return Err(TimeoutError::unknown_signal(s)),
+        };
+
+        let sig = if sign == '+' { base + n } else { base - n };
+        if !(nix::libc::SIGRTMIN()..=nix::libc::SIGRTMAX()).contains(&sig) {
+            return Err(TimeoutError::unknown_signal(s));
+        }
+        Ok(Some(TimeoutSignal(sig)))
+    }
+
+    /// Whether the command can handle or ignore this signal; false only for
+    /// SIGKILL and SIGSTOP
+    pub fn is_catchable(&self) -> bool {
+        !matches!(self.as_signal(), Some(Signal::SIGKILL | Signal::SIGSTOP))
+    }
+
+    /// The `Signal` this wraps; `None` for real-time signals
+    pub fn as_signal(&self) -> Option<Signal> {
+        Signal::try_from(self.0).ok()
+    }
+
+    /// What the signal conventionally means, worded as `strsignal` does
+    pub fn description(&self) -> &'static str {
+        match self.as_signal() {
+            Some(Signal::SIGHUP) => "Hangup",
+            Some(Signal::SIGINT) => "Interrupt",
+            Some(Signal::SIGQUIT) => "Quit",
+            Some(Signal::SIGKILL) => "Killed",
+            Some(Signal::SIGTERM) => "Terminated",
+            Some(Signal::SIGUSR1) => "User defined signal 1",
+            Some(Signal::SIGUSR2) => "User defined signal 2",
+            Some(Signal::SIGALRM) => "Alarm clock",
+            Some(Signal::SIGCONT) => "Continued",
+            Some(Signal::SIGSTOP) => "Stopped (signal)",
+            Some(_) => "Signal",
+            None => "Real-time signal",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        #[cfg(target_os = "linux")]
+        {
+            // Named from whichever end is nearer, as `kill -l` does
+            let (min, max) = (nix::libc::SIGRTMIN(), nix::libc::SIGRTMAX());
+            if (min..=max).contains(&self.0) {
+                return if self.0 - min <= max - self.0 {
+                    RTMIN_NAMES[(self.0 - min) as usize]
+                } else {
+                    RTMAX_NAMES[(max - self.0) as usize]
+                };
+            }
+        }
+
+        match self.as_signal() {
+            Some(sig) => sig.as_str(),
+            None => "UNKNOWN",
+        }
+    }
+
+    pub fn send_to_process(&self, pid: Pid) -> Result<(), TimeoutError> {
+        // Raw kill(2), since nix's only takes a `Signal`
+        let result = Errno::result(unsafe { kill(pid.as_raw(), self.0) });
+        debug!("kill({}, {}) = {:?}", pid, self, result);
+        result
+            .map(drop)
+            .map_err(|e| TimeoutError::SignalSendFailed {
+                signal: self.as_str().to_string(),
+                source: e,
+            })
+    }
+
+    pub fn send_to_group(&self, pgid: Pid) -> Result<(), TimeoutError> {
+        if pgid == getpgrp() {
+            return self.send_to_own_group(pgid);
+        }
+        // Try killpg first (process group signal)
+        let result = Errno::result(unsafe { killpg(pgid.as_raw(), self.0) });
+        debug!("killpg({}, {}) = {:?}", pgid, self, result);
+        match result {
+            Ok(_) => Ok(()),
+            Err(Errno::ESRCH) => {
+                // On macOS, killpg may fail with ESRCH even when the process exists
+                // Fall back to killing the process directly
+                self.send_to_process(pgid)
+            }
+            Err(e) => Err(TimeoutError::SignalSendFailed {
+                signal: self.as_str().to_string(),
+                source: e,
+            }),
+        }
+    }
+
+    /// Signal the process group timeout shares with COMMAND, as GNU timeout
+    /// does: timeout ignores the signal while it goes out, so it does not
+    /// take it too. A signal generated while ignored is discarded there and
+    /// then, so timeout's own handler is back before anything could reach it.
+    /// SIGKILL and SIGSTOP cannot be ignored, so they go to each of the
+    /// other processes in the group instead.
+    fn send_to_own_group(&self, pgid: Pid) -> Result<(), TimeoutError> {
+        use nix::libc::{sigaction, SIG_IGN};
+
+        if matches!(self.as_signal(), Some(Signal::SIGKILL | Signal::SIGSTOP)) {
+            let me = getpid();
+            for pid in group_members(pgid).into_iter().filter(|pid| *pid != me) {
+                match self.send_to_process(pid) {
+                    // Gone since the group was listed
+                    Err(TimeoutError::SignalSendFailed {
+                        source: Errno::ESRCH,
+                        ..
+                    }) => {}
+                    result => result?,
+                }
+            }
+            return Ok(());
+        }
+
+        // SAFETY: sigaction is zeroable, and the old action is put back
+        // exactly as it was
+        let result = unsafe {
+            let mut ignore: sigaction = std::mem::zeroed();
+            ignore.sa_sigaction = SIG_IGN;
+            let mut old: sigaction = std::mem::zeroed();
+            if sigaction(self.0, &ignore, &mut old) != 0 {
+                return Err(TimeoutError::SignalSendFailed {
+                    signal: self.as_str().to_string(),
+                    source: Errno::last(),
+                });
+            }
+            let result = Errno::result(killpg(pgid.as_raw(), self.0));
+            sigaction(self.0, &old, std::ptr::null_mut());
+            result
+        };
+        debug!("killpg({}, {}) = {:?}, ignored here", pgid, self, result);
+        result
+            .map(drop)
+            .map_err(|e| TimeoutError::SignalSendFailed {
+                signal: self.as_str().to_string(),
+                source: e,
+            })
+    }
+}
+
+/// The processes in group `pgid`, read from /proc
+#[cfg(target_os = "linux")]
+fn group_members(pgid: Pid) -> Vec<Pid> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<i32>().ok())
+        .filter(|pid| {
+            // The command name in brackets may hold spaces, so the fields
+            // are counted from its closing bracket: state, ppid, pgrp
+            std::fs::read_to_string(format!("/proc/{}/stat", pid))
+                .ok()
+                .and_then(|stat| {
+                    let rest = &stat[stat.rfind(')')? + 1..];
+                    rest.split_whitespace().nth(2)?.parse::<i32>().ok()
+                })
+                == Some(pgid.as_raw())
+        })
+        .map(Pid::from_raw)
+        .collect()
+}
+
+/// The processes in group `pgid`. Without /proc to list them none are
+/// found, and SIGKILL and SIGSTOP reach only COMMAND itself, which
+/// `SignalsInFlight` signals first.
+#[cfg(not(target_os = "linux"))]
+fn group_members(_pgid: Pid) -> Vec<Pid> {
+    Vec::new()
+}
+
+/// The signals recently sent to one COMMAND, so that a signal timeout has
+/// just sent and the same one passed on from timeout's parent moments later
+/// go out only once
+#[derive(Debug)]
+pub struct SignalsInFlight {
+    pid: Pid,
+    /// The process group to signal; `None` to signal the process alone
+    /// (--foreground, --no-process-group)
+    group: Option<Pid>,
+    sent: HashMap<TimeoutSignal, Instant>,
+    /// Every signal that went out, in order, for the metrics
+    log: Vec<(TimeoutSignal, Instant)>,
+}
+
+impl SignalsInFlight {
+    pub fn new(pid: Pid, group: Option<Pid>) -> SignalsInFlight {
+        SignalsInFlight {
+            pid,
+            group,
+            sent: HashMap::new(),
+            log: Vec::new(),
+        }
+    }
+
+    /// Send `signal` to COMMAND, or to its process group, unless the same
+    /// signal went out less than COALESCE_WINDOW ago. Returns whether it was
+    /// sent.
+    pub fn send(&mut self, signal: TimeoutSignal) -> Result<bool, TimeoutError> {
+        let now = Instant::now();
+        if let Some(since) = self
+            .sent
+            .get(&signal)
+            .map(|at| now.duration_since(*at))
+            .filter(|since| *since < COALESCE_WINDOW)
+        {
+            debug!("{} to {} coalesced, sent {:?} ago", signal, self.pid, since);
+            return Ok(false);
+        }
+        self.send_now(signal)?;
+        self.sent.insert(signal, now);
+        Ok(true)
+    }
+
+    /// Send `signal` even if it went out moments ago, as a SIGCONT that
+    /// wakes COMMAND up each time must
+    pub fn send_now(&mut self, signal: TimeoutSignal) -> Result<(), TimeoutError> {
+        match self.group {
+            None => signal.send_to_process(self.pid)?,
+            Some(group) => {
+                // These skip timeout in a group it shares, so COMMAND gets
+                // them directly in case the group cannot be listed
+                if matches!(signal.as_signal(), Some(Signal::SIGKILL | Signal::SIGSTOP)) {
+                    signal.send_to_process(self.pid)?;
+                }
+                signal.send_to_group(group)?;
+            }
+        }
+        match self.group {
+            None => detail!(
+                "{}: sent {} to process {}",
+                "Signal".cyan(),
+                signal,
+                self.pid
+            ),
+            Some(group) => detail!(
+                "{}: sent {} to process group {}",
+                "Signal".cyan(),
+                signal,
+                group
+            ),
+        }
+        self.log.push((signal, Instant::now()));
+        Ok(())
+    }
+
+    /// The signals sent so far and when
+    pub fn log(&self) -> &[(TimeoutSignal, Instant)] {
+        &self.log
+    }
+}
+
+/// Look a signal number up among those `TimeoutSignal::all` yields, so
+/// real-time signals are accepted on Linux
+impl TryFrom<i32> for TimeoutSignal {
+    type Error = TimeoutError;
+
+    fn try_from(n: i32) -> Result<Self, Self::Error> {
+        TimeoutSignal::all()
+            .find(|sig| sig.0 == n)
+            .ok_or_else(|| TimeoutError::unknown_signal(&n.to_string()))
+    }
+}
+
+impl TryFrom<&str> for TimeoutSignal {
+    type Error = TimeoutError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        TimeoutSignal::from_str_or_num(s)
+    }
+}
+
+/// Lets clap parse `--signal` straight into a `TimeoutSignal`
+impl FromStr for TimeoutSignal {
+    type Err = TimeoutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TimeoutSignal::from_str_or_num(s)
+    }
+}
+
+impl fmt::Display for TimeoutSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Serialized as its name, e.g. `"SIGTERM"`
+impl Serialize for TimeoutSignal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
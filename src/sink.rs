@@ -0,0 +1,296 @@
+// src/sink.rs
+// Where each run's outcome is recorded once COMMAND has finished: the --log
+// targets, --webhook, --statsd, --otel and --prom-textfile
+
+use crate::journald::Journal;
+use crate::plan::Plan;
+use crate::prom::PromTextfile;
+use crate::statsd::Statsd;
+use crate::syslog::{Syslog, SyslogTarget};
+use crate::{
+    timestamp, MetricsRecord, Platform, TimeoutError, TimeoutMetrics, METRICS_SCHEMA_VERSION,
+};
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// How a run ended, for --webhook-on and the log severity. A timeout is not
+/// also a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutcomeEvent {
+    Success,
+    Failure,
+    Timeout,
+}
+
+impl OutcomeEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutcomeEvent::Success => "success",
+            OutcomeEvent::Failure => "failure",
+            OutcomeEvent::Timeout => "timeout",
+        }
+    }
+}
+
+/// One finished run, as every sink sees it
+pub struct Outcome<'a> {
+    pub command: &'a str,
+    pub argv: &'a [String],
+    /// timeout's own exit code
+    pub exit_code: i32,
+    /// `None` when COMMAND could not be run at all
+    pub metrics: Option<&'a TimeoutMetrics>,
+    pub error: Option<&'a str>,
+    pub ended: SystemTime,
+}
+
+impl<'a> Outcome<'a> {
+    pub fn new(
+        command: &'a str,
+        argv: &'a [String],
+        exit_code: i32,
+        metrics: Option<&'a TimeoutMetrics>,
+        error: Option<&'a str>,
+    ) -> Outcome<'a> {
+        Outcome {
+            command,
+            argv,
+            exit_code,
+            metrics,
+            error,
+            ended: SystemTime::now(),
+        }
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.metrics.is_some_and(|m| m.timed_out)
+    }
+
+    pub fn event(&self) -> OutcomeEvent {
+        match (self.exit_code, self.timed_out()) {
+            (_, true) => OutcomeEvent::Timeout,
+            (0, false) => OutcomeEvent::Success,
+            _ => OutcomeEvent::Failure,
+        }
+    }
+
+    /// The event, or exec_error when COMMAND could not be run, as --statsd
+    /// and --prom-textfile name it
+    pub fn name(&self) -> &'static str {
+        match (self.error, self.event()) {
+            (Some(_), _) => "exec_error",
+            (None, event) => event.as_str(),
+        }
+    }
+
+    /// syslog severity, which journald's PRIORITY shares: err when COMMAND
+    /// could not be run, warning for a timeout, notice for any other
+    /// failure and info for success
+    pub fn severity(&self) -> u8 {
+        match (self.error, self.event()) {
+            (Some(_), _) => 3,
+            (None, OutcomeEvent::Timeout) => 4,
+            (None, OutcomeEvent::Failure) => 5,
+            (None, OutcomeEvent::Success) => 6,
+        }
+    }
+
+    pub fn started(&self) -> SystemTime {
+        self.metrics
+            .and_then(|m| self.ended.checked_sub(m.total_elapsed))
+            .unwrap_or(self.ended)
+    }
+
+    /// The --verbose summary, or the error when COMMAND could not be run
+    pub fn message(&self) -> String {
+        match (self.metrics, self.error) {
+            (_, Some(error)) => format!("timeout: {}", error),
+            (Some(metrics), None) => metrics.to_string(),
+            (None, None) => format!(
+                "Command '{}' exited with code {}",
+                self.command, self.exit_code
+            ),
+        }
+    }
+
+    /// The outcome as named fields, for the log targets: those that apply
+    /// of command, argv, exit_code, timed_out, elapsed_ms, signal, attempt,
+    /// reason and error
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut fields = vec![
+            ("command", self.command.to_string()),
+            ("argv", self.argv.join(" ")),
+            ("exit_code", self.exit_code.to_string()),
+            ("timed_out", self.timed_out().to_string()),
+        ];
+        if let Some(metrics) = self.metrics {
+            fields.extend([
+                ("elapsed_ms", metrics.elapsed.as_millis().to_string()),
+                ("signal", metrics.signal_name().to_string()),
+                ("attempt", metrics.attempt.to_string()),
+            ]);
+            if let Some(reason) = metrics.timeout_reason {
+                fields.push(("reason", reason.as_str().to_string()));
+            }
+        }
+        if let Some(error) = self.error {
+            fields.push(("error", error.to_string()));
+        }
+        fields
+    }
+
+    /// The outcome as one JSON object: the TIMEOUT_METRICS fields (or, when
+    /// COMMAND could not be run at all, its argv, exit code and the error)
+    /// plus the host, the event and when the run ended
+    pub fn to_json(&self) -> String {
+        let report = Report {
+            run: match self.metrics {
+                Some(metrics) => Run::Metrics(metrics.record()),
+                None => Run::NotRun {
+                    schema_version: METRICS_SCHEMA_VERSION,
+                    command: self.command,
+                    argv: self.argv,
+                    timed_out: false,
+                    exit_code: self.exit_code,
+                    platform: Platform::name(),
+                    started_at: self.started(),
+                },
+            },
+            hostname: hostname(),
+            event: self.event().as_str(),
+            error: self.error,
+            ended_at: self.ended,
+        };
+        serde_json::to_string(&report).expect("outcome serializes to JSON")
+    }
+}
+
+/// What --webhook and --log syslog's RFC 3164 messages carry
+#[derive(Serialize)]
+struct Report<'a> {
+    #[serde(flatten)]
+    run: Run<'a>,
+    hostname: Option<String>,
+    event: &'static str,
+    error: Option<&'a str>,
+    #[serde(serialize_with = "timestamp")]
+    ended_at: SystemTime,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Run<'a> {
+    Metrics(MetricsRecord<'a>),
+    /// The few fields there are when COMMAND could not be run
+    NotRun {
+        schema_version: u32,
+        command: &'a str,
+        argv: &'a [String],
+        timed_out: bool,
+        exit_code: i32,
+        platform: &'static str,
+        #[serde(serialize_with = "timestamp")]
+        started_at: SystemTime,
+    },
+}
+
+/// Somewhere an outcome is recorded
+pub trait OutcomeSink: Send + Sync + fmt::Debug {
+    /// Record one outcome, returning whether it got there. A failure is
+    /// reported on stderr by the sink and never changes the exit code.
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>>;
+
+    /// Whether this is a --log target, which --quiet lets stand in for
+    /// timeout's own error messages on stderr
+    fn is_log(&self) -> bool {
+        true
+    }
+}
+
+/// Where timeout records how each run ended (--log)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTarget {
+    /// Only the usual stderr messages
+    Stderr,
+    Journald,
+    Syslog(SyslogTarget),
+}
+
+impl LogTarget {
+    pub fn parse(input: &str) -> Result<LogTarget, TimeoutError> {
+        match input {
+            "stderr" => Ok(LogTarget::Stderr),
+            "journald" => Ok(LogTarget::Journald),
+            _ if input.starts_with("syslog") => SyslogTarget::parse(input).map(LogTarget::Syslog),
+            _ => Err(TimeoutError::InvalidLogTarget {
+                input: input.to_string(),
+                reason: "expected stderr, journald, syslog[:FACILITY] or syslog://HOST[:PORT]"
+                    .to_string(),
+            }),
+        }
+    }
+
+    /// The target as it would be given to --log
+    pub fn describe(&self) -> String {
+        match self {
+            LogTarget::Stderr => "stderr".to_string(),
+            LogTarget::Journald => "journald".to_string(),
+            LogTarget::Syslog(target) => target.to_string(),
+        }
+    }
+}
+
+/// Connect every --log target and --statsd, and add --webhook, --otel and
+/// --prom-textfile. Targets that cannot be reached have already warned and
+/// are left out, so their runs are only reported on stderr.
+pub fn open(plan: &Plan) -> Vec<Arc<dyn OutcomeSink>> {
+    let mut sinks: Vec<Arc<dyn OutcomeSink>> = Vec::new();
+    for target in &plan.log {
+        match target {
+            LogTarget::Stderr => {}
+            LogTarget::Journald => {
+                if let Some(journal) = Journal::open() {
+                    sinks.push(Arc::new(journal));
+                }
+            }
+            LogTarget::Syslog(target) => {
+                if let Some(syslog) = Syslog::open(target.clone(), plan.syslog_format) {
+                    sinks.push(Arc::new(syslog));
+                }
+            }
+        }
+    }
+    if let Some(webhook) = &plan.webhook {
+        sinks.push(Arc::new(webhook.clone()));
+    }
+    if let Some(statsd) = plan.statsd.as_ref().and_then(Statsd::open) {
+        sinks.push(Arc::new(statsd));
+    }
+    if let Some(otel) = &plan.otel {
+        sinks.push(otel.clone());
+    }
+    if let Some(path) = &plan.prom_textfile {
+        sinks.push(Arc::new(PromTextfile::new(path)));
+    }
+    sinks
+}
+
+#[cfg(unix)]
+pub fn hostname() -> Option<String> {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+}
+
+#[cfg(not(unix))]
+pub fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
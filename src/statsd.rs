@@ -0,0 +1,175 @@
+// src/statsd.rs
+// A few StatsD datapoints per run sent over UDP (--statsd), with DogStatsD
+// tags if asked for (--statsd-tags)
+
+use crate::logging::{info, warning};
+use crate::sink::{Outcome, OutcomeSink};
+use crate::syslog::{host_port, udp_connect};
+use crate::TimeoutError;
+use owo_colors::OwoColorize;
+use std::future::Future;
+use std::net::UdpSocket;
+use std::pin::Pin;
+
+const DEFAULT_PORT: u16 = 8125;
+
+const DEFAULT_PREFIX: &str = "timeout";
+
+/// Where and how to send datapoints (--statsd, --statsd-prefix,
+/// --statsd-tags)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsdTarget {
+    /// `HOST:PORT`
+    pub address: String,
+    pub prefix: String,
+    /// Add DogStatsD `|#name:value,...` tags to each datapoint
+    pub tags: bool,
+}
+
+impl StatsdTarget {
+    /// Check `HOST[:PORT]` (port 8125 by default) and the metric name
+    /// prefix, which may not hold the characters the line protocol uses as
+    /// separators
+    pub fn new(
+        address: &str,
+        prefix: Option<&str>,
+        tags: bool,
+    ) -> Result<StatsdTarget, TimeoutError> {
+        let invalid = |input: &str, reason: String| TimeoutError::InvalidStatsd {
+            input: input.to_string(),
+            reason,
+        };
+        let address = host_port(address, DEFAULT_PORT).map_err(|e| invalid(address, e))?;
+        let prefix = prefix.unwrap_or(DEFAULT_PREFIX);
+        if prefix.is_empty()
+            || prefix
+                .chars()
+                .any(|c| matches!(c, ':' | '|' | '@' | '#' | ',') || c.is_whitespace())
+        {
+            return Err(invalid(
+                prefix,
+                "a prefix must be non-empty, without ':', '|', '@', '#', ',' or spaces".to_string(),
+            ));
+        }
+        Ok(StatsdTarget {
+            address,
+            prefix: prefix.to_string(),
+            tags,
+        })
+    }
+
+    /// The datagram for one run: a `runs.OUTCOME` counter, where OUTCOME is
+    /// success, failure, timeout or exec_error (COMMAND could not be run),
+    /// a gauge of timeout's exit code and, when COMMAND ran, an
+    /// `elapsed_ms` timer and a `kill_after_used` gauge, one per line
+    pub fn packet(&self, outcome: &Outcome) -> String {
+        let outcome_name = outcome.name();
+        let tags = match self.tags {
+            true => format!(
+                "|#command:{},timed_out:{},outcome:{}",
+                tag_value(outcome.command),
+                outcome.timed_out(),
+                outcome_name
+            ),
+            false => String::new(),
+        };
+
+        let mut datapoints = vec![
+            (format!("runs.{}", outcome_name), "1".to_string(), "c"),
+            ("exit_code".to_string(), outcome.exit_code.to_string(), "g"),
+        ];
+        if let Some(metrics) = outcome.metrics {
+            datapoints.extend([
+                (
+                    "elapsed_ms".to_string(),
+                    metrics.total_elapsed.as_millis().to_string(),
+                    "ms",
+                ),
+                (
+                    "kill_after_used".to_string(),
+                    u8::from(metrics.kill_after_used).to_string(),
+                    "g",
+                ),
+            ]);
+        }
+        datapoints
+            .into_iter()
+            .map(|(name, value, kind)| {
+                format!("{}.{}:{}|{}{}", self.prefix, name, value, kind, tags)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// `value` with the characters that end a DogStatsD tag or datapoint
+/// (`,`, `|`, `#`, line breaks) and other whitespace or control
+/// characters replaced by `_`
+fn tag_value(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ',' | '|' | '#' => '_',
+            c if c.is_whitespace() || c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// A --statsd target with its socket
+#[derive(Debug)]
+pub struct Statsd {
+    target: StatsdTarget,
+    socket: UdpSocket,
+}
+
+impl Statsd {
+    /// Resolve the address and connect a socket to it. If that fails,
+    /// warn and return `None`; nothing is sent.
+    pub fn open(target: &StatsdTarget) -> Option<Statsd> {
+        match udp_connect(&target.address) {
+            Ok(socket) => Some(Statsd {
+                target: target.clone(),
+                socket,
+            }),
+            Err(e) => {
+                warning!(
+                    "{}: --statsd {}: {}; not sending metrics",
+                    "Warning".yellow(),
+                    target.address,
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Fire and forget: all of a run's datapoints go in one datagram, and a
+/// failed send is not reported
+impl OutcomeSink for Statsd {
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(async move {
+            let sent = self
+                .socket
+                .send(self.target.packet(outcome).as_bytes())
+                .is_ok();
+            if sent && verbose {
+                info!(
+                    "{}: sent statsd metrics to {}",
+                    "Info".cyan(),
+                    self.target.address
+                );
+            }
+            sent
+        })
+    }
+
+    fn is_log(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,78 @@
+// src/status_fd.rs
+// --status-fd: one JSON object describing how the run ended, written to a
+// descriptor the caller set up, as GPG's --status-fd does
+
+use crate::logging::warning;
+use crate::sink::Outcome;
+use crate::TimeoutError;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// The object written, e.g. `{"timed_out":true,"exit_code":124,
+/// "signal":"SIGTERM","elapsed_ms":5003,"child_pid":4242}`. Everything
+/// about COMMAND is null when it could not be run.
+#[derive(Debug, Serialize)]
+struct Status<'a> {
+    timed_out: bool,
+    /// timeout's own exit code
+    exit_code: i32,
+    signal: Option<&'a str>,
+    elapsed_ms: Option<u64>,
+    child_pid: Option<u32>,
+}
+
+/// An opened --status-fd descriptor
+#[derive(Debug)]
+pub struct StatusFd {
+    fd: i32,
+    file: Mutex<File>,
+}
+
+impl StatusFd {
+    /// Check now that `fd` was inherited and is open for writing, so a
+    /// wrapper that forgot `3>result.json` hears about it before COMMAND
+    /// starts
+    pub fn open(fd: i32) -> Result<StatusFd, TimeoutError> {
+        #[cfg(unix)]
+        let file = crate::metrics::inherited(fd);
+        #[cfg(not(unix))]
+        let file = Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "file descriptors are Unix-only",
+        ));
+        match file {
+            Ok(file) => Ok(StatusFd {
+                fd,
+                file: Mutex::new(file),
+            }),
+            Err(source) => Err(TimeoutError::StatusFdFailed { fd, source }),
+        }
+    }
+
+    /// Write the object for a run that ends with `exit_code`, in one write
+    pub fn write(&self, outcome: &Outcome, exit_code: i32) {
+        let metrics = outcome.metrics;
+        let status = Status {
+            timed_out: outcome.timed_out(),
+            exit_code,
+            signal: metrics.and_then(|m| m.signal_sent.is_some().then(|| m.signal_name())),
+            elapsed_ms: metrics.map(|m| m.elapsed.as_millis() as u64),
+            child_pid: metrics.and_then(|m| m.pid),
+        };
+        let line = format!(
+            "{}\n",
+            serde_json::to_string(&status).expect("status serializes to JSON")
+        );
+        if let Err(e) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            warning!(
+                "{}: cannot write status to fd {}: {}",
+                "Warning".yellow(),
+                self.fd,
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// src/stdio.rs
+// Redirection of the child's standard streams to and from files
+
+use crate::args::Args;
+use crate::TimeoutError;
+use std::fs::{File, OpenOptions};
+use std::sync::Arc;
+
+#[cfg(unix)]
+const NULL_DEVICE: &str = "/dev/null";
+#[cfg(not(unix))]
+const NULL_DEVICE: &str = "NUL";
+
+/// Where the child's stdin comes from
+#[derive(Debug, Clone, Default)]
+pub enum StdinSource {
+    /// Share timeout's own stdin
+    #[default]
+    Inherit,
+    /// No stdin at all; reads fail with EBADF (`null` on Windows)
+    Close,
+    /// Read from this file (`/dev/null` for `--stdin null`)
+    File(Arc<File>),
+}
+
+/// Files the child's standard streams are connected to instead of ours.
+///
+/// The files are opened by timeout itself, so a bad path fails with 125
+/// before anything runs, and shared by every command in batch or parallel
+/// mode.
+#[derive(Debug, Clone, Default)]
+pub struct Redirects {
+    pub stdin: StdinSource,
+    pub stdout: Option<Arc<File>>,
+    pub stderr: Option<Arc<File>>,
+    /// Send stderr wherever stdout goes, like `2>&1`
+    pub merge_output: bool,
+}
+
+impl Redirects {
+    /// Open the files named by --stdin, --stdout, --stderr and --quiet-child
+    pub fn open(args: &Args) -> Result<Self, TimeoutError> {
+        let stdin = match args.stdin.as_deref() {
+            None | Some("inherit") => StdinSource::Inherit,
+            Some("close") => StdinSource::Close,
+            Some("null") => StdinSource::File(open_input(NULL_DEVICE)?),
+            Some(path) => StdinSource::File(open_input(path)?),
+        };
+
+        let (stdout, stderr) = if args.quiet_child {
+            (Some(NULL_DEVICE), Some(NULL_DEVICE))
+        } else {
+            (args.stdout.as_deref(), args.stderr.as_deref())
+        };
+
+        Ok(Redirects {
+            stdin,
+            stdout: stdout
+                .map(|path| open_output(path, args.stdout_append))
+                .transpose()?,
+            stderr: stderr
+                .map(|path| open_output(path, args.stderr_append))
+                .transpose()?,
+            merge_output: args.merge_output,
+        })
+    }
+
+    /// Point fds 0, 1 and 2 at the redirect targets. Called in the forked
+    /// child, so it only uses dup2 and close; returns false if that fails.
+    #[cfg(unix)]
+    pub fn apply_in_child(&self) -> bool {
+        use std::os::fd::AsRawFd;
+
+        let stdin_ok = match &self.stdin {
+            StdinSource::Inherit => true,
+            StdinSource::Close => (unsafe { nix::libc::close(0) }) != -1,
+            StdinSource::File(f) => (unsafe { nix::libc::dup2(f.as_raw_fd(), 0) }) != -1,
+        };
+
+        let dup2 = |file: &Option<Arc<File>>, fd| {
+            file.as_ref()
+                .is_none_or(|f| unsafe { nix::libc::dup2(f.as_raw_fd(), fd) } != -1)
+        };
+        stdin_ok
+            && dup2(&self.stdout, 1)
+            && dup2(&self.stderr, 2)
+            && (!self.merge_output || unsafe { nix::libc::dup2(1, 2) } != -1)
+    }
+
+    /// Handles for the child's stdin, stdout and stderr; `None` leaves a
+    /// stream inherited. Windows cannot start a process without a stdin,
+    /// so `Close` gives it the null device instead.
+    #[cfg(windows)]
+    pub fn child_stdio(&self) -> std::io::Result<[Option<std::process::Stdio>; 3]> {
+        use std::os::windows::io::AsHandle;
+        use std::process::Stdio;
+
+        let stdin = match &self.stdin {
+            StdinSource::Inherit => None,
+            StdinSource::Close => Some(Stdio::null()),
+            StdinSource::File(f) => Some(f.try_clone()?.into()),
+        };
+
+        let stdout = self.stdout.as_deref().map(File::try_clone).transpose()?;
+        let stderr = if self.merge_output {
+            match &stdout {
+                Some(file) => Some(file.try_clone()?),
+                None => Some(File::from(
+                    std::io::stdout().as_handle().try_clone_to_owned()?,
+                )),
+            }
+        } else {
+            self.stderr.as_deref().map(File::try_clone).transpose()?
+        };
+        Ok([stdin, stdout.map(Into::into), stderr.map(Into::into)])
+    }
+}
+
+fn open_input(path: &str) -> Result<Arc<File>, TimeoutError> {
+    File::open(path)
+        .map(Arc::new)
+        .map_err(|e| TimeoutError::RedirectFailed {
+            path: path.to_string(),
+            source: e,
+        })
+}
+
+fn open_output(path: &str, append: bool) -> Result<Arc<File>, TimeoutError> {
+    let mut options = OpenOptions::new();
+    options.create(true);
+    if append {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+
+    options
+        .open(path)
+        .map(Arc::new)
+        .map_err(|e| TimeoutError::RedirectFailed {
+            path: path.to_string(),
+            source: e,
+        })
+}
@@ -0,0 +1,94 @@
+// src/summary.rs
+// Where the --summary-format line of each run goes: stderr, or the
+// --summary-output FILE
+
+use crate::logging::warning;
+use crate::sink::Outcome;
+use crate::summary_format::{SummaryFields, Template};
+use crate::TimeoutError;
+use owo_colors::OwoColorize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// An opened --summary-format target
+#[derive(Debug)]
+pub struct SummaryOutput {
+    template: Template,
+    path: Option<String>,
+    /// `None` for stderr
+    file: Option<Mutex<File>>,
+}
+
+impl SummaryOutput {
+    /// Open `path` for appending now, so a file that cannot be created
+    /// fails the run before COMMAND starts
+    pub fn open(template: &Template, path: Option<&str>) -> Result<SummaryOutput, TimeoutError> {
+        let file = match path {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(|source| TimeoutError::SummaryOutputFailed {
+                        path: path.to_string(),
+                        source,
+                    })?,
+            ),
+            None => None,
+        };
+        Ok(SummaryOutput {
+            template: template.clone(),
+            path: path.map(str::to_string),
+            file: file.map(Mutex::new),
+        })
+    }
+
+    /// Write the line for one run, which ends with `exit_code`. `limit` is
+    /// the time limit to show when COMMAND could not be run.
+    pub fn write(&self, outcome: &Outcome, exit_code: i32, limit: Duration) {
+        let metrics = outcome.metrics;
+        let fields = SummaryFields {
+            outcome: outcome_name(outcome),
+            command: outcome.command,
+            argv: outcome.argv,
+            elapsed: metrics.map(|m| m.elapsed),
+            duration: Some(metrics.map_or(limit, |m| m.duration)).filter(|&d| d != Duration::MAX),
+            signal: metrics.and_then(|m| m.signal_sent.is_some().then(|| m.signal_name())),
+            exit_code,
+            timed_out: outcome.timed_out(),
+            reason: metrics.and_then(|m| m.timeout_reason.map(|r| r.to_string())),
+            attempt: metrics.map(|m| m.attempt),
+            iteration: metrics.map(|m| m.iteration),
+            pid: metrics.and_then(|m| m.pid),
+            max_rss_bytes: metrics.and_then(|m| m.usage.max_rss_bytes),
+            error: outcome.error,
+        };
+        let line = format!("{}\n", self.template.render(&fields));
+        let Some(file) = &self.file else {
+            eprint!("{}", line);
+            return;
+        };
+        if let Err(e) = file.lock().unwrap().write_all(line.as_bytes()) {
+            warning!(
+                "{}: cannot write summary to {}: {}",
+                "Warning".yellow(),
+                self.path.as_deref().unwrap_or_default(),
+                e
+            );
+        }
+    }
+}
+
+/// ok, failed, timeout, signal (timeout passed on a Ctrl-C or SIGTERM) or
+/// exec-error
+fn outcome_name(outcome: &Outcome) -> &'static str {
+    match outcome.metrics {
+        None => "exec-error",
+        Some(m) if m.timed_out => "timeout",
+        Some(m) if m.signal_sent.is_some() => "signal",
+        Some(_) if outcome.exit_code == 0 => "ok",
+        Some(_) => "failed",
+    }
+}
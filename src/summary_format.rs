@@ -0,0 +1,174 @@
+// src/summary_format.rs
+// --summary-format: a one-line template over a run's outcome, e.g.
+// "[{outcome}] {command} took {elapsed} (limit {duration}) exit={exit_code}"
+
+use std::time::Duration;
+
+/// Every `{placeholder}` a template may use
+pub const PLACEHOLDERS: [&str; 15] = [
+    "outcome",
+    "command",
+    "argv",
+    "elapsed",
+    "duration",
+    "remaining",
+    "signal",
+    "exit_code",
+    "timed_out",
+    "reason",
+    "attempt",
+    "iteration",
+    "pid",
+    "max_rss",
+    "error",
+];
+
+/// What a summary line is made from. Everything but `outcome`, `command`,
+/// `argv`, `exit_code` and `error` is missing when COMMAND could not be run.
+#[derive(Debug, Clone, Default)]
+pub struct SummaryFields<'a> {
+    /// ok, failed, timeout, signal or exec-error
+    pub outcome: &'a str,
+    pub command: &'a str,
+    pub argv: &'a [String],
+    pub elapsed: Option<Duration>,
+    /// The time limit; `None` for no limit
+    pub duration: Option<Duration>,
+    pub signal: Option<&'a str>,
+    /// timeout's own exit code
+    pub exit_code: i32,
+    pub timed_out: bool,
+    pub reason: Option<String>,
+    pub attempt: Option<u32>,
+    pub iteration: Option<u32>,
+    pub pid: Option<u32>,
+    pub max_rss_bytes: Option<u64>,
+    pub error: Option<&'a str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(&'static str),
+}
+
+/// A parsed --summary-format template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parse `format`, where `{name}` is a placeholder and `{{` and `}}`
+    /// are literal braces. Fails on an unknown placeholder or an
+    /// unmatched brace, with the reason.
+    pub fn parse(format: &str) -> Result<Template, String> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = format.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+                '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+                '{' => {
+                    let rest = &format[i + 1..];
+                    let name = match rest.find('}') {
+                        Some(end) => &rest[..end],
+                        None => return Err(format!("unclosed '{{' at byte {}", i)),
+                    };
+                    let field = PLACEHOLDERS.iter().find(|&&p| p == name).ok_or_else(|| {
+                        format!(
+                            "unknown placeholder {{{}}} (known: {}; write {{{{ and }}}} for braces)",
+                            name,
+                            PLACEHOLDERS.join(", ")
+                        )
+                    })?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                    for _ in 0..=name.chars().count() {
+                        chars.next();
+                    }
+                }
+                '}' => {
+                    return Err(format!(
+                        "unmatched '}}' at byte {}; write }}}} for a brace",
+                        i
+                    ))
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Template {
+            source: format.to_string(),
+            segments,
+        })
+    }
+
+    /// The template as given
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The summary line for `fields`, without a newline. Values that are
+    /// not known come out as `-`.
+    pub fn render(&self, fields: &SummaryFields) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Field(name) => out.push_str(&value(name, fields)),
+            }
+        }
+        out
+    }
+}
+
+fn value(name: &str, fields: &SummaryFields) -> String {
+    let known = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    match name {
+        "outcome" => fields.outcome.to_string(),
+        "command" => fields.command.to_string(),
+        "argv" => fields.argv.join(" "),
+        "elapsed" => known(fields.elapsed.map(human_duration)),
+        "duration" => match fields.duration {
+            Some(duration) => human_duration(duration),
+            None => "none".to_string(),
+        },
+        "remaining" => match (fields.duration, fields.elapsed) {
+            (Some(duration), Some(elapsed)) => human_duration(duration.saturating_sub(elapsed)),
+            (None, Some(_)) => "none".to_string(),
+            (_, None) => "-".to_string(),
+        },
+        "signal" => known(fields.signal.map(str::to_string)),
+        "exit_code" => fields.exit_code.to_string(),
+        "timed_out" => fields.timed_out.to_string(),
+        "reason" => known(fields.reason.clone()),
+        "attempt" => known(fields.attempt.map(|n| n.to_string())),
+        "iteration" => known(fields.iteration.map(|n| n.to_string())),
+        "pid" => known(fields.pid.map(|pid| pid.to_string())),
+        "max_rss" => known(fields.max_rss_bytes.map(crate::rusage::binary_size)),
+        "error" => known(fields.error.map(str::to_string)),
+        _ => unreachable!("{} is in PLACEHOLDERS", name),
+    }
+}
+
+/// A duration for people: `250ms`, `5s`, `5.03s`, `2m 5s` or `1h 2m 5s`
+pub fn human_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        format!("{}ms", duration.as_millis())
+    } else if secs < 60 {
+        let secs = format!("{:.2}", duration.as_secs_f64());
+        format!("{}s", secs.trim_end_matches('0').trim_end_matches('.'))
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m {}s", secs / 3600, secs % 3600 / 60, secs % 60)
+    }
+}
@@ -0,0 +1,411 @@
+// src/syslog.rs
+// Run outcomes as syslog messages (--log syslog), to /dev/log or to a
+// remote server over UDP or TCP
+
+use crate::logfile::{civil_from_days, rfc3339};
+use crate::logging::{info, warning};
+use crate::sink::{hostname, Outcome, OutcomeSink};
+use crate::TimeoutError;
+use owo_colors::OwoColorize;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+
+/// The local syslog daemon's socket
+#[cfg(unix)]
+const DEV_LOG: &str = "/dev/log";
+
+const DEFAULT_PORT: u16 = 514;
+
+/// Limit on connecting to and writing to a TCP server, so an unreachable
+/// one holds up timeout's exit by seconds rather than minutes
+const TCP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Structured data ID for the outcome fields in RFC 5424 messages. 32473 is
+/// the enterprise number RFC 5612 sets aside for examples; there is no
+/// registered one for timeout.
+const SD_ID: &str = "timeout@32473";
+
+/// Facility names and their codes, as syslog.conf spells them
+const FACILITIES: [(&str, u8); 20] = [
+    ("kern", 0),
+    ("user", 1),
+    ("mail", 2),
+    ("daemon", 3),
+    ("auth", 4),
+    ("syslog", 5),
+    ("lpr", 6),
+    ("news", 7),
+    ("uucp", 8),
+    ("cron", 9),
+    ("authpriv", 10),
+    ("ftp", 11),
+    ("local0", 16),
+    ("local1", 17),
+    ("local2", 18),
+    ("local3", 19),
+    ("local4", 20),
+    ("local5", 21),
+    ("local6", 22),
+    ("local7", 23),
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Message format (--syslog-format)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogFormat {
+    /// Structured data carries the outcome fields, the message the summary
+    Rfc5424,
+    /// BSD syslog: the message is the outcome as a JSON object
+    Rfc3164,
+}
+
+impl SyslogFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyslogFormat::Rfc5424 => "rfc5424",
+            SyslogFormat::Rfc3164 => "rfc3164",
+        }
+    }
+}
+
+/// How messages reach the syslog server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transport {
+    /// A datagram to /dev/log
+    Local,
+    /// A datagram to HOST:PORT
+    Udp(String),
+    /// A TCP connection per message to HOST:PORT, octet-counted as RFC 6587
+    /// describes
+    Tcp(String),
+}
+
+/// A --log syslog target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyslogTarget {
+    pub transport: Transport,
+    pub facility: &'static str,
+}
+
+impl SyslogTarget {
+    /// Parse `syslog[:FACILITY]`, `syslog://HOST[:PORT][/FACILITY]` or
+    /// `syslog+tcp://HOST[:PORT][/FACILITY]`; the facility defaults to user
+    pub fn parse(input: &str) -> Result<SyslogTarget, TimeoutError> {
+        let invalid = |reason: String| TimeoutError::InvalidLogTarget {
+            input: input.to_string(),
+            reason,
+        };
+
+        let (transport, facility) = if let Some(rest) = input.strip_prefix("syslog://") {
+            let (address, facility) = split_facility(rest);
+            (
+                Transport::Udp(host_port(address, DEFAULT_PORT).map_err(invalid)?),
+                facility,
+            )
+        } else if let Some(rest) = input.strip_prefix("syslog+tcp://") {
+            let (address, facility) = split_facility(rest);
+            (
+                Transport::Tcp(host_port(address, DEFAULT_PORT).map_err(invalid)?),
+                facility,
+            )
+        } else if input == "syslog" {
+            (Transport::Local, None)
+        } else if let Some(facility) = input.strip_prefix("syslog:") {
+            (Transport::Local, Some(facility))
+        } else {
+            return Err(invalid(
+                "expected syslog[:FACILITY], syslog://HOST[:PORT][/FACILITY] or \
+                 syslog+tcp://HOST[:PORT][/FACILITY]"
+                    .to_string(),
+            ));
+        };
+
+        let facility = match facility {
+            None => "user",
+            Some(name) => FACILITIES
+                .iter()
+                .map(|(facility, _)| *facility)
+                .find(|facility| facility.eq_ignore_ascii_case(name))
+                .ok_or_else(|| {
+                    invalid(format!(
+                        "unknown facility '{}' (use kern, user, mail, daemon, auth, syslog, lpr, \
+                         news, uucp, cron, authpriv, ftp or local0 to local7)",
+                        name
+                    ))
+                })?,
+        };
+        Ok(SyslogTarget {
+            transport,
+            facility,
+        })
+    }
+
+    fn facility_code(&self) -> u8 {
+        FACILITIES
+            .iter()
+            .find(|(name, _)| *name == self.facility)
+            .map_or(1, |(_, code)| *code)
+    }
+}
+
+/// Split `HOST[:PORT][/FACILITY]` at the slash
+fn split_facility(rest: &str) -> (&str, Option<&str>) {
+    match rest.split_once('/') {
+        Some((address, facility)) => (address, Some(facility)),
+        None => (rest, None),
+    }
+}
+
+/// `HOST[:PORT]` as `HOST:PORT`, with `default_port` if there is none; an
+/// IPv6 HOST goes in brackets
+pub fn host_port(address: &str, default_port: u16) -> Result<String, String> {
+    let (host, port) = match address.strip_prefix('[') {
+        Some(rest) => {
+            let (host, rest) = rest
+                .split_once(']')
+                .ok_or_else(|| "missing ']' after an IPv6 address".to_string())?;
+            let port = match rest {
+                "" => None,
+                _ => Some(
+                    rest.strip_prefix(':')
+                        .ok_or_else(|| format!("unexpected '{}' after ']'", rest))?,
+                ),
+            };
+            (format!("[{}]", host), port)
+        }
+        None => match address.split_once(':') {
+            Some((host, port)) => (host.to_string(), Some(port)),
+            None => (address.to_string(), None),
+        },
+    };
+    if host.is_empty() || host == "[]" {
+        return Err("missing host".to_string());
+    }
+    let port: u16 = match port {
+        None => default_port,
+        Some(port) => port
+            .parse()
+            .map_err(|_| format!("invalid port '{}'", port))?,
+    };
+    Ok(format!("{}:{}", host, port))
+}
+
+impl fmt::Display for SyslogTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.transport {
+            Transport::Local => write!(f, "syslog:{}", self.facility),
+            Transport::Udp(address) => write!(f, "syslog://{}/{}", address, self.facility),
+            Transport::Tcp(address) => write!(f, "syslog+tcp://{}/{}", address, self.facility),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Connection {
+    #[cfg(unix)]
+    Local(std::os::unix::net::UnixDatagram),
+    Udp(UdpSocket),
+    /// Connected afresh for each message, so a server restart between runs
+    /// does not lose the next one
+    Tcp(Vec<SocketAddr>),
+}
+
+/// A connected --log syslog target
+#[derive(Debug)]
+pub struct Syslog {
+    target: SyslogTarget,
+    format: SyslogFormat,
+    connection: Connection,
+}
+
+impl Syslog {
+    /// Connect to the target (TIMEOUT_SYSLOG_SOCKET overrides /dev/log).
+    /// If it cannot be reached, warn and return `None`: runs are then only
+    /// reported on stderr as usual.
+    pub fn open(target: SyslogTarget, format: SyslogFormat) -> Option<Syslog> {
+        match Self::connect(&target.transport) {
+            Ok(connection) => Some(Syslog {
+                target,
+                format,
+                connection,
+            }),
+            Err(e) => {
+                warning!(
+                    "{}: --log {}: {}; logging to stderr instead",
+                    "Warning".yellow(),
+                    target,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn connect(transport: &Transport) -> io::Result<Connection> {
+        match transport {
+            #[cfg(unix)]
+            Transport::Local => {
+                let path =
+                    std::env::var_os("TIMEOUT_SYSLOG_SOCKET").unwrap_or_else(|| DEV_LOG.into());
+                let socket = std::os::unix::net::UnixDatagram::unbound()?;
+                socket.connect(&path).map_err(|e| {
+                    io::Error::new(
+                        e.kind(),
+                        format!("cannot connect to {}: {}", path.to_string_lossy(), e),
+                    )
+                })?;
+                Ok(Connection::Local(socket))
+            }
+            #[cfg(not(unix))]
+            Transport::Local => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("there is no /dev/log on {}", crate::Platform::name()),
+            )),
+            Transport::Udp(address) => Ok(Connection::Udp(udp_connect(address)?)),
+            Transport::Tcp(address) => Ok(Connection::Tcp(resolve(address)?)),
+        }
+    }
+
+    /// One message for `outcome`, in the configured format
+    fn message(&self, outcome: &Outcome) -> String {
+        let priority = self.target.facility_code() * 8 + outcome.severity();
+        let host = hostname()
+            .filter(|name| !name.is_empty() && !name.contains(' '))
+            .unwrap_or_else(|| "-".to_string());
+        let pid = std::process::id();
+        match self.format {
+            SyslogFormat::Rfc5424 => {
+                let params: String = outcome
+                    .fields()
+                    .iter()
+                    .map(|(name, value)| format!(" {}=\"{}\"", name, escape_param(value)))
+                    .collect();
+                format!(
+                    "<{}>1 {} {} timeout {} outcome [{}{}] {}",
+                    priority,
+                    rfc3339(outcome.ended),
+                    host,
+                    pid,
+                    SD_ID,
+                    params,
+                    outcome.message()
+                )
+            }
+            SyslogFormat::Rfc3164 => format!(
+                "<{}>{} {} timeout[{}]: {}",
+                priority,
+                bsd_timestamp(outcome.ended),
+                host,
+                pid,
+                outcome.to_json()
+            ),
+        }
+    }
+
+    async fn send(&self, message: &str) -> io::Result<()> {
+        match &self.connection {
+            #[cfg(unix)]
+            Connection::Local(socket) => socket.send(message.as_bytes()).map(drop),
+            Connection::Udp(socket) => socket.send(message.as_bytes()).map(drop),
+            Connection::Tcp(addresses) => {
+                let frame = format!("{} {}", message.len(), message);
+                tokio::time::timeout(TCP_TIMEOUT, async {
+                    let mut stream = tokio::net::TcpStream::connect(&addresses[..]).await?;
+                    stream.write_all(frame.as_bytes()).await?;
+                    stream.shutdown().await
+                })
+                .await
+                .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()))
+            }
+        }
+    }
+}
+
+impl OutcomeSink for Syslog {
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(async move {
+            match self.send(&self.message(outcome)).await {
+                Ok(()) => {
+                    if verbose {
+                        info!("{}: logged to {}", "Info".cyan(), self.target);
+                    }
+                    true
+                }
+                Err(e) => {
+                    warning!(
+                        "{}: --log {}: cannot send: {}",
+                        "Warning".yellow(),
+                        self.target,
+                        e
+                    );
+                    false
+                }
+            }
+        })
+    }
+}
+
+/// A UDP socket bound to an ephemeral port and connected to `address`
+pub fn udp_connect(address: &str) -> io::Result<UdpSocket> {
+    let address = resolve(address)?[0];
+    let any: SocketAddr = match address {
+        SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+        SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+    };
+    let socket = UdpSocket::bind(any)?;
+    socket.connect(address)?;
+    Ok(socket)
+}
+
+fn resolve(address: &str) -> io::Result<Vec<SocketAddr>> {
+    let addresses: Vec<_> = address.to_socket_addrs()?.collect();
+    if addresses.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} has no address", address),
+        ));
+    }
+    Ok(addresses)
+}
+
+/// Backslash the characters RFC 5424 does not allow bare in a PARAM-VALUE
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// RFC 3164's `Mmm dd hh:mm:ss`, in UTC
+fn bsd_timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (_, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{} {:>2} {:02}:{:02}:{:02}",
+        MONTHS[month as usize - 1],
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,121 @@
+// src/time_format.rs
+// --time: the time(1)-style report of a run, laid out by a GNU time
+// format string (--time-format)
+
+use crate::rusage::ResourceUsage;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// The report printed without --time-format. %T is timeout's own
+/// directive; the rest are GNU time's.
+pub const DEFAULT_FORMAT: &str = "real\t%es\nuser\t%Us\nsys\t%Ss\nmaxrss\t%MKB\ntimed out\t%T";
+
+/// What a --time report is made from
+#[derive(Debug, Clone, Default)]
+pub struct TimeReport {
+    /// COMMAND and its arguments
+    pub command: String,
+    pub elapsed: Duration,
+    pub usage: ResourceUsage,
+    pub exit_code: i32,
+    pub timed_out: bool,
+}
+
+/// Expand `format` for `report`, with a newline at the end as GNU time
+/// adds. These GNU time directives are understood:
+///
+/// - `%e` real time in seconds, `%E` as [hours:]minutes:seconds
+/// - `%U`, `%S` user and system CPU seconds; `%P` their share of real time
+/// - `%M` peak resident set size in KiB
+/// - `%F`, `%R` major and minor page faults
+/// - `%I`, `%O` block input and output operations
+/// - `%x` exit status, `%C` the command line
+/// - `%%` a percent sign, and the escapes `\n`, `\t` and `\\`
+///
+/// `%T` (`yes` or `no`) says whether the run timed out. Anything else
+/// comes out as `?` followed by the character, as in GNU time, and values
+/// that were not collected (page faults and block I/O on Windows, or
+/// anything for a process left running) as `?`.
+pub fn render(format: &str, report: &TimeReport) -> String {
+    let usage = &report.usage;
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => match chars.next() {
+                Some('e') => {
+                    let _ = write!(out, "{:.2}", report.elapsed.as_secs_f64());
+                }
+                Some('E') => out.push_str(&clock(report.elapsed)),
+                Some('U') => out.push_str(&seconds(usage.user_cpu)),
+                Some('S') => out.push_str(&seconds(usage.sys_cpu)),
+                Some('P') => out.push_str(&cpu_percent(report)),
+                Some('M') => out.push_str(&count(usage.max_rss_bytes.map(|b| b / 1024))),
+                Some('F') => out.push_str(&count(usage.major_faults)),
+                Some('R') => out.push_str(&count(usage.minor_faults)),
+                Some('I') => out.push_str(&count(usage.block_input_ops)),
+                Some('O') => out.push_str(&count(usage.block_output_ops)),
+                Some('x') => {
+                    let _ = write!(out, "{}", report.exit_code);
+                }
+                Some('C') => out.push_str(&report.command),
+                Some('T') => out.push_str(if report.timed_out { "yes" } else { "no" }),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('?');
+                    out.push(other);
+                }
+                None => out.push('?'),
+            },
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('\\') => out.push('\\'),
+                Some(other) => {
+                    out.push('?');
+                    out.push('\\');
+                    out.push(other);
+                }
+                None => out.push('?'),
+            },
+            c => out.push(c),
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// GNU time's %E: `h:mm:ss` from an hour up, `m:ss.cc` below it
+fn clock(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!(
+            "{}:{:02}.{:02}",
+            secs / 60,
+            secs % 60,
+            elapsed.subsec_millis() / 10
+        )
+    }
+}
+
+fn seconds(time: Option<Duration>) -> String {
+    time.map_or("?".to_string(), |t| format!("{:.2}", t.as_secs_f64()))
+}
+
+fn count(n: Option<u64>) -> String {
+    n.map_or("?".to_string(), |n| n.to_string())
+}
+
+/// CPU time as a percentage of real time, which is over 100% when
+/// COMMAND kept several cores busy
+fn cpu_percent(report: &TimeReport) -> String {
+    let real = report.elapsed.as_secs_f64();
+    match (report.usage.user_cpu, report.usage.sys_cpu) {
+        (Some(user), Some(sys)) if real > 0.0 => {
+            format!("{:.0}%", (user + sys).as_secs_f64() * 100.0 / real)
+        }
+        _ => "?%".to_string(),
+    }
+}
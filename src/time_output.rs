@@ -0,0 +1,74 @@
+// src/time_output.rs
+// Where the --time report of each run goes: stderr, or the
+// --time-output FILE
+
+use crate::logging::warning;
+use crate::time_format::{self, TimeReport};
+use crate::{TimeoutError, TimeoutMetrics};
+use owo_colors::OwoColorize;
+use std::fs::File;
+use std::io::Write;
+use std::sync::Mutex;
+
+/// An opened --time target
+#[derive(Debug)]
+pub struct TimeOutput {
+    format: String,
+    path: Option<String>,
+    /// `None` for stderr
+    file: Option<Mutex<File>>,
+}
+
+impl TimeOutput {
+    /// Open `path` now, truncating it as GNU time's -o does, so a file that
+    /// cannot be created fails the run before COMMAND starts. Every run of
+    /// --retries or --every then adds its report to it.
+    pub fn open(format: &str, path: Option<&str>) -> Result<TimeOutput, TimeoutError> {
+        let file = match path {
+            Some(path) => {
+                Some(
+                    File::create(path).map_err(|source| TimeoutError::TimeOutputFailed {
+                        path: path.to_string(),
+                        source,
+                    })?,
+                )
+            }
+            None => None,
+        };
+        Ok(TimeOutput {
+            format: format.to_string(),
+            path: path.map(str::to_string),
+            file: file.map(Mutex::new),
+        })
+    }
+
+    /// Write the report of one run. Like the metrics record it is data,
+    /// not a message, so TIMEOUT_LOG does not filter it.
+    pub fn write(&self, metrics: &TimeoutMetrics) {
+        let report = TimeReport {
+            // With --pid there is only the process name
+            command: if metrics.argv.is_empty() {
+                metrics.command.clone()
+            } else {
+                metrics.argv.join(" ")
+            },
+            elapsed: metrics.elapsed,
+            usage: metrics.usage,
+            exit_code: metrics.exit_code,
+            timed_out: metrics.timed_out,
+        };
+        let text = time_format::render(&self.format, &report);
+        let Some(file) = &self.file else {
+            eprint!("{}", text);
+            return;
+        };
+        if let Err(e) = file.lock().unwrap().write_all(text.as_bytes()) {
+            warning!(
+                "{}: cannot write --time report to {}: {}",
+                "Warning".yellow(),
+                self.path.as_deref().unwrap_or_default(),
+                e
+            );
+        }
+    }
+}
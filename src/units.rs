@@ -0,0 +1,191 @@
+// src/units.rs
+// Durations and sizes as given on the command line
+
+use crate::{Platform, TimeoutError};
+use std::time::Duration;
+
+/// Parse a DURATION: a non-negative number with an optional `s`, `m`, `h`
+/// or `d` suffix (seconds by default)
+pub fn parse_duration(input: &str) -> Result<Duration, TimeoutError> {
+    let input = input.trim();
+
+    if input == "0" {
+        return Ok(Duration::from_secs(0));
+    }
+
+    let (value_str, multiplier) = match input.chars().last() {
+        // The suffix may be any letter, including one that is several
+        // bytes long in UTF-8, so the split is made before the whole char
+        Some(last) if last.is_alphabetic() => {
+            let (val, suffix) = input.split_at(input.len() - last.len_utf8());
+            let mult = match suffix {
+                "s" => 1,
+                "m" => 60,
+                "h" => 3600,
+                "d" => 86400,
+                _ => {
+                    return Err(TimeoutError::InvalidDuration {
+                        input: input.to_string(),
+                        reason: format!("invalid time suffix '{}'", suffix),
+                    })
+                }
+            };
+            (val, mult)
+        }
+        _ => (input, 1),
+    };
+
+    let value: f64 = value_str
+        .parse()
+        .map_err(|_| TimeoutError::InvalidDuration {
+            input: input.to_string(),
+            reason: format!("invalid numeric value '{}'", value_str),
+        })?;
+
+    if value.is_nan() {
+        return Err(TimeoutError::InvalidDuration {
+            input: input.to_string(),
+            reason: format!("invalid numeric value '{}'", value_str),
+        });
+    }
+
+    if value < 0.0 {
+        return Err(TimeoutError::InvalidDuration {
+            input: input.to_string(),
+            reason: "duration cannot be negative".to_string(),
+        });
+    }
+
+    // Duration::from_secs_f64 panics on overflow; keep the nanosecond count
+    // within u64
+    let seconds = value * multiplier as f64;
+    if seconds > u64::MAX as f64 / 1e9 {
+        return Err(TimeoutError::InvalidDuration {
+            input: input.to_string(),
+            reason: "duration too large (maximum ~585 years)".to_string(),
+        });
+    }
+
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Size suffixes, longest first so "KiB" is not read as "B" after "Ki".
+/// Matched case-insensitively; `KB` and friends are decimal, the rest binary.
+const SIZE_SUFFIXES: &[(&str, u64)] = &[
+    ("KIB", 1 << 10),
+    ("MIB", 1 << 20),
+    ("GIB", 1 << 30),
+    ("TIB", 1 << 40),
+    ("PIB", 1 << 50),
+    ("KB", 1000),
+    ("MB", 1000 * 1000),
+    ("GB", 1000 * 1000 * 1000),
+    ("TB", 1000 * 1000 * 1000 * 1000),
+    ("PB", 1000 * 1000 * 1000 * 1000 * 1000),
+    ("K", 1 << 10),
+    ("M", 1 << 20),
+    ("G", 1 << 30),
+    ("T", 1 << 40),
+    ("P", 1 << 50),
+];
+
+/// Parse a size in bytes, with an optional size suffix or as a percentage
+/// of physical memory, e.g. "512M" or "50%"
+pub fn parse_memory_limit(input: &str) -> Result<u64, TimeoutError> {
+    let input = input.trim();
+
+    if let Some(percent) = input.strip_suffix('%') {
+        return parse_memory_percent(input, percent);
+    }
+
+    let suffix = SIZE_SUFFIXES.iter().find(|(suffix, _)| {
+        input.len() > suffix.len()
+            && input.is_char_boundary(input.len() - suffix.len())
+            && input[input.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+    });
+
+    let (value_str, multiplier) = match suffix {
+        Some((suffix, mult)) => (&input[..input.len() - suffix.len()], *mult),
+        None if input.ends_with(|c: char| c.is_alphabetic()) => {
+            // Where the trailing letters start, always on a char boundary
+            let start = input.trim_end_matches(|c: char| c.is_alphabetic()).len();
+            return Err(TimeoutError::InvalidMemoryLimit {
+                input: input.to_string(),
+                reason: format!(
+                    "invalid size suffix '{}' (use K, M, G, T, P, KB, ..., PB or KiB, ..., PiB)",
+                    &input[start..]
+                ),
+            });
+        }
+        None => (input, 1),
+    };
+
+    let value: u64 = value_str
+        .parse()
+        .map_err(|_| TimeoutError::InvalidMemoryLimit {
+            input: input.to_string(),
+            reason: format!("invalid numeric value '{}'", value_str),
+        })?;
+
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| TimeoutError::InvalidMemoryLimit {
+            input: input.to_string(),
+            reason: "value too large".to_string(),
+        })
+}
+
+/// Resolve a size given as a percentage of physical memory, e.g. "50%"
+fn parse_memory_percent(input: &str, percent: &str) -> Result<u64, TimeoutError> {
+    let invalid = |reason: String| TimeoutError::InvalidMemoryLimit {
+        input: input.to_string(),
+        reason,
+    };
+
+    let percent: f64 = percent
+        .trim()
+        .parse()
+        .map_err(|_| invalid(format!("invalid percentage '{}'", percent)))?;
+    if !(percent > 0.0 && percent <= 100.0) {
+        return Err(invalid(
+            "percentage must be above 0 and at most 100".to_string(),
+        ));
+    }
+
+    let total = Platform::total_memory()
+        .ok_or_else(|| invalid("cannot determine total system memory".to_string()))?;
+    Ok((total as f64 * percent / 100.0) as u64)
+}
+
+/// Parse a --max-output size, using the same syntax as --mem-limit
+pub fn parse_output_limit(input: &str) -> Result<u64, TimeoutError> {
+    parse_memory_limit(input).map_err(|e| match e {
+        TimeoutError::InvalidMemoryLimit { input, reason } => {
+            TimeoutError::InvalidOutputLimit { input, reason }
+        }
+        e => e,
+    })
+}
+
+/// A size in bytes for people to read, in the largest unit that holds it
+/// exactly, e.g. `512MiB` or `1500KB`, so it parses back to the same
+/// number. Sizes no unit divides are shown as `N bytes`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [(&str, u64); 10] = [
+        ("PiB", 1 << 50),
+        ("TiB", 1 << 40),
+        ("GiB", 1 << 30),
+        ("MiB", 1 << 20),
+        ("KiB", 1 << 10),
+        ("PB", 1000 * 1000 * 1000 * 1000 * 1000),
+        ("TB", 1000 * 1000 * 1000 * 1000),
+        ("GB", 1000 * 1000 * 1000),
+        ("MB", 1000 * 1000),
+        ("KB", 1000),
+    ];
+    UNITS
+        .iter()
+        .find(|(_, size)| bytes != 0 && bytes.is_multiple_of(*size))
+        .map(|(unit, size)| format!("{}{}", bytes / size, unit))
+        .unwrap_or_else(|| format!("{} bytes", bytes))
+}
@@ -0,0 +1,172 @@
+// src/webhook.rs
+// JSON report of each run POSTed to --webhook
+
+use crate::logging::{info, warning};
+use crate::sink::{Outcome, OutcomeEvent, OutcomeSink};
+use crate::TimeoutError;
+use owo_colors::OwoColorize;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Delivery attempts before giving up
+#[cfg(feature = "webhook")]
+const ATTEMPTS: u32 = 2;
+
+/// Limit on each attempt, so an unreachable server holds up timeout's own
+/// exit by seconds rather than minutes. minreq counts in whole seconds.
+#[cfg(feature = "webhook")]
+const ATTEMPT_TIMEOUT_SECS: u64 = 3;
+
+/// Where and when to report runs (--webhook, --webhook-on, --webhook-header)
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: String,
+    /// Extra request headers, in the order given
+    pub headers: Vec<(String, String)>,
+    /// Outcomes to report; every outcome when empty
+    pub events: Vec<OutcomeEvent>,
+}
+
+impl Webhook {
+    /// Check the URL and split each `Name: value` header. Event names have
+    /// already been checked by clap.
+    pub fn new(url: &str, headers: &[String], events: &[String]) -> Result<Webhook, TimeoutError> {
+        check_url(url)?;
+        let headers = headers
+            .iter()
+            .map(|header| match header.split_once(':') {
+                Some((name, value)) if !name.trim().is_empty() => {
+                    Ok((name.trim().to_string(), value.trim().to_string()))
+                }
+                _ => Err(TimeoutError::InvalidWebhook {
+                    input: header.clone(),
+                    reason: "expected a header of the form 'Name: value'".to_string(),
+                }),
+            })
+            .collect::<Result<_, _>>()?;
+        let events = events
+            .iter()
+            .map(|event| match event.as_str() {
+                "success" => OutcomeEvent::Success,
+                "timeout" => OutcomeEvent::Timeout,
+                _ => OutcomeEvent::Failure,
+            })
+            .collect();
+        Ok(Webhook {
+            url: url.to_string(),
+            headers,
+            events,
+        })
+    }
+
+    /// Whether runs that end this way are reported
+    pub fn wants(&self, event: OutcomeEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+
+    /// The events to report, for --dry-run
+    pub fn describe_events(&self) -> Vec<&'static str> {
+        if self.events.is_empty() {
+            return vec!["success", "failure", "timeout"];
+        }
+        self.events.iter().map(OutcomeEvent::as_str).collect()
+    }
+
+    /// POST `body`, returning whether it was delivered
+    async fn send(&self, body: String, verbose: bool) -> bool {
+        let webhook = self.clone();
+        let result = tokio::task::spawn_blocking(move || webhook.post(&body))
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+        match result {
+            Ok(status) => {
+                if verbose {
+                    info!("{}: POST {}: {}", "Webhook".cyan(), self.url, status);
+                }
+                true
+            }
+            Err(reason) => {
+                warning!(
+                    "{}: --webhook delivery to {} failed: {}",
+                    "Warning".yellow(),
+                    self.url,
+                    reason
+                );
+                false
+            }
+        }
+    }
+
+    /// Blocking POST with a bounded retry; returns the status line
+    #[cfg(feature = "webhook")]
+    fn post(&self, body: &str) -> Result<String, String> {
+        let mut last_error = String::new();
+        for attempt in 1..=ATTEMPTS {
+            let mut request = minreq::post(&self.url)
+                .with_header("Content-Type", "application/json")
+                .with_timeout(ATTEMPT_TIMEOUT_SECS)
+                .with_body(body);
+            for (name, value) in &self.headers {
+                request = request.with_header(name, value);
+            }
+            match request.send() {
+                Ok(response) if (200..300).contains(&response.status_code) => {
+                    return Ok(format!(
+                        "{} {}",
+                        response.status_code, response.reason_phrase
+                    ))
+                }
+                Ok(response) => {
+                    last_error = format!("{} {}", response.status_code, response.reason_phrase)
+                }
+                Err(e) => last_error = e.to_string(),
+            }
+            if attempt < ATTEMPTS {
+                std::thread::sleep(std::time::Duration::from_millis(250));
+            }
+        }
+        Err(format!("{} (after {} attempts)", last_error, ATTEMPTS))
+    }
+
+    #[cfg(not(feature = "webhook"))]
+    fn post(&self, _body: &str) -> Result<String, String> {
+        Err("this timeout was built without the webhook feature".to_string())
+    }
+}
+
+/// The body is `Outcome::to_json`, POSTed once any completion hook has run
+impl OutcomeSink for Webhook {
+    fn record<'a>(
+        &'a self,
+        outcome: &'a Outcome<'a>,
+        verbose: bool,
+    ) -> Pin<Box<dyn Future<Output = bool> + Send + 'a>> {
+        Box::pin(async move {
+            self.wants(outcome.event()) && self.send(outcome.to_json(), verbose).await
+        })
+    }
+
+    fn is_log(&self) -> bool {
+        false
+    }
+}
+
+/// Only http:// is built in; https:// needs the webhook-tls feature
+fn check_url(url: &str) -> Result<(), TimeoutError> {
+    let reason = if cfg!(not(feature = "webhook")) {
+        "this timeout was built without the webhook feature"
+    } else if url.starts_with("http://") {
+        return Ok(());
+    } else if url.starts_with("https://") {
+        if cfg!(feature = "webhook-tls") {
+            return Ok(());
+        }
+        "https:// URLs need timeout built with the webhook-tls feature"
+    } else {
+        "expected an http:// or https:// URL"
+    };
+    Err(TimeoutError::InvalidWebhook {
+        input: url.to_string(),
+        reason: reason.to_string(),
+    })
+}
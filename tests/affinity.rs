@@ -0,0 +1,55 @@
+// tests/affinity.rs
+// CPU list parsing and printing for --cpu-affinity
+
+use timeout::affinity::{format_cpu_list, parse_cpu_list, MAX_CPUS};
+#[test]
+fn single_cpus_and_ranges() {
+    assert_eq!(parse_cpu_list("0"), Ok(vec![0]));
+    assert_eq!(parse_cpu_list("0,2-4"), Ok(vec![0, 2, 3, 4]));
+    assert_eq!(parse_cpu_list("0-3,7"), Ok(vec![0, 1, 2, 3, 7]));
+    assert_eq!(parse_cpu_list("5-5"), Ok(vec![5]));
+}
+
+#[test]
+fn sorted_without_duplicates() {
+    assert_eq!(parse_cpu_list("7,0-2,1,3"), Ok(vec![0, 1, 2, 3, 7]));
+    assert_eq!(parse_cpu_list(" 1 , 0 "), Ok(vec![0, 1]));
+}
+
+#[test]
+fn rejects_malformed_lists() {
+    for input in ["", ",", "0,", "a", "1-", "-1", "1-2-3", "0x1", "1.5"] {
+        assert!(parse_cpu_list(input).is_err(), "{:?} accepted", input);
+    }
+    assert_eq!(
+        parse_cpu_list("4-2"),
+        Err("range 4-2 goes backwards".to_string())
+    );
+}
+
+#[test]
+fn rejects_cpus_past_the_set_size() {
+    assert_eq!(
+        parse_cpu_list(&(MAX_CPUS - 1).to_string()),
+        Ok(vec![MAX_CPUS - 1])
+    );
+    assert_eq!(
+        parse_cpu_list("0-1024"),
+        Err("CPU 1024 is out of range (0-1023)".to_string())
+    );
+}
+
+#[test]
+fn formats_runs_as_ranges() {
+    assert_eq!(format_cpu_list(&[0]), "0");
+    assert_eq!(format_cpu_list(&[0, 2, 3, 4]), "0,2-4");
+    assert_eq!(format_cpu_list(&[0, 1, 2, 3, 7, 9, 10]), "0-3,7,9-10");
+}
+
+#[test]
+fn formatting_round_trips() {
+    for input in ["0", "0,2-4", "1-3,5,7-9", "0-1023"] {
+        let cpus = parse_cpu_list(input).unwrap();
+        assert_eq!(format_cpu_list(&cpus), input);
+    }
+}
@@ -0,0 +1,284 @@
+// tests/config.rs
+// Finding, parsing and merging the config file
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use timeout::config::{environment, merge, parse, search_path, Settings, Source};
+
+/// An environment holding only `vars`
+fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<OsString> {
+    let vars: Vec<(String, OsString)> = vars
+        .iter()
+        .map(|(name, value)| (name.to_string(), OsString::from(value)))
+        .collect();
+    move |name| {
+        vars.iter()
+            .find(|(var, _)| var == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+fn kill_after(value: &str) -> Settings {
+    Settings {
+        kill_after: Some(value.to_string()),
+        ..Settings::default()
+    }
+}
+
+#[test]
+fn search_order() {
+    assert_eq!(
+        search_path(env(&[
+            ("TIMEOUT_CONFIG", "/etc/timeout.toml"),
+            ("XDG_CONFIG_HOME", "/xdg"),
+            ("HOME", "/home/me"),
+        ])),
+        vec![(PathBuf::from("/etc/timeout.toml"), true)]
+    );
+    assert_eq!(
+        search_path(env(&[("XDG_CONFIG_HOME", "/xdg"), ("HOME", "/home/me")])),
+        vec![
+            (PathBuf::from("/xdg/timeout/config.toml"), false),
+            (PathBuf::from("/home/me/.config/timeout/config.toml"), false),
+        ]
+    );
+    // Empty variables are unset, and a relative XDG_CONFIG_HOME is ignored
+    assert_eq!(
+        search_path(env(&[
+            ("TIMEOUT_CONFIG", ""),
+            ("XDG_CONFIG_HOME", "relative"),
+            ("HOME", "/home/me"),
+        ])),
+        vec![(PathBuf::from("/home/me/.config/timeout/config.toml"), false)]
+    );
+    // The same file is only listed once
+    assert_eq!(
+        search_path(env(&[
+            ("XDG_CONFIG_HOME", "/home/me/.config"),
+            ("HOME", "/home/me")
+        ]))
+        .len(),
+        1
+    );
+    assert!(search_path(env(&[])).is_empty());
+}
+
+#[test]
+fn parses_every_key() {
+    let text = r#"
+# CI defaults
+kill_after = "10s"
+signal = "INT"
+preserve_status = true
+verbose = true
+color = "never"
+metrics_file = "/var/log/timeout.jsonl"
+"#;
+    assert_eq!(
+        parse(Path::new("config.toml"), text),
+        Ok(Settings {
+            kill_after: Some("10s".to_string()),
+            signal: Some("INT".to_string()),
+            preserve_status: Some(true),
+            verbose: Some(true),
+            color: Some("never".to_string()),
+            metrics_file: Some("/var/log/timeout.jsonl".to_string()),
+        })
+    );
+    assert_eq!(parse(Path::new("config.toml"), ""), Ok(Settings::default()));
+}
+
+#[test]
+fn reports_where_a_file_is_wrong() {
+    let path = Path::new("/etc/timeout.toml");
+    let error = parse(path, "kill_after = \"10s\"\nkill_afer = \"5s\"\n").unwrap_err();
+    assert_eq!(error.path, path);
+    assert!(
+        error
+            .reason
+            .starts_with("line 2, column 1: unknown field `kill_afer`"),
+        "{}",
+        error.reason
+    );
+
+    let error = parse(path, "verbose = yes\n").unwrap_err();
+    assert!(
+        error.reason.starts_with("line 1, column 11: "),
+        "{}",
+        error.reason
+    );
+
+    let error = parse(path, "preserve_status = \"true\"\n").unwrap_err();
+    assert!(error.reason.starts_with("line 1, "), "{}", error.reason);
+
+    let error = parse(path, "color = \"sometimes\"\n").unwrap_err();
+    assert_eq!(
+        error.reason,
+        "color must be auto, always or never, not 'sometimes'"
+    );
+}
+
+#[test]
+fn command_line_over_environment_over_file_over_default() {
+    let cli = kill_after("1s");
+    let env = kill_after("2s");
+    let file = kill_after("3s");
+    let none = Settings::default();
+
+    let pick = |cli: &Settings, env: &Settings, file: &Settings| {
+        let effective = merge(cli, env, file).kill_after;
+        (effective.value, effective.source)
+    };
+    assert_eq!(
+        pick(&cli, &env, &file),
+        (Some("1s".to_string()), Source::CommandLine)
+    );
+    assert_eq!(
+        pick(&none, &env, &file),
+        (Some("2s".to_string()), Source::Environment)
+    );
+    assert_eq!(
+        pick(&none, &none, &file),
+        (Some("3s".to_string()), Source::File)
+    );
+    assert_eq!(pick(&none, &none, &none), (None, Source::Default));
+}
+
+#[test]
+fn built_in_defaults() {
+    let effective = merge(
+        &Settings::default(),
+        &Settings::default(),
+        &Settings::default(),
+    );
+    assert_eq!(effective.preserve_status.value, Some(false));
+    assert_eq!(effective.verbose.value, Some(false));
+    assert_eq!(effective.color.value.as_deref(), Some("auto"));
+    assert_eq!(effective.metrics_file.value, None);
+    assert_eq!(effective.color.source, Source::Default);
+    assert!(effective.file_settings().is_empty());
+}
+
+#[test]
+fn environment_variables_stand_in_for_settings() {
+    let file = Settings {
+        color: Some("always".to_string()),
+        metrics_file: Some("/from/file".to_string()),
+        ..Settings::default()
+    };
+
+    let effective = merge(
+        &Settings::default(),
+        &environment(env(&[
+            ("NO_COLOR", "1"),
+            ("TIMEOUT_METRICS_FILE", "/from/env"),
+        ])),
+        &file,
+    );
+    assert_eq!(effective.color.value.as_deref(), Some("auto"));
+    assert_eq!(effective.color.source, Source::Environment);
+    assert_eq!(effective.metrics_file.value.as_deref(), Some("/from/env"));
+
+    // Unset, empty or CLICOLOR_FORCE=0 leave the file's choice alone
+    let effective = merge(
+        &Settings::default(),
+        &environment(env(&[("NO_COLOR", ""), ("CLICOLOR_FORCE", "0")])),
+        &file,
+    );
+    assert_eq!(effective.color.value.as_deref(), Some("always"));
+    assert_eq!(effective.color.source, Source::File);
+    assert_eq!(
+        effective.file_settings(),
+        vec![
+            ("color", "always".to_string()),
+            ("metrics_file", "/from/file".to_string())
+        ]
+    );
+}
+
+#[test]
+fn single_purpose_variables() {
+    let settings = environment(env(&[
+        ("TIMEOUT_KILL_AFTER", "30s"),
+        ("TIMEOUT_SIGNAL", "INT"),
+    ]));
+    assert_eq!(settings.kill_after.as_deref(), Some("30s"));
+    assert_eq!(settings.signal.as_deref(), Some("INT"));
+    assert_eq!(
+        environment(env(&[("TIMEOUT_KILL_AFTER", "")])),
+        Settings::default()
+    );
+}
+
+#[test]
+fn options_fill_unset_settings() {
+    let mut settings = Settings {
+        kill_after: Some("30s".to_string()),
+        ..Settings::default()
+    };
+    settings.fill("kill_after", "5s");
+    settings.fill("signal", "HUP");
+    settings.fill("preserve_status", "true");
+    settings.fill("verbose", "2");
+    settings.fill("timestamps", "elapsed");
+    assert_eq!(
+        settings,
+        Settings {
+            kill_after: Some("30s".to_string()),
+            signal: Some("HUP".to_string()),
+            preserve_status: Some(true),
+            verbose: Some(true),
+            ..Settings::default()
+        }
+    );
+}
+
+#[test]
+fn file_settings_become_option_defaults() {
+    let file = Settings {
+        kill_after: Some("10s".to_string()),
+        preserve_status: Some(true),
+        verbose: Some(true),
+        ..Settings::default()
+    };
+    let cli = Settings {
+        preserve_status: Some(true),
+        ..Settings::default()
+    };
+    // What the command line gives is not defaulted again
+    assert_eq!(
+        merge(&cli, &Settings::default(), &file).file_settings(),
+        vec![
+            ("kill_after", "10s".to_string()),
+            ("verbose", "1".to_string())
+        ]
+    );
+}
+
+#[test]
+fn dumps_as_toml() {
+    let file = Settings {
+        signal: Some("INT".to_string()),
+        metrics_file: Some("C:\\logs\\\"runs\".jsonl".to_string()),
+        ..Settings::default()
+    };
+    let dump = merge(&kill_after("5s"), &Settings::default(), &file).to_toml();
+    assert_eq!(
+        dump,
+        "kill_after = \"5s\"  # command line\n\
+         signal = \"INT\"  # config file\n\
+         preserve_status = false  # default\n\
+         verbose = false  # default\n\
+         color = \"auto\"  # default\n\
+         metrics_file = \"C:\\\\logs\\\\\\\"runs\\\".jsonl\"  # config file\n"
+    );
+
+    // It reads back as the same settings
+    let lines: String = dump
+        .lines()
+        .map(|line| line.split("  #").next().unwrap())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let reread = parse(Path::new("dump.toml"), &lines).unwrap();
+    assert_eq!(reread.metrics_file, file.metrics_file);
+}
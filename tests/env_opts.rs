@@ -0,0 +1,142 @@
+// tests/env_opts.rs
+// Splitting TIMEOUT_OPTS into words and reading them as options, against a
+// small stand-in for timeout's own command line
+
+use clap::{Arg, ArgAction, Command};
+use timeout::env_opts::{parse, split, Opt, OptsError};
+
+fn command() -> Command {
+    Command::new("timeout")
+        .arg(Arg::new("kill_after").short('k').long("kill-after"))
+        .arg(Arg::new("signal").short('s').long("signal"))
+        .arg(
+            Arg::new("preserve_status")
+                .long("preserve-status")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(ArgAction::Count),
+        )
+        .arg(
+            Arg::new("log")
+                .long("log")
+                .action(ArgAction::Append)
+                .conflicts_with("no_log"),
+        )
+        .arg(Arg::new("no_log").long("no-log").action(ArgAction::SetTrue))
+        .arg(Arg::new("duration").value_name("DURATION").required(true))
+        .arg(Arg::new("command").value_name("COMMAND").required(true))
+}
+
+fn words(value: &str) -> Vec<String> {
+    split(value).unwrap()
+}
+
+fn opt(id: &str, values: &[&str]) -> Opt {
+    Opt {
+        id: id.to_string(),
+        values: values.iter().map(|v| v.to_string()).collect(),
+    }
+}
+
+fn error(value: &str) -> String {
+    parse(&command(), &words(value)).unwrap_err().to_string()
+}
+
+#[test]
+fn splits_like_sh() {
+    assert_eq!(
+        words("-k 30  --color never"),
+        ["-k", "30", "--color", "never"]
+    );
+    assert_eq!(
+        words(r#"--log-file '/tmp/a b.log' --on-timeout "echo {pid}; kill -QUIT {pid}""#),
+        [
+            "--log-file",
+            "/tmp/a b.log",
+            "--on-timeout",
+            "echo {pid}; kill -QUIT {pid}"
+        ]
+    );
+    assert_eq!(words(r"a\ b 'it''s' x\'y"), ["a b", "its", "x'y"]);
+    assert_eq!(words("--summary-format ''"), ["--summary-format", ""]);
+    // No expansion
+    assert_eq!(words("$HOME ~ *"), ["$HOME", "~", "*"]);
+}
+
+#[test]
+fn empty_values() {
+    assert!(words("").is_empty());
+    assert!(words(" \t\n ").is_empty());
+    assert_eq!(parse(&command(), &[]), Ok(vec![]));
+}
+
+#[test]
+fn unbalanced_quotes() {
+    for value in ["-k '30", r#"--signal "INT"#, r"-k 30\"] {
+        assert_eq!(
+            split(value).unwrap_err().to_string(),
+            "TIMEOUT_OPTS: unterminated quote or trailing backslash"
+        );
+    }
+}
+
+#[test]
+fn options_as_defaults() {
+    assert_eq!(
+        parse(
+            &command(),
+            &words("-k 30 --preserve-status -vv --log a --log=b -sINT")
+        ),
+        Ok(vec![
+            opt("kill_after", &["30"]),
+            opt("signal", &["INT"]),
+            opt("preserve_status", &["true"]),
+            opt("verbose", &["2"]),
+            opt("log", &["a", "b"]),
+        ])
+    );
+}
+
+#[test]
+fn positionals_are_rejected() {
+    assert_eq!(
+        error("-k 30 5s"),
+        "TIMEOUT_OPTS: only options may be given here, not DURATION '5s'"
+    );
+    assert_eq!(
+        error("-- -k"),
+        "TIMEOUT_OPTS: only options may be given here, not DURATION '-k'"
+    );
+}
+
+#[test]
+fn invalid_options_name_the_variable() {
+    assert_eq!(
+        error("--bogus"),
+        "TIMEOUT_OPTS: unexpected argument '--bogus' found"
+    );
+    assert_eq!(
+        error("--kill-after"),
+        "TIMEOUT_OPTS: a value is required for '--kill-after <kill_after>' but none was supplied"
+    );
+    assert_eq!(
+        error("-k 1 -k 2"),
+        "TIMEOUT_OPTS: the argument '--kill-after <kill_after>' cannot be used multiple times"
+    );
+    assert_eq!(
+        error("--log a --no-log"),
+        "TIMEOUT_OPTS: the argument '--log <log>' cannot be used with '--no-log'"
+    );
+    assert_eq!(
+        error("--help"),
+        "TIMEOUT_OPTS: --help and --version cannot be given here"
+    );
+    assert_eq!(
+        parse(&command(), &words("--bogus")),
+        Err(OptsError("unexpected argument '--bogus' found".to_string()))
+    );
+}
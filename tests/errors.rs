@@ -0,0 +1,131 @@
+// tests/errors.rs
+// TimeoutError equality, for the variants wrapping io::Error and Errno
+
+use std::io::{Error, ErrorKind};
+use timeout::TimeoutError;
+
+fn exec_failed(cmd: &str, kind: ErrorKind) -> TimeoutError {
+    TimeoutError::ExecFailed {
+        cmd: cmd.to_string(),
+        source: Error::from(kind),
+    }
+}
+
+#[test]
+fn io_errors_compare_by_kind() {
+    assert_eq!(
+        exec_failed("make", ErrorKind::NotFound),
+        exec_failed("make", ErrorKind::NotFound)
+    );
+    // The message is not compared, only the kind
+    assert_eq!(
+        exec_failed("make", ErrorKind::NotFound),
+        TimeoutError::ExecFailed {
+            cmd: "make".to_string(),
+            source: Error::new(ErrorKind::NotFound, "no such file"),
+        }
+    );
+    assert_ne!(
+        exec_failed("make", ErrorKind::NotFound),
+        exec_failed("make", ErrorKind::PermissionDenied)
+    );
+    assert_ne!(
+        exec_failed("make", ErrorKind::NotFound),
+        exec_failed("cmake", ErrorKind::NotFound)
+    );
+}
+
+#[test]
+fn io_errors_in_different_variants_differ() {
+    let log = TimeoutError::LogFileFailed {
+        path: "/tmp/run.log".to_string(),
+        source: Error::from(ErrorKind::PermissionDenied),
+    };
+    let summary = TimeoutError::SummaryOutputFailed {
+        path: "/tmp/run.log".to_string(),
+        source: Error::from(ErrorKind::PermissionDenied),
+    };
+    assert_eq!(
+        log,
+        TimeoutError::LogFileFailed {
+            path: "/tmp/run.log".to_string(),
+            source: Error::from(ErrorKind::PermissionDenied),
+        }
+    );
+    assert_ne!(log, summary);
+}
+
+#[test]
+fn status_fd_compares_fd_and_kind() {
+    let bad_fd = |fd, kind| TimeoutError::StatusFdFailed {
+        fd,
+        source: Error::from(kind),
+    };
+    assert_eq!(
+        bad_fd(3, ErrorKind::InvalidInput),
+        bad_fd(3, ErrorKind::InvalidInput)
+    );
+    assert_ne!(
+        bad_fd(3, ErrorKind::InvalidInput),
+        bad_fd(4, ErrorKind::InvalidInput)
+    );
+    assert_ne!(
+        bad_fd(3, ErrorKind::InvalidInput),
+        bad_fd(3, ErrorKind::BrokenPipe)
+    );
+}
+
+#[cfg(unix)]
+mod errno {
+    use nix::errno::Errno;
+    use timeout::TimeoutError;
+
+    #[test]
+    fn errnos_compare_directly() {
+        assert_eq!(
+            TimeoutError::ForkFailed(Errno::EAGAIN),
+            TimeoutError::ForkFailed(Errno::EAGAIN)
+        );
+        assert_ne!(
+            TimeoutError::ForkFailed(Errno::EAGAIN),
+            TimeoutError::ForkFailed(Errno::ENOMEM)
+        );
+        assert_ne!(
+            TimeoutError::ForkFailed(Errno::EPERM),
+            TimeoutError::ProcessGroupFailed(Errno::EPERM)
+        );
+    }
+
+    #[test]
+    fn signal_send_compares_signal_and_errno() {
+        let failed = |signal: &str, errno| TimeoutError::SignalSendFailed {
+            signal: signal.to_string(),
+            source: errno,
+        };
+        assert_eq!(
+            failed("SIGTERM", Errno::ESRCH),
+            failed("SIGTERM", Errno::ESRCH)
+        );
+        assert_ne!(
+            failed("SIGTERM", Errno::ESRCH),
+            failed("SIGTERM", Errno::EPERM)
+        );
+        assert_ne!(
+            failed("SIGTERM", Errno::ESRCH),
+            failed("SIGKILL", Errno::ESRCH)
+        );
+    }
+
+    #[test]
+    fn wrapped_io_errors_compare_by_kind() {
+        let pipe = |kind| TimeoutError::PipeFailed(std::io::Error::from(kind));
+        assert_eq!(
+            pipe(std::io::ErrorKind::OutOfMemory),
+            pipe(std::io::ErrorKind::OutOfMemory)
+        );
+        assert_ne!(
+            pipe(std::io::ErrorKind::OutOfMemory),
+            TimeoutError::PtyFailed(std::io::Error::from(std::io::ErrorKind::OutOfMemory))
+        );
+    }
+}
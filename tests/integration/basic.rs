@@ -0,0 +1,91 @@
+// tests/integration/basic.rs
+// Time limits and exit codes
+
+use crate::{assert_elapsed, helper, timed, timeout};
+use predicates::str::{contains, is_match};
+use std::time::Duration;
+
+#[test]
+fn sleep_forever_times_out_with_124() {
+    let (assert, elapsed) = timed(timeout().arg("0.3").arg(helper("sleep-forever")));
+    assert.code(124);
+    assert_elapsed(elapsed, Duration::from_millis(300), Duration::from_secs(5));
+}
+
+#[test]
+fn cpu_burn_is_stopped_at_the_deadline() {
+    let (assert, elapsed) = timed(timeout().arg("0.3").arg(helper("cpu-burn")));
+    assert.code(124);
+    assert_elapsed(elapsed, Duration::from_millis(300), Duration::from_secs(5));
+}
+
+#[test]
+fn status_replaces_124() {
+    timeout()
+        .args(["--status", "7", "0.2"])
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(7);
+}
+
+#[test]
+fn verbose_reports_the_timeout() {
+    timeout()
+        .args(["--verbose", "0.2"])
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(124)
+        .stderr(contains("timed out after"))
+        .stderr(contains("exit code 124"));
+}
+
+#[test]
+fn missing_command_exits_127() {
+    timeout()
+        .args(["1", "nonexistent_command_xyz"])
+        .assert()
+        .code(127)
+        .stderr(contains("nonexistent_command_xyz"));
+}
+
+#[test]
+fn invalid_duration_exits_125() {
+    timeout()
+        .arg("soon")
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(125)
+        .stderr(contains("invalid duration 'soon'"));
+}
+
+#[cfg(unix)]
+#[test]
+fn suppressed_exit_code_becomes_0() {
+    for (code, expected) in [(1, 0), (3, 0), (2, 2)] {
+        timeout()
+            .args(["--suppress-exit-code", "1", "--suppress-exit-code", "3"])
+            .args(["1", "sh", "-c", &format!("exit {}", code)])
+            .assert()
+            .code(expected);
+    }
+}
+
+#[test]
+fn suppressed_exit_code_leaves_timeouts_alone() {
+    timeout()
+        .args(["--suppress-exit-code", "124", "0.2"])
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(124);
+}
+
+#[test]
+fn summary_line_of_a_timed_out_run() {
+    timeout()
+        .args(["--summary-format", "[{outcome}] took {elapsed} (limit {duration}) exit={exit_code} {signal} {{{attempt}}}"])
+        .arg("0.3")
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(124)
+        .stderr(is_match(r"\A\[timeout\] took ([3-9][0-9]{2}ms|[0-9.]+s) \(limit 300ms\) exit=124 (SIGTERM|none) \{1\}\n\z").unwrap());
+}
@@ -0,0 +1,88 @@
+// tests/integration/capabilities.rs
+// --capabilities: a JSON report that provisioning scripts can rely on
+
+use crate::timeout;
+use serde_json::Value;
+
+/// The parsed --capabilities document
+fn report() -> Value {
+    let output = timeout().arg("--capabilities").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stderr.is_empty());
+    serde_json::from_slice(&output.stdout).expect("--capabilities prints JSON")
+}
+
+#[test]
+fn follows_the_schema() {
+    let report = report();
+    assert_eq!(report["schema_version"], 1);
+    assert_eq!(report["version"], env!("CARGO_PKG_VERSION"));
+    assert!(report["platform"].is_string());
+    assert!(report["release"].is_string() || report["release"].is_null());
+
+    let features = report["features"].as_object().unwrap();
+    for name in [
+        "cpu_limit",
+        "mem_limit",
+        "pdeathsig",
+        "pidfd",
+        "clone3",
+        "signalfd",
+        "kqueue",
+        "cgroup_v2",
+        "landlock",
+        "cpu_affinity",
+        "ionice",
+        "namespaces",
+        "boottime",
+        "pty",
+        "job_objects",
+    ] {
+        let feature = &features[name];
+        let supported = feature["supported"]
+            .as_bool()
+            .unwrap_or_else(|| panic!("{} has no supported flag", name));
+        // A reason exactly when unsupported
+        assert_eq!(
+            feature["reason"].is_string(),
+            !supported,
+            "{}: {}",
+            name,
+            feature
+        );
+    }
+}
+
+#[test]
+fn matches_the_build() {
+    let features = &report()["features"];
+    assert_eq!(
+        features["pdeathsig"]["supported"],
+        cfg!(target_os = "linux")
+    );
+    assert_eq!(features["job_objects"]["supported"], cfg!(windows));
+    assert_eq!(features["pty"]["supported"], cfg!(unix));
+    assert_eq!(
+        features["mem_limit"]["supported"],
+        cfg!(any(
+            target_os = "linux",
+            target_os = "freebsd",
+            target_os = "dragonfly"
+        ))
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn probes_the_kernel() {
+    let features = &report()["features"];
+    // Where the kernel has Landlock, the detail names its ABI level
+    if features["landlock"]["supported"] == true {
+        let detail = features["landlock"]["detail"].as_str().unwrap();
+        assert!(detail.starts_with("ABI "), "{}", detail);
+    }
+    assert_eq!(
+        features["cgroup_v2"]["supported"],
+        std::path::Path::new("/sys/fs/cgroup/cgroup.controllers").exists()
+    );
+}
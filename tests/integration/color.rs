@@ -0,0 +1,57 @@
+// tests/integration/color.rs
+// --color, NO_COLOR and CLICOLOR_FORCE: timeout's own messages are colored
+// only when asked to be or when stderr is a terminal, which here it never is
+
+use crate::{helper, timeout};
+
+/// Whether the -v messages of a timed-out run contain an escape code
+fn colored(flags: &[&str], env: &[(&str, &str)]) -> bool {
+    let mut cmd = timeout();
+    cmd.env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .env_remove("CLICOLOR");
+    for (key, value) in env {
+        cmd.env(key, value);
+    }
+    let output = cmd
+        .args(flags)
+        .arg("-v")
+        .arg("0.2")
+        .arg(helper("sleep-forever"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(124));
+    assert!(!output.stderr.is_empty());
+    output.stderr.contains(&0x1b)
+}
+
+#[test]
+fn auto_leaves_a_pipe_plain() {
+    assert!(!colored(&[], &[]));
+    assert!(!colored(&["--color=auto"], &[]));
+}
+
+#[test]
+fn always_colors_a_pipe() {
+    assert!(colored(&["--color=always"], &[]));
+    assert!(colored(&["--color", "always"], &[("NO_COLOR", "1")]));
+}
+
+#[test]
+fn never_wins_over_clicolor_force() {
+    assert!(!colored(&["--color=never"], &[("CLICOLOR_FORCE", "1")]));
+}
+
+#[test]
+fn auto_follows_clicolor_force_and_no_color() {
+    assert!(colored(&[], &[("CLICOLOR_FORCE", "1")]));
+    assert!(!colored(&[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]));
+}
+
+#[test]
+fn unknown_color_is_a_usage_error() {
+    timeout()
+        .args(["--color=sometimes", "1", "true"])
+        .assert()
+        .code(2);
+}
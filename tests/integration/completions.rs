@@ -0,0 +1,80 @@
+// tests/integration/completions.rs
+// --generate-completions and --completions-dir
+
+use crate::timeout;
+use predicates::str::contains;
+
+#[test]
+fn writes_every_shell_into_a_directory() {
+    let dir = std::env::temp_dir().join(format!("timeout-completions-{}", std::process::id()));
+    // Created by timeout, parents and all
+    let target = dir.join("share").join("completions");
+
+    timeout()
+        .arg("--completions-dir")
+        .arg(&target)
+        .assert()
+        .success()
+        .stdout("");
+    let sizes: Vec<_> = [
+        "timeout.bash",
+        "_timeout",
+        "timeout.fish",
+        "_timeout.ps1",
+        "timeout.elv",
+        "timeout.nu",
+    ]
+    .iter()
+    .map(|name| (name, std::fs::metadata(target.join(name)).map(|m| m.len())))
+    .collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+    for (name, size) in sizes {
+        assert!(size.unwrap_or(0) > 0, "{} is missing or empty", name);
+    }
+}
+
+#[test]
+fn fails_when_the_directory_cannot_be_made() {
+    let file =
+        std::env::temp_dir().join(format!("timeout-completions-file-{}", std::process::id()));
+    std::fs::write(&file, "").unwrap();
+
+    let assert = timeout()
+        .arg("--completions-dir")
+        .arg(file.join("completions"))
+        .assert();
+    std::fs::remove_file(&file).unwrap();
+    assert
+        .code(125)
+        .stderr(contains("failed to write completions"));
+}
+
+#[test]
+fn takes_shell_names_in_any_case() {
+    timeout()
+        .args(["--generate-completions", "Fish"])
+        .assert()
+        .success()
+        .stdout(contains("complete -c timeout"));
+    timeout()
+        .args(["--generate-completions", "tcsh"])
+        .assert()
+        .code(2)
+        .stderr(contains("possible values"));
+}
+
+#[test]
+fn writes_nushell_completions_of_its_own() {
+    timeout()
+        .args(["--generate-completions", "nushell"])
+        .assert()
+        .success()
+        .stdout(contains("export extern \"timeout\" ["))
+        .stdout(contains("--signal(-s): string"))
+        .stdout(contains("...args: string"));
+    timeout()
+        .args(["--generate-completions", "NU"])
+        .assert()
+        .success()
+        .stdout(contains("export use completions *"));
+}
@@ -0,0 +1,267 @@
+// tests/integration/config.rs
+// Default options from a config file, and how the command line and the
+// environment override them
+
+use crate::timeout;
+use predicates::str::contains;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// A config file holding `text`, removed when dropped
+struct ConfigFile(PathBuf);
+
+impl ConfigFile {
+    fn new(name: &str, text: &str) -> ConfigFile {
+        let path = std::env::temp_dir().join(format!(
+            "timeout-config-{}-{}.toml",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, text).unwrap();
+        ConfigFile(path)
+    }
+}
+
+impl Drop for ConfigFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// The --dry-run=json plan for `timeout FLAGS 5 true` with `config` as
+/// TIMEOUT_CONFIG
+fn plan(config: &ConfigFile, flags: &[&str]) -> Value {
+    let output = timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .env_remove("TIMEOUT_METRICS_FILE")
+        .arg("--dry-run=json")
+        .args(flags)
+        .args(["5", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn file_settings_are_defaults() {
+    let config = ConfigFile::new(
+        "defaults",
+        "kill_after = \"10s\"\nmetrics_file = \"/tmp/runs.jsonl\"\n",
+    );
+    let plan = plan(&config, &[]);
+    assert_eq!(plan["kill_after_ms"], 10000);
+    assert_eq!(plan["metrics"], "file /tmp/runs.jsonl");
+}
+
+#[test]
+fn command_line_wins() {
+    let config = ConfigFile::new("cli", "kill_after = \"10s\"\n");
+    assert_eq!(plan(&config, &["-k", "2"])["kill_after_ms"], 2000);
+    assert_eq!(
+        plan(&config, &["--no-config"])["kill_after_ms"],
+        Value::Null
+    );
+}
+
+#[test]
+fn conflicting_settings_are_dropped() {
+    // --no-kill cannot be combined with --kill-after
+    let config = ConfigFile::new("conflict", "kill_after = \"10s\"\n");
+    let plan = plan(&config, &["--no-kill"]);
+    assert_eq!(plan["kill_after_ms"], Value::Null);
+    assert_eq!(plan["keep_running"], true);
+}
+
+#[cfg(unix)]
+#[test]
+fn signal_and_preserve_status() {
+    let config = ConfigFile::new("signal", "signal = \"INT\"\npreserve_status = true\n");
+    assert_eq!(plan(&config, &[])["signal"], "SIGINT");
+    assert_eq!(plan(&config, &["-s", "HUP"])["signal"], "SIGHUP");
+
+    timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .args(["5", "sh", "-c", "exit 3"])
+        .assert()
+        .code(3);
+    // --preserve-status: the status of COMMAND, killed by SIGINT
+    timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .args(["0.2", "sleep", "5"])
+        .assert()
+        .code(128 + 2);
+}
+
+#[test]
+fn unknown_keys_are_errors() {
+    let config = ConfigFile::new("unknown", "kill_after = \"10s\"\nkill_afer = \"5s\"\n");
+    timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .args(["5", "true"])
+        .assert()
+        .code(125)
+        .stderr(contains(config.0.display().to_string()))
+        .stderr(contains("line 2, column 1: unknown field `kill_afer`"));
+}
+
+#[test]
+fn bad_values_are_errors() {
+    for (name, text, reason) in [
+        ("syntax", "kill_after = 10s\n", "line 1, column 14"),
+        (
+            "duration",
+            "kill_after = \"soon\"\n",
+            "kill_after: invalid duration",
+        ),
+        (
+            "color",
+            "color = \"blue\"\n",
+            "color must be auto, always or never",
+        ),
+        ("type", "verbose = 1\n", "line 1, column 11"),
+    ] {
+        let config = ConfigFile::new(name, text);
+        timeout()
+            .env("TIMEOUT_CONFIG", &config.0)
+            .args(["5", "true"])
+            .assert()
+            .code(125)
+            .stderr(contains(reason));
+    }
+}
+
+#[test]
+fn a_missing_named_file_is_an_error() {
+    let missing = std::env::temp_dir().join("timeout-config-missing.toml");
+    timeout()
+        .arg("--config")
+        .arg(&missing)
+        .args(["5", "true"])
+        .assert()
+        .code(125)
+        .stderr(contains("timeout-config-missing.toml"));
+    timeout()
+        .env("TIMEOUT_CONFIG", &missing)
+        .args(["5", "true"])
+        .assert()
+        .code(125);
+}
+
+#[test]
+fn dump_config_shows_sources() {
+    let config = ConfigFile::new("dump", "kill_after = \"10s\"\nverbose = true\n");
+    timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .env_remove("NO_COLOR")
+        .env_remove("CLICOLOR_FORCE")
+        .env_remove("TIMEOUT_METRICS_FILE")
+        .args(["--dump-config", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(contains(format!("# config file: {}", config.0.display())))
+        .stdout(contains("kill_after = \"10s\"  # config file"))
+        .stdout(contains("verbose = true  # config file"))
+        .stdout(contains("color = \"never\"  # command line"))
+        .stdout(contains("# metrics_file is not set"));
+}
+
+/// The --dry-run=json plan for `timeout FLAGS 5 true` with `vars` set and
+/// no config file
+fn env_plan(vars: &[(&str, &str)], flags: &[&str]) -> Value {
+    let output = timeout()
+        .envs(vars.iter().copied())
+        .arg("--no-config")
+        .arg("--dry-run=json")
+        .args(flags)
+        .args(["5", "true"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn timeout_opts_are_defaults() {
+    let opts = [("TIMEOUT_OPTS", "-k 30 --idle-timeout '1m'")];
+    let plan = env_plan(&opts, &[]);
+    assert_eq!(plan["kill_after_ms"], 30000);
+    assert_eq!(plan["idle_timeout_ms"], 60000);
+    assert_eq!(env_plan(&opts, &["-k", "2"])["kill_after_ms"], 2000);
+    // --no-kill cannot be combined with --kill-after
+    let plan = env_plan(&opts, &["--no-kill"]);
+    assert_eq!(plan["kill_after_ms"], Value::Null);
+    assert_eq!(plan["idle_timeout_ms"], 60000);
+}
+
+#[cfg(unix)]
+#[test]
+fn single_purpose_variables() {
+    let vars = [
+        ("TIMEOUT_OPTS", "-k 30 -s INT"),
+        ("TIMEOUT_KILL_AFTER", "10s"),
+        ("TIMEOUT_SIGNAL", "HUP"),
+    ];
+    let plan = env_plan(&vars, &[]);
+    assert_eq!(plan["kill_after_ms"], 10000);
+    assert_eq!(plan["signal"], "SIGHUP");
+    assert_eq!(env_plan(&vars, &["-s", "USR1"])["signal"], "SIGUSR1");
+}
+
+#[test]
+fn environment_wins_over_the_file() {
+    let config = ConfigFile::new("environment", "kill_after = \"10s\"\n");
+    timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .env("TIMEOUT_OPTS", "--kill-after 20s")
+        .arg("--dump-config")
+        .assert()
+        .success()
+        .stdout(contains("kill_after = \"20s\"  # environment"));
+    let plan = timeout()
+        .env("TIMEOUT_CONFIG", &config.0)
+        .env("TIMEOUT_KILL_AFTER", "30s")
+        .args(["--dry-run=json", "5", "true"])
+        .output()
+        .unwrap();
+    let plan: Value = serde_json::from_slice(&plan.stdout).unwrap();
+    assert_eq!(plan["kill_after_ms"], 30000);
+}
+
+#[test]
+fn bad_environment_names_the_variable() {
+    for (var, value, message) in [
+        (
+            "TIMEOUT_OPTS",
+            "--bogus",
+            "TIMEOUT_OPTS: unexpected argument '--bogus' found",
+        ),
+        (
+            "TIMEOUT_OPTS",
+            "-k 30 5s sleep",
+            "TIMEOUT_OPTS: only options may be given here, not DURATION '5s'",
+        ),
+        (
+            "TIMEOUT_OPTS",
+            "--on-timeout 'echo",
+            "TIMEOUT_OPTS: unterminated quote",
+        ),
+        (
+            "TIMEOUT_OPTS",
+            "-k soon",
+            "TIMEOUT_OPTS: --kill-after: invalid duration 'soon'",
+        ),
+        (
+            "TIMEOUT_KILL_AFTER",
+            "soon",
+            "TIMEOUT_KILL_AFTER: invalid duration 'soon'",
+        ),
+    ] {
+        timeout()
+            .env(var, value)
+            .args(["5", "true"])
+            .assert()
+            .code(125)
+            .stderr(contains(message));
+    }
+}
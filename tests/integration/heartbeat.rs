@@ -0,0 +1,95 @@
+// tests/integration/heartbeat.rs
+// --heartbeat: one line per INTERVAL while COMMAND runs, and none after
+
+use crate::{helper, timeout};
+
+/// The lines a run of `timeout <flags> 1 sleep-forever` printed on stdout
+/// and stderr
+fn lines(flags: &[&str]) -> (Vec<String>, Vec<String>) {
+    let output = timeout()
+        .env_remove("TIMEOUT_METRICS")
+        .args(flags)
+        .arg("1")
+        .arg(helper("sleep-forever"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(124));
+    let split = |bytes: Vec<u8>| {
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    (split(output.stdout), split(output.stderr))
+}
+
+#[test]
+fn one_line_per_interval() {
+    let (stdout, stderr) = lines(&["--heartbeat", "0.2"]);
+    // 0.2s, 0.4s, 0.6s and 0.8s, and maybe 1s on a loaded machine
+    assert!(
+        (3..=5).contains(&stdout.len()),
+        "expected about 4 heartbeats, got {:?}",
+        stdout
+    );
+    for line in &stdout {
+        assert!(
+            line.starts_with("[timeout] still running ") && line.contains(" elapsed, "),
+            "unexpected heartbeat {:?}",
+            line
+        );
+    }
+    assert!(stderr.is_empty(), "{:?}", stderr);
+}
+
+#[test]
+fn custom_text_on_stderr() {
+    let (stdout, stderr) = lines(&["--heartbeat", "0.3:still alive", "--heartbeat-stderr"]);
+    assert!(stdout.is_empty(), "{:?}", stdout);
+    assert!(
+        (2..=3).contains(&stderr.len()) && stderr.iter().all(|line| line == "still alive"),
+        "{:?}",
+        stderr
+    );
+}
+
+#[test]
+fn quiet_turns_it_off() {
+    let (stdout, stderr) = lines(&["-q", "--heartbeat", "0.2"]);
+    assert!(stdout.is_empty() && stderr.is_empty());
+}
+
+#[test]
+fn invalid_interval_exits_125() {
+    for interval in ["0", "soon", "1x:text"] {
+        timeout()
+            .args(["--heartbeat", interval, "1", "true"])
+            .assert()
+            .code(125);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn stops_when_command_exits() {
+    let output = timeout()
+        .args(["--heartbeat", "0.2", "10", "sh", "-c", "sleep 0.5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let count = String::from_utf8(output.stdout).unwrap().lines().count();
+    assert!((1..=3).contains(&count), "{} heartbeats", count);
+}
+
+#[cfg(unix)]
+#[test]
+fn ends_a_partial_line_first() {
+    let output = timeout()
+        .args(["--heartbeat", "0.3:beat", "--idle-timeout", "10", "0.5"])
+        .args(["sh", "-c", "printf partial; exec sleep 5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(124));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "partial\nbeat\n");
+}
@@ -0,0 +1,9 @@
+// tests/integration/helpers/cpu_burn.rs
+// Helper for the integration tests: spins one CPU until a signal ends it
+
+fn main() {
+    let mut n: u64 = 0;
+    loop {
+        n = std::hint::black_box(n.wrapping_mul(6364136223846793005).wrapping_add(1));
+    }
+}
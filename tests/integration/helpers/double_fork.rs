@@ -0,0 +1,38 @@
+// tests/integration/helpers/double_fork.rs
+// Helper for the integration tests: forks a grandchild that sleeps for a
+// minute and prints its PID. With no arguments the helper then exits at
+// once, leaving the grandchild behind; with `wait` it sleeps as well.
+
+#[cfg(unix)]
+fn main() {
+    use nix::unistd::{fork, ForkResult};
+    use std::io::Write;
+    use std::os::fd::AsRawFd;
+    use std::time::Duration;
+
+    let wait = std::env::args().nth(1).as_deref() == Some("wait");
+    match unsafe { fork() }.expect("fork") {
+        ForkResult::Child => {
+            // Let go of the pipes, so whoever reads the helper's output
+            // is not kept waiting for the grandchild too
+            let null = std::fs::File::open("/dev/null").expect("open /dev/null");
+            for fd in 0..=2 {
+                unsafe { nix::libc::dup2(null.as_raw_fd(), fd) };
+            }
+            std::thread::sleep(Duration::from_secs(60));
+        }
+        ForkResult::Parent { child } => {
+            println!("{}", child);
+            std::io::stdout().flush().expect("flush stdout");
+            if wait {
+                std::thread::sleep(Duration::from_secs(60));
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("double-fork needs fork()");
+    std::process::exit(2);
+}
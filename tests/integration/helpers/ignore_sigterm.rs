@@ -0,0 +1,22 @@
+// tests/integration/helpers/ignore_sigterm.rs
+// Helper for the integration tests: ignores SIGTERM, so only SIGKILL (or
+// another signal) ends it. Prints "ready" once SIGTERM is ignored.
+
+#[cfg(unix)]
+fn main() {
+    use nix::sys::signal::{signal, SigHandler, Signal};
+    use std::io::Write;
+
+    unsafe { signal(Signal::SIGTERM, SigHandler::SigIgn) }.expect("ignore SIGTERM");
+    println!("ready");
+    std::io::stdout().flush().expect("flush stdout");
+    loop {
+        std::thread::sleep(std::time::Duration::from_secs(3600));
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("ignore-sigterm needs Unix signals");
+    std::process::exit(2);
+}
@@ -0,0 +1,16 @@
+// tests/integration/helpers/memory_hog.rs
+// Helper for the integration tests: touches the number of MiB given as its
+// argument (default 64), then exits 0
+
+fn main() {
+    let mib: usize = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(64);
+    let mut memory = vec![0u8; mib << 20];
+    // One write per page, so every page is resident
+    for page in memory.chunks_mut(4096) {
+        page[0] = 1;
+    }
+    std::hint::black_box(&memory);
+}
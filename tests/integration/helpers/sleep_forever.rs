@@ -0,0 +1,8 @@
+// tests/integration/helpers/sleep_forever.rs
+// Helper for the integration tests: runs until a signal ends it
+
+fn main() {
+    loop {
+        std::thread::sleep(std::time::Duration::from_secs(3600));
+    }
+}
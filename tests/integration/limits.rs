@@ -0,0 +1,18 @@
+// tests/integration/limits.rs
+// Resource limits, on the platforms that enforce them
+
+use crate::{assert_elapsed, helper, timed, timeout};
+use std::time::Duration;
+
+#[test]
+fn cpu_limit_stops_cpu_burn_before_the_deadline() {
+    let (assert, elapsed) = timed(
+        timeout()
+            .args(["--cpu-limit", "1", "30"])
+            .arg(helper("cpu-burn")),
+    );
+    // The soft and hard RLIMIT_CPU are the same, so the kernel goes straight
+    // to SIGKILL; COMMAND did not time out
+    assert.code(137);
+    assert_elapsed(elapsed, Duration::from_millis(900), Duration::from_secs(10));
+}
@@ -0,0 +1,104 @@
+// tests/integration/main.rs
+// Integration tests: the timeout binary run against the helper programs in
+// tests/integration/helpers, which Cargo.toml declares as examples so that
+// `cargo test` builds them
+
+mod basic;
+mod capabilities;
+mod color;
+mod completions;
+mod config;
+mod heartbeat;
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+mod limits;
+mod manpage;
+mod metrics;
+#[cfg(target_os = "linux")]
+mod namespaces;
+#[cfg(feature = "otel")]
+mod otel;
+mod pid_file;
+#[cfg(unix)]
+mod process_group;
+mod prom;
+#[cfg(all(target_os = "linux", debug_assertions))]
+mod reaper;
+#[cfg(unix)]
+mod signals;
+mod statsd;
+#[cfg(unix)]
+mod verbosity;
+
+use assert_cmd::assert::Assert;
+use assert_cmd::cargo::cargo_bin_cmd;
+use assert_cmd::Command;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::time::{Duration, Instant};
+
+/// The timeout binary under test. A run that hangs fails its test after
+/// 30 seconds rather than holding up the whole suite.
+pub fn timeout() -> Command {
+    let mut cmd = cargo_bin_cmd!("timeout");
+    cmd.timeout(Duration::from_secs(30));
+    cmd
+}
+
+/// Path to a helper program, e.g. `helper("sleep-forever")`. Examples are
+/// built next to the binary, in its `examples` directory; `cargo test`
+/// builds them, but a run limited with `--test` does not, so they are built
+/// here on first use when missing.
+pub fn helper(name: &str) -> PathBuf {
+    static BUILD: Once = Once::new();
+    let bin = PathBuf::from(env!("CARGO_BIN_EXE_timeout"));
+    let dir = bin.parent().expect("binary has a parent directory");
+    let path = dir
+        .join("examples")
+        .join(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+    if !path.exists() {
+        BUILD.call_once(|| build_examples(dir));
+    }
+    assert!(
+        path.exists(),
+        "helper {} not found at {}; run `cargo build --examples` first",
+        name,
+        path.display()
+    );
+    path
+}
+
+/// Build the helper examples with the profile of the binary in `dir`
+fn build_examples(dir: &Path) {
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut build = std::process::Command::new(cargo);
+    build.args([
+        "build",
+        "--examples",
+        "--manifest-path",
+        concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+    ]);
+    if dir.ends_with("release") {
+        build.arg("--release");
+    }
+    // Failure shows up as the helper still missing
+    let _ = build.status();
+}
+
+/// Run `cmd` to completion, returning the result and how long it took
+pub fn timed(cmd: &mut Command) -> (Assert, Duration) {
+    let started = Instant::now();
+    let assert = cmd.assert();
+    (assert, started.elapsed())
+}
+
+/// Check that `elapsed` is at least `min` and comfortably under `max`,
+/// which is generous because test machines are often loaded
+pub fn assert_elapsed(elapsed: Duration, min: Duration, max: Duration) {
+    assert!(
+        elapsed >= min && elapsed < max,
+        "took {:?}, expected between {:?} and {:?}",
+        elapsed,
+        min,
+        max
+    );
+}
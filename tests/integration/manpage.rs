@@ -0,0 +1,68 @@
+// tests/integration/manpage.rs
+// --generate-man: the page packagers install as timeout(1)
+
+use crate::timeout;
+
+/// The page --generate-man prints
+fn page() -> String {
+    let output = timeout().arg("--generate-man").output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    String::from_utf8(output.stdout).expect("the man page is UTF-8")
+}
+
+#[test]
+fn lists_the_exit_statuses() {
+    let page = page();
+    let section = page
+        .split(".SH \"EXIT STATUS\"")
+        .nth(1)
+        .expect("an EXIT STATUS section");
+    let section = section.split("\n.SH ").next().unwrap();
+    for status in ["124", "125", "126", "127", "137"] {
+        assert!(
+            section.contains(&format!(".TP\n\\fB{}\\fR", status)),
+            "no entry for {} in:\n{}",
+            status,
+            section
+        );
+    }
+}
+
+#[test]
+fn documents_options_and_units() {
+    let page = page();
+    assert!(page.contains(".SH OPTIONS"));
+    for option in [
+        r"\fB\-k\fR, \fB\-\-kill\-after\fR",
+        r"\fB\-s\fR, \fB\-\-signal\fR",
+    ] {
+        assert!(page.contains(option), "no entry for {}", option);
+    }
+    assert!(page.contains(".SH \"DURATIONS AND SIZES\""));
+}
+
+#[cfg(unix)]
+#[test]
+fn includes_long_help() {
+    let page = page();
+    assert!(page.contains(r"\fB\-f\fR, \fB\-\-foreground\fR"));
+    // The example from --foreground's long help, not only its summary
+    assert!(page.contains(r"timeout \-\-foreground 10m vim notes.txt"));
+}
+
+#[test]
+fn writes_to_output() {
+    let dir = std::env::temp_dir().join(format!("timeout-manpage-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("timeout.1");
+
+    timeout()
+        .args(["--generate-man", "--output"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout("");
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(written, page());
+}
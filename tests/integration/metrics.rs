@@ -0,0 +1,197 @@
+// tests/integration/metrics.rs
+// The TIMEOUT_METRICS JSON line
+
+use crate::{helper, timeout};
+use assert_cmd::Command;
+use serde_json::Value;
+
+/// Awkward strings for JSON: quotes, backslashes, newlines and other
+/// control characters
+const AWKWARD: [&str; 4] = ["say \"hi\"", r"C:\path\to", "two\nlines", "bell\u{7}tab\t"];
+
+/// Run `cmd` with TIMEOUT_METRICS set and parse the metrics line
+fn metrics(cmd: &mut Command) -> Value {
+    let output = cmd.env("TIMEOUT_METRICS", "1").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let line = stderr
+        .lines()
+        .find(|line| line.starts_with('{'))
+        .unwrap_or_else(|| panic!("no metrics line in {:?}", stderr));
+    serde_json::from_str(line).unwrap_or_else(|e| panic!("{}: {}", e, line))
+}
+
+#[test]
+fn arguments_round_trip() {
+    let command = helper("sleep-forever");
+    let json = metrics(timeout().arg("0.2").arg(&command).args(AWKWARD));
+    let mut argv = vec![command.to_str().unwrap()];
+    argv.extend(AWKWARD);
+    assert_eq!(json["argv"], serde_json::json!(argv));
+    assert_eq!(json["timed_out"], true);
+    assert_eq!(json["exit_code"], 124);
+}
+
+#[cfg(unix)]
+#[test]
+fn command_name_round_trips() {
+    let dir = std::env::temp_dir().join(format!("timeout-metrics-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let command = dir.join(AWKWARD.concat());
+    std::os::unix::fs::symlink(helper("sleep-forever"), &command).unwrap();
+
+    let json = metrics(timeout().arg("0.2").arg(&command));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(json["command"], command.to_str().unwrap());
+    assert_eq!(json["argv"][0], command.to_str().unwrap());
+}
+
+#[test]
+fn schema_version_start_and_pid() {
+    let json = metrics(timeout().arg("0.2").arg(helper("sleep-forever")));
+    assert_eq!(json["schema_version"], 2);
+    assert_eq!(json["mode"], "command");
+    assert!(json["pid"].as_u64().is_some_and(|pid| pid > 0), "{}", json);
+    let started_at = json["started_at"].as_str().unwrap();
+    assert!(
+        started_at.len() == 24 && started_at.ends_with('Z') && started_at.as_bytes()[10] == b'T',
+        "{}",
+        started_at
+    );
+    #[cfg(unix)]
+    assert_eq!(json["signal"], "SIGTERM");
+}
+
+#[test]
+fn csv_header_only_in_new_or_empty_file() {
+    let dir = std::env::temp_dir().join(format!("timeout-metrics-csv-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let new = dir.join("new.csv");
+    let existing = dir.join("existing.csv");
+    std::fs::write(&existing, "earlier record\n").unwrap();
+
+    for file in [&new, &new, &existing] {
+        timeout()
+            .args(["--metrics-format", "csv", "--metrics-file"])
+            .arg(file)
+            .arg("0.2")
+            .arg(helper("sleep-forever"))
+            .args(AWKWARD)
+            .assert()
+            .code(124)
+            .stderr("");
+    }
+    let new = std::fs::read_to_string(&new).unwrap();
+    let existing = std::fs::read_to_string(&existing).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // AWKWARD holds a newline, so records are counted by their first field
+    let header = "schema_version,command,argv,";
+    assert!(new.starts_with(header), "{}", new);
+    assert_eq!(new.matches(header).count(), 1, "{}", new);
+    assert_eq!(new.matches("\n2,").count(), 2, "{}", new);
+    assert!(new.lines().next().unwrap().ends_with(",mode"), "{}", new);
+    assert!(existing.starts_with("earlier record\n2,"), "{}", existing);
+    assert!(!existing.contains(header), "{}", existing);
+}
+
+#[cfg(unix)]
+#[test]
+fn peak_rss_of_memory_hog() {
+    let json = metrics(timeout().arg("10").arg(helper("memory-hog")).arg("64"));
+    assert_eq!(json["exit_code"], 0);
+    let rss = json["max_rss_bytes"].as_u64().unwrap();
+    // In bytes, not KiB: at least the 64 MiB touched, and nowhere near 64 GiB
+    assert!((64 << 20..1 << 30).contains(&rss), "{}", json);
+    assert!(
+        json["minor_faults"].as_u64().unwrap() >= 64 * 256 / 2,
+        "{}",
+        json
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn cpu_time_of_command_stopped_at_the_deadline() {
+    let json = metrics(timeout().arg("0.5").arg(helper("cpu-burn")));
+    assert_eq!(json["timed_out"], true);
+    let user = json["user_cpu_ms"].as_u64().unwrap();
+    let sys = json["sys_cpu_ms"].as_u64().unwrap();
+    // Spinning for 0.5s, though a loaded machine may not give it all of it
+    assert!((50..2000).contains(&(user + sys)), "{}", json);
+}
+
+#[cfg(unix)]
+#[test]
+fn no_resource_usage_for_a_watched_pid() {
+    let mut child = std::process::Command::new(helper("sleep-forever"))
+        .spawn()
+        .unwrap();
+    let json = metrics(timeout().args(["--pid", &child.id().to_string(), "0.2"]));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(json["mode"], "pid");
+    assert_eq!(json["max_rss_bytes"], Value::Null);
+    assert_eq!(json["user_cpu_ms"], Value::Null);
+}
+
+#[cfg(unix)]
+#[test]
+fn shutdown_latency_of_command_that_traps_sigterm() {
+    // Takes half a second to exit after SIGTERM
+    let json = metrics(timeout().args([
+        "-k",
+        "5",
+        "0.3",
+        "sh",
+        "-c",
+        "trap 'sleep 0.5; kill $!; exit 0' TERM; sleep 10 & wait",
+    ]));
+    assert_eq!(json["signals_sent"][0]["signal"], "SIGTERM", "{}", json);
+    assert_eq!(json["signals_sent"][1]["signal"], "SIGCONT", "{}", json);
+    assert_eq!(json["kill_signal_sent"], false);
+    let sent = json["term_signal_sent_at_ms"].as_u64().unwrap();
+    assert!((300..1000).contains(&sent), "{}", json);
+    assert_eq!(json["signals_sent"][0]["offset_ms"].as_u64(), Some(sent));
+    let exited = json["child_exited_at_ms"].as_u64().unwrap();
+    let latency = json["shutdown_latency_ms"].as_u64().unwrap();
+    // Each is rounded down to the millisecond on its own
+    assert!(latency.abs_diff(exited - sent) <= 1, "{}", json);
+    assert!((450..3000).contains(&latency), "{}", json);
+}
+
+#[cfg(unix)]
+#[test]
+fn kill_escalation_in_signals_sent() {
+    let json = metrics(
+        timeout()
+            .args(["-k", "0.3", "0.2"])
+            .arg(helper("ignore-sigterm")),
+    );
+    let signals: Vec<_> = json["signals_sent"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["signal"].as_str().unwrap())
+        .collect();
+    assert_eq!(signals, ["SIGTERM", "SIGCONT", "SIGKILL"], "{}", json);
+    assert_eq!(json["kill_signal_sent"], true);
+    let term = json["signals_sent"][0]["offset_ms"].as_u64().unwrap();
+    let kill = json["signals_sent"][2]["offset_ms"].as_u64().unwrap();
+    assert!(kill >= term + 300, "{}", json);
+    assert!(
+        json["shutdown_latency_ms"].as_u64().unwrap() >= 300,
+        "{}",
+        json
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn no_shutdown_for_a_command_that_exits_in_time() {
+    let json = metrics(timeout().args(["5", "sh", "-c", "exit 0"]));
+    assert_eq!(json["signals_sent"], serde_json::json!([]));
+    assert_eq!(json["kill_signal_sent"], false);
+    assert_eq!(json["term_signal_sent_at_ms"], Value::Null);
+    assert_eq!(json["shutdown_latency_ms"], Value::Null);
+    assert!(json["child_exited_at_ms"].as_u64().is_some(), "{}", json);
+}
@@ -0,0 +1,68 @@
+// tests/integration/namespaces.rs
+// --namespaced: COMMAND gets namespaces of its own, or timeout says why not
+
+use crate::{helper, timeout};
+use predicates::str::contains;
+
+/// The namespaces COMMAND ran in, as `readlink` prints them, or `None`
+/// when this machine does not allow new namespaces
+fn namespaces_of_command() -> Option<Vec<String>> {
+    let output = timeout()
+        .args(["--namespaced", "5", "readlink"])
+        .args(NAMESPACES.map(|ns| format!("/proc/self/ns/{}", ns)))
+        .output()
+        .unwrap();
+    if output.status.code() == Some(126) {
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(
+            stderr.contains("--namespaced needs CAP_SYS_ADMIN"),
+            "{}",
+            stderr
+        );
+        return None;
+    }
+    assert_eq!(output.status.code(), Some(0));
+    Some(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+const NAMESPACES: [&str; 4] = ["mnt", "net", "uts", "ipc"];
+
+#[test]
+fn command_gets_new_namespaces() {
+    let Some(inside) = namespaces_of_command() else {
+        return;
+    };
+    for (ns, inside) in NAMESPACES.iter().zip(&inside) {
+        let outside = std::fs::read_link(format!("/proc/self/ns/{}", ns)).unwrap();
+        assert_ne!(inside, outside.to_str().unwrap(), "same {} namespace", ns);
+    }
+}
+
+#[test]
+fn timeout_still_applies() {
+    if namespaces_of_command().is_none() {
+        return;
+    }
+    timeout()
+        .args(["--namespaced", "0.3"])
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(124);
+}
+
+#[test]
+fn cannot_be_used_with_pid() {
+    timeout()
+        .args(["--namespaced", "--pid"])
+        .arg(std::process::id().to_string())
+        .arg("5")
+        .assert()
+        .code(2)
+        .stderr(contains("cannot be used with"));
+}
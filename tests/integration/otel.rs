@@ -0,0 +1,185 @@
+// tests/integration/otel.rs
+// --otel span export, against a mock OTLP/HTTP collector
+
+use crate::{assert_elapsed, helper, timed, timeout};
+use assert_cmd::Command;
+use serde_json::Value;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::Output;
+use std::thread;
+use std::time::Duration;
+
+const PARENT: &str = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+
+/// Run `cmd` with --otel and then `args`, exporting to a collector that
+/// answers 200 to one request, and return its output with the export
+/// request's path and body
+fn export<S: AsRef<OsStr>>(
+    cmd: &mut Command,
+    args: impl IntoIterator<Item = S>,
+) -> (Output, String, Value) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let collector = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        let path = request_line.split(' ').nth(1).unwrap().to_string();
+        (path, serde_json::from_slice(&body).unwrap())
+    });
+    let output = cmd
+        .env("OTEL_EXPORTER_OTLP_ENDPOINT", endpoint)
+        .env_remove("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+        .arg("--otel")
+        .args(args)
+        .output()
+        .unwrap();
+    let (path, body) = collector.join().unwrap();
+    (output, path, body)
+}
+
+/// The one span in an export request
+fn span(body: &Value) -> &Value {
+    &body["resourceSpans"][0]["scopeSpans"][0]["spans"][0]
+}
+
+/// The value of attribute `key`, as OTLP/JSON writes it
+fn attribute<'a>(span: &'a Value, key: &str) -> &'a Value {
+    span["attributes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|kv| kv["key"] == key)
+        .map(|kv| &kv["value"])
+        .unwrap_or_else(|| panic!("no attribute {} in {}", key, span))
+}
+
+#[cfg(unix)]
+#[test]
+fn span_links_caller_and_command() {
+    let file = std::env::temp_dir().join(format!("timeout-otel-{}", std::process::id()));
+    let (output, path, body) = export(
+        timeout().env("TRACEPARENT", PARENT),
+        [
+            "1".as_ref(),
+            "sh".as_ref(),
+            "-c".as_ref(),
+            "printf %s \"$TRACEPARENT\" > \"$0\"".as_ref(),
+            file.as_os_str(),
+        ],
+    );
+    let seen = std::fs::read_to_string(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(path, "/v1/traces");
+
+    let span = span(&body);
+    assert_eq!(span["name"], "sh");
+    assert_eq!(span["traceId"], "0af7651916cd43dd8448eb211c80319c");
+    assert_eq!(span["parentSpanId"], "b7ad6b7169203331");
+    assert!(span.get("status").is_none(), "{}", span);
+    let span_id = span["spanId"].as_str().unwrap();
+    assert_eq!(
+        seen,
+        format!("00-0af7651916cd43dd8448eb211c80319c-{}-01", span_id)
+    );
+}
+
+#[test]
+fn timeout_is_an_error_with_metrics_attributes() {
+    let (output, _, body) = export(
+        timeout().env_remove("TRACEPARENT"),
+        ["0.2".as_ref(), helper("sleep-forever").as_os_str()],
+    );
+    assert_eq!(output.status.code(), Some(124));
+    let span = span(&body);
+    assert!(span.get("parentSpanId").is_none(), "{}", span);
+    assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+    assert_eq!(span["status"]["code"], 2);
+    assert_eq!(attribute(span, "timeout.timed_out")["boolValue"], true);
+    assert_eq!(attribute(span, "timeout.exit_code")["intValue"], "124");
+    assert_eq!(attribute(span, "timeout.duration_ms")["intValue"], "200");
+    let elapsed: u64 = attribute(span, "timeout.elapsed_ms")["intValue"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(elapsed >= 200, "{}", elapsed);
+    let start: u128 = span["startTimeUnixNano"].as_str().unwrap().parse().unwrap();
+    let end: u128 = span["endTimeUnixNano"].as_str().unwrap().parse().unwrap();
+    assert!(end - start >= 200_000_000, "{} to {}", start, end);
+}
+
+#[test]
+fn exec_failure_is_an_error() {
+    let (output, _, body) = export(
+        timeout().env_remove("TRACEPARENT"),
+        ["1", "/nonexistent/command"],
+    );
+    assert_eq!(output.status.code(), Some(127));
+    let span = span(&body);
+    assert_eq!(span["status"]["code"], 2);
+    assert!(
+        span["status"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("not found"),
+        "{}",
+        span
+    );
+}
+
+#[test]
+fn malformed_traceparent_starts_a_new_trace() {
+    let (_, _, body) = export(
+        timeout().env(
+            "TRACEPARENT",
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+        ),
+        ["1", "true"],
+    );
+    let span = span(&body);
+    assert!(span.get("parentSpanId").is_none(), "{}", span);
+    assert_ne!(span["traceId"], "00000000000000000000000000000000");
+}
+
+#[test]
+fn unresponsive_collector_holds_up_exit_briefly() {
+    // Accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let (assert, elapsed) = timed(
+        timeout()
+            .env(
+                "OTEL_EXPORTER_OTLP_ENDPOINT",
+                format!("http://{}", listener.local_addr().unwrap()),
+            )
+            .args(["--otel", "1", "true"]),
+    );
+    assert
+        .code(0)
+        .stderr(predicates::str::contains("--otel export"));
+    assert_elapsed(elapsed, Duration::ZERO, Duration::from_secs(5));
+    drop(listener);
+}
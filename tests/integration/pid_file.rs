@@ -0,0 +1,101 @@
+// tests/integration/pid_file.rs
+// --pid-file: written once COMMAND has started, removed when it ends
+
+use crate::{helper, timeout};
+use predicates::str::contains;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// A fresh path in a directory of its own, removed by the caller
+fn pid_file_path(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("timeout-pid-file-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("job.pid")
+}
+
+#[test]
+fn holds_the_pid_while_command_runs_and_is_removed_on_timeout() {
+    let path = pid_file_path("timeout");
+    let child = Command::new(env!("CARGO_BIN_EXE_timeout"))
+        .env("TIMEOUT_METRICS", "1")
+        .arg("--pid-file")
+        .arg(&path)
+        .arg("1")
+        .arg(helper("sleep-forever"))
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let started = Instant::now();
+    let contents = loop {
+        match std::fs::read_to_string(&path) {
+            Ok(contents) if contents.ends_with('\n') => break contents,
+            _ if started.elapsed() > Duration::from_secs(5) => panic!("no PID in {:?}", path),
+            _ => std::thread::sleep(Duration::from_millis(20)),
+        }
+    };
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(124));
+    assert!(!path.exists(), "{:?} left behind", path);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    let lines: Vec<u32> = contents.lines().map(|l| l.parse().unwrap()).collect();
+    #[cfg(unix)]
+    assert_eq!(lines.len(), 2, "{:?}", contents);
+    #[cfg(not(unix))]
+    assert_eq!(lines.len(), 1, "{:?}", contents);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!("\"pid\":{},", lines[0])),
+        "PID {} is not COMMAND's: {}",
+        lines[0],
+        stderr
+    );
+}
+
+#[test]
+fn stale_file_is_an_error() {
+    let path = pid_file_path("stale");
+    std::fs::write(&path, "12345\n").unwrap();
+    timeout()
+        .arg("--pid-file")
+        .arg(&path)
+        .args(["5", "sh", "-c", "exit 0"])
+        .assert()
+        .code(125)
+        .stderr(contains("already exists"))
+        .stderr(contains("--pid-file-force"));
+    // Someone else's file is left alone
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "12345\n");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn force_overwrites_a_stale_file() {
+    let path = pid_file_path("force");
+    std::fs::write(&path, "12345\n").unwrap();
+    timeout()
+        .arg("--pid-file")
+        .arg(&path)
+        .arg("--pid-file-force")
+        .args(["5", "sh", "-c", "exit 3"])
+        .assert()
+        .code(3);
+    assert!(!path.exists(), "{:?} left behind", path);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn removed_when_command_cannot_run() {
+    let path = pid_file_path("exec");
+    timeout()
+        .arg("--pid-file")
+        .arg(&path)
+        .args(["5", "/nonexistent/command"])
+        .assert()
+        .code(127);
+    assert!(!path.exists(), "{:?} left behind", path);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
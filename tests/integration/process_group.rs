@@ -0,0 +1,217 @@
+// tests/integration/process_group.rs
+// COMMAND's own children: timeout signals the whole process group, but
+// does not wait for processes that outlive COMMAND
+
+use crate::{assert_elapsed, helper, timed, timeout};
+use nix::errno::Errno;
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::os::unix::process::CommandExt;
+use std::time::{Duration, Instant};
+
+/// The grandchild's PID, which double-fork prints before anything else
+fn grandchild(stdout: &[u8]) -> Pid {
+    let pid = String::from_utf8_lossy(stdout)
+        .trim()
+        .parse()
+        .expect("double-fork prints the grandchild's PID");
+    Pid::from_raw(pid)
+}
+
+/// Whether `pid` is still running. A zombie does not count: the grandchild
+/// is reparented, and its new parent may be slow to reap it.
+fn running(pid: Pid) -> bool {
+    if kill(pid, None) == Err(Errno::ESRCH) {
+        return false;
+    }
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => !stat
+            .rsplit(')')
+            .next()
+            .is_some_and(|rest| rest.trim_start().starts_with('Z')),
+        // Without /proc, a process that can still be signalled is running
+        Err(_) => true,
+    }
+}
+
+/// Wait up to `limit` for `pid` to stop running
+fn ended_within(pid: Pid, limit: Duration) -> bool {
+    let started = Instant::now();
+    while started.elapsed() < limit {
+        if !running(pid) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    false
+}
+
+#[test]
+fn exit_does_not_wait_for_the_grandchild() {
+    let (assert, elapsed) = timed(timeout().arg("10").arg(helper("double-fork")));
+    let assert = assert.code(0);
+    assert_elapsed(elapsed, Duration::ZERO, Duration::from_secs(5));
+
+    let pid = grandchild(&assert.get_output().stdout);
+    assert!(running(pid), "grandchild {} should outlive COMMAND", pid);
+    let _ = kill(pid, Signal::SIGKILL);
+}
+
+/// Run double-fork under timeout with `flags` and report whether the
+/// grandchild outlived the timeout, killing it if so
+fn grandchild_survives(flags: &[&str]) -> bool {
+    let assert = timeout()
+        .args(flags)
+        .arg("0.3")
+        .arg(helper("double-fork"))
+        .arg("wait")
+        .assert()
+        .code(124);
+
+    let pid = grandchild(&assert.get_output().stdout);
+    let ended = ended_within(pid, Duration::from_secs(2));
+    if !ended {
+        let _ = kill(pid, Signal::SIGKILL);
+    }
+    !ended
+}
+
+#[test]
+fn timeout_signals_the_grandchild_too() {
+    assert!(
+        !grandchild_survives(&[]),
+        "the grandchild survived the timeout"
+    );
+}
+
+#[test]
+fn only_no_process_group_spares_the_grandchild() {
+    assert!(
+        !grandchild_survives(&[]),
+        "the default should reach the grandchild"
+    );
+    assert!(
+        grandchild_survives(&["--no-process-group"]),
+        "--no-process-group should only signal COMMAND itself"
+    );
+}
+
+#[test]
+fn kill_signal_reaches_the_grandchild_but_spares_timeout() {
+    // SIGKILL cannot be ignored, so timeout must leave itself out of the
+    // group it shares with COMMAND, and still exit 124
+    assert!(
+        !grandchild_survives(&["-s", "KILL"]),
+        "the grandchild survived SIGKILL"
+    );
+}
+
+#[test]
+fn foreground_leaves_the_grandchild_alone() {
+    let assert = timeout()
+        .args(["--foreground", "0.3"])
+        .arg(helper("double-fork"))
+        .arg("wait")
+        .assert()
+        .code(124);
+
+    let pid = grandchild(&assert.get_output().stdout);
+    let survived = running(pid);
+    let _ = kill(pid, Signal::SIGKILL);
+    assert!(survived, "--foreground should only signal COMMAND itself");
+}
+
+#[test]
+fn no_process_group_leaves_the_grandchild_alone() {
+    let assert = timeout()
+        .args(["--no-process-group", "0.3"])
+        .arg(helper("double-fork"))
+        .arg("wait")
+        .assert()
+        .code(124);
+
+    let pid = grandchild(&assert.get_output().stdout);
+    let survived = running(pid);
+    let _ = kill(pid, Signal::SIGKILL);
+    assert!(
+        survived,
+        "--no-process-group should only signal COMMAND itself"
+    );
+}
+
+#[test]
+fn no_process_group_keeps_the_callers_group() {
+    let group_of_command = |flags: &[&str]| {
+        let output = timeout()
+            .args(flags)
+            .args(["5", "sh", "-c", "ps -o pgid= -p $$"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(0));
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    };
+    let ours = nix::unistd::getpgrp().to_string();
+    assert_eq!(group_of_command(&["--no-process-group"]), ours);
+    assert_ne!(group_of_command(&[]), ours);
+}
+
+#[test]
+fn runs_as_a_session_leader() {
+    // As under setsid(1): timeout already leads its own process group
+    let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_timeout"));
+    cmd.arg("0.5").arg(helper("sleep-forever"));
+    unsafe {
+        cmd.pre_exec(|| {
+            nix::unistd::setsid()
+                .map(drop)
+                .map_err(std::io::Error::from)
+        });
+    }
+    let mut cmd = assert_cmd::Command::from_std(cmd);
+    cmd.timeout(Duration::from_secs(30));
+
+    let (assert, elapsed) = timed(&mut cmd);
+    assert.code(124);
+    assert_elapsed(elapsed, Duration::from_millis(500), Duration::from_secs(5));
+}
+
+#[test]
+fn set_session_signals_the_grandchild_too() {
+    // COMMAND leads a group of its own, so killpg reaches everything in it
+    let assert = timeout()
+        .args(["--set-session", "0.3"])
+        .arg(helper("double-fork"))
+        .arg("wait")
+        .assert()
+        .code(124);
+
+    let pid = grandchild(&assert.get_output().stdout);
+    let ended = ended_within(pid, Duration::from_secs(2));
+    if !ended {
+        let _ = kill(pid, Signal::SIGKILL);
+    }
+    assert!(ended, "grandchild {} survived the timeout", pid);
+}
+
+#[test]
+fn set_session_starts_a_new_session() {
+    let output = timeout()
+        .args([
+            "--set-session",
+            "5",
+            "sh",
+            "-c",
+            "ps -o pid=,pgid=,sid= -p $$",
+        ])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    let ids: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .split_whitespace()
+        .map(String::from)
+        .collect();
+    assert_eq!(ids.len(), 3, "{:?}", ids);
+    assert_eq!(ids[1], ids[0], "COMMAND should lead its process group");
+    assert_eq!(ids[2], ids[0], "COMMAND should lead its session");
+}
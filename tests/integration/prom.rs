@@ -0,0 +1,117 @@
+// tests/integration/prom.rs
+// The --prom-textfile file across runs
+
+use crate::{helper, timeout};
+use std::path::{Path, PathBuf};
+
+/// A fresh directory for the textfile, removed by the caller
+fn textfile_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("timeout-prom-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// The value of the sample `series` (name and labels) in the file
+fn sample(path: &Path, series: &str) -> String {
+    let text = std::fs::read_to_string(path).unwrap();
+    text.lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .unwrap_or_else(|| panic!("no {} in\n{}", series, text))
+        .to_string()
+}
+
+#[test]
+fn counter_carries_over_between_runs() {
+    let dir = textfile_dir("counter");
+    let path = dir.join("job.prom");
+    let sleep = helper("sleep-forever");
+    let command = sleep.to_str().unwrap().replace('\\', r"\\");
+    for _ in 0..2 {
+        timeout()
+            .arg("--prom-textfile")
+            .arg(&path)
+            .arg("0.2")
+            .arg(&sleep)
+            .assert()
+            .code(124);
+    }
+    timeout()
+        .arg("--prom-textfile")
+        .arg(&path)
+        .args(["1", "true"])
+        .assert()
+        .success();
+
+    let series = |outcome: &str| {
+        format!(
+            "timeout_runs_total{{command=\"{}\",outcome=\"{}\"}}",
+            command, outcome
+        )
+    };
+    assert_eq!(sample(&path, &series("timeout")), "2");
+    assert_eq!(sample(&path, &series("success")), "0");
+    assert_eq!(
+        sample(
+            &path,
+            "timeout_runs_total{command=\"true\",outcome=\"success\"}"
+        ),
+        "1"
+    );
+    assert_eq!(
+        sample(&path, "timeout_last_run_timed_out{command=\"true\"}"),
+        "0"
+    );
+    assert_eq!(
+        sample(&path, "timeout_last_run_exit_code{command=\"true\"}"),
+        "0"
+    );
+    let duration: f64 = sample(&path, "timeout_last_run_duration_seconds{command=\"true\"}")
+        .parse()
+        .unwrap();
+    assert!(duration < 1.0, "{}", duration);
+
+    // Only the textfile itself is left behind
+    let names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["job.prom"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn exec_failure_is_counted_without_a_duration() {
+    let dir = textfile_dir("exec");
+    let path = dir.join("job.prom");
+    timeout()
+        .arg("--prom-textfile")
+        .arg(&path)
+        .args(["1", "/nonexistent/command"])
+        .assert()
+        .code(127);
+    assert_eq!(
+        sample(
+            &path,
+            "timeout_runs_total{command=\"/nonexistent/command\",outcome=\"exec_error\"}"
+        ),
+        "1"
+    );
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("duration"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn unwritable_textfile_warns_and_keeps_the_exit_code() {
+    let dir = textfile_dir("unwritable");
+    timeout()
+        .arg("--prom-textfile")
+        .arg(dir.join("missing").join("job.prom"))
+        .args(["1", "sh", "-c", "exit 3"])
+        .assert()
+        .code(3)
+        .stderr(predicates::str::contains("Warning"))
+        .stderr(predicates::str::contains("--prom-textfile"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
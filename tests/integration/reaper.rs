@@ -0,0 +1,34 @@
+// tests/integration/reaper.rs
+// COMMAND is reaped even when timeout leaves run_with_timeout early. Debug
+// builds panic after the fork on request, once COMMAND has exited unreaped.
+
+use crate::timeout;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+#[test]
+fn panic_after_fork_still_reaps_the_child() {
+    timeout()
+        .env("TIMEOUT_DEBUG", "1")
+        .env("TIMEOUT_TEST_PANIC_AFTER_FORK", "1")
+        .args(["5", "sh", "-c", "exit 3"])
+        .assert()
+        .code(101)
+        .stderr(contains("panicked"))
+        .stderr(
+            predicates::str::is_match(
+                r"reaper: waitpid\([0-9]+, WNOHANG\) = Ok\(Exited\(Pid\([0-9]+\), 3\)\)",
+            )
+            .unwrap(),
+        );
+}
+
+#[test]
+fn reaper_stands_down_once_the_child_is_waited_for() {
+    timeout()
+        .env("TIMEOUT_DEBUG", "1")
+        .args(["5", "sh", "-c", "exit 3"])
+        .assert()
+        .code(3)
+        .stderr(contains("reaper:").not());
+}
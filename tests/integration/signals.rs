@@ -0,0 +1,92 @@
+// tests/integration/signals.rs
+// Which signals reach COMMAND, and what timeout exits with afterwards
+
+use crate::{assert_elapsed, helper, timed, timeout};
+use predicates::str::{contains, is_match};
+use std::time::Duration;
+
+#[test]
+fn sigterm_ignored_until_kill_after() {
+    let (assert, elapsed) = timed(
+        timeout()
+            .args(["--verbose", "--kill-after", "0.5", "0.3"])
+            .arg(helper("ignore-sigterm")),
+    );
+    // 128 + SIGKILL
+    assert
+        .code(137)
+        .stdout("ready\n")
+        .stderr(contains("SIGTERM"))
+        .stderr(contains("SIGKILL"));
+    assert_elapsed(elapsed, Duration::from_millis(800), Duration::from_secs(5));
+}
+
+#[test]
+fn signal_kill_ends_ignore_sigterm_at_once() {
+    let (assert, elapsed) = timed(
+        timeout()
+            .args(["--signal", "KILL", "0.3"])
+            .arg(helper("ignore-sigterm")),
+    );
+    assert.code(124);
+    assert_elapsed(elapsed, Duration::from_millis(300), Duration::from_secs(5));
+}
+
+#[test]
+fn preserve_status_reports_the_signal() {
+    // 128 + SIGINT
+    timeout()
+        .args(["--preserve-status", "--signal", "INT", "0.2"])
+        .arg(helper("sleep-forever"))
+        .assert()
+        .code(130);
+}
+
+#[test]
+fn no_notify_skips_straight_to_sigkill() {
+    let (assert, elapsed) = timed(
+        timeout()
+            .args(["--no-notify", "--verbose", "--kill-after", "0.3", "0.3"])
+            .arg(helper("ignore-sigterm")),
+    );
+    assert
+        .code(137)
+        .stderr(contains("skipping initial signal"))
+        .stderr(contains("SIGKILL"));
+    assert_elapsed(elapsed, Duration::from_millis(600), Duration::from_secs(5));
+}
+
+#[test]
+fn time_report_after_kill_escalation() {
+    timeout()
+        .args(["--time-format", "%T %x %e", "--kill-after", "0.3", "0.2"])
+        .arg(helper("ignore-sigterm"))
+        .assert()
+        .code(137)
+        .stderr(is_match(r"\Ayes 137 [0-9]+\.[0-9]{2}\n\z").unwrap());
+}
+
+#[test]
+fn pre_kill_hook_is_killed_after_ten_seconds() {
+    // --hook-timeout does not apply to --pre-kill
+    let (assert, elapsed) = timed(
+        timeout()
+            .args(["--pre-kill", "sleep 60", "0.2"])
+            .arg(helper("sleep-forever")),
+    );
+    assert
+        .code(124)
+        .stderr(contains("--pre-kill hook still running after 10s"));
+    assert_elapsed(elapsed, Duration::from_secs(10), Duration::from_secs(15));
+}
+
+#[test]
+fn pre_kill_hook_is_told_about_sigkill_under_no_notify() {
+    timeout()
+        .args(["--no-notify", "--kill-after", "0.2"])
+        .args(["--pre-kill", "echo {SIGNAL}", "0.2"])
+        .arg(helper("ignore-sigterm"))
+        .assert()
+        .code(137)
+        .stdout("ready\nSIGKILL\n");
+}
@@ -0,0 +1,132 @@
+// tests/integration/statsd.rs
+// The --statsd datagram sent for each run
+
+use crate::{helper, timeout};
+use std::ffi::OsStr;
+use std::net::UdpSocket;
+use std::process::Output;
+use std::time::Duration;
+
+/// Listen as the StatsD server, run timeout with --statsd pointed at it and
+/// then `args`, and return the datapoints it was sent, one per line
+fn datapoints<S: AsRef<OsStr>>(args: impl IntoIterator<Item = S>) -> (Vec<String>, Output) {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .unwrap();
+    let output = timeout()
+        .arg("--statsd")
+        .arg(server.local_addr().unwrap().to_string())
+        .args(args)
+        .output()
+        .unwrap();
+    let mut buf = [0; 65536];
+    let len = server.recv(&mut buf).expect("no datagram");
+    let lines = String::from_utf8(buf[..len].to_vec())
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect();
+    (lines, output)
+}
+
+/// The value of `name` among `lines`, checking its type
+fn value<'a>(lines: &'a [String], name: &str, kind: &str) -> &'a str {
+    lines
+        .iter()
+        .find_map(|line| {
+            line.strip_prefix(name)?
+                .strip_prefix(':')?
+                .strip_suffix(kind)?
+                .strip_suffix('|')
+        })
+        .unwrap_or_else(|| panic!("no {}|{} in {:?}", name, kind, lines))
+}
+
+#[test]
+fn success_packet() {
+    let (lines, output) = datapoints(["1", "true"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(lines.len(), 4, "{:?}", lines);
+    assert_eq!(lines[0], "timeout.runs.success:1|c");
+    assert_eq!(value(&lines, "timeout.exit_code", "g"), "0");
+    assert_eq!(value(&lines, "timeout.kill_after_used", "g"), "0");
+    let elapsed: u64 = value(&lines, "timeout.elapsed_ms", "ms").parse().unwrap();
+    assert!(elapsed < 1000, "{}", elapsed);
+}
+
+#[cfg(unix)]
+#[test]
+fn timeout_packet_with_kill_after() {
+    let (lines, output) = datapoints([
+        "--statsd-prefix".as_ref(),
+        "ci.job".as_ref(),
+        "-k".as_ref(),
+        "0.2".as_ref(),
+        "0.2".as_ref(),
+        helper("ignore-sigterm").as_os_str(),
+    ]);
+    assert_eq!(output.status.code(), Some(137));
+    assert_eq!(lines[0], "ci.job.runs.timeout:1|c");
+    assert_eq!(value(&lines, "ci.job.exit_code", "g"), "137");
+    assert_eq!(value(&lines, "ci.job.kill_after_used", "g"), "1");
+}
+
+#[test]
+fn exec_error_packet() {
+    let (lines, output) = datapoints(["1", "/nonexistent/command"]);
+    assert_eq!(output.status.code(), Some(127));
+    assert_eq!(
+        lines,
+        ["timeout.runs.exec_error:1|c", "timeout.exit_code:127|g"]
+    );
+}
+
+/// Separators of the line protocol in the command name must not split a
+/// datapoint or a tag
+#[cfg(unix)]
+#[test]
+fn tags_survive_awkward_command_names() {
+    let dir = std::env::temp_dir().join(format!("timeout-statsd-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let command = dir.join("a,b|c#d e\nf:g");
+    std::os::unix::fs::symlink("/bin/sh", &command).unwrap();
+
+    let (lines, _) = datapoints([
+        "--statsd-tags".as_ref(),
+        "1".as_ref(),
+        command.as_os_str(),
+        "-c".as_ref(),
+        "exit 3".as_ref(),
+    ]);
+    std::fs::remove_dir_all(&dir).unwrap();
+    let tags = format!(
+        "|#command:{},timed_out:false,outcome:failure",
+        command
+            .to_str()
+            .unwrap()
+            .replace([',', '|', '#', ' ', '\n'], "_")
+    );
+    assert_eq!(lines.len(), 4, "{:?}", lines);
+    for line in &lines {
+        let (datapoint, line_tags) = line.split_at(line.find("|#").unwrap());
+        assert_eq!(line_tags, tags);
+        assert_eq!(datapoint.matches('|').count(), 1, "{}", line);
+    }
+    assert_eq!(lines[0], format!("timeout.runs.failure:1|c{}", tags));
+}
+
+#[test]
+fn unreachable_server_is_ignored() {
+    // Bound but never read, then closed, so nothing listens on the port
+    let port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    timeout()
+        .args(["--statsd", &format!("127.0.0.1:{}", port), "1", "true"])
+        .assert()
+        .success()
+        .stderr("");
+}
@@ -0,0 +1,88 @@
+// tests/integration/verbosity.rs
+// What -v, -vv and -vvv print for a simple timed-out run. -v has to stay
+// exactly as it was: scripts grep it.
+
+use crate::{helper, timeout};
+use regex::Regex;
+
+/// stderr of `timeout <flags> 0.2 sleep-forever`, with the numbers, sizes
+/// and helper path that change from run to run replaced
+fn stderr_at(flags: &[&str]) -> String {
+    let output = timeout()
+        .env_remove("TIMEOUT_DEBUG")
+        .env_remove("TIMEOUT_LOG")
+        .env_remove("TIMEOUT_METRICS")
+        .args(flags)
+        .arg("0.2")
+        .arg(helper("sleep-forever"))
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(124));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let stderr = stderr.replace(helper("sleep-forever").to_str().unwrap(), "HELPER");
+    let size = Regex::new(r"\d+(\.\d+)? (B|KiB|MiB|GiB)\b").unwrap();
+    let number = Regex::new(r"\d+(\.\d+)?").unwrap();
+    number
+        .replace_all(&size.replace_all(&stderr, "SIZE"), "N")
+        .into_owned()
+}
+
+#[test]
+fn quiet_without_verbose() {
+    assert_eq!(stderr_at(&[]), "");
+}
+
+#[test]
+fn verbose_output_is_unchanged() {
+    assert_eq!(
+        stderr_at(&["-v"]),
+        "\
+Timeout: sending signal SIGTERM to command 'HELPER'
+Summary: Command 'HELPER' timed out after Ns (limit: Nms), sent SIGTERM, exit code N.
+Resources: peak rss SIZE, cpu Ns user / Ns sys.
+Shutdown: child exited Ns after SIGTERM.
+"
+    );
+}
+
+const STEPS: &str = "\
+Limits: duration Nms, kill-after none, signal SIGTERM, idle timeout none, max output none, cpu none, memory none
+Timeout: sending signal SIGTERM to command 'HELPER'
+Signal: sent SIGTERM to process group N
+Signal: sent SIGCONT to process group N
+Wait: PID N was killed by SIGTERM
+Summary: Command 'HELPER' timed out after Ns (limit: Nms), sent SIGTERM, exit code N.
+Resources: peak rss SIZE, cpu Ns user / Ns sys.
+Shutdown: child exited Ns after SIGTERM.
+";
+
+#[test]
+fn twice_verbose_adds_each_step() {
+    assert_eq!(stderr_at(&["-vv"]), STEPS);
+}
+
+#[test]
+fn thrice_verbose_adds_the_debug_trace() {
+    let stderr = stderr_at(&["-vvv"]);
+    let (debug, rest): (Vec<&str>, Vec<&str>) =
+        stderr.lines().partition(|line| line.starts_with("Debug: "));
+    assert_eq!(format!("{}\n", rest.join("\n")), STEPS);
+    assert!(
+        debug
+            .iter()
+            .any(|line| line.contains("select: deadline arm fired")),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn verbose_output_has_no_colors_when_piped() {
+    let output = timeout()
+        .arg("-vv")
+        .arg("0.2")
+        .arg(helper("sleep-forever"))
+        .output()
+        .unwrap();
+    assert!(!output.stderr.contains(&0x1b), "{:?}", output.stderr);
+}
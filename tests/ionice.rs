@@ -0,0 +1,88 @@
+// tests/ionice.rs
+// --ionice CLASS[:LEVEL] values, good and bad
+
+use timeout::ionice::{IoClass, IoPriority};
+fn priority(class: IoClass, level: u8) -> IoPriority {
+    IoPriority { class, level }
+}
+
+#[test]
+fn classes_and_levels() {
+    assert_eq!(
+        IoPriority::parse("best-effort:4"),
+        Ok(priority(IoClass::BestEffort, 4))
+    );
+    assert_eq!(
+        IoPriority::parse("realtime:0"),
+        Ok(priority(IoClass::Realtime, 0))
+    );
+    assert_eq!(IoPriority::parse("idle"), Ok(priority(IoClass::Idle, 0)));
+    assert_eq!(
+        IoPriority::parse("Best-Effort:7"),
+        Ok(priority(IoClass::BestEffort, 7))
+    );
+}
+
+#[test]
+fn ionice_class_names_and_numbers() {
+    assert_eq!(
+        IoPriority::parse("be:2"),
+        Ok(priority(IoClass::BestEffort, 2))
+    );
+    assert_eq!(
+        IoPriority::parse("rt:1"),
+        Ok(priority(IoClass::Realtime, 1))
+    );
+    assert_eq!(IoPriority::parse("1:5"), Ok(priority(IoClass::Realtime, 5)));
+    assert_eq!(
+        IoPriority::parse("2:6"),
+        Ok(priority(IoClass::BestEffort, 6))
+    );
+    assert_eq!(IoPriority::parse("3"), Ok(priority(IoClass::Idle, 0)));
+}
+
+#[test]
+fn level_defaults_to_4() {
+    assert_eq!(
+        IoPriority::parse("best-effort"),
+        Ok(priority(IoClass::BestEffort, 4))
+    );
+    assert_eq!(
+        IoPriority::parse("realtime"),
+        Ok(priority(IoClass::Realtime, 4))
+    );
+}
+
+#[test]
+fn rejects_bad_values() {
+    for input in [
+        "",
+        "fast",
+        "0",
+        "best-effort:8",
+        "best-effort:-1",
+        "be:x",
+        "be:",
+        "realtime:4:1",
+    ] {
+        assert!(IoPriority::parse(input).is_err(), "{:?} accepted", input);
+    }
+    assert_eq!(
+        IoPriority::parse("idle:3"),
+        Err("the idle class has no levels".to_string())
+    );
+}
+
+#[test]
+fn kernel_values() {
+    assert_eq!(priority(IoClass::Realtime, 0).value(), 1 << 13);
+    assert_eq!(priority(IoClass::BestEffort, 4).value(), (2 << 13) | 4);
+    assert_eq!(priority(IoClass::Idle, 0).value(), 3 << 13);
+}
+
+#[test]
+fn display_round_trips() {
+    for input in ["realtime:0", "best-effort:4", "idle"] {
+        assert_eq!(IoPriority::parse(input).unwrap().to_string(), input);
+    }
+}
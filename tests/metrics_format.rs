@@ -0,0 +1,167 @@
+// tests/metrics_format.rs
+// logfmt and CSV escaping for --metrics-format
+
+use serde_json::{json, Value};
+use timeout::metrics_format::{csv_field, csv_header, csv_record, logfmt, logfmt_value};
+
+/// Command strings that break naive quoting: separators, quotes,
+/// backslashes, line breaks, control characters, edge whitespace and
+/// non-ASCII
+const NASTY: [&str; 14] = [
+    "",
+    " ",
+    "plain",
+    "two words",
+    "key=value",
+    "say \"hi\"",
+    r"C:\path\to",
+    r#"ends with \"#,
+    "a,b,c",
+    "two\nlines",
+    "cr\r\nlf",
+    "bell\u{7}tab\t",
+    " padded ",
+    "naïve ☃ 日本",
+];
+
+/// Read back one logfmt value as `logfmt_value` wrote it
+fn unquote_logfmt(s: &str) -> String {
+    let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) else {
+        return s.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next().unwrap() {
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            't' => out.push('\t'),
+            'u' => {
+                let hex: String = chars.by_ref().take(4).collect();
+                out.push(char::from_u32(u32::from_str_radix(&hex, 16).unwrap()).unwrap());
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Split one CSV line back into fields, per RFC 4180
+fn parse_csv(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => fields.last_mut().unwrap().push(c),
+        }
+    }
+    assert!(!quoted, "unterminated quote in {:?}", line);
+    fields
+}
+
+#[test]
+fn logfmt_values_round_trip() {
+    for s in NASTY {
+        let written = logfmt_value(s);
+        assert_eq!(
+            unquote_logfmt(&written),
+            s,
+            "{:?} written as {}",
+            s,
+            written
+        );
+    }
+}
+
+#[test]
+fn logfmt_values_are_one_token() {
+    for s in NASTY {
+        let written = logfmt_value(s);
+        assert!(!written.is_empty(), "{:?} written as nothing", s);
+        assert!(
+            !written.chars().any(char::is_control),
+            "{:?} written as {:?}",
+            s,
+            written
+        );
+        let bare = !written.starts_with('"');
+        assert!(
+            !bare || !written.contains([' ', '=', '"']),
+            "{:?} written bare as {}",
+            s,
+            written
+        );
+    }
+}
+
+#[test]
+fn logfmt_quotes_only_when_needed() {
+    assert_eq!(logfmt_value("sleep"), "sleep");
+    assert_eq!(logfmt_value("/usr/bin/sleep"), "/usr/bin/sleep");
+    assert_eq!(logfmt_value(""), r#""""#);
+    assert_eq!(logfmt_value("a b"), r#""a b""#);
+    assert_eq!(logfmt_value("a=b"), r#""a=b""#);
+    assert_eq!(logfmt_value("say \"hi\""), r#""say \"hi\"""#);
+    assert_eq!(logfmt_value("bell\u{7}"), r#""bell\u0007""#);
+}
+
+#[test]
+fn logfmt_line() {
+    let fields = vec![
+        ("command".to_string(), json!("my cmd")),
+        ("argv".to_string(), json!(["a b", "c"])),
+        ("exit_code".to_string(), json!(0)),
+        ("timed_out".to_string(), json!(false)),
+        ("signal".to_string(), Value::Null),
+    ];
+    assert_eq!(
+        logfmt(&fields),
+        r#"command="my cmd" argv="[\"a b\",\"c\"]" exit_code=0 timed_out=false signal="#
+    );
+}
+
+#[test]
+fn csv_fields_round_trip() {
+    let line = NASTY.map(csv_field).join(",");
+    assert_eq!(parse_csv(&line), NASTY);
+}
+
+#[test]
+fn csv_quotes_only_when_needed() {
+    assert_eq!(csv_field("sleep"), "sleep");
+    assert_eq!(csv_field("two words"), "two words");
+    assert_eq!(csv_field("a,b"), r#""a,b""#);
+    assert_eq!(csv_field("say \"hi\""), r#""say ""hi""""#);
+    assert_eq!(csv_field(" padded"), r#"" padded""#);
+    assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+}
+
+#[test]
+fn csv_header_and_record_line_up() {
+    let fields: Vec<(String, Value)> = NASTY
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (format!("f{}", i), json!(s)))
+        .chain([("argv".to_string(), json!(NASTY))])
+        .chain([("signal".to_string(), Value::Null)])
+        .collect();
+    let header = parse_csv(&csv_header(&fields));
+    let record = parse_csv(&csv_record(&fields));
+    assert_eq!(header.len(), record.len());
+    assert_eq!(header.last().unwrap(), "signal");
+    assert_eq!(record.last().unwrap(), "");
+    assert_eq!(&record[..NASTY.len()], NASTY);
+    let argv: Vec<String> = serde_json::from_str(&record[NASTY.len()]).unwrap();
+    assert_eq!(argv, NASTY);
+}
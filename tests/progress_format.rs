@@ -0,0 +1,139 @@
+// tests/progress_format.rs
+// Rendering the --progress status line
+
+use std::time::Duration;
+use timeout::progress_format::{clock, render, ProgressLine, Stage};
+
+fn secs(secs: f64) -> Duration {
+    Duration::from_secs_f64(secs)
+}
+
+fn running(elapsed: f64, limit: f64) -> ProgressLine<'static> {
+    ProgressLine {
+        elapsed: secs(elapsed),
+        limit: Some(secs(limit)),
+        action: "SIGTERM",
+        kill_after: None,
+        stage: Stage::Running,
+    }
+}
+
+#[test]
+fn counts_down_to_the_signal() {
+    assert_eq!(
+        render(&running(192.4, 600.0), 80),
+        "⏱ 03:12 / 10:00, SIGTERM in 06:48"
+    );
+}
+
+#[test]
+fn countdown_rounds_up() {
+    assert_eq!(
+        render(&running(0.2, 2.0), 80),
+        "⏱ 00:00 / 00:02, SIGTERM in 00:02"
+    );
+    assert_eq!(
+        render(&running(1.0, 2.0), 80),
+        "⏱ 00:01 / 00:02, SIGTERM in 00:01"
+    );
+    assert_eq!(
+        render(&running(2.5, 2.0), 80),
+        "⏱ 00:02 / 00:02, SIGTERM in 00:00"
+    );
+}
+
+#[test]
+fn no_time_limit() {
+    let line = ProgressLine {
+        limit: None,
+        ..running(75.0, 0.0)
+    };
+    assert_eq!(render(&line, 80), "⏱ 01:15, no time limit");
+}
+
+#[test]
+fn escalation_with_kill_after() {
+    let line = ProgressLine {
+        kill_after: Some(secs(10.0)),
+        stage: Stage::Stopping {
+            signal: Some("SIGTERM"),
+            since: secs(2.5),
+        },
+        ..running(603.0, 600.0)
+    };
+    assert_eq!(
+        render(&line, 80),
+        "⏱ 10:03 / 10:00, sent SIGTERM, killing in 00:08"
+    );
+}
+
+#[test]
+fn escalation_without_kill_after() {
+    let line = ProgressLine {
+        stage: Stage::Stopping {
+            signal: Some("SIGTERM"),
+            since: secs(3.0),
+        },
+        ..running(603.0, 600.0)
+    };
+    assert_eq!(
+        render(&line, 80),
+        "⏱ 10:03 / 10:00, sent SIGTERM, waiting for exit"
+    );
+}
+
+#[test]
+fn escalation_after_sigkill() {
+    let line = ProgressLine {
+        kill_after: Some(secs(10.0)),
+        stage: Stage::Stopping {
+            signal: Some("SIGKILL"),
+            since: secs(0.1),
+        },
+        ..running(610.0, 600.0)
+    };
+    assert_eq!(
+        render(&line, 80),
+        "⏱ 10:10 / 10:00, sent SIGKILL, waiting for exit"
+    );
+}
+
+#[test]
+fn timed_out_before_any_signal() {
+    let line = ProgressLine {
+        action: "timeout",
+        kill_after: Some(secs(5.0)),
+        stage: Stage::Stopping {
+            signal: None,
+            since: secs(1.0),
+        },
+        ..running(601.0, 600.0)
+    };
+    assert_eq!(
+        render(&line, 80),
+        "⏱ 10:01 / 10:00, timed out, killing in 00:04"
+    );
+    let line = ProgressLine {
+        kill_after: None,
+        ..line
+    };
+    assert_eq!(render(&line, 80), "⏱ 10:01 / 10:00, timed out");
+}
+
+#[test]
+fn cut_to_the_terminal_width() {
+    let line = render(&running(192.0, 600.0), 20);
+    assert_eq!(line, "⏱ 03:12 / 10:00, S");
+    assert_eq!(line.chars().count(), 18);
+    assert_eq!(render(&running(192.0, 600.0), 1), "");
+}
+
+#[test]
+fn clock_formats() {
+    assert_eq!(clock(secs(0.0)), "00:00");
+    assert_eq!(clock(secs(59.9)), "00:59");
+    assert_eq!(clock(secs(600.0)), "10:00");
+    assert_eq!(clock(secs(3599.0)), "59:59");
+    assert_eq!(clock(secs(3600.0)), "1:00:00");
+    assert_eq!(clock(secs(90061.0)), "25:01:01");
+}
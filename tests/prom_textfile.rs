@@ -0,0 +1,147 @@
+// tests/prom_textfile.rs
+// Reading back and merging the timeout_runs_total counter of a
+// --prom-textfile file
+
+use timeout::prom_textfile::{escape_label, merge, parse_runs_total, render, LastRun, RunCounts};
+fn run<'a>(command: &'a str, outcome: &'a str) -> LastRun<'a> {
+    LastRun {
+        command,
+        outcome,
+        timestamp: 1_700_000_000.25,
+        duration: Some(1.5),
+        exit_code: 0,
+        timed_out: outcome == "timeout",
+    }
+}
+
+fn count(counts: &RunCounts, command: &str, outcome: &str) -> f64 {
+    counts[&(command.to_string(), outcome.to_string())]
+}
+
+#[test]
+fn label_values_are_escaped() {
+    assert_eq!(escape_label("plain"), "plain");
+    assert_eq!(escape_label(r#"say "hi""#), r#"say \"hi\""#);
+    assert_eq!(escape_label(r"C:\path"), r"C:\\path");
+    assert_eq!(escape_label("two\nlines"), r"two\nlines");
+    assert_eq!(escape_label("tab\tand ☃"), "tab\tand ☃");
+}
+
+#[test]
+fn first_run_starts_every_outcome_at_zero() {
+    let mut counts = parse_runs_total("");
+    merge(&mut counts, &run("make", "timeout"));
+    assert_eq!(counts.len(), 4);
+    assert_eq!(count(&counts, "make", "timeout"), 1.0);
+    for outcome in ["success", "failure", "exec_error"] {
+        assert_eq!(count(&counts, "make", outcome), 0.0);
+    }
+}
+
+#[test]
+fn counts_survive_a_round_trip() {
+    let mut counts = RunCounts::new();
+    for outcome in ["success", "success", "failure", "timeout", "success"] {
+        let text = render(&run("make", outcome), &counts);
+        counts = parse_runs_total(&text);
+        merge(&mut counts, &run("make", outcome));
+    }
+    assert_eq!(count(&counts, "make", "success"), 3.0);
+    assert_eq!(count(&counts, "make", "failure"), 1.0);
+    assert_eq!(count(&counts, "make", "timeout"), 1.0);
+    assert_eq!(count(&counts, "make", "exec_error"), 0.0);
+    assert_eq!(
+        parse_runs_total(&render(&run("make", "success"), &counts)),
+        counts
+    );
+}
+
+#[test]
+fn awkward_commands_keep_their_own_counts() {
+    let commands = [
+        "",
+        "a\"b",
+        r"back\slash",
+        r#"\""#,
+        "two\nlines",
+        "x,y=z}",
+        "日本",
+    ];
+    let mut counts = RunCounts::new();
+    for command in commands {
+        merge(&mut counts, &run(command, "failure"));
+    }
+    let text = render(&run("plain", "success"), &counts);
+    assert_eq!(text.lines().filter(|l| l.is_empty()).count(), 0, "{}", text);
+    let parsed = parse_runs_total(&text);
+    assert_eq!(parsed, counts);
+    for command in commands {
+        assert_eq!(count(&parsed, command, "failure"), 1.0, "{:?}", command);
+    }
+}
+
+#[test]
+fn samples_written_by_others_are_understood() {
+    let text = "\
+# HELP timeout_runs_total Runs by how they ended
+# TYPE timeout_runs_total counter
+timeout_runs_total{outcome=\"success\",command=\"make\"} 41 1700000000000
+timeout_runs_total{ command = \"make\" , outcome = \"timeout\" , } 2.0
+  timeout_runs_total{command=\"make\",outcome=\"failure\"} 1e1
+";
+    let counts = parse_runs_total(text);
+    assert_eq!(count(&counts, "make", "success"), 41.0);
+    assert_eq!(count(&counts, "make", "timeout"), 2.0);
+    assert_eq!(count(&counts, "make", "failure"), 10.0);
+    assert_eq!(counts.len(), 3, "{:?}", counts);
+}
+
+#[test]
+fn damaged_samples_are_skipped() {
+    let text = "\
+timeout_last_run_exit_code{command=\"make\"} 2
+timeout_runs_total_created{command=\"make\",outcome=\"success\"} 5
+timeout_runs_total{command=\"make\",outcome=\"success\"} 7
+timeout_runs_total{command=\"make\",outcome=\"failure\"} lots
+timeout_runs_total{command=\"make\",outcome=\"timeout\"} -3
+timeout_runs_total{command=\"make\",outcome=\"exec_error\"} NaN
+timeout_runs_total{command=\"make\"} 9
+timeout_runs_total{command=\"unterminated,outcome=\"success\"} 9
+timeout_runs_total{command=\"make\",outcome=\"success\"
+";
+    let counts = parse_runs_total(text);
+    assert_eq!(counts.len(), 1, "{:?}", counts);
+    assert_eq!(count(&counts, "make", "success"), 7.0);
+}
+
+#[test]
+fn gauges_describe_the_last_run() {
+    let mut counts = RunCounts::new();
+    let last = LastRun {
+        exit_code: 124,
+        ..run("sleep", "timeout")
+    };
+    merge(&mut counts, &last);
+    let text = render(&last, &counts);
+    for sample in [
+        "timeout_last_run_timestamp_seconds{command=\"sleep\"} 1700000000.25",
+        "timeout_last_run_duration_seconds{command=\"sleep\"} 1.5",
+        "timeout_last_run_exit_code{command=\"sleep\"} 124",
+        "timeout_last_run_timed_out{command=\"sleep\"} 1",
+        "timeout_runs_total{command=\"sleep\",outcome=\"timeout\"} 1",
+    ] {
+        assert!(
+            text.lines().any(|l| l == sample),
+            "no {} in\n{}",
+            sample,
+            text
+        );
+    }
+
+    let not_run = LastRun {
+        duration: None,
+        exit_code: 127,
+        ..run("missing", "exec_error")
+    };
+    assert!(!render(&not_run, &counts).contains("duration"));
+}
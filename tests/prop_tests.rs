@@ -0,0 +1,227 @@
+// tests/prop_tests.rs
+// Property tests for the DURATION, SIZE and SIGNAL parsers
+
+use proptest::prelude::*;
+use std::time::Duration;
+#[cfg(unix)]
+use timeout::signal;
+use timeout::{units, Platform, TimeoutError};
+
+/// Every DURATION suffix, with its length in seconds
+const DURATION_SUFFIXES: [(&str, u64); 5] =
+    [("", 1), ("s", 1), ("m", 60), ("h", 3600), ("d", 86400)];
+
+/// The longest DURATION timeout accepts, in seconds: about 585 years
+const MAX_SECONDS: u64 = u64::MAX / 1_000_000_000;
+
+/// Every SIZE suffix, with its multiplier. Each is also accepted in lower
+/// or mixed case.
+const SIZE_SUFFIXES: [(&str, u64); 16] = [
+    ("", 1),
+    ("K", 1 << 10),
+    ("M", 1 << 20),
+    ("G", 1 << 30),
+    ("T", 1 << 40),
+    ("P", 1 << 50),
+    ("KB", 1_000),
+    ("MB", 1_000_000),
+    ("GB", 1_000_000_000),
+    ("TB", 1_000_000_000_000),
+    ("PB", 1_000_000_000_000_000),
+    ("KiB", 1 << 10),
+    ("MiB", 1 << 20),
+    ("GiB", 1 << 30),
+    ("TiB", 1 << 40),
+    ("PiB", 1 << 50),
+];
+
+/// One of `suffixes`, in a random mix of upper and lower case when `fold`
+fn suffix<const N: usize>(
+    suffixes: &'static [(&'static str, u64); N],
+    fold: bool,
+) -> impl Strategy<Value = (String, u64)> {
+    (0..N, any::<u64>()).prop_map(move |(i, cases)| {
+        let (suffix, multiplier) = suffixes[i];
+        let suffix = suffix
+            .chars()
+            .enumerate()
+            .map(|(n, c)| match fold && cases >> n & 1 == 1 {
+                true => c.to_ascii_lowercase(),
+                false => c,
+            })
+            .collect();
+        (suffix, multiplier)
+    })
+}
+
+proptest! {
+    #[test]
+    fn whole_durations_parse(
+        (suffix, seconds, value) in suffix(&DURATION_SUFFIXES, false)
+            .prop_flat_map(|(suffix, seconds)| (Just(suffix), Just(seconds), 0..=MAX_SECONDS / seconds))
+    ) {
+        let parsed = units::parse_duration(&format!("{}{}", value, suffix));
+        prop_assert_eq!(parsed.unwrap(), Duration::from_secs(value * seconds));
+    }
+
+    #[test]
+    fn fractional_durations_parse(
+        (suffix, seconds, value) in suffix(&DURATION_SUFFIXES, false)
+            .prop_flat_map(|(suffix, seconds)| (Just(suffix), Just(seconds), 0.0..=(MAX_SECONDS / seconds) as f64))
+    ) {
+        let parsed = units::parse_duration(&format!(" {}{} ", value, suffix)).unwrap();
+        let expected = value * seconds as f64;
+        prop_assert!((parsed.as_secs_f64() - expected).abs() <= expected * 1e-9 + 1e-9);
+    }
+
+    #[test]
+    fn overlong_durations_are_rejected(
+        (suffix, value) in suffix(&DURATION_SUFFIXES, false)
+            .prop_flat_map(|(suffix, seconds)| (Just(suffix), (MAX_SECONDS / seconds + 1)..u64::MAX))
+    ) {
+        match units::parse_duration(&format!("{}{}", value, suffix)) {
+            Err(TimeoutError::InvalidDuration { reason, .. }) => {
+                prop_assert_eq!(reason, "duration too large (maximum ~585 years)")
+            }
+            other => prop_assert!(false, "{}{} gave {:?}", value, suffix, other),
+        }
+    }
+
+    #[test]
+    fn unknown_duration_suffixes_are_rejected(
+        value in 0u32..1_000_000,
+        suffix in any::<char>().prop_filter("a letter that is not a suffix", |c| {
+            c.is_alphabetic() && !"smhd".contains(*c)
+        }),
+    ) {
+        let input = format!("{}{}", value, suffix);
+        match units::parse_duration(&input) {
+            Err(TimeoutError::InvalidDuration { input: reported, reason }) => {
+                prop_assert_eq!(reported, input);
+                prop_assert_eq!(reason, format!("invalid time suffix '{}'", suffix));
+            }
+            other => prop_assert!(false, "{:?} gave {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn sizes_in_range_parse((suffix, multiplier) in suffix(&SIZE_SUFFIXES, true), value in any::<u64>()) {
+        let value = value / multiplier;
+        let parsed = units::parse_memory_limit(&format!("{}{}", value, suffix));
+        prop_assert_eq!(parsed.unwrap(), value * multiplier);
+    }
+
+    #[test]
+    fn sizes_never_overflow((suffix, multiplier) in suffix(&SIZE_SUFFIXES, true), value in any::<u64>()) {
+        let input = format!("{}{}", value, suffix);
+        match (units::parse_memory_limit(&input), value.checked_mul(multiplier)) {
+            (Ok(bytes), Some(expected)) => prop_assert_eq!(bytes, expected),
+            (Err(TimeoutError::InvalidMemoryLimit { reason, .. }), None) => {
+                prop_assert_eq!(reason, "value too large")
+            }
+            (parsed, expected) => prop_assert!(false, "{:?} gave {:?}, expected {:?}", input, parsed, expected),
+        }
+    }
+
+    #[test]
+    fn output_limits_parse_as_sizes((suffix, multiplier) in suffix(&SIZE_SUFFIXES, true), value in any::<u64>()) {
+        let value = value / multiplier;
+        let parsed = units::parse_output_limit(&format!("{}{}", value, suffix));
+        prop_assert_eq!(parsed.unwrap(), value * multiplier);
+    }
+
+    #[test]
+    fn formatted_sizes_parse_back((_, multiplier) in suffix(&SIZE_SUFFIXES, false), value in any::<u64>()) {
+        let bytes = value / multiplier * multiplier;
+        let formatted = units::format_size(bytes);
+        match formatted.strip_suffix(" bytes") {
+            Some(plain) => prop_assert_eq!(plain.parse::<u64>().unwrap(), bytes),
+            None => prop_assert_eq!(units::parse_memory_limit(&formatted).unwrap(), bytes),
+        }
+    }
+
+    #[test]
+    fn percentages_stay_within_memory(percent in 0.001f64..=100.0) {
+        let bytes = units::parse_memory_limit(&format!("{}%", percent)).unwrap();
+        prop_assert!(bytes <= Platform::total_memory().unwrap());
+    }
+}
+
+#[cfg(unix)]
+mod signals {
+    use super::signal::{exit_code, TimeoutSignal};
+    use super::TimeoutError;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    /// Every signal `--signal` accepts here, as listed by --list-signals
+    fn known() -> impl Strategy<Value = TimeoutSignal> {
+        select(TimeoutSignal::all().collect::<Vec<_>>())
+    }
+
+    proptest! {
+        #[test]
+        fn known_names_parse(signal in known()) {
+            prop_assert_eq!(TimeoutSignal::from_str_or_num(signal.as_str()).unwrap(), signal);
+        }
+
+        #[test]
+        fn names_parse_in_any_case_without_sig(signal in known(), lower in any::<bool>(), prefix in any::<bool>()) {
+            let mut name = signal.as_str().to_string();
+            if !prefix {
+                name = name.split_off(3);
+            }
+            if lower {
+                name = name.to_lowercase();
+            }
+            prop_assert_eq!(TimeoutSignal::from_str_or_num(&name).unwrap(), signal);
+        }
+
+        #[test]
+        fn known_numbers_parse(signal in known()) {
+            prop_assert_eq!(TimeoutSignal::from_str_or_num(&signal.0.to_string()).unwrap(), signal);
+        }
+
+        #[test]
+        fn signal_exit_codes_fit_in_a_byte(sig in 1..1024i32) {
+            let code = exit_code(sig);
+            prop_assert!((129..=255).contains(&code));
+            prop_assert_eq!(code == 128 + sig, sig <= 127);
+        }
+
+        #[test]
+        fn known_signals_exit_128_plus_n(signal in known()) {
+            prop_assert_eq!(exit_code(signal.0), 128 + signal.0);
+        }
+
+        #[test]
+        fn anything_else_is_an_unknown_signal(input in "\\PC*") {
+            let name = input.to_uppercase();
+            let name = name.strip_prefix("SIG").unwrap_or(&name);
+            // SIGRTMIN and SIGRTMAX have spellings besides their listed names
+            prop_assume!(!name.starts_with("RTM"));
+            let known = TimeoutSignal::all()
+                .any(|signal| signal.as_str()[3..] == *name || name.parse() == Ok(signal.0));
+            prop_assume!(!known);
+            match TimeoutSignal::from_str_or_num(&input) {
+                Err(TimeoutError::UnknownSignal { input: reported, .. }) => prop_assert_eq!(reported, input),
+                other => prop_assert!(false, "{:?} gave {:?}", input, other),
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn real_time_signal_bounds() {
+        assert_eq!(
+            exit_code(nix::libc::SIGRTMIN()),
+            128 + nix::libc::SIGRTMIN()
+        );
+        assert_eq!(
+            exit_code(nix::libc::SIGRTMAX()),
+            128 + nix::libc::SIGRTMAX()
+        );
+        assert_eq!(exit_code(127), 255);
+        assert_eq!(exit_code(128), 255);
+    }
+}
@@ -0,0 +1,270 @@
+// tests/runner.rs
+// TimeoutRunner, the library API, and the events it reports
+#![cfg(unix)]
+
+use std::time::{Duration, Instant};
+use timeout::{TimeoutEvent, TimeoutRunner, TimeoutSignal};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::oneshot;
+
+/// Every event the run reported, once the stream has ended
+async fn collect(mut events: UnboundedReceiver<TimeoutEvent>) -> Vec<TimeoutEvent> {
+    let mut collected = Vec::new();
+    while let Some(event) = events.recv().await {
+        collected.push(event);
+    }
+    collected
+}
+
+fn sigterm() -> TimeoutSignal {
+    "TERM".parse().unwrap()
+}
+
+#[tokio::test]
+async fn returns_the_commands_status() {
+    let code = TimeoutRunner::new("sh", Duration::from_secs(10))
+        .args(["-c", "exit 3"])
+        .run()
+        .await
+        .unwrap();
+    assert_eq!(code, 3);
+}
+
+#[tokio::test]
+async fn a_missing_command_is_127() {
+    let code = TimeoutRunner::new("/nonexistent/command", Duration::from_secs(10))
+        .run()
+        .await
+        .unwrap();
+    assert_eq!(code, 127);
+}
+
+#[tokio::test]
+async fn reports_a_timeout_as_it_happens() {
+    let (run, events) = TimeoutRunner::new("sleep", Duration::from_millis(200))
+        .args(["10"])
+        .run_with_events();
+    assert_eq!(run.await.unwrap(), 124);
+
+    let events = collect(events).await;
+    assert!(matches!(events[0], TimeoutEvent::Started { pid } if pid > 0));
+    match events[1] {
+        TimeoutEvent::TimedOut { elapsed } => assert!(elapsed >= Duration::from_millis(200)),
+        ref other => panic!("expected TimedOut, got {:?}", other),
+    }
+    assert_eq!(
+        events[2..],
+        [
+            TimeoutEvent::SignalSent { signal: sigterm() },
+            TimeoutEvent::Exited { code: 124 },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn a_command_that_finishes_in_time_only_starts_and_exits() {
+    let (run, events) = TimeoutRunner::new("true", Duration::from_secs(10)).run_with_events();
+    assert_eq!(run.await.unwrap(), 0);
+
+    let events = collect(events).await;
+    assert_eq!(events.len(), 2, "{:?}", events);
+    assert_eq!(events[1], TimeoutEvent::Exited { code: 0 });
+}
+
+#[tokio::test]
+async fn kill_after_reports_the_kill() {
+    // The ignored SIGTERM survives the exec
+    let (run, events) = TimeoutRunner::new("sh", Duration::from_millis(100))
+        .args(["-c", "trap '' TERM; exec sleep 10"])
+        .signal(sigterm())
+        .kill_after(Duration::from_millis(100))
+        .run_with_events();
+    assert_eq!(run.await.unwrap(), 137);
+
+    let events = collect(events).await;
+    assert!(events.contains(&TimeoutEvent::SignalSent { signal: sigterm() }));
+    assert_eq!(
+        events[events.len() - 2..],
+        [TimeoutEvent::KillSent, TimeoutEvent::Exited { code: 137 }]
+    );
+}
+
+#[tokio::test]
+async fn an_uncatchable_signal_is_reported_as_the_kill() {
+    let (run, events) = TimeoutRunner::new("sleep", Duration::from_millis(100))
+        .args(["10"])
+        .signal("KILL".parse().unwrap())
+        .run_with_events();
+    assert_eq!(run.await.unwrap(), 124);
+    assert!(collect(events).await.contains(&TimeoutEvent::KillSent));
+}
+
+#[tokio::test]
+async fn going_over_the_output_limit_is_a_resource_event() {
+    let (run, events) = TimeoutRunner::new("sh", Duration::from_secs(10))
+        .args(["-c", "echo 0123456789; echo 0123456789; exec sleep 10"])
+        .max_output(15)
+        .run_with_events();
+    assert_eq!(run.await.unwrap(), 123);
+
+    let events = collect(events).await;
+    assert!(events.contains(&TimeoutEvent::ResourceLimitExceeded {
+        resource: "output".to_string()
+    }));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, TimeoutEvent::TimedOut { .. })));
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly"))]
+#[tokio::test]
+async fn using_up_the_cpu_limit_is_a_resource_event() {
+    let (run, events) = TimeoutRunner::new("sh", Duration::from_secs(30))
+        .args(["-c", "while :; do :; done"])
+        .cpu_limit(1)
+        .run_with_events();
+    assert_eq!(run.await.unwrap(), 137);
+
+    let events = collect(events).await;
+    assert_eq!(
+        events[events.len() - 2..],
+        [
+            TimeoutEvent::ResourceLimitExceeded {
+                resource: "cpu".to_string()
+            },
+            TimeoutEvent::Exited { code: 137 },
+        ]
+    );
+}
+
+/// A cancellation future, and what cancels it `after` a while
+fn cancel_after(after: Duration) -> impl std::future::Future<Output = ()> {
+    let (cancel, cancelled) = oneshot::channel::<()>();
+    tokio::spawn(async move {
+        tokio::time::sleep(after).await;
+        let _ = cancel.send(());
+    });
+    async move {
+        let _ = cancelled.await;
+    }
+}
+
+#[tokio::test]
+async fn cancelling_stops_the_command_with_its_own_status() {
+    let started = Instant::now();
+    let (run, events) = TimeoutRunner::new("sleep", Duration::from_secs(30))
+        .args(["30"])
+        .with_cancellation(cancel_after(Duration::from_millis(200)))
+        .run_with_events();
+    // Killed by SIGTERM, rather than timed out
+    assert_eq!(run.await.unwrap(), 143);
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    let events = collect(events).await;
+    assert_eq!(
+        events[1..],
+        [
+            TimeoutEvent::SignalSent { signal: sigterm() },
+            TimeoutEvent::Exited { code: 143 },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn cancelling_waits_out_kill_after() {
+    let (run, events) = TimeoutRunner::new("sh", Duration::from_secs(30))
+        .args(["-c", "trap '' TERM; exec sleep 30"])
+        .kill_after(Duration::from_millis(200))
+        .with_cancellation(cancel_after(Duration::from_millis(100)))
+        .run_with_events();
+    assert_eq!(run.await.unwrap(), 137);
+
+    let events = collect(events).await;
+    assert_eq!(
+        events[events.len() - 2..],
+        [TimeoutEvent::KillSent, TimeoutEvent::Exited { code: 137 }]
+    );
+}
+
+#[tokio::test]
+async fn a_command_that_finishes_first_is_not_cancelled() {
+    let code = TimeoutRunner::new("true", Duration::from_secs(30))
+        .with_cancellation(cancel_after(Duration::from_secs(5)))
+        .run()
+        .await
+        .unwrap();
+    assert_eq!(code, 0);
+}
+
+#[tokio::test]
+async fn extending_through_the_handle_moves_the_deadline() {
+    let started = Instant::now();
+    let (run, handle) = TimeoutRunner::new("sleep", Duration::from_millis(300))
+        .args(["0.8"])
+        .run_with_handle();
+    let extend = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let extended = handle.extend(Duration::from_secs(5)).await;
+        (extended, handle.remaining())
+    };
+    let (code, (extended, remaining)) = tokio::join!(run, extend);
+    // sleep finished where the first deadline would have stopped it
+    assert_eq!(code.unwrap(), 0);
+    assert!(started.elapsed() >= Duration::from_millis(800));
+    assert!(extended);
+    let remaining = remaining.unwrap();
+    assert!(
+        remaining > Duration::from_secs(4) && remaining <= Duration::from_millis(5300),
+        "{:?}",
+        remaining
+    );
+}
+
+#[tokio::test]
+async fn the_handle_counts_down_and_stops_once_the_run_is_over() {
+    let mut runner = TimeoutRunner::new("true", Duration::from_secs(60));
+    let handle = runner.handle();
+    let remaining = handle.remaining().unwrap();
+    assert!(remaining > Duration::from_secs(59), "{:?}", remaining);
+
+    assert_eq!(runner.run().await.unwrap(), 0);
+    assert!(!handle.extend(Duration::from_secs(1)).await);
+}
+
+#[tokio::test]
+async fn without_a_limit_there_is_nothing_to_extend() {
+    let (run, handle) = TimeoutRunner::new("sleep", Duration::MAX)
+        .args(["0.3"])
+        .run_with_handle();
+    let extend = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        (
+            handle.remaining(),
+            handle.extend(Duration::from_secs(1)).await,
+        )
+    };
+    let (code, (remaining, extended)) = tokio::join!(run, extend);
+    assert_eq!(code.unwrap(), 0);
+    assert_eq!(remaining, None);
+    assert!(!extended);
+}
+
+#[tokio::test]
+async fn cancelling_through_the_handle_waits_for_the_command() {
+    let mut runner = TimeoutRunner::new("sleep", Duration::from_secs(30)).args(["30"]);
+    let handle = runner.handle();
+    let (run, events) = runner.run_with_events();
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        handle.cancel().await;
+    };
+    let (code, ()) = tokio::join!(run, cancel);
+    assert_eq!(code.unwrap(), 143);
+    assert_eq!(
+        collect(events).await[1..],
+        [
+            TimeoutEvent::SignalSent { signal: sigterm() },
+            TimeoutEvent::Exited { code: 143 },
+        ]
+    );
+}
@@ -0,0 +1,62 @@
+// tests/rusage.rs
+// Converting struct rusage for the metrics record and the --verbose line
+
+use std::time::Duration;
+use timeout::rusage::{binary_size, ResourceUsage};
+
+#[test]
+fn binary_sizes() {
+    assert_eq!(binary_size(512), "512 B");
+    assert_eq!(binary_size(1024), "1.0 KiB");
+    assert_eq!(binary_size(1536 << 10), "1.5 MiB");
+    assert_eq!(binary_size(812 << 20), "812 MiB");
+    assert_eq!(binary_size(3 << 30), "3.0 GiB");
+    assert_eq!(binary_size(2048 << 40), "2048 TiB");
+}
+
+#[test]
+fn summary() {
+    let usage = ResourceUsage {
+        max_rss_bytes: Some(812 << 20),
+        user_cpu: Some(Duration::from_millis(42_080)),
+        sys_cpu: Some(Duration::from_millis(3_010)),
+        ..Default::default()
+    };
+    assert_eq!(
+        usage.summary().unwrap(),
+        "peak rss 812 MiB, cpu 42.1s user / 3.0s sys"
+    );
+    assert_eq!(ResourceUsage::default().summary(), None);
+}
+
+#[test]
+fn serializes_as_milliseconds_or_null() {
+    let usage = ResourceUsage {
+        user_cpu: Some(Duration::from_micros(1_500_900)),
+        ..Default::default()
+    };
+    let json = serde_json::to_value(usage).unwrap();
+    assert_eq!(json["user_cpu_ms"], 1500);
+    assert_eq!(json["sys_cpu_ms"], serde_json::Value::Null);
+    assert_eq!(json["max_rss_bytes"], serde_json::Value::Null);
+}
+
+#[cfg(unix)]
+#[test]
+fn from_rusage() {
+    let mut raw: nix::libc::rusage = unsafe { std::mem::zeroed() };
+    raw.ru_maxrss = 2048;
+    raw.ru_utime.tv_sec = 1;
+    raw.ru_utime.tv_usec = 250_000;
+    raw.ru_minflt = 97;
+    raw.ru_oublock = 8;
+    let usage = ResourceUsage::from(&raw);
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    assert_eq!(usage.max_rss_bytes, Some(2048));
+    #[cfg(not(any(target_os = "macos", target_os = "ios")))]
+    assert_eq!(usage.max_rss_bytes, Some(2 << 20));
+    assert_eq!(usage.user_cpu, Some(Duration::from_millis(1250)));
+    assert_eq!(usage.sys_cpu, Some(Duration::ZERO));
+    assert_eq!(usage.minor_faults, Some(97));
+    assert_eq!(usage.block_output_ops, Some(8));
+}
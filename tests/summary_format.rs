@@ -0,0 +1,140 @@
+// tests/summary_format.rs
+// Parsing and rendering --summary-format templates
+
+use std::time::Duration;
+use timeout::summary_format::{human_duration, SummaryFields, Template, PLACEHOLDERS};
+
+fn timed_out<'a>(argv: &'a [String]) -> SummaryFields<'a> {
+    SummaryFields {
+        outcome: "timeout",
+        command: "sleep",
+        argv,
+        elapsed: Some(Duration::from_millis(5_032)),
+        duration: Some(Duration::from_secs(5)),
+        signal: Some("SIGTERM"),
+        exit_code: 124,
+        timed_out: true,
+        reason: Some("duration".to_string()),
+        attempt: Some(2),
+        iteration: Some(1),
+        pid: Some(4242),
+        max_rss_bytes: Some(3 << 20),
+        error: None,
+    }
+}
+
+fn render(format: &str, fields: &SummaryFields) -> String {
+    Template::parse(format).unwrap().render(fields)
+}
+
+#[test]
+fn the_example_line() {
+    let argv = ["sleep".to_string(), "10".to_string()];
+    assert_eq!(
+        render(
+            "[{outcome}] {command} took {elapsed} (limit {duration}) exit={exit_code}",
+            &timed_out(&argv)
+        ),
+        "[timeout] sleep took 5.03s (limit 5s) exit=124"
+    );
+}
+
+#[test]
+fn every_placeholder() {
+    let argv = ["sleep".to_string(), "10".to_string()];
+    let format = PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join("|");
+    assert_eq!(
+        render(&format, &timed_out(&argv)),
+        "timeout|sleep|sleep 10|5.03s|5s|0ms|SIGTERM|124|true|duration|2|1|4242|3.0 MiB|-"
+    );
+}
+
+#[test]
+fn remaining_time() {
+    let mut fields = SummaryFields {
+        elapsed: Some(Duration::from_millis(1_500)),
+        duration: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    assert_eq!(render("{remaining}", &fields), "58.5s");
+    fields.duration = None;
+    assert_eq!(render("{duration} {remaining}", &fields), "none none");
+    fields.elapsed = None;
+    assert_eq!(render("{elapsed} {remaining}", &fields), "- -");
+}
+
+#[test]
+fn command_that_could_not_run() {
+    let argv = ["nope".to_string()];
+    let fields = SummaryFields {
+        outcome: "exec-error",
+        command: "nope",
+        argv: &argv,
+        exit_code: 127,
+        error: Some("command not found: nope"),
+        ..Default::default()
+    };
+    assert_eq!(
+        render(
+            "{outcome} {exit_code} {pid} {signal} {attempt}: {error}",
+            &fields
+        ),
+        "exec-error 127 - - -: command not found: nope"
+    );
+}
+
+#[test]
+fn escaped_braces() {
+    let fields = SummaryFields::default();
+    assert_eq!(
+        render("{{}} {{exit_code}} {{{exit_code}}}", &fields),
+        "{} {exit_code} {0}"
+    );
+    assert_eq!(render("no placeholders", &fields), "no placeholders");
+    assert_eq!(render("", &fields), "");
+}
+
+#[test]
+fn multibyte_text_around_placeholders() {
+    let fields = SummaryFields {
+        outcome: "ok",
+        ..Default::default()
+    };
+    assert_eq!(render("→{outcome}← ✓", &fields), "→ok← ✓");
+}
+
+#[test]
+fn unknown_placeholders_are_rejected() {
+    let error = Template::parse("{outcome} {elapsd}").unwrap_err();
+    assert!(
+        error.starts_with("unknown placeholder {elapsd}"),
+        "{}",
+        error
+    );
+    assert!(Template::parse("{}")
+        .unwrap_err()
+        .starts_with("unknown placeholder {}"));
+    assert!(Template::parse("{Outcome}").is_err());
+}
+
+#[test]
+fn unmatched_braces_are_rejected() {
+    assert_eq!(
+        Template::parse("{outcome").unwrap_err(),
+        "unclosed '{' at byte 0"
+    );
+    assert_eq!(
+        Template::parse("a}b").unwrap_err(),
+        "unmatched '}' at byte 1; write }} for a brace"
+    );
+}
+
+#[test]
+fn human_durations() {
+    assert_eq!(human_duration(Duration::ZERO), "0ms");
+    assert_eq!(human_duration(Duration::from_millis(250)), "250ms");
+    assert_eq!(human_duration(Duration::from_secs(5)), "5s");
+    assert_eq!(human_duration(Duration::from_millis(5_100)), "5.1s");
+    assert_eq!(human_duration(Duration::from_millis(125_900)), "2m 5s");
+    assert_eq!(human_duration(Duration::from_secs(3_725)), "1h 2m 5s");
+}
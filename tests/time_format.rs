@@ -0,0 +1,83 @@
+// tests/time_format.rs
+// GNU time directives in --time-format
+
+use std::time::Duration;
+use timeout::rusage::ResourceUsage;
+use timeout::time_format::{render, TimeReport, DEFAULT_FORMAT};
+
+fn report() -> TimeReport {
+    TimeReport {
+        command: "make -j8".to_string(),
+        elapsed: Duration::from_millis(5_034),
+        usage: ResourceUsage {
+            max_rss_bytes: Some(812 << 20),
+            user_cpu: Some(Duration::from_millis(14_100)),
+            sys_cpu: Some(Duration::from_millis(1_010)),
+            minor_faults: Some(2_048),
+            major_faults: Some(3),
+            block_input_ops: Some(16),
+            block_output_ops: Some(64),
+        },
+        exit_code: 124,
+        timed_out: true,
+    }
+}
+
+#[test]
+fn times() {
+    assert_eq!(render("%e %U %S", &report()), "5.03 14.10 1.01\n");
+}
+
+#[test]
+fn clock_time() {
+    let mut report = report();
+    assert_eq!(render("%E", &report), "0:05.03\n");
+    report.elapsed = Duration::from_millis(754_250);
+    assert_eq!(render("%E", &report), "12:34.25\n");
+    report.elapsed = Duration::from_secs(3 * 3600 + 62);
+    assert_eq!(render("%E", &report), "3:01:02\n");
+}
+
+#[test]
+fn cpu_percentage() {
+    let mut report = report();
+    assert_eq!(render("%P", &report), "300%\n");
+    report.elapsed = Duration::ZERO;
+    assert_eq!(render("%P", &report), "?%\n");
+}
+
+#[test]
+fn memory_and_io_counts() {
+    assert_eq!(render("%M %F %R %I %O", &report()), "831488 3 2048 16 64\n");
+}
+
+#[test]
+fn exit_status_command_and_timed_out() {
+    let mut report = report();
+    assert_eq!(render("%x %C %T", &report), "124 make -j8 yes\n");
+    report.timed_out = false;
+    assert_eq!(render("%T", &report), "no\n");
+}
+
+#[test]
+fn escapes_and_unknown_directives() {
+    assert_eq!(render("100%% \\t\\n\\\\", &report()), "100% \t\n\\\n");
+    assert_eq!(render("%q \\a %", &report()), "?q ?\\a ?\n");
+}
+
+#[test]
+fn values_not_collected() {
+    let report = TimeReport {
+        elapsed: Duration::from_millis(250),
+        ..Default::default()
+    };
+    assert_eq!(render("%e %U %S %M %P", &report), "0.25 ? ? ? ?%\n");
+}
+
+#[test]
+fn default_format() {
+    assert_eq!(
+        render(DEFAULT_FORMAT, &report()),
+        "real\t5.03s\nuser\t14.10s\nsys\t1.01s\nmaxrss\t831488KB\ntimed out\tyes\n"
+    );
+}